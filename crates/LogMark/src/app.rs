@@ -1,46 +1,148 @@
 use eframe::App;
-use egui::{Context, SidePanel, CentralPanel, TextEdit, Window, Align2, Key};
-use egui_graphs::{Graph, GraphView, SettingsInteraction, LayoutStateRandom, LayoutRandom};
-use petgraph::{stable_graph::StableGraph, Directed};
-use petgraph::visit::EdgeRef;
+use egui::{Context, SidePanel, CentralPanel, TextEdit, TopBottomPanel, Window, Align2, Key};
+use egui_graphs::{Graph, GraphView, MetadataFrame, SettingsInteraction, SettingsNavigation, LayoutStateRandom, LayoutRandom};
+use petgraph::{stable_graph::{EdgeIndex, NodeIndex, StableGraph}, Directed, Direction};
+use petgraph::visit::{EdgeRef, IntoEdgeReferences};
 use egui_commonmark::{CommonMarkCache, CommonMarkViewer};
 use regex::Regex;
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
 
-use crate::graph::{LogNode, LogEdge, LogNodeData};
+use crate::graph::{LogNode, LogEdge, LogEdgeData, LogNodeData, NoteShape, NoteVisibility, DEFAULT_NOTE_RADIUS, MAX_NOTE_RADIUS, MIN_NOTE_RADIUS};
+use crate::graph;
+use crate::layout;
+use crate::storage::{self, LogGraph, SavedGraph, SavedViewport};
 
 /// Type alias for GraphView with our custom node/edge types and random layout
-type LogMarkGraphView<'a> = GraphView<'a, LogNodeData, (), Directed, u32, LogNode, LogEdge, LayoutStateRandom, LayoutRandom>;
+type LogMarkGraphView<'a> = GraphView<'a, LogNodeData, LogEdgeData, Directed, u32, LogNode, LogEdge, LayoutStateRandom, LayoutRandom>;
+
+/// Number of undo steps kept; older snapshots are dropped.
+const MAX_HISTORY: usize = 100;
+
+/// Note labels offered while typing a `[[wikilink]]` in the content editor
+struct LinkCompletion {
+    /// Char index just after the `[[` being completed
+    start: usize,
+    candidates: Vec<String>,
+    selected: usize,
+    /// A suggestion was clicked; it is inserted before the editor runs next frame
+    clicked: bool,
+    /// Where the popup was last shown. Pressing on it takes focus from the
+    /// editor, which must not close it before the click lands.
+    rect: egui::Rect,
+}
+
+/// Most labels listed in the wikilink suggestion popup.
+const MAX_LINK_SUGGESTIONS: usize = 8;
+
+/// Entries of the popup shown after typing `/`, with the markup each inserts.
+const SLASH_COMMANDS: [(&str, &str); 4] = [("Heading 1", "# "), ("Heading 2", "## "), ("Bullet", "- "), ("To-do", "- [ ] ")];
+
+/// Graph element a right-click context menu was opened on
+#[derive(Clone, Copy)]
+enum ContextTarget {
+    Node(NodeIndex),
+    Edge(EdgeIndex),
+}
 
 pub struct LogMarkApp {
-    graph: Graph<LogNodeData, (), Directed, u32, LogNode, LogEdge>,
+    graph: LogGraph,
     editing_label: Option<petgraph::stable_graph::NodeIndex>,
     label_edit_buffer: String,
+    // New label and the number of wikilinks to the old one, while the label
+    // editor asks whether to update them
+    pending_rename: Option<(String, usize)>,
     markdown_cache: CommonMarkCache,
     
     // For wikilinks detection
     wikilink_regex: Regex,
+    // For `#tag` detection; a `#` must start a word, so headings don't count
+    tag_regex: Regex,
+
+    // Only notes with this tag are drawn normally; the rest are dimmed, or hidden
+    tag_filter: Option<String>,
+    hide_filtered: bool,
 
     // Where to place the inline label editor (screen coords)
     editing_pos: Option<egui::Pos2>,
 
     // Sidebar collapsed/expanded
     sidebar_expanded: bool,
+
+    // File the graph was last saved to or loaded from
+    file_path: Option<PathBuf>,
+    // Result of the last save/load, shown in the sidebar
+    status: Option<String>,
+
+    // Element under the pointer when the context menu was opened
+    context_target: Option<ContextTarget>,
+
+    // Keep the whole graph in view; turned off once the view is moved to a node
+    fit_to_screen: bool,
+    // Node to centre the graph view on next frame
+    focus_request: Option<NodeIndex>,
+    // Canvas point to centre the graph view on next frame, from a minimap click
+    center_request: Option<egui::Pos2>,
+    // Note the keyboard last stepped away from; Tab cycles through its links
+    nav_origin: Option<NodeIndex>,
+    // Overview of the whole graph in the corner of the view
+    show_minimap: bool,
+    // Fit every visible note into the view next frame
+    zoom_to_fit_request: bool,
+    // Graph view camera as of the last frame, written out on save
+    viewport: Option<SavedViewport>,
+    // Canvas point at the middle of the graph view as of the last frame, where new notes go
+    view_center: egui::Pos2,
+    // Saved camera to restore next frame, after loading a file
+    viewport_request: Option<SavedViewport>,
+
+    // Let several notes be selected at once for the bulk actions in the sidebar
+    multi_select: bool,
+    // Tag typed into the bulk "Apply tag" box
+    bulk_tag: String,
+
+    // Remove untouched notes that were only created by a link once that link is deleted
+    remove_orphaned_links: bool,
+    // Create a note for a wikilink to a missing label; when off the link is
+    // left broken and highlighted, so typos don't spawn stray notes
+    auto_create_links: bool,
+
+    // Sort the sidebar's recent notes by creation rather than modification time
+    recent_by_created: bool,
+
+    // Sidebar search box text, and whether it is a regex rather than a plain substring
+    search_query: String,
+    search_regex: bool,
+
+    // Graph snapshots taken before each edit, most recent last
+    undo_stack: Vec<SavedGraph>,
+    redo_stack: Vec<SavedGraph>,
+    // Note whose content is being typed into; one snapshot covers the whole burst
+    content_edit_node: Option<NodeIndex>,
+
+    // Wikilink suggestions for the `[[` at the editor's cursor, and the `[[`
+    // whose popup was closed with Escape so it stays closed
+    link_completion: Option<LinkCompletion>,
+    link_completion_dismissed: Option<usize>,
 }
 
 impl LogMarkApp {
     pub fn new(_cc: &eframe::CreationContext<'_>) -> Self {
         let mut g = StableGraph::new();
         
-        let idx1 = g.add_node(LogNodeData { 
-            label: "Home".to_string(), 
-            content: "# Welcome to LogMark\n\nThis is a graph-based note taking app.\n\nTry adding a link like [[Ideas]]".to_string() 
-        });
-        let idx2 = g.add_node(LogNodeData { 
-            label: "Ideas".to_string(), 
-            content: "## My Ideas\n\n- [ ] Build a spaceship\n- [ ] Learn Rust".to_string() 
-        });
+        let idx1 = g.add_node(LogNodeData::new(
+            "Home".to_string(),
+            "# Welcome to LogMark\n\nThis is a graph-based note taking app.\n\nTry adding a link like [[Ideas]]".to_string(),
+        ));
+        let idx2 = g.add_node(LogNodeData::new(
+            "Ideas".to_string(),
+            "## My Ideas\n\n- [ ] Build a spaceship\n- [ ] Learn Rust".to_string(),
+        ));
         
-        g.add_edge(idx1, idx2, ());
+        g.add_edge(idx1, idx2, LogEdgeData::wikilink("Try adding a link like Ideas".to_string()));
 
         let mut graph = Graph::from(&g);
         
@@ -52,32 +154,938 @@ impl LogMarkApp {
             node.set_location(egui::Pos2::new(100.0, 100.0));
         }
 
+        Self::with_graph(graph)
+    }
+
+    fn with_graph(graph: LogGraph) -> Self {
         Self {
             graph,
             editing_label: None,
             label_edit_buffer: String::new(),
+            pending_rename: None,
             markdown_cache: CommonMarkCache::default(),
             wikilink_regex: Regex::new(r"\[\[(.*?)\]\]").unwrap(),
+            tag_regex: Regex::new(r"(?:^|\s)#([\w/-]+)").unwrap(),
+            tag_filter: None,
+            hide_filtered: false,
             editing_pos: None,
             sidebar_expanded: true,
+            file_path: None,
+            status: None,
+            context_target: None,
+            fit_to_screen: true,
+            focus_request: None,
+            center_request: None,
+            nav_origin: None,
+            show_minimap: true,
+            zoom_to_fit_request: false,
+            viewport: None,
+            view_center: egui::Pos2::ZERO,
+            viewport_request: None,
+            multi_select: false,
+            bulk_tag: String::new(),
+            remove_orphaned_links: true,
+            auto_create_links: true,
+            recent_by_created: false,
+            search_query: String::new(),
+            search_regex: false,
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            content_edit_node: None,
+            link_completion: None,
+            link_completion_dismissed: None,
+        }
+    }
+
+    /// Write all notes, their positions and links to `path` as JSON.
+    ///
+    /// # Errors
+    /// Fails if the file cannot be written.
+    pub fn save_to_path(&self, path: &Path) -> io::Result<()> {
+        let mut doc = SavedGraph::from_graph(&self.graph);
+        doc.viewport = self.viewport;
+        let json = serde_json::to_string_pretty(&doc)?;
+        fs::write(path, json)
+    }
+
+    /// Load a graph previously written by [`Self::save_to_path`].
+    ///
+    /// # Errors
+    /// Fails if the file cannot be read or is not a valid `LogMark` document.
+    pub fn load_from_path(path: &Path) -> io::Result<Self> {
+        let doc: SavedGraph = serde_json::from_str(&fs::read_to_string(path)?)?;
+        let viewport = doc.viewport;
+        let mut app = Self::with_graph(doc.into_graph()?);
+        app.file_path = Some(path.to_path_buf());
+        app.viewport_request = viewport;
+        Ok(app)
+    }
+
+    /// The graph in Graphviz DOT format, one statement per note and per link.
+    pub fn export_dot(&self) -> String {
+        let mut dot = String::from("digraph {\n");
+        for idx in self.graph.g().node_indices() {
+            if let Some(node) = self.graph.node(idx) {
+                let _ = writeln!(dot, "    n{} [label=\"{}\"];", idx.index(), dot_escape(&node.payload().label));
+            }
+        }
+        for edge in self.graph.g().edge_references() {
+            let label = &edge.weight().payload().label;
+            let attrs = if label.is_empty() { String::new() } else { format!(" [label=\"{}\"]", dot_escape(label)) };
+            let _ = writeln!(dot, "    n{} -> n{}{attrs};", edge.source().index(), edge.target().index());
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Ask for a file and write [`Self::export_dot`] to it.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn export_dot_to_file(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("Graphviz DOT", &["dot", "gv"])
+            .set_file_name("notes.dot")
+            .save_file()
+        else {
+            return;
+        };
+        self.status = Some(match fs::write(&path, self.export_dot()) {
+            Ok(()) => format!("Exported {}", path.display()),
+            Err(e) => format!("Export failed: {e}"),
+        });
+    }
+
+    /// Ask for a folder and write every note into it as a Markdown file.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn export_vault_to_folder(&mut self) {
+        let Some(dir) = rfd::FileDialog::new().set_title("Export vault").pick_folder() else {
+            return;
+        };
+        self.status = Some(match storage::export_vault(&self.graph, &dir, &self.wikilink_regex) {
+            Ok(count) => format!("Exported {count} notes to {}", dir.display()),
+            Err(e) => format!("Export failed: {e}"),
+        });
+    }
+
+    /// Ask for a folder of Markdown files and replace the graph with one note
+    /// per file, linked up by their wikilinks. The import is one undo step.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn import_vault_from_folder(&mut self) {
+        let Some(dir) = rfd::FileDialog::new().set_title("Import vault").pick_folder() else {
+            return;
+        };
+        match storage::read_vault(&dir) {
+            Ok(notes) => {
+                let count = notes.len();
+                self.load_vault(notes);
+                self.status = Some(format!("Imported {count} notes from {}", dir.display()));
+            }
+            Err(e) => self.status = Some(format!("Import failed: {e}")),
+        }
+    }
+
+    /// Replace the graph with one note per `(label, content)` pair, keeping
+    /// settings and undo history. Every note exists before links are
+    /// resolved, so a wikilink to a later note finds it rather than creating
+    /// a stub.
+    fn load_vault(&mut self, notes: Vec<(String, String)>) {
+        self.record_history();
+        self.graph = Graph::from(&StableGraph::default());
+        self.clear_graph_state();
+        let indices: Vec<NodeIndex> = notes
+            .into_iter()
+            .map(|(label, content)| self.graph.add_node(LogNodeData::new(label, content)))
+            .collect();
+        // Start from a circle; the force layout below untangles it
+        let spread = 40.0 * indices.len() as f32;
+        for (i, &idx) in indices.iter().enumerate() {
+            let angle = i as f32 / indices.len() as f32 * std::f32::consts::TAU;
+            if let Some(node) = self.graph.node_mut(idx) {
+                node.set_location(egui::Pos2::new(angle.cos(), angle.sin()) * spread);
+            }
+        }
+        for &idx in &indices {
+            self.update_tags(idx);
+            self.handle_wikilinks(idx);
+        }
+        self.auto_layout();
+        self.zoom_to_fit_request = true;
+    }
+
+    /// Spread the notes out with a force-directed simulation, starting from
+    /// their current positions.
+    fn auto_layout(&mut self) {
+        let indices: Vec<NodeIndex> = self.graph.g().node_indices().collect();
+        self.layout_notes(&indices);
+    }
+
+    /// Force-directed layout of just `indices` and the links among them,
+    /// centred where they already were. Other notes stay put.
+    fn layout_notes(&mut self, indices: &[NodeIndex]) {
+        let positions: Vec<egui::Pos2> = indices
+            .iter()
+            .filter_map(|&idx| self.graph.node(idx).map(egui_graphs::Node::location))
+            .collect();
+        let slot = |idx: NodeIndex| indices.iter().position(|&i| i == idx);
+        let edges: Vec<(usize, usize)> = self
+            .graph
+            .g()
+            .edge_references()
+            .filter_map(|e| Some((slot(e.source())?, slot(e.target())?)))
+            .collect();
+
+        let laid_out = layout::fruchterman_reingold(&positions, &edges, layout::ITERATIONS);
+        for (&idx, pos) in indices.iter().zip(laid_out) {
+            if let Some(node) = self.graph.node_mut(idx) {
+                node.set_location(pos);
+            }
+        }
+    }
+
+    /// Snapshot the graph before an edit so it can be undone. Any redo history
+    /// is discarded, since it no longer follows from the current state.
+    fn record_history(&mut self) {
+        self.undo_stack.push(SavedGraph::from_graph(&self.graph));
+        if self.undo_stack.len() > MAX_HISTORY {
+            self.undo_stack.remove(0);
+        }
+        self.redo_stack.clear();
+        self.content_edit_node = None;
+    }
+
+    fn undo(&mut self) {
+        if let Some(snapshot) = self.undo_stack.pop() {
+            let current = SavedGraph::from_graph(&self.graph);
+            if self.restore(snapshot) {
+                self.redo_stack.push(current);
+            }
+        }
+    }
+
+    fn redo(&mut self) {
+        if let Some(snapshot) = self.redo_stack.pop() {
+            let current = SavedGraph::from_graph(&self.graph);
+            if self.restore(snapshot) {
+                self.undo_stack.push(current);
+            }
+        }
+    }
+
+    /// Replace the graph with `snapshot`. Node indices are renumbered, so
+    /// anything holding on to one is reset.
+    fn restore(&mut self, snapshot: SavedGraph) -> bool {
+        let Ok(graph) = snapshot.into_graph() else {
+            return false;
+        };
+        self.graph = graph;
+        self.clear_graph_state();
+        true
+    }
+
+    /// Drop editor and navigation state that points at nodes of a replaced
+    /// graph.
+    fn clear_graph_state(&mut self) {
+        self.editing_label = None;
+        self.editing_pos = None;
+        self.pending_rename = None;
+        self.context_target = None;
+        self.focus_request = None;
+        self.center_request = None;
+        self.nav_origin = None;
+        self.content_edit_node = None;
+    }
+
+    /// Radius slider and shape picker for the selected note. A slider drag is
+    /// a single undo step.
+    fn note_style_ui(&mut self, ui: &mut egui::Ui, idx: NodeIndex) {
+        let Some(node) = self.graph.node(idx) else { return };
+        let (mut radius, mut shape) = (node.payload().radius, node.payload().shape);
+        let mut edited = false;
+        ui.horizontal(|ui| {
+            ui.label("Size:");
+            let response = ui.add(egui::Slider::new(&mut radius, MIN_NOTE_RADIUS..=MAX_NOTE_RADIUS));
+            if response.drag_started() || (response.changed() && !response.dragged()) {
+                edited = true;
+            }
+            egui::ComboBox::from_id_salt("note_shape")
+                .selected_text(shape.label())
+                .show_ui(ui, |ui| {
+                    for option in NoteShape::ALL {
+                        edited |= ui.selectable_value(&mut shape, option, option.label()).changed();
+                    }
+                });
+        });
+        if edited {
+            self.record_history();
+        }
+        if let Some(node) = self.graph.node_mut(idx) {
+            let payload = node.payload_mut();
+            payload.radius = radius;
+            payload.shape = shape;
+        }
+    }
+
+    /// Scaled-down overview of every visible note in the bottom-right corner of
+    /// `view`, outlining the part of the canvas on screen. Clicking or dragging
+    /// in it centres the view on that point.
+    fn minimap_ui(&mut self, ui: &mut egui::Ui, view: egui::Rect) {
+        const MINIMAP_SIZE: egui::Vec2 = egui::vec2(180.0, 120.0);
+        const MINIMAP_MARGIN: f32 = 10.0;
+
+        let Some(bounds) = self.visible_bounds() else {
+            return;
+        };
+        let meta = MetadataFrame::new(None).load(ui);
+        let to_canvas = |screen: egui::Vec2| ((screen - meta.pan) / meta.zoom).to_pos2();
+        let viewport = egui::Rect::from_min_max(to_canvas(egui::Vec2::ZERO), to_canvas(view.size()));
+        // Keep the viewport outline inside the map even when scrolled away from the notes
+        let world = bounds.union(viewport).expand(20.0);
+
+        let frame = egui::Rect::from_min_size(view.max - MINIMAP_SIZE - egui::Vec2::splat(MINIMAP_MARGIN), MINIMAP_SIZE);
+        let scale = (frame.width() / world.width()).min(frame.height() / world.height());
+        let offset = frame.center() - world.center().to_vec2() * scale;
+        let to_map = |p: egui::Pos2| offset + p.to_vec2() * scale;
+
+        let response = ui.interact(frame, ui.id().with("minimap"), egui::Sense::click_and_drag());
+        if response.clicked() || response.dragged() {
+            if let Some(pos) = response.interact_pointer_pos() {
+                self.center_request = Some(((pos - offset) / scale).to_pos2());
+                self.fit_to_screen = false;
+            }
+        }
+
+        let painter = ui.painter_at(frame);
+        painter.rect_filled(frame, 4.0, egui::Color32::from_black_alpha(180));
+        let g = self.graph.g();
+        let shown = |idx: NodeIndex| self.graph.node(idx).map(|n| n.payload().visibility).filter(|v| *v != NoteVisibility::Hidden);
+        for edge in g.edge_references() {
+            let ends = [edge.source(), edge.target()].map(|idx| shown(idx).and(self.graph.node(idx)).map(|n| to_map(n.location())));
+            if let [Some(a), Some(b)] = ends {
+                painter.line_segment([a, b], egui::Stroke::new(1.0, egui::Color32::from_gray(90)));
+            }
+        }
+        for idx in g.node_indices() {
+            let (Some(visibility), Some(node)) = (shown(idx), self.graph.node(idx)) else {
+                continue;
+            };
+            let data = node.payload();
+            let color = data.tags.first().map_or(egui::Color32::from_rgb(100, 150, 200), |t| crate::graph::tag_color(t));
+            let color = if visibility == NoteVisibility::Dimmed { color.gamma_multiply(0.25) } else { color };
+            painter.circle_filled(to_map(node.location()), (data.radius * scale).max(1.5), color);
+        }
+        let outline = egui::Rect::from_min_max(to_map(viewport.min), to_map(viewport.max));
+        painter.rect_stroke(outline, 0.0, egui::Stroke::new(1.0, egui::Color32::WHITE), egui::StrokeKind::Inside);
+    }
+
+    /// Make `idx` the only selected node and centre the graph view on it.
+    fn focus_node(&mut self, idx: NodeIndex) {
+        for other in self.graph.selected_nodes().to_vec() {
+            if let Some(node) = self.graph.node_mut(other) {
+                node.set_selected(false);
+            }
+        }
+        if let Some(node) = self.graph.node_mut(idx) {
+            node.set_selected(true);
+        }
+        self.graph.set_selected_nodes(vec![idx]);
+        self.focus_request = Some(idx);
+        self.fit_to_screen = false;
+    }
+
+    /// Notes linked from (`Outgoing`) or to (`Incoming`) `idx`, skipping itself
+    /// and notes hidden by the tag filter, in a stable order.
+    fn linked_notes(&self, idx: NodeIndex, direction: Direction) -> Vec<NodeIndex> {
+        let mut notes: Vec<NodeIndex> = self
+            .graph
+            .g()
+            .neighbors_directed(idx, direction)
+            .filter(|&n| n != idx && self.graph.node(n).is_some_and(|n| n.payload().visibility != NoteVisibility::Hidden))
+            .collect();
+        notes.sort();
+        notes.dedup();
+        notes
+    }
+
+    /// Walk the graph from the single selected note with the keyboard: Right
+    /// follows its first link, Left goes back to the note it was reached from
+    /// (or its first backlink), and Tab / Shift+Tab or Down / Up step to the
+    /// next or previous link of that origin note.
+    fn handle_graph_navigation(&mut self, ctx: &Context) {
+        let &[current] = self.graph.selected_nodes() else {
+            return;
+        };
+        let (forward, back, next, previous) = ctx.input_mut(|i| {
+            (
+                i.consume_key(egui::Modifiers::NONE, Key::ArrowRight),
+                i.consume_key(egui::Modifiers::NONE, Key::ArrowLeft),
+                i.consume_key(egui::Modifiers::NONE, Key::Tab) || i.consume_key(egui::Modifiers::NONE, Key::ArrowDown),
+                i.consume_key(egui::Modifiers::SHIFT, Key::Tab) || i.consume_key(egui::Modifiers::NONE, Key::ArrowUp),
+            )
+        });
+        // The origin only counts while the selection is still one of its links
+        let origin = self.nav_origin.filter(|&o| self.linked_notes(o, Direction::Outgoing).contains(&current));
+        let step_in = || self.linked_notes(current, Direction::Outgoing).first().map(|&n| (n, Some(current)));
+        let target = if forward {
+            step_in()
+        } else if back {
+            origin
+                .or_else(|| self.linked_notes(current, Direction::Incoming).first().copied())
+                .map(|n| (n, None))
+        } else if next || previous {
+            match origin {
+                Some(origin) => {
+                    let siblings = self.linked_notes(origin, Direction::Outgoing);
+                    let at = siblings.iter().position(|&n| n == current).unwrap_or(0);
+                    let step = if next { 1 } else { siblings.len() - 1 };
+                    Some((siblings[(at + step) % siblings.len()], Some(origin)))
+                }
+                // Nothing to cycle yet, so Tab steps into the note's own links
+                None => step_in(),
+            }
+        } else {
+            None
+        };
+        if let Some((idx, origin)) = target {
+            self.focus_node(idx);
+            self.nav_origin = origin;
+        }
+    }
+
+    /// Canvas-space box around every note that isn't hidden by the tag filter,
+    /// including the node circles themselves.
+    fn visible_bounds(&self) -> Option<egui::Rect> {
+        self.graph
+            .g()
+            .node_weights()
+            .filter(|n| n.payload().visibility != NoteVisibility::Hidden)
+            .map(|n| egui::Rect::from_center_size(n.location(), egui::Vec2::splat(2.0 * n.display().radius)))
+            .reduce(egui::Rect::union)
+    }
+
+    /// Labels of the notes linking to `idx`, one entry per source note.
+    fn backlinks(&self, idx: NodeIndex) -> Vec<(NodeIndex, String)> {
+        let mut links: Vec<(NodeIndex, String)> = Vec::new();
+        for edge in self.graph.g().edges_directed(idx, Direction::Incoming) {
+            let source = edge.source();
+            if source == idx || links.iter().any(|(i, _)| *i == source) {
+                continue;
+            }
+            if let Some(node) = self.graph.node(source) {
+                links.push((source, node.payload().label.clone()));
+            }
+        }
+        links
+    }
+
+    /// Notes whose label or content matches the search box, each with a
+    /// short snippet of text around the first match.
+    ///
+    /// Plain queries match case-insensitively; regex mode uses the pattern as
+    /// written (prefix it with `(?i)` to ignore case).
+    fn search(&self) -> Result<Vec<(NodeIndex, String, String)>, regex::Error> {
+        let query = if self.search_regex {
+            self.search_query.clone()
+        } else {
+            format!("(?i){}", regex::escape(&self.search_query))
+        };
+        let pattern = Regex::new(&query)?;
+
+        let mut results = Vec::new();
+        for idx in self.graph.g().node_indices() {
+            let Some(node) = self.graph.node(idx) else {
+                continue;
+            };
+            let data = node.payload();
+            if let Some(m) = pattern.find(&data.content) {
+                results.push((idx, data.label.clone(), snippet(&data.content, m.start(), m.end())));
+            } else if pattern.is_match(&data.label) {
+                results.push((idx, data.label.clone(), String::new()));
+            }
+        }
+        Ok(results)
+    }
+
+    fn search_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.add(TextEdit::singleline(&mut self.search_query).hint_text("Search notes…"));
+            ui.checkbox(&mut self.search_regex, "Regex");
+        });
+        if self.search_query.is_empty() {
+            return;
+        }
+
+        match self.search() {
+            Ok(results) if results.is_empty() => {
+                ui.label("No matches.");
+            }
+            Ok(results) => {
+                egui::ScrollArea::vertical().id_salt("search").max_height(150.0).show(ui, |ui| {
+                    for (idx, label, snippet) in results {
+                        if ui.link(label).clicked() {
+                            self.focus_node(idx);
+                        }
+                        if !snippet.is_empty() {
+                            ui.small(snippet);
+                        }
+                    }
+                });
+            }
+            Err(e) => {
+                ui.colored_label(ui.visuals().error_fg_color, format!("Invalid regex: {e}"));
+            }
+        }
+        ui.separator();
+    }
+
+    /// Sidebar shown instead of the note editor while several notes are
+    /// selected: a summary and the bulk actions.
+    fn selection_ui(&mut self, ui: &mut egui::Ui, selected: &[NodeIndex]) {
+        ui.heading(format!("{} nodes selected", selected.len()));
+        ui.add_space(5.0);
+
+        if ui.button("🗑 Delete all").clicked() {
+            self.record_history();
+            self.delete_nodes(selected);
+            self.status = Some(format!("Deleted {} notes", selected.len()));
+            return;
+        }
+
+        if ui.button("Layout selection").on_hover_text("Auto-layout just these notes and the links between them").clicked() {
+            self.record_history();
+            self.layout_notes(selected);
+        }
+
+        ui.add_space(5.0);
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.bulk_tag)
+                    .hint_text("tag")
+                    .desired_width(100.0),
+            );
+            let tag = self.bulk_tag.trim().trim_start_matches('#').to_string();
+            let valid = !tag.is_empty() && tag.chars().all(|c| c.is_alphanumeric() || matches!(c, '_' | '/' | '-'));
+            if ui.add_enabled(valid, egui::Button::new("Apply tag")).clicked() {
+                self.record_history();
+                let mut tagged = 0;
+                for &idx in selected {
+                    let Some(node) = self.graph.node_mut(idx) else {
+                        continue;
+                    };
+                    if node.payload().tags.contains(&tag) {
+                        continue;
+                    }
+                    let content = &mut node.payload_mut().content;
+                    if !content.is_empty() && !content.ends_with(char::is_whitespace) {
+                        content.push(' ');
+                    }
+                    content.push('#');
+                    content.push_str(&tag);
+                    node.payload_mut().touch();
+                    self.update_tags(idx);
+                    tagged += 1;
+                }
+                self.status = Some(format!("Tagged {tagged} notes with #{tag}"));
+                self.bulk_tag.clear();
+            }
+        });
+    }
+
+    /// Collapsible note and link counts, with the most linked-to notes as
+    /// links that select them.
+    fn stats_ui(&mut self, ui: &mut egui::Ui) {
+        const TOP_LINKED: usize = 5;
+
+        egui::CollapsingHeader::new("Stats").show(ui, |ui| {
+            let g = self.graph.g();
+            let orphans = g.node_indices().filter(|&idx| g.neighbors_undirected(idx).next().is_none()).count();
+            ui.label(format!("Notes: {}", g.node_count()));
+            ui.label(format!("Links: {}", g.edge_count()));
+            ui.label(format!("Orphans: {orphans}"));
+
+            let mut linked: Vec<(NodeIndex, usize)> = g
+                .node_indices()
+                .map(|idx| (idx, g.edges_directed(idx, Direction::Incoming).count()))
+                .filter(|&(_, count)| count > 0)
+                .collect();
+            linked.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+            linked.truncate(TOP_LINKED);
+            if linked.is_empty() {
+                return;
+            }
+            ui.label("Most linked:");
+            for (idx, count) in linked {
+                let Some(label) = self.graph.node(idx).map(|n| n.payload().label.clone()) else {
+                    continue;
+                };
+                if ui.link(format!("{label} ({count})")).clicked() {
+                    self.focus_node(idx);
+                }
+            }
+        });
+    }
+
+    /// Collapsible list of the most recently modified (or created) notes, as
+    /// links that select them.
+    fn recent_ui(&mut self, ui: &mut egui::Ui) {
+        const RECENT_NOTES: usize = 10;
+
+        egui::CollapsingHeader::new("Recent").show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("By:");
+                ui.selectable_value(&mut self.recent_by_created, false, "Modified");
+                ui.selectable_value(&mut self.recent_by_created, true, "Created");
+            });
+            let stamp = |data: &LogNodeData| if self.recent_by_created { data.created } else { data.modified };
+            let mut recent: Vec<(NodeIndex, String, u64)> = self
+                .graph
+                .g()
+                .node_indices()
+                .filter_map(|idx| self.graph.node(idx).map(|n| (idx, n.payload().label.clone(), stamp(n.payload()))))
+                .collect();
+            recent.sort_by(|a, b| b.2.cmp(&a.2).then(a.0.cmp(&b.0)));
+            recent.truncate(RECENT_NOTES);
+            let now = graph::now_secs();
+            for (idx, label, time) in recent {
+                ui.horizontal(|ui| {
+                    if ui.link(label).clicked() {
+                        self.focus_node(idx);
+                    }
+                    ui.weak(format_age(now, time));
+                });
+            }
+        });
+    }
+
+    /// Arrow keys, Enter/Tab and Escape for the wikilink suggestion popup, or
+    /// a clicked suggestion. Returns whether a label was inserted into `content`.
+    fn link_completion_input(&mut self, ui: &egui::Ui, editor_id: egui::Id, content: &mut String) -> bool {
+        let Some(completion) = &mut self.link_completion else {
+            return false;
+        };
+        let mut accept = std::mem::take(&mut completion.clicked);
+        if !accept && !ui.memory(|m| m.has_focus(editor_id)) {
+            return false;
+        }
+        let (down, up, enter, escape) = ui.input_mut(|i| {
+            (
+                i.consume_key(egui::Modifiers::NONE, Key::ArrowDown),
+                i.consume_key(egui::Modifiers::NONE, Key::ArrowUp),
+                i.consume_key(egui::Modifiers::NONE, Key::Enter) || i.consume_key(egui::Modifiers::NONE, Key::Tab),
+                i.consume_key(egui::Modifiers::NONE, Key::Escape),
+            )
+        });
+        let count = completion.candidates.len();
+        if down {
+            completion.selected = (completion.selected + 1) % count;
+        }
+        if up {
+            completion.selected = (completion.selected + count - 1) % count;
+        }
+        if escape {
+            self.link_completion_dismissed = Some(completion.start);
+            self.link_completion = None;
+            return false;
+        }
+        accept |= enter;
+        if !accept {
+            return false;
+        }
+
+        // Replace the typed query with the label, closing the link unless it already is
+        let label = completion.candidates[completion.selected].clone();
+        let start = completion.start;
+        self.link_completion = None;
+        let mut state = TextEdit::load_state(ui.ctx(), editor_id).unwrap_or_default();
+        let cursor = state.cursor.char_range().map_or(start, |r| r.primary.index).max(start);
+        let byte = |char_index: usize| content.char_indices().nth(char_index).map_or(content.len(), |(b, _)| b);
+        let (from, to) = (byte(start), byte(cursor));
+        let closing = if content[to..].starts_with("]]") { "" } else { "]]" };
+        content.replace_range(from..to, &format!("{label}{closing}"));
+        let end = start + label.chars().count() + 2;
+        state.cursor.set_char_range(Some(egui::text_selection::CCursorRange::one(egui::text::CCursor::new(end))));
+        state.store(ui.ctx(), editor_id);
+        ui.memory_mut(|m| m.request_focus(editor_id));
+        true
+    }
+
+    /// Work out which labels to suggest for the `[[` before the editor's
+    /// cursor, or close the popup once the cursor has left the link.
+    fn update_link_completion(&mut self, idx: NodeIndex, content: &str, output: &egui::text_edit::TextEditOutput) {
+        let over_popup = self.link_completion.as_ref().is_some_and(|c| {
+            output.response.ctx.pointer_hover_pos().is_some_and(|p| c.rect.contains(p))
+        });
+        let cursor = output
+            .cursor_range
+            .filter(|r| (output.response.has_focus() || over_popup) && r.primary == r.secondary)
+            .map(|r| r.primary.index);
+        let Some((start, query)) = cursor.and_then(|c| wikilink_query(content, c)) else {
+            self.link_completion = None;
+            self.link_completion_dismissed = None;
+            return;
+        };
+        if self.link_completion_dismissed == Some(start) {
+            return;
+        }
+
+        // Labels containing the query, those starting with it first
+        let query = query.to_lowercase();
+        let mut candidates: Vec<String> = self
+            .graph
+            .g()
+            .node_indices()
+            .filter(|&i| i != idx)
+            .filter_map(|i| self.graph.node(i).map(|n| n.payload().label.clone()))
+            .filter(|label| label.to_lowercase().contains(&query))
+            .collect();
+        candidates.sort_by_key(|label| (!label.to_lowercase().starts_with(&query), label.to_lowercase()));
+        candidates.dedup();
+        candidates.truncate(MAX_LINK_SUGGESTIONS);
+        if candidates.is_empty() {
+            self.link_completion = None;
+            return;
+        }
+
+        let previous = self.link_completion.take().filter(|c| c.start == start);
+        let selected = previous.as_ref().and_then(|c| candidates.iter().position(|l| *l == c.candidates[c.selected]));
+        let rect = previous.map_or(egui::Rect::NOTHING, |c| c.rect);
+        self.link_completion = Some(LinkCompletion { start, candidates, selected: selected.unwrap_or(0), clicked: false, rect });
+    }
+
+    /// Suggestion list just below the editor's cursor.
+    fn link_completion_popup(&mut self, ctx: &Context, output: &egui::text_edit::TextEditOutput) {
+        let (Some(completion), Some(cursor)) = (&mut self.link_completion, output.cursor_range) else {
+            return;
+        };
+        let pos = output.galley_pos + output.galley.pos_from_cursor(cursor.primary).left_bottom().to_vec2();
+        completion.rect = egui::Area::new(egui::Id::new("wikilink_popup"))
+            .order(egui::Order::Foreground)
+            .fixed_pos(pos)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    for (i, label) in completion.candidates.iter().enumerate() {
+                        if ui.selectable_label(i == completion.selected, label).clicked() {
+                            completion.selected = i;
+                            completion.clicked = true;
+                        }
+                    }
+                });
+            })
+            .response
+            .rect;
+    }
+
+    /// Remove nodes together with their incident edges. Wikilink text in other
+    /// notes is left as written; only the auto-created edges go away.
+    fn delete_nodes(&mut self, nodes: &[NodeIndex]) {
+        for &idx in nodes {
+            self.graph.remove_node(idx);
+            if self.editing_label == Some(idx) {
+                self.editing_label = None;
+                self.editing_pos = None;
+            }
+        }
+        self.prune_selection();
+    }
+
+    fn delete_edge(&mut self, edge: EdgeIndex) {
+        self.graph.remove_edge(edge);
+        self.prune_selection();
+    }
+
+    /// Drop selected indices that no longer exist, so nothing dereferences a
+    /// removed (or later reused) index.
+    fn prune_selection(&mut self) {
+        let nodes = self.graph.selected_nodes().iter().copied().filter(|&n| self.graph.node(n).is_some()).collect();
+        let edges = self.graph.selected_edges().iter().copied().filter(|&e| self.graph.edge(e).is_some()).collect();
+        self.graph.set_selected_nodes(nodes);
+        self.graph.set_selected_edges(edges);
+    }
+
+    /// Ctrl+S saves (asking for a file the first time), Ctrl+O opens.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn handle_file_shortcuts(&mut self, ctx: &Context) {
+        let (save, open) = ctx.input_mut(|i| {
+            (
+                i.consume_key(egui::Modifiers::COMMAND, Key::S),
+                i.consume_key(egui::Modifiers::COMMAND, Key::O),
+            )
+        });
+
+        if save {
+            self.save_file();
+        }
+        if open {
+            self.open_file();
+        }
+    }
+
+    /// Save to the current file, asking for one the first time.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save_file(&mut self) {
+        let path = self.file_path.clone().or_else(|| {
+            rfd::FileDialog::new()
+                .add_filter("LogMark graph", &["json"])
+                .set_file_name("notes.json")
+                .save_file()
+        });
+        if let Some(path) = path {
+            self.status = Some(match self.save_to_path(&path) {
+                Ok(()) => format!("Saved {}", path.display()),
+                Err(e) => format!("Save failed: {e}"),
+            });
+            self.file_path = Some(path);
+        }
+    }
+
+    /// Ask for a graph file and replace the current graph with it.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn open_file(&mut self) {
+        if let Some(path) = rfd::FileDialog::new()
+            .add_filter("LogMark graph", &["json"])
+            .pick_file()
+        {
+            match Self::load_from_path(&path) {
+                Ok(app) => {
+                    *self = app;
+                    self.status = Some(format!("Opened {}", path.display()));
+                }
+                Err(e) => self.status = Some(format!("Open failed: {e}")),
+            }
+        }
+    }
+
+    /// Add an empty note called "Untitled N" (the lowest free N) in the
+    /// middle of the view, select it and open its label for editing.
+    fn new_note(&mut self) {
+        self.record_history();
+        let labels: HashSet<String> = self.graph.g().node_weights().map(|n| n.payload().label.clone()).collect();
+        // One more candidate than there are notes, so at least one is free
+        let label = (1..=labels.len() + 1)
+            .map(|n| format!("Untitled {n}"))
+            .find(|l| !labels.contains(l))
+            .unwrap();
+        // Step off any note already sitting at the spot
+        let mut pos = self.view_center;
+        while self.graph.g().node_weights().any(|n| n.location().distance(pos) < n.display().radius) {
+            pos += egui::Vec2::splat(DEFAULT_NOTE_RADIUS);
+        }
+        let idx = self.graph.add_node(LogNodeData::new(label.clone(), String::new()));
+        self.graph.node_mut(idx).unwrap().set_location(pos);
+        self.focus_node(idx);
+        self.editing_label = Some(idx);
+        self.editing_pos = None;
+        self.label_edit_buffer = label;
+    }
+
+    /// New note and file buttons along the top of the window.
+    fn toolbar_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if ui.button("➕ New Note").clicked() {
+                self.new_note();
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                ui.separator();
+                if ui.button("💾 Save").on_hover_text("Ctrl+S").clicked() {
+                    self.save_file();
+                }
+                if ui.button("📂 Open").on_hover_text("Ctrl+O").clicked() {
+                    self.open_file();
+                }
+                ui.separator();
+                if ui.button("Export DOT").clicked() {
+                    self.export_dot_to_file();
+                }
+                if ui.button("Export Vault").on_hover_text("Write each note to a Markdown file").clicked() {
+                    self.export_vault_to_folder();
+                }
+                if ui.button("Import Vault").on_hover_text("Replace the graph with a folder of Markdown files").clicked() {
+                    self.import_vault_from_folder();
+                }
+            }
+        });
+    }
+
+    /// Re-read the `#tag` tokens in a note's content.
+    fn update_tags(&mut self, node_idx: NodeIndex) {
+        let Some(node) = self.graph.node_mut(node_idx) else {
+            return;
+        };
+        let mut tags: Vec<String> = Vec::new();
+        for cap in self.tag_regex.captures_iter(&node.payload().content) {
+            let tag = cap[1].to_string();
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+        node.payload_mut().tags = tags;
+    }
+
+    /// Every tag used by any note, sorted.
+    fn all_tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = self
+            .graph
+            .g()
+            .node_weights()
+            .flat_map(|n| n.payload().tags.iter().cloned())
+            .collect();
+        tags.sort();
+        tags.dedup();
+        tags
+    }
+
+    /// Mark each note shown, dimmed or hidden according to the tag filter.
+    fn apply_tag_filter(&mut self) {
+        let indices: Vec<NodeIndex> = self.graph.g().node_indices().collect();
+        for idx in indices {
+            let Some(node) = self.graph.node_mut(idx) else {
+                continue;
+            };
+            let data = node.payload_mut();
+            data.visibility = match &self.tag_filter {
+                Some(tag) if !data.tags.contains(tag) => {
+                    if self.hide_filtered { NoteVisibility::Hidden } else { NoteVisibility::Dimmed }
+                }
+                _ => NoteVisibility::Shown,
+            };
+        }
+    }
+
+    /// Flag edges that have a partner running the other way, so both are
+    /// drawn curved instead of on top of each other.
+    fn mark_reciprocal_edges(&mut self) {
+        let edges: Vec<EdgeIndex> = self.graph.g().edge_indices().collect();
+        for edge in edges {
+            let Some((a, b)) = self.graph.g().edge_endpoints(edge) else {
+                continue;
+            };
+            let reciprocal = a != b && self.graph.g().find_edge(b, a).is_some();
+            if let Some(e) = self.graph.edge_mut(edge) {
+                e.payload_mut().reciprocal = reciprocal;
+            }
         }
     }
 
     fn handle_wikilinks(&mut self, node_idx: petgraph::stable_graph::NodeIndex) {
         let content = self.graph.node(node_idx).unwrap().payload().content.clone();
         
-        // Find all wikilinks
+        // Find all wikilinks, with the alias or surrounding sentence they label their edge with
         let mut new_links = Vec::new();
         for cap in self.wikilink_regex.captures_iter(&content) {
-            if let Some(m) = cap.get(1) {
-                new_links.push(m.as_str().to_string());
+            if let (Some(whole), Some(m)) = (cap.get(0), cap.get(1)) {
+                let (target, alias) = split_wikilink(m.as_str());
+                let edge_label = match alias {
+                    Some(alias) => alias.to_string(),
+                    None => self.link_sentence(&content, whole.start(), whole.end()),
+                };
+                new_links.push((target.to_string(), edge_label));
             }
         }
 
         // For each link, ensure a node exists and an edge exists
         let mut target_indices = Vec::new();
+        let mut edge_labels = Vec::new();
         
-        for link_label in new_links {
+        for (link_label, edge_label) in new_links {
             // Check if node exists
             let mut target_idx = None;
             for idx in self.graph.g().node_indices() {
@@ -89,198 +1097,779 @@ impl LogMarkApp {
 
             let target_idx = match target_idx {
                 Some(idx) => idx,
-                None => {
-                    // Create new node
-                    let new_node_data = LogNodeData {
-                        label: link_label.clone(),
-                        content: format!("# {}", link_label),
-                    };
-                    let idx = self.graph.add_node(new_node_data);
-                    // Position it somewhere near the source (randomly or fixed offset for now)
-                    // In a real app, we'd run a layout algorithm or place it smarter
-                    let source_pos = self.graph.node(node_idx).unwrap().location();
-                    self.graph.node_mut(idx).unwrap().set_location(source_pos + egui::Vec2::new(50.0, 50.0));
-                    idx
-                }
+                None if self.auto_create_links => self.create_note_near(node_idx, &link_label),
+                // Left dangling; the editor highlights it and offers to create the note
+                None => continue,
             };
             target_indices.push(target_idx);
+            edge_labels.push(edge_label);
         }
 
-        // Sync edges: Add edges that don't exist
-        for target_idx in target_indices {
+        // Sync edges: Add edges that don't exist, and relabel the ones that do.
+        // A target linked more than once keeps the first link's label.
+        for (i, &target_idx) in target_indices.iter().enumerate() {
             if target_idx == node_idx { continue; } // Don't link to self for now
+            if target_indices[..i].contains(&target_idx) { continue; }
             
-            let mut edge_exists = false;
-            for edge in self.graph.g().edges(node_idx) {
-                if edge.target() == target_idx {
-                    edge_exists = true;
-                    break;
+            let existing = self.graph.g().edges(node_idx).find(|e| e.target() == target_idx).map(|e| e.id());
+            match existing {
+                Some(edge) => {
+                    if let Some(e) = self.graph.edge_mut(edge) {
+                        if e.payload().auto && e.payload().label != edge_labels[i] {
+                            e.payload_mut().label.clone_from(&edge_labels[i]);
+                        }
+                    }
+                }
+                None => {
+                    self.graph.add_edge(node_idx, target_idx, LogEdgeData::wikilink(edge_labels[i].clone()));
                 }
             }
+        }
+
+        // Remove wikilink edges whose link is no longer in the content
+        let stale: Vec<(EdgeIndex, NodeIndex)> = self
+            .graph
+            .g()
+            .edges(node_idx)
+            .filter(|e| e.weight().payload().auto && !target_indices.contains(&e.target()))
+            .map(|e| (e.id(), e.target()))
+            .collect();
+        for &(edge, _) in &stale {
+            self.graph.remove_edge(edge);
+        }
 
-            if !edge_exists {
-                self.graph.add_edge(node_idx, target_idx, ());
+        if self.remove_orphaned_links {
+            let orphans: Vec<NodeIndex> = stale
+                .into_iter()
+                .map(|(_, target)| target)
+                .filter(|&target| self.is_untouched_orphan(target))
+                .collect();
+            self.delete_nodes(&orphans);
+        } else {
+            self.prune_selection();
+        }
+    }
+
+    /// Wikilinks across all notes whose target is `label`.
+    fn count_wikilinks_to(&self, label: &str) -> usize {
+        self.graph
+            .g()
+            .node_weights()
+            .map(|n| rename_wikilinks(&self.wikilink_regex, &n.payload().content, label, label).1)
+            .sum()
+    }
+
+    /// Whether a note other than `idx` is called `label`.
+    fn label_taken(&self, idx: NodeIndex, label: &str) -> bool {
+        self.graph.g().node_indices().any(|other| other != idx && self.graph.node(other).is_some_and(|n| n.payload().label == label))
+    }
+
+    /// Give `idx` the label `new_label` and close the label editor. With
+    /// `update_links`, every `[[old]]` and `[[old|alias]]` in the notes is
+    /// rewritten to the new label and those notes' links are reconciled.
+    /// A label another note already has is refused, since links could no
+    /// longer tell the two apart.
+    fn rename_note(&mut self, idx: NodeIndex, new_label: &str, update_links: bool) {
+        if self.label_taken(idx, new_label) {
+            self.status = Some(format!("A note called \"{new_label}\" already exists"));
+            self.pending_rename = None;
+            return;
+        }
+        self.record_history();
+        let old_label = self.graph.node(idx).map(|n| n.payload().label.clone());
+        if let Some(node) = self.graph.node_mut(idx) {
+            node.payload_mut().label = new_label.to_string();
+        }
+        if let (Some(old_label), true) = (old_label, update_links) {
+            let sources: Vec<NodeIndex> = self.graph.g().node_indices().collect();
+            let mut updated = 0;
+            for source in sources {
+                let content = &self.graph.node(source).unwrap().payload().content;
+                let (content, count) = rename_wikilinks(&self.wikilink_regex, content, &old_label, new_label);
+                if count > 0 {
+                    self.graph.node_mut(source).unwrap().payload_mut().set_content(content);
+                    self.handle_wikilinks(source);
+                    updated += count;
+                }
             }
+            self.status = Some(format!("Renamed \"{old_label}\" and updated {updated} links"));
         }
+        self.editing_label = None;
+        self.editing_pos = None;
+        self.pending_rename = None;
     }
-}
 
-impl App for LogMarkApp {
-    fn update(&mut self, ctx: &Context, _frame: &mut eframe::Frame) {
-        // Double-click handling is done via the GraphView response below.
+    /// The sentence of `content` containing the link at `start..end`, with
+    /// wikilinks shown as their display text and line breaks collapsed.
+    fn link_sentence(&self, content: &str, start: usize, end: usize) -> String {
+        let is_break = |c: char| matches!(c, '.' | '!' | '?' | '\n');
+        let from = content[..start].rfind(is_break).map_or(0, |i| i + 1);
+        let to = content[end..].find(is_break).map_or(content.len(), |i| end + i);
+        let sentence = self.wikilink_regex.replace_all(&content[from..to], |cap: &regex::Captures| {
+            let (target, alias) = split_wikilink(&cap[1]);
+            alias.unwrap_or(target).to_string()
+        });
+        sentence.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
 
-        // Label Editor Window
-        if let Some(idx) = self.editing_label {
-            let mut open = true;
+    /// Replace the `/` that opened the slash popup at the end of `content`
+    /// with `markup`, as its own undo step, and re-read its tags and links.
+    fn apply_slash_command(&mut self, idx: NodeIndex, content: &mut String, markup: &str) {
+        self.record_history();
+        content.truncate(content.len().saturating_sub(1));
+        content.push_str(markup);
+        if let Some(node) = self.graph.node_mut(idx) {
+            node.payload_mut().set_content(content.clone());
+        }
+        self.update_tags(idx);
+        self.handle_wikilinks(idx);
+    }
 
-            let mut win = Window::new("Edit Label");
-            if let Some(pos) = self.editing_pos {
-                win = win.anchor(Align2::LEFT_TOP, [pos.x, pos.y]);
-            } else {
-                win = win.anchor(Align2::CENTER_CENTER, [0.0, 0.0]);
+    /// Add a stub note titled `label` next to `source`, as following a new
+    /// wikilink does.
+    fn create_note_near(&mut self, source: NodeIndex, label: &str) -> NodeIndex {
+        let new_node_data = LogNodeData::new(label.to_string(), format!("# {label}"));
+        let idx = self.graph.add_node(new_node_data);
+        // Position it somewhere near the source (randomly or fixed offset for now)
+        // In a real app, we'd run a layout algorithm or place it smarter
+        let source_pos = self.graph.node(source).unwrap().location();
+        self.graph.node_mut(idx).unwrap().set_location(source_pos + egui::Vec2::new(50.0, 50.0));
+        idx
+    }
+
+    /// Link targets in `content` that no note is called, in order, without repeats.
+    fn broken_links(&self, content: &str) -> Vec<String> {
+        let mut broken: Vec<String> = Vec::new();
+        for cap in self.wikilink_regex.captures_iter(content) {
+            let (target, _) = split_wikilink(&cap[1]);
+            let exists = self.graph.g().node_weights().any(|n| n.payload().label == target);
+            if !exists && !broken.iter().any(|b| b == target) {
+                broken.push(target.to_string());
             }
+        }
+        broken
+    }
+
+    /// A note nothing links to or from whose content is still the stub that
+    /// wikilink creation gave it.
+    fn is_untouched_orphan(&self, idx: NodeIndex) -> bool {
+        let Some(node) = self.graph.node(idx) else {
+            return false;
+        };
+        let data = node.payload();
+        self.graph.g().neighbors_undirected(idx).next().is_none() && data.content == format!("# {}", data.label)
+    }
 
-            win.open(&mut open).show(ctx, |ui| {
-                let response = ui.text_edit_singleline(&mut self.label_edit_buffer);
-                if response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter)) {
-                    if let Some(node) = self.graph.node_mut(idx) {
-                        node.payload_mut().label = self.label_edit_buffer.clone();
+    /// Delete, undo/redo and arrow-key navigation on the graph, unless a text
+    /// field is being edited (it has its own undo).
+    fn handle_graph_shortcuts(&mut self, ctx: &Context) {
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+        if ctx.input(|i| i.key_pressed(Key::Delete)) && !self.graph.selected_nodes().is_empty() {
+            let selected = self.graph.selected_nodes().to_vec();
+            self.record_history();
+            self.delete_nodes(&selected);
+        }
+        // Check redo first: Ctrl+Z also matches when Shift is held
+        let (redo, undo) = ctx.input_mut(|i| {
+            (
+                i.consume_key(egui::Modifiers::COMMAND | egui::Modifiers::SHIFT, Key::Z),
+                i.consume_key(egui::Modifiers::COMMAND, Key::Z),
+            )
+        });
+        if redo {
+            self.redo();
+        } else if undo {
+            self.undo();
+        }
+        self.handle_graph_navigation(ctx);
+    }
+
+    /// The inline label editor opened by double-clicking a note, with the
+    /// prompt to update links when a renamed note is linked to.
+    fn label_editor_window(&mut self, ctx: &Context) {
+        let Some(idx) = self.editing_label else {
+            return;
+        };
+        let mut open = true;
+
+        let mut win = Window::new("Edit Label");
+        if let Some(pos) = self.editing_pos {
+            win = win.anchor(Align2::LEFT_TOP, [pos.x, pos.y]);
+        } else {
+            win = win.anchor(Align2::CENTER_CENTER, [0.0, 0.0]);
+        }
+
+        win.open(&mut open).show(ctx, |ui| {
+            if let Some((new_label, references)) = self.pending_rename.clone() {
+                let links = if references == 1 { "1 link points".to_string() } else { format!("{references} links point") };
+                ui.label(format!("{links} to the old label. Update them to [[{new_label}]]?"));
+                ui.horizontal(|ui| {
+                    if ui.button("Update links").clicked() {
+                        self.rename_note(idx, &new_label, true);
+                    }
+                    if ui.button("Rename only").on_hover_text("Leave the links pointing at the old label").clicked() {
+                        self.rename_note(idx, &new_label, false);
                     }
-                    self.editing_label = None;
-                    self.editing_pos = None;
+                    if ui.button("Cancel").clicked() {
+                        self.pending_rename = None;
+                    }
+                });
+                return;
+            }
+            let response = ui.text_edit_singleline(&mut self.label_edit_buffer);
+            let taken = self.label_taken(idx, &self.label_edit_buffer);
+            if taken {
+                ui.colored_label(ui.visuals().error_fg_color, "Another note already has this label");
+            }
+            if response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter)) && !taken {
+                let new_label = self.label_edit_buffer.clone();
+                let old_label = self.graph.node(idx).map(|n| n.payload().label.clone()).unwrap_or_default();
+                let references = if new_label == old_label { 0 } else { self.count_wikilinks_to(&old_label) };
+                if references > 0 {
+                    self.pending_rename = Some((new_label, references));
+                } else {
+                    self.rename_note(idx, &new_label, false);
                 }
-            });
+            }
+        });
 
-            if !open {
-                self.editing_label = None;
-                self.editing_pos = None;
+        if !open {
+            self.editing_label = None;
+            self.editing_pos = None;
+            self.pending_rename = None;
+        }
+    }
+
+    fn sidebar_ui(&mut self, ctx: &Context, ui: &mut egui::Ui) {
+        // Collapse/Expand button
+        ui.horizontal(|ui| {
+            if ui.button(if self.sidebar_expanded { "«" } else { "»" }).clicked() {
+                self.sidebar_expanded = !self.sidebar_expanded;
             }
+            ui.heading("Sidebar");
+        });
+
+        if !self.sidebar_expanded {
+            return;
         }
 
-        // Sidebar
-        SidePanel::right("right_panel")
-            .resizable(true)
-            .default_width(if self.sidebar_expanded { 300.0 } else { 48.0 })
-            .show(ctx, |ui| {
-                // Collapse/Expand button
-                ui.horizontal(|ui| {
-                    if ui.button(if self.sidebar_expanded { "«" } else { "»" }).clicked() {
-                        self.sidebar_expanded = !self.sidebar_expanded;
+        self.search_ui(ui);
+
+        ui.horizontal(|ui| {
+            if ui.button("Auto Layout").clicked() {
+                self.record_history();
+                self.auto_layout();
+                self.zoom_to_fit_request = true;
+            }
+            if ui.button("Zoom to fit").on_hover_text("Frame the visible notes once").clicked() {
+                self.zoom_to_fit_request = true;
+            }
+            if !self.fit_to_screen
+                && ui.button("Fit to screen").on_hover_text("Keep refitting the whole graph to the view as it changes").clicked()
+            {
+                self.fit_to_screen = true;
+            }
+        });
+
+        ui.checkbox(&mut self.remove_orphaned_links, "Remove notes orphaned by deleted links");
+        ui.checkbox(&mut self.auto_create_links, "Create notes for new links");
+        if ui.checkbox(&mut self.multi_select, "Multi-select").changed() && !self.multi_select {
+            // Back to single selection: keep only the first note
+            let selected = self.graph.selected_nodes().to_vec();
+            for &idx in selected.iter().skip(1) {
+                if let Some(node) = self.graph.node_mut(idx) {
+                    node.set_selected(false);
+                }
+            }
+            self.graph.set_selected_nodes(selected.into_iter().take(1).collect());
+        }
+        ui.checkbox(&mut self.show_minimap, "Show minimap");
+
+        ui.horizontal(|ui| {
+            let tags = self.all_tags();
+            if self.tag_filter.as_ref().is_some_and(|t| !tags.contains(t)) {
+                self.tag_filter = None;
+            }
+            egui::ComboBox::from_label("Tag")
+                .selected_text(self.tag_filter.as_deref().map_or("All notes".to_string(), |t| format!("#{t}")))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.tag_filter, None, "All notes");
+                    for tag in tags {
+                        let text = format!("#{tag}");
+                        ui.selectable_value(&mut self.tag_filter, Some(tag), text);
                     }
-                    ui.heading("Sidebar");
                 });
+            ui.checkbox(&mut self.hide_filtered, "Hide others");
+        });
+
+        self.stats_ui(ui);
+        self.recent_ui(ui);
+
+        if let Some(status) = &self.status {
+            ui.small(status);
+        }
+
+        let selected = self.graph.selected_nodes().to_vec();
+        if selected.len() > 1 {
+            self.selection_ui(ui, &selected);
+        } else if let Some(&idx) = selected.first() {
+            self.note_editor_ui(ctx, ui, idx);
+        } else {
+            ui.label("Select a node to edit its content.");
+        }
+    }
+
+    /// Content editor for the note `idx`, with its broken-link fixes, slash
+    /// commands and preview.
+    fn note_editor_ui(&mut self, ctx: &Context, ui: &mut egui::Ui, idx: NodeIndex) {
+        ui.heading("Node Content");
+        ui.separator();
+
+        // We need to get the content, edit it, and put it back.
+        // To avoid holding a mutable borrow on graph while drawing UI that might need it,
+        // we clone the content string.
+        let mut content = self.graph.node(idx).unwrap().payload().content.clone();
+        let label = self.graph.node(idx).unwrap().payload().label.clone();
+
+        ui.label(format!("Editing: {}", label));
+        self.note_style_ui(ui, idx);
+
+        // Suggestion keys have to be taken before the editor sees them
+        let editor_id = egui::Id::new("note_content_editor");
+        let completed = self.link_completion_input(ui, editor_id, &mut content);
+
+        // Links to labels no note has are drawn in the error colour
+        let labels: HashSet<String> = self.graph.g().node_weights().map(|n| n.payload().label.clone()).collect();
+        let wikilink_regex = self.wikilink_regex.clone();
+        let mut layouter = |ui: &egui::Ui, buf: &dyn egui::TextBuffer, wrap_width: f32| {
+            let mut job = highlight_broken_links(ui, buf.as_str(), &wikilink_regex, &labels);
+            job.wrap.max_width = wrap_width;
+            ui.fonts_mut(|f| f.layout_job(job))
+        };
+
+        let output = ui.allocate_ui_with_layout(
+            ui.available_size() - egui::Vec2::new(0.0, 200.0), // Leave space for preview
+            egui::Layout::centered_and_justified(ui.layout().main_dir()),
+            |ui| {
+                TextEdit::multiline(&mut content)
+                    .id(editor_id)
+                    .desired_width(f32::INFINITY)
+                    .code_editor()
+                    .layouter(&mut layouter)
+                    .show(ui)
+            },
+        ).inner;
+        let response = output.response.clone();
+        self.update_link_completion(idx, &content, &output);
+        self.link_completion_popup(ctx, &output);
 
-                if !self.sidebar_expanded {
-                    return;
+        if response.changed() || completed {
+            if self.content_edit_node != Some(idx) {
+                self.record_history();
+                self.content_edit_node = Some(idx);
+            }
+            // Update content in graph
+            if let Some(node) = self.graph.node_mut(idx) {
+                node.payload_mut().set_content(content.clone());
+            }
+            // Handle wikilinks and tags
+            self.update_tags(idx);
+            self.handle_wikilinks(idx);
+        }
+        if response.lost_focus() {
+            self.content_edit_node = None;
+        }
+
+        for target in self.broken_links(&content) {
+            ui.horizontal(|ui| {
+                ui.colored_label(ui.visuals().error_fg_color, format!("No note called [[{target}]]"));
+                if ui.small_button("Create note").clicked() {
+                    self.record_history();
+                    self.create_note_near(idx, &target);
+                    self.handle_wikilinks(idx);
                 }
+            });
+        }
 
-                if let Some(idx) = self.graph.selected_nodes().first() {
-                    let idx = *idx; // Copy index to avoid borrow checker issues
-                    
-                    ui.heading("Node Content");
-                    ui.separator();
-                    
-                    // We need to get the content, edit it, and put it back.
-                    // To avoid holding a mutable borrow on graph while drawing UI that might need it,
-                    // we clone the content string.
-                    let mut content = self.graph.node(idx).unwrap().payload().content.clone();
-                    let label = self.graph.node(idx).unwrap().payload().label.clone();
-
-                    ui.label(format!("Editing: {}", label));
-                    
-                    let response = ui.add_sized(
-                        ui.available_size() - egui::Vec2::new(0.0, 200.0), // Leave space for preview
-                        TextEdit::multiline(&mut content)
-                            .desired_width(f32::INFINITY)
-                            .code_editor()
-                    );
-
-                    if response.changed() {
-                        // Update content in graph
-                        if let Some(node) = self.graph.node_mut(idx) {
-                            node.payload_mut().content = content.clone();
-                        }
-                        // Handle wikilinks
-                        self.handle_wikilinks(idx);
-                    }
+        // Slash command simple popup when typing '/'
+        if content.ends_with('/') {
+            self.slash_command_popup(ctx, idx, &mut content, response.rect.max);
+        }
 
-                    // Slash command simple popup when typing '/'
-                    if content.ends_with('/') {
-                        // Draw a tiny suggestion window anchored near the editor rect
-                        let popup_pos = response.rect.max;
-                        let mut open_popup = true;
-                        Window::new("slash_popup")
-                            .open(&mut open_popup)
-                            .collapsible(false)
-                            .resizable(false)
-                            .anchor(Align2::LEFT_TOP, [popup_pos.x + 6.0, popup_pos.y + 6.0])
-                            .show(ctx, |ui| {
-                                ui.label("Insert:");
-                                if ui.button("Heading 1").clicked() {
-                                    // replace trailing '/' with '# '
-                                    content.truncate(content.len().saturating_sub(1));
-                                    content.push_str("# ");
-                                    if let Some(node) = self.graph.node_mut(idx) {
-                                        node.payload_mut().content = content.clone();
-                                    }
-                                }
-                                if ui.button("Heading 2").clicked() {
-                                    content.truncate(content.len().saturating_sub(1));
-                                    content.push_str("## ");
-                                    if let Some(node) = self.graph.node_mut(idx) {
-                                        node.payload_mut().content = content.clone();
-                                    }
-                                }
-                                if ui.button("Bullet").clicked() {
-                                    content.truncate(content.len().saturating_sub(1));
-                                    content.push_str("- ");
-                                    if let Some(node) = self.graph.node_mut(idx) {
-                                        node.payload_mut().content = content.clone();
-                                    }
-                                }
-                                if ui.button("To-do").clicked() {
-                                    content.truncate(content.len().saturating_sub(1));
-                                    content.push_str("- [ ] ");
-                                    if let Some(node) = self.graph.node_mut(idx) {
-                                        node.payload_mut().content = content.clone();
-                                    }
-                                }
-                            });
-                    }
+        self.note_preview_ui(ui, idx, &content);
+    }
 
-                    ui.separator();
-                    ui.heading("Preview");
-                    
-                    egui::ScrollArea::vertical().show(ui, |ui| {
-                            CommonMarkViewer::new()
-                            .show(ui, &mut self.markdown_cache, &content);
-                    });
+    /// Rendered Markdown of `content` and the notes linking to `idx`.
+    fn note_preview_ui(&mut self, ui: &mut egui::Ui, idx: NodeIndex, content: &str) {
+        ui.separator();
+        ui.heading("Preview");
 
-                } else {
-                    ui.label("Select a node to edit its content.");
+        // Leave room below the preview for the backlinks
+        egui::ScrollArea::vertical()
+            .id_salt("preview")
+            .max_height((ui.available_height() - 100.0).max(60.0))
+            .show(ui, |ui| {
+                CommonMarkViewer::new()
+                .show(ui, &mut self.markdown_cache, content);
+        });
+
+        ui.separator();
+        ui.heading("Linked from");
+        let backlinks = self.backlinks(idx);
+        if backlinks.is_empty() {
+            ui.label("No notes link here yet.");
+        }
+        egui::ScrollArea::vertical().id_salt("backlinks").show(ui, |ui| {
+            for (source, label) in backlinks {
+                if ui.link(label).clicked() {
+                    self.focus_node(source);
+                }
+            }
+        });
+    }
+
+    /// The slash-command suggestions, anchored at `anchor` next to the editor.
+    fn slash_command_popup(&mut self, ctx: &Context, idx: NodeIndex, content: &mut String, anchor: egui::Pos2) {
+        let mut open_popup = true;
+        Window::new("slash_popup")
+            .open(&mut open_popup)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(Align2::LEFT_TOP, [anchor.x + 6.0, anchor.y + 6.0])
+            .show(ctx, |ui| {
+                ui.label("Insert:");
+                for (name, markup) in SLASH_COMMANDS {
+                    if ui.button(name).clicked() {
+                        self.apply_slash_command(idx, content, markup);
+                    }
                 }
             });
+    }
 
-        // Graph View
-        CentralPanel::default().show(ctx, |ui| {
-            let mut widget: LogMarkGraphView<'_> = GraphView::new(&mut self.graph);
-            widget = widget.with_interactions(
-                &SettingsInteraction::default()
-                    .with_dragging_enabled(true)
-                    .with_node_selection_enabled(true)
-                    .with_node_selection_multi_enabled(false)
-            );
+    /// Pan or zoom the graph view for focus, centre, saved-viewport and
+    /// zoom-to-fit requests made since the last frame.
+    fn apply_view_requests(&mut self, ui: &mut egui::Ui) {
+        // Pan so the requested node or point sits in the middle of the view
+        let focus = self.focus_request.take().and_then(|idx| self.graph.node(idx).map(egui_graphs::Node::location));
+        if let Some(pos) = focus.or(self.center_request.take()) {
+            let mut meta = MetadataFrame::new(None).load(ui);
+            meta.pan = ui.available_size() / 2.0 - pos.to_vec2() * meta.zoom;
+            meta.save(ui);
+        }
 
-            let resp = ui.add(&mut widget);
-            // If the graph widget reports a double click, begin inline label editing for selected node
-            if resp.double_clicked() {
-                if let Some(idx) = self.graph.selected_nodes().first() {
-                    self.editing_label = Some(*idx);
-                    self.label_edit_buffer = self.graph.node(*idx).unwrap().payload().label.clone();
-                    // Anchor editor to pointer position if available
-                    self.editing_pos = resp.hover_pos();
+        // Restore the saved camera, unless it would leave every note off-screen
+        if let Some(viewport) = self.viewport_request.take() {
+            let pan = egui::Vec2::from(viewport.pan);
+            let view = egui::Rect::from_min_size(egui::Pos2::ZERO, ui.available_size());
+            let on_screen = self.visible_bounds().is_some_and(|b| {
+                egui::Rect::from_min_max(b.min * viewport.zoom + pan, b.max * viewport.zoom + pan).intersects(view)
+            });
+            if on_screen && viewport.zoom > 0.0 {
+                let mut meta = MetadataFrame::new(None).load(ui);
+                (meta.pan, meta.zoom) = (pan, viewport.zoom);
+                meta.save(ui);
+                self.fit_to_screen = false;
+            } else {
+                self.zoom_to_fit_request = true;
+            }
+        }
+
+        if std::mem::take(&mut self.zoom_to_fit_request) {
+            if let Some(bounds) = self.visible_bounds() {
+                let mut meta = MetadataFrame::new(None).load(ui);
+                (meta.pan, meta.zoom) = layout::fit_view(bounds, ui.available_size());
+                meta.save(ui);
+                self.fit_to_screen = false;
+            }
+        }
+    }
+
+    /// The graph itself, with the minimap, double-click renaming and the
+    /// right-click menu.
+    fn graph_view_ui(&mut self, ui: &mut egui::Ui) {
+        self.apply_view_requests(ui);
+
+        let mut widget: LogMarkGraphView<'_> = GraphView::new(&mut self.graph);
+        widget = widget.with_navigations(
+            &SettingsNavigation::default()
+                .with_fit_to_screen_enabled(self.fit_to_screen)
+                .with_zoom_and_pan_enabled(true)
+        );
+        widget = widget.with_interactions(
+            &SettingsInteraction::default()
+                .with_dragging_enabled(true)
+                .with_node_selection_enabled(true)
+                .with_node_selection_multi_enabled(self.multi_select)
+        );
+
+        let resp = ui.add(&mut widget);
+        let meta = MetadataFrame::new(None).load(ui);
+        self.viewport = Some(SavedViewport { pan: meta.pan.into(), zoom: meta.zoom });
+        self.view_center = ((resp.rect.size() / 2.0 - meta.pan) / meta.zoom).to_pos2();
+        if self.show_minimap {
+            self.minimap_ui(ui, resp.rect);
+        }
+        // If the graph widget reports a double click, begin inline label editing for selected node
+        if resp.double_clicked() {
+            if let Some(idx) = self.graph.selected_nodes().first() {
+                self.editing_label = Some(*idx);
+                self.label_edit_buffer = self.graph.node(*idx).unwrap().payload().label.clone();
+                // Anchor editor to pointer position if available
+                self.editing_pos = resp.hover_pos();
+            }
+        }
+
+        self.graph_context_menu(ui, &resp);
+    }
+
+    /// Delete menu for the note or link under a right click.
+    fn graph_context_menu(&mut self, ui: &egui::Ui, resp: &egui::Response) {
+        // Remember what was right-clicked; the menu itself stays open across frames
+        if resp.secondary_clicked() {
+            self.context_target = resp.interact_pointer_pos().and_then(|pos| {
+                let meta = MetadataFrame::new(None).load(ui);
+                let local = (pos - resp.rect.left_top()).to_pos2();
+                self.graph
+                    .node_by_screen_pos(&meta, local)
+                    .map(ContextTarget::Node)
+                    .or_else(|| self.graph.edge_by_screen_pos(&meta, local).map(ContextTarget::Edge))
+            });
+        }
+        resp.context_menu(|ui| match self.context_target {
+            Some(ContextTarget::Node(idx)) => {
+                if ui.button("Delete node").clicked() {
+                    self.record_history();
+                    self.delete_nodes(&[idx]);
+                    self.context_target = None;
+                    ui.close();
+                }
+            }
+            Some(ContextTarget::Edge(edge)) => {
+                if ui.button("Delete edge").clicked() {
+                    self.record_history();
+                    self.delete_edge(edge);
+                    self.context_target = None;
+                    ui.close();
                 }
             }
+            None => {
+                ui.close();
+            }
         });
     }
 }
+
+/// How long before `now` the timestamp `then` was, coarsely: "just now",
+/// "5 min ago", "3 h ago", "2 d ago". Zero means it was never recorded.
+fn format_age(now: u64, then: u64) -> String {
+    if then == 0 {
+        return "unknown".to_string();
+    }
+    match now.saturating_sub(then) {
+        s if s < 60 => "just now".to_string(),
+        s if s < 3600 => format!("{} min ago", s / 60),
+        s if s < 86_400 => format!("{} h ago", s / 3600),
+        s => format!("{} d ago", s / 86_400),
+    }
+}
+
+/// Target note and optional display alias of a wikilink's inner text, split
+/// at the first `|` as in `[[Target|alias]]`.
+pub(crate) fn split_wikilink(inner: &str) -> (&str, Option<&str>) {
+    match inner.split_once('|') {
+        Some((target, alias)) if !alias.trim().is_empty() => (target.trim(), Some(alias.trim())),
+        Some((target, _)) => (target.trim(), None),
+        None => (inner.trim(), None),
+    }
+}
+
+/// `content` with every wikilink to `old` pointing at `new` instead, keeping
+/// any `|alias`, and how many links were rewritten.
+fn rename_wikilinks(wikilinks: &Regex, content: &str, old: &str, new: &str) -> (String, usize) {
+    let mut count = 0;
+    let renamed = wikilinks.replace_all(content, |cap: &regex::Captures| match split_wikilink(&cap[1]) {
+        (target, alias) if target == old => {
+            count += 1;
+            match alias {
+                Some(alias) => format!("[[{new}|{alias}]]"),
+                None => format!("[[{new}]]"),
+            }
+        }
+        _ => cap[0].to_string(),
+    });
+    (renamed.into_owned(), count)
+}
+
+/// Char index just after the `[[` of an unclosed wikilink that the cursor
+/// (a char index) is typing into, and the text typed after it so far.
+fn wikilink_query(content: &str, cursor: usize) -> Option<(usize, &str)> {
+    let byte = content.char_indices().nth(cursor).map_or(content.len(), |(b, _)| b);
+    let before = &content[..byte];
+    let open = before.rfind("[[")? + 2;
+    let query = &before[open..];
+    if query.contains(['[', ']', '|', '\n']) {
+        return None;
+    }
+    Some((before[..open].chars().count(), query))
+}
+
+/// Editor text in the monospace font, with wikilinks whose target is not in
+/// `labels` underlined in the error colour.
+fn highlight_broken_links(ui: &egui::Ui, text: &str, wikilinks: &Regex, labels: &HashSet<String>) -> egui::text::LayoutJob {
+    let font = egui::TextStyle::Monospace.resolve(ui.style());
+    let normal = egui::TextFormat::simple(font, ui.visuals().widgets.inactive.text_color());
+    let error = ui.visuals().error_fg_color;
+    let broken = egui::TextFormat { color: error, underline: egui::Stroke::new(1.0, error), ..normal.clone() };
+    let mut job = egui::text::LayoutJob::default();
+    let mut last = 0;
+    for cap in wikilinks.captures_iter(text) {
+        let (Some(link), Some(inner)) = (cap.get(0), cap.get(1)) else {
+            continue;
+        };
+        if labels.contains(split_wikilink(inner.as_str()).0) {
+            continue;
+        }
+        job.append(&text[last..link.start()], 0.0, normal.clone());
+        job.append(link.as_str(), 0.0, broken.clone());
+        last = link.end();
+    }
+    job.append(&text[last..], 0.0, normal);
+    job
+}
+
+/// Escape `label` for use inside a double-quoted DOT string.
+fn dot_escape(label: &str) -> String {
+    let mut out = String::with_capacity(label.len());
+    for c in label.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Up to `SNIPPET_CONTEXT` characters either side of `content[start..end]`
+/// on one line, with ellipses where text was cut.
+fn snippet(content: &str, start: usize, end: usize) -> String {
+    const SNIPPET_CONTEXT: usize = 30;
+    let from = content[..start].char_indices().rev().nth(SNIPPET_CONTEXT - 1).map_or(0, |(i, _)| i);
+    let to = content[end..].char_indices().nth(SNIPPET_CONTEXT).map_or(content.len(), |(i, _)| end + i);
+    let ellipsis = |cut: bool| if cut { "…" } else { "" };
+    format!("{}{}{}", ellipsis(from > 0), &content[from..to], ellipsis(to < content.len())).replace('\n', " ")
+}
+
+impl App for LogMarkApp {
+    fn update(&mut self, ctx: &Context, _frame: &mut eframe::Frame) {
+        // Double-click handling is done via the GraphView response below.
+
+        #[cfg(not(target_arch = "wasm32"))]
+        self.handle_file_shortcuts(ctx);
+        self.handle_graph_shortcuts(ctx);
+        self.label_editor_window(ctx);
+
+        TopBottomPanel::top("toolbar").show(ctx, |ui| self.toolbar_ui(ui));
+
+        // Sidebar
+        SidePanel::right("right_panel")
+            .resizable(true)
+            .default_width(if self.sidebar_expanded { 300.0 } else { 48.0 })
+            .show(ctx, |ui| self.sidebar_ui(ctx, ui));
+
+        self.apply_tag_filter();
+        self.mark_reciprocal_edges();
+
+        // Graph View
+        CentralPanel::default().show(ctx, |ui| self.graph_view_ui(ui));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn empty_app() -> LogMarkApp {
+        LogMarkApp::with_graph(Graph::from(&StableGraph::default()))
+    }
+
+    #[test]
+    fn split_wikilink_trims_target_and_alias() {
+        assert_eq!(split_wikilink("Ideas"), ("Ideas", None));
+        assert_eq!(split_wikilink("  Ideas \t"), ("Ideas", None));
+        assert_eq!(split_wikilink("Ideas|my ideas"), ("Ideas", Some("my ideas")));
+        assert_eq!(split_wikilink(" Ideas | my ideas "), ("Ideas", Some("my ideas")));
+        // an empty alias shows the target
+        assert_eq!(split_wikilink("Ideas|"), ("Ideas", None));
+        assert_eq!(split_wikilink("Ideas|  "), ("Ideas", None));
+        // only the first bar splits
+        assert_eq!(split_wikilink("Ideas|a|b"), ("Ideas", Some("a|b")));
+    }
+
+    #[test]
+    fn link_sentence_stops_at_punctuation_and_line_breaks() {
+        let app = empty_app();
+        let sentence = |content: &str, link: &str| {
+            let start = content.find(link).unwrap();
+            app.link_sentence(content, start, start + link.len())
+        };
+        assert_eq!(sentence("Intro. See [[Ideas]] here! Next", "[[Ideas]]"), "See Ideas here");
+        assert_eq!(sentence("# Title\nRead [[Ideas|the  ideas]]\tfirst\nthen more", "[[Ideas|the  ideas]]"), "Read the ideas first");
+        assert_eq!(sentence("[[Ideas]]", "[[Ideas]]"), "Ideas");
+        // other links in the sentence show as their display text too
+        assert_eq!(sentence("Both [[A]] and\n[[B|bee]] and [[C]]?", "[[B|bee]]"), "bee and C");
+    }
+
+    #[test]
+    fn rename_wikilinks_rewrites_only_the_old_target() {
+        let wikilinks = empty_app().wikilink_regex;
+        let content = "[[Old]], [[Old|alias]], [[ Old ]] and [[Older]]";
+        let (renamed, count) = rename_wikilinks(&wikilinks, content, "Old", "New");
+        assert_eq!(renamed, "[[New]], [[New|alias]], [[New]] and [[Older]]");
+        assert_eq!(count, 3);
+        assert_eq!(rename_wikilinks(&wikilinks, "[[Older]]", "Old", "New"), ("[[Older]]".to_string(), 0));
+    }
+
+    #[test]
+    fn rename_refuses_a_label_another_note_has() {
+        let mut g = StableGraph::new();
+        let a = g.add_node(LogNodeData::new("A".to_string(), "[[B]]".to_string()));
+        g.add_node(LogNodeData::new("B".to_string(), String::new()));
+        let mut app = LogMarkApp::with_graph(Graph::from(&g));
+        app.rename_note(a, "B", true);
+        assert_eq!(app.graph.node(a).unwrap().payload().label, "A");
+        assert!(app.status.as_deref().is_some_and(|s| s.contains("already exists")));
+        app.rename_note(a, "C", true);
+        assert_eq!(app.graph.node(a).unwrap().payload().label, "C");
+    }
+
+    #[test]
+    fn slash_command_is_its_own_undo_step() {
+        let mut g = StableGraph::new();
+        let a = g.add_node(LogNodeData::new("A".to_string(), "Intro\n/".to_string()));
+        let mut app = LogMarkApp::with_graph(Graph::from(&g));
+        let mut content = "Intro\n/".to_string();
+        app.apply_slash_command(a, &mut content, "## ");
+        assert_eq!(content, "Intro\n## ");
+        assert_eq!(app.graph.node(a).unwrap().payload().content, "Intro\n## ");
+        app.undo();
+        let restored = app.graph.g().node_weights().next().unwrap().payload().content.clone();
+        assert_eq!(restored, "Intro\n/");
+    }
+
+    #[test]
+    fn slash_command_refreshes_tags_and_links() {
+        let mut g = StableGraph::new();
+        let a = g.add_node(LogNodeData::new("A".to_string(), "#draft see [[B]]\n/".to_string()));
+        let mut app = LogMarkApp::with_graph(Graph::from(&g));
+        let mut content = "#draft see [[B]]\n/".to_string();
+        app.apply_slash_command(a, &mut content, "- ");
+        assert_eq!(app.graph.node(a).unwrap().payload().tags, vec!["draft".to_string()]);
+        assert_eq!(app.graph.g().node_count(), 2);
+        assert_eq!(app.graph.g().edge_count(), 1);
+    }
+}
@@ -1,13 +1,165 @@
 use egui::{Color32, Pos2, Shape, Stroke, Vec2, FontId, FontFamily};
+use egui::ecolor::Hsva;
 use egui_graphs::{DisplayNode, DisplayEdge, DrawContext, NodeProps, EdgeProps, Node};
 use petgraph::Directed;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use web_time::{SystemTime, UNIX_EPOCH};
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub struct LogNodeData {
     pub label: String,
     pub content: String,
+    /// `#tag` tokens found in `content`, in order of first appearance.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Size of the note in canvas units: the circle radius, or half the
+    /// width of a square or diamond.
+    #[serde(default = "default_radius")]
+    pub radius: f32,
+    #[serde(default)]
+    pub shape: NoteShape,
+    /// When the note was created and its content last changed, in seconds
+    /// since the Unix epoch; 0 in documents saved before they were recorded.
+    #[serde(default)]
+    pub created: u64,
+    #[serde(default)]
+    pub modified: u64,
+    /// How the sidebar tag filter wants this note drawn. View state only.
+    #[serde(skip)]
+    pub visibility: NoteVisibility,
+}
+
+impl LogNodeData {
+    pub fn new(label: String, content: String) -> Self {
+        let now = now_secs();
+        Self {
+            label,
+            content,
+            tags: Vec::new(),
+            radius: DEFAULT_NOTE_RADIUS,
+            shape: NoteShape::Circle,
+            created: now,
+            modified: now,
+            visibility: NoteVisibility::Shown,
+        }
+    }
+
+    /// Replace the content and stamp the note as modified now.
+    pub fn set_content(&mut self, content: String) {
+        self.content = content;
+        self.touch();
+    }
+
+    /// Stamp the note as modified now, after editing `content` in place.
+    pub fn touch(&mut self) {
+        self.modified = now_secs();
+    }
+}
+
+/// Seconds since the Unix epoch, or 0 if the clock is set before it.
+pub fn now_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+pub const DEFAULT_NOTE_RADIUS: f32 = 30.0;
+pub const MIN_NOTE_RADIUS: f32 = 10.0;
+pub const MAX_NOTE_RADIUS: f32 = 100.0;
+
+fn default_radius() -> f32 {
+    DEFAULT_NOTE_RADIUS
+}
+
+/// Outline a note is drawn with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum NoteShape {
+    #[default]
+    Circle,
+    Square,
+    Diamond,
+}
+
+impl NoteShape {
+    pub const ALL: [Self; 3] = [Self::Circle, Self::Square, Self::Diamond];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Circle => "Circle",
+            Self::Square => "Square",
+            Self::Diamond => "Diamond",
+        }
+    }
+
+    /// Whether `offset` from the centre falls within the shape of size `radius`.
+    pub fn contains(self, offset: Vec2, radius: f32) -> bool {
+        match self {
+            Self::Circle => offset.length() <= radius,
+            Self::Square => offset.x.abs().max(offset.y.abs()) <= radius,
+            Self::Diamond => offset.x.abs() + offset.y.abs() <= radius,
+        }
+    }
+
+    /// Offset from the centre to the outline along `dir`.
+    pub fn boundary(self, dir: Vec2, radius: f32) -> Vec2 {
+        let dir = dir.normalized();
+        // Distance to the outline for a unit direction: the square's edge is
+        // where the larger component reaches `radius`, the diamond's where
+        // the components sum to it
+        let scale = match self {
+            Self::Circle => 1.0,
+            Self::Square => 1.0 / dir.x.abs().max(dir.y.abs()),
+            Self::Diamond => 1.0 / (dir.x.abs() + dir.y.abs()),
+        };
+        if scale.is_finite() { dir * radius * scale } else { Vec2::ZERO }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NoteVisibility {
+    #[default]
+    Shown,
+    Dimmed,
+    Hidden,
+}
+
+/// Stable color for a tag, so every note sharing its first tag shares a color.
+pub fn tag_color(tag: &str) -> Color32 {
+    let mut hasher = DefaultHasher::new();
+    tag.hash(&mut hasher);
+    let hue = (hasher.finish() % 360) as f32 / 360.0;
+    Hsva::new(hue, 0.55, 0.8, 1.0).into()
+}
+
+/// Edge payload.
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct LogEdgeData {
+    /// Created from a `[[wikilink]]` in the source note's content, and removed
+    /// again when the link is. Manually drawn edges are left alone.
+    pub auto: bool,
+    /// The link's `[[Target|alias]]` text, or else the sentence around it.
+    #[serde(default)]
+    pub label: String,
+    /// Another edge runs the opposite way between the same notes, so this one
+    /// is drawn curved. View state only.
+    #[serde(skip)]
+    pub reciprocal: bool,
+}
+
+impl LogEdgeData {
+    pub fn wikilink(label: String) -> Self {
+        Self { auto: true, label, reciprocal: false }
+    }
 }
 
+/// Edge labels longer than this many characters are cut short with an ellipsis.
+const MAX_EDGE_LABEL_CHARS: usize = 32;
+
+/// Bend of an edge with a reciprocal partner, as a fraction of its length.
+/// Each direction bows to its own left, so the pair separate.
+pub const RECIPROCAL_CURVATURE: f32 = 0.2;
+
+/// Number of straight pieces a curved edge is split into for hit-testing.
+const CURVE_HIT_SEGMENTS: usize = 8;
+
 #[derive(Clone, Debug)]
 pub struct LogNode {
     pub pos: Pos2,
@@ -16,6 +168,9 @@ pub struct LogNode {
     pub dragged: bool,
     pub hovered: bool,
     pub radius: f32,
+    pub shape: NoteShape,
+    pub tag: Option<String>,
+    pub visibility: NoteVisibility,
 }
 
 impl From<NodeProps<LogNodeData>> for LogNode {
@@ -26,32 +181,40 @@ impl From<NodeProps<LogNodeData>> for LogNode {
             selected: node_props.selected,
             dragged: node_props.dragged,
             hovered: node_props.hovered,
-            radius: 30.0,
+            radius: node_props.payload.radius,
+            shape: node_props.payload.shape,
+            tag: node_props.payload.tags.first().cloned(),
+            visibility: node_props.payload.visibility,
         }
     }
 }
 
-impl DisplayNode<LogNodeData, (), Directed, u32> for LogNode {
+impl DisplayNode<LogNodeData, LogEdgeData, Directed, u32> for LogNode {
     fn is_inside(&self, pos: Pos2) -> bool {
-        let dir = pos - self.pos;
-        dir.length() <= self.radius
+        if self.visibility == NoteVisibility::Hidden {
+            return false;
+        }
+        self.shape.contains(pos - self.pos, self.radius)
     }
 
     fn closest_boundary_point(&self, dir: Vec2) -> Pos2 {
-        self.pos + dir.normalized() * self.radius
+        self.pos + self.shape.boundary(dir, self.radius)
     }
 
     fn shapes(&mut self, ctx: &DrawContext) -> Vec<Shape> {
         let mut shapes = Vec::new();
+        if self.visibility == NoteVisibility::Hidden {
+            return shapes;
+        }
         let screen_pos = ctx.meta.canvas_to_screen_pos(self.pos);
         let screen_radius = ctx.meta.canvas_to_screen_size(self.radius);
 
-        let color = if self.selected {
-            Color32::from_rgb(100, 200, 255)
-        } else if self.hovered {
-            Color32::from_rgb(150, 150, 200)
-        } else {
-            Color32::from_rgb(100, 150, 200)
+        let color = match &self.tag {
+            Some(tag) if self.hovered || self.selected => tag_color(tag).lerp_to_gamma(Color32::WHITE, 0.25),
+            Some(tag) => tag_color(tag),
+            None if self.selected => Color32::from_rgb(100, 200, 255),
+            None if self.hovered => Color32::from_rgb(150, 150, 200),
+            None => Color32::from_rgb(100, 150, 200),
         };
 
         let stroke = if self.selected {
@@ -60,19 +223,35 @@ impl DisplayNode<LogNodeData, (), Directed, u32> for LogNode {
             Stroke::new(1.0, Color32::GRAY)
         };
 
-        shapes.push(egui::epaint::CircleShape {
-            center: screen_pos,
-            radius: screen_radius,
-            fill: color,
-            stroke,
-        }.into());
+        let fade = if self.visibility == NoteVisibility::Dimmed { 0.25 } else { 1.0 };
+        let (color, stroke) = (color.gamma_multiply(fade), Stroke::new(stroke.width, stroke.color.gamma_multiply(fade)));
+
+        let r = screen_radius;
+        shapes.push(match self.shape {
+            NoteShape::Circle => egui::epaint::CircleShape {
+                center: screen_pos,
+                radius: r,
+                fill: color,
+                stroke,
+            }.into(),
+            NoteShape::Square => Shape::convex_polygon(
+                vec![screen_pos + Vec2::new(-r, -r), screen_pos + Vec2::new(r, -r), screen_pos + Vec2::new(r, r), screen_pos + Vec2::new(-r, r)],
+                color,
+                stroke,
+            ),
+            NoteShape::Diamond => Shape::convex_polygon(
+                vec![screen_pos + Vec2::new(0.0, -r), screen_pos + Vec2::new(r, 0.0), screen_pos + Vec2::new(0.0, r), screen_pos + Vec2::new(-r, 0.0)],
+                color,
+                stroke,
+            ),
+        });
 
         let font_size = (screen_radius * 0.4).max(8.0).min(16.0);
         let galley = ctx.ctx.fonts_mut(|f| {
             f.layout_no_wrap(
                 self.label.clone(),
                 FontId::new(font_size, FontFamily::Proportional),
-                Color32::WHITE,
+                Color32::WHITE.gamma_multiply(fade),
             )
         });
 
@@ -81,7 +260,7 @@ impl DisplayNode<LogNodeData, (), Directed, u32> for LogNode {
             screen_pos.y - galley.size().y / 2.0,
         );
 
-        shapes.push(egui::epaint::TextShape::new(text_pos, galley, Color32::WHITE).into());
+        shapes.push(egui::epaint::TextShape::new(text_pos, galley, Color32::WHITE.gamma_multiply(fade)).into());
         shapes
     }
 
@@ -91,73 +270,134 @@ impl DisplayNode<LogNodeData, (), Directed, u32> for LogNode {
         self.dragged = state.dragged;
         self.hovered = state.hovered;
         self.label = state.payload.label.clone();
+        self.radius = state.payload.radius;
+        self.shape = state.payload.shape;
+        self.tag = state.payload.tags.first().cloned();
+        self.visibility = state.payload.visibility;
     }
 }
 
 #[derive(Clone, Debug)]
 pub struct LogEdge {
     pub selected: bool,
+    pub auto: bool,
+    pub label: String,
+    /// Sideways offset of the curve's control point from the midpoint, as a
+    /// fraction of the edge length; 0 draws a straight line.
+    pub curvature: f32,
 }
 
-impl From<EdgeProps<()>> for LogEdge {
-    fn from(edge_props: EdgeProps<()>) -> Self {
+impl From<EdgeProps<LogEdgeData>> for LogEdge {
+    fn from(edge_props: EdgeProps<LogEdgeData>) -> Self {
         Self {
             selected: edge_props.selected,
+            auto: edge_props.payload.auto,
+            label: edge_props.payload.label.clone(),
+            curvature: curvature_for(&edge_props.payload),
         }
     }
 }
 
-impl DisplayEdge<LogNodeData, (), Directed, u32, LogNode> for LogEdge {
+fn curvature_for(data: &LogEdgeData) -> f32 {
+    if data.reciprocal { RECIPROCAL_CURVATURE } else { 0.0 }
+}
+
+impl LogEdge {
+    /// Start, control and end point of the edge as a quadratic Bézier in
+    /// canvas space, with the ends clipped to the notes' outlines.
+    fn curve(
+        &self,
+        start: &Node<LogNodeData, LogEdgeData, Directed, u32, LogNode>,
+        end: &Node<LogNodeData, LogEdgeData, Directed, u32, LogNode>,
+    ) -> [Pos2; 3] {
+        let (start_pos, end_pos) = (start.location(), end.location());
+        let line = end_pos - start_pos;
+        let control = start_pos + line * 0.5 + Vec2::new(-line.y, line.x) * self.curvature;
+        [
+            start.display().closest_boundary_point(control - start_pos),
+            control,
+            end.display().closest_boundary_point(control - end_pos),
+        ]
+    }
+}
+
+fn quadratic_point([p0, p1, p2]: [Pos2; 3], t: f32) -> Pos2 {
+    let u = 1.0 - t;
+    (p0.to_vec2() * u * u + p1.to_vec2() * 2.0 * u * t + p2.to_vec2() * t * t).to_pos2()
+}
+
+fn distance_to_segment(pos: Pos2, a: Pos2, b: Pos2) -> f32 {
+    let ab = b - a;
+    let t = ((pos - a).dot(ab) / ab.length_sq().max(f32::EPSILON)).clamp(0.0, 1.0);
+    pos.distance(a + ab * t)
+}
+
+impl DisplayEdge<LogNodeData, LogEdgeData, Directed, u32, LogNode> for LogEdge {
     fn is_inside(
         &self,
-        start: &Node<LogNodeData, (), Directed, u32, LogNode>,
-        end: &Node<LogNodeData, (), Directed, u32, LogNode>,
+        start: &Node<LogNodeData, LogEdgeData, Directed, u32, LogNode>,
+        end: &Node<LogNodeData, LogEdgeData, Directed, u32, LogNode>,
         pos: Pos2,
     ) -> bool {
-        let start_pos = start.location();
-        let end_pos = end.location();
-        let radius = 5.0;
-        let line_vec = end_pos - start_pos;
-        let point_vec = pos - start_pos;
-        let line_len = line_vec.length();
-        if line_len < 0.001 {
+        if start.payload().visibility == NoteVisibility::Hidden || end.payload().visibility == NoteVisibility::Hidden {
             return false;
         }
-        let proj = point_vec.dot(line_vec) / line_len;
-        if proj < 0.0 || proj > line_len {
+        let radius = 5.0;
+        if (end.location() - start.location()).length() < 0.001 {
             return false;
         }
-        let closest = start_pos + line_vec.normalized() * proj;
-        (pos - closest).length() <= radius
+        if self.curvature == 0.0 {
+            return distance_to_segment(pos, start.location(), end.location()) <= radius;
+        }
+        // Approximate the curve with a few straight pieces
+        let curve = self.curve(start, end);
+        let points: Vec<Pos2> = (0..=CURVE_HIT_SEGMENTS)
+            .map(|i| quadratic_point(curve, i as f32 / CURVE_HIT_SEGMENTS as f32))
+            .collect();
+        points.windows(2).any(|w| distance_to_segment(pos, w[0], w[1]) <= radius)
     }
 
     fn shapes(
         &mut self,
-        start: &Node<LogNodeData, (), Directed, u32, LogNode>,
-        end: &Node<LogNodeData, (), Directed, u32, LogNode>,
+        start: &Node<LogNodeData, LogEdgeData, Directed, u32, LogNode>,
+        end: &Node<LogNodeData, LogEdgeData, Directed, u32, LogNode>,
         ctx: &DrawContext,
     ) -> Vec<Shape> {
-        let start_pos = start.location();
-        let end_pos = end.location();
+        let visibility = |n: &Node<LogNodeData, LogEdgeData, Directed, u32, LogNode>| n.payload().visibility;
+        if visibility(start) == NoteVisibility::Hidden || visibility(end) == NoteVisibility::Hidden {
+            return Vec::new();
+        }
+        let fade = if visibility(start) == NoteVisibility::Shown && visibility(end) == NoteVisibility::Shown { 1.0 } else { 0.25 };
 
-        let dir = (end_pos - start_pos).normalized();
-        let start_boundary = start.display().closest_boundary_point(dir);
-        let end_boundary = end.display().closest_boundary_point(-dir);
-        
+        let [start_boundary, control, end_boundary] = self.curve(start, end);
         let screen_start = ctx.meta.canvas_to_screen_pos(start_boundary);
+        let screen_control = ctx.meta.canvas_to_screen_pos(control);
         let screen_end = ctx.meta.canvas_to_screen_pos(end_boundary);
 
         let color = if self.selected {
             Color32::from_rgb(255, 200, 100)
         } else {
             Color32::from_rgb(128, 128, 128)
-        };
+        }
+        .gamma_multiply(fade);
         let stroke = Stroke::new(2.0, color);
 
         let mut shapes = Vec::new();
-        shapes.push(egui::epaint::Shape::line_segment([screen_start, screen_end], stroke));
+        let dir = if self.curvature == 0.0 {
+            shapes.push(egui::epaint::Shape::line_segment([screen_start, screen_end], stroke));
+            (screen_end - screen_start).normalized()
+        } else {
+            shapes.push(egui::epaint::QuadraticBezierShape::from_points_stroke(
+                [screen_start, screen_control, screen_end],
+                false,
+                Color32::TRANSPARENT,
+                stroke,
+            ).into());
+            // The curve leaves its control point along the end tangent
+            (screen_end - screen_control).normalized()
+        };
 
-        // Arrow head
+        // Arrow head, pointing along the tangent at the end
         let arrow_size = 10.0;
         let perp = Vec2::new(-dir.y, dir.x);
         let tip = screen_end - dir * arrow_size;
@@ -170,10 +410,62 @@ impl DisplayEdge<LogNodeData, (), Directed, u32, LogNode> for LogEdge {
             Stroke::NONE,
         ));
 
+        if !self.label.is_empty() {
+            let text = if self.label.chars().count() > MAX_EDGE_LABEL_CHARS {
+                format!("{}…", self.label.chars().take(MAX_EDGE_LABEL_CHARS - 1).collect::<String>())
+            } else {
+                self.label.clone()
+            };
+            let text_color = Color32::LIGHT_GRAY.gamma_multiply(fade);
+            let galley = ctx.ctx.fonts_mut(|f| {
+                f.layout_no_wrap(text, FontId::new(11.0, FontFamily::Proportional), text_color)
+            });
+            let mid = quadratic_point([screen_start, screen_control, screen_end], 0.5);
+            let text_pos = mid - galley.size() / 2.0;
+            shapes.push(egui::epaint::TextShape::new(text_pos, galley, text_color).into());
+        }
+
         shapes
     }
 
-    fn update(&mut self, state: &EdgeProps<()>) {
+    fn update(&mut self, state: &EdgeProps<LogEdgeData>) {
         self.selected = state.selected;
+        self.auto = state.payload.auto;
+        self.label.clone_from(&state.payload.label);
+        self.curvature = curvature_for(&state.payload);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn boundary_points_lie_on_the_shape() {
+        let radius = 20.0;
+        for shape in NoteShape::ALL {
+            for step in 0..36 {
+                let dir = Vec2::angled(step as f32 * 10f32.to_radians() + 0.05);
+                let edge = shape.boundary(dir, radius);
+                let size = match shape {
+                    NoteShape::Circle => edge.length(),
+                    NoteShape::Square => edge.x.abs().max(edge.y.abs()),
+                    NoteShape::Diamond => edge.x.abs() + edge.y.abs(),
+                };
+                assert!((size - radius).abs() < 1e-3, "{shape:?} {dir:?}");
+                assert!(edge.normalized().dot(dir) > 0.999);
+                assert!(shape.contains(edge * 0.999, radius), "{shape:?} {dir:?}");
+                assert!(!shape.contains(edge * 1.01, radius), "{shape:?} {dir:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn corners_separate_the_shapes() {
+        let corner = Vec2::splat(15.0);
+        assert!(NoteShape::Square.contains(corner, 20.0));
+        assert!(!NoteShape::Circle.contains(corner, 20.0));
+        assert!(!NoteShape::Diamond.contains(corner, 20.0));
+        assert_eq!(NoteShape::Square.boundary(Vec2::ZERO, 20.0), Vec2::ZERO);
     }
 }
@@ -0,0 +1,157 @@
+use egui::{Pos2, Rect, Vec2};
+
+/// Number of simulation steps run by [`fruchterman_reingold`].
+pub const ITERATIONS: usize = 200;
+/// Preferred distance between linked notes, in canvas units.
+pub const IDEAL_EDGE_LENGTH: f32 = 120.0;
+/// Screen-space gap kept between the fitted graph and the viewport edge.
+pub const FIT_MARGIN: f32 = 40.0;
+/// Zoom limits for [`fit_view`]; the upper one keeps a lone note from filling the screen.
+pub const MIN_FIT_ZOOM: f32 = 0.05;
+pub const MAX_FIT_ZOOM: f32 = 2.0;
+
+/// Fruchterman–Reingold force-directed layout.
+///
+/// All nodes repel each other (`k² / d`) while edges pull their endpoints
+/// together (`d² / k`). Each step's movement is capped by a temperature that
+/// cools linearly to zero. The result is re-centred on the input centroid so
+/// the graph doesn't drift. There is no randomness: the same input always
+/// yields the same output, and coincident nodes are pushed apart along a
+/// direction derived from their indices.
+pub fn fruchterman_reingold(positions: &[Pos2], edges: &[(usize, usize)], iterations: usize) -> Vec<Pos2> {
+    let n = positions.len();
+    let mut pos = positions.to_vec();
+    if n < 2 {
+        return pos;
+    }
+
+    let k = IDEAL_EDGE_LENGTH;
+    let start_temp = k * (n as f32).sqrt() * 0.5;
+    let origin = centroid(positions);
+    let mut displacement = vec![Vec2::ZERO; n];
+
+    for step in 0..iterations {
+        displacement.fill(Vec2::ZERO);
+
+        for i in 0..n {
+            for j in (i + 1)..n {
+                let delta = pos[i] - pos[j];
+                let dist = delta.length();
+                let dir = if dist > 1e-3 {
+                    delta / dist
+                } else {
+                    let angle = (i * 31 + j * 17) as f32;
+                    Vec2::angled(angle)
+                };
+                let force = k * k / dist.max(1.0);
+                displacement[i] += dir * force;
+                displacement[j] -= dir * force;
+            }
+        }
+
+        for &(a, b) in edges {
+            if a == b || a >= n || b >= n {
+                continue;
+            }
+            let delta = pos[a] - pos[b];
+            let dist = delta.length();
+            if dist < 1e-3 {
+                continue;
+            }
+            let force = dist * dist / k;
+            displacement[a] -= delta / dist * force;
+            displacement[b] += delta / dist * force;
+        }
+
+        let temp = start_temp * (1.0 - step as f32 / iterations as f32);
+        for (p, d) in pos.iter_mut().zip(&displacement) {
+            let len = d.length();
+            if len > 0.0 {
+                *p += *d / len * len.min(temp);
+            }
+        }
+    }
+
+    let shift = origin - centroid(&pos);
+    for p in &mut pos {
+        *p += shift;
+    }
+    pos
+}
+
+/// Pan and zoom that show all of `bounds` (canvas units) centred in a
+/// viewport of `viewport` size, with [`FIT_MARGIN`] to spare.
+pub fn fit_view(bounds: Rect, viewport: Vec2) -> (Vec2, f32) {
+    let room = (viewport - Vec2::splat(2.0 * FIT_MARGIN)).max(Vec2::splat(1.0));
+    let size = bounds.size().max(Vec2::splat(1.0));
+    let zoom = (room.x / size.x).min(room.y / size.y).clamp(MIN_FIT_ZOOM, MAX_FIT_ZOOM);
+    let pan = viewport / 2.0 - bounds.center().to_vec2() * zoom;
+    (pan, zoom)
+}
+
+fn centroid(points: &[Pos2]) -> Pos2 {
+    let sum = points.iter().fold(Vec2::ZERO, |acc, p| acc + p.to_vec2());
+    (sum / points.len().max(1) as f32).to_pos2()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn min_separation(points: &[Pos2]) -> f32 {
+        let mut min = f32::INFINITY;
+        for (i, a) in points.iter().enumerate() {
+            for b in &points[i + 1..] {
+                min = min.min(a.distance(*b));
+            }
+        }
+        min
+    }
+
+    fn piled_up() -> (Vec<Pos2>, Vec<(usize, usize)>) {
+        // what repeated `+50,+50` wikilink placement produces
+        let positions = (0..6).map(|i| Pos2::new(i as f32 * 2.0, i as f32 * 2.0)).collect();
+        let edges = vec![(0, 1), (0, 2), (0, 3), (3, 4), (4, 5)];
+        (positions, edges)
+    }
+
+    #[test]
+    fn layout_increases_node_separation() {
+        let (positions, edges) = piled_up();
+        let laid_out = fruchterman_reingold(&positions, &edges, ITERATIONS);
+        assert!(min_separation(&laid_out) > min_separation(&positions) * 10.0);
+    }
+
+    #[test]
+    fn layout_is_deterministic() {
+        let (positions, edges) = piled_up();
+        let first = fruchterman_reingold(&positions, &edges, ITERATIONS);
+        let second = fruchterman_reingold(&positions, &edges, ITERATIONS);
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn fit_view_centres_the_bounds() {
+        let bounds = Rect::from_min_max(Pos2::new(-100.0, 0.0), Pos2::new(300.0, 200.0));
+        let viewport = Vec2::new(800.0, 600.0);
+        let (pan, zoom) = fit_view(bounds, viewport);
+        let on_screen = |p: Pos2| p.to_vec2() * zoom + pan;
+        assert!((on_screen(bounds.center()) - viewport / 2.0).length() < 1e-3);
+        assert!(on_screen(bounds.min).x >= FIT_MARGIN - 1e-3);
+        assert!(on_screen(bounds.max).x <= viewport.x - FIT_MARGIN + 1e-3);
+    }
+
+    #[test]
+    fn fit_view_handles_a_single_point() {
+        let (pan, zoom) = fit_view(Rect::from_center_size(Pos2::new(5.0, 5.0), Vec2::ZERO), Vec2::new(800.0, 600.0));
+        assert!(zoom.is_finite() && pan.is_finite());
+        assert!(zoom <= MAX_FIT_ZOOM);
+    }
+
+    #[test]
+    fn coincident_nodes_are_separated() {
+        let positions = vec![Pos2::ZERO; 3];
+        let laid_out = fruchterman_reingold(&positions, &[], ITERATIONS);
+        assert!(min_separation(&laid_out) > 1.0);
+    }
+}
@@ -1,5 +1,7 @@
 pub mod app;
 pub mod graph;
+pub mod layout;
+pub mod storage;
 
 pub use app::LogMarkApp;
 
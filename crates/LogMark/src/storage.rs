@@ -0,0 +1,278 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use egui::Pos2;
+use egui_graphs::Graph;
+use petgraph::stable_graph::{NodeIndex, StableGraph};
+use petgraph::Directed;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::app::split_wikilink;
+use crate::graph::{LogEdge, LogEdgeData, LogNode, LogNodeData};
+
+/// The graph type `LogMark` edits and persists.
+pub type LogGraph = Graph<LogNodeData, LogEdgeData, Directed, u32, LogNode, LogEdge>;
+
+/// A note together with its canvas position.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SavedNode {
+    #[serde(flatten)]
+    pub data: LogNodeData,
+    pub x: f32,
+    pub y: f32,
+}
+
+/// On-disk JSON document. Edges refer to positions in `nodes`, not to graph
+/// indices, so removed nodes leave no holes in the file.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SavedGraph {
+    pub nodes: Vec<SavedNode>,
+    /// Edges created from wikilinks.
+    pub edges: Vec<SavedEdge>,
+    /// Edges drawn by hand, which wikilink syncing never removes.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub manual_edges: Vec<[usize; 2]>,
+    /// Camera at the time of saving, so reopening returns to the same view.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub viewport: Option<SavedViewport>,
+}
+
+/// Graph view pan, in screen points, and zoom factor.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SavedViewport {
+    pub pan: [f32; 2],
+    pub zoom: f32,
+}
+
+/// A wikilink edge: a bare `[from, to]` pair when it has no label, which is
+/// also how documents from before edge labels store every edge.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SavedEdge {
+    Plain([usize; 2]),
+    Labeled { from: usize, to: usize, label: String },
+}
+
+impl SavedGraph {
+    pub fn from_graph(graph: &LogGraph) -> Self {
+        let mut doc = Self::default();
+        let mut slots = std::collections::HashMap::new();
+        let nodes = graph.g().node_indices().filter_map(|idx| Some((idx, graph.node(idx)?)));
+        for (slot, (idx, node)) in nodes.enumerate() {
+            let pos = node.location();
+            slots.insert(idx, slot);
+            doc.nodes.push(SavedNode { data: node.payload().clone(), x: pos.x, y: pos.y });
+        }
+        for edge in graph.g().edge_indices() {
+            let (Some((a, b)), Some(e)) = (graph.g().edge_endpoints(edge), graph.edge(edge)) else {
+                continue;
+            };
+            let ends = [slots[&a], slots[&b]];
+            let label = &e.payload().label;
+            if !e.payload().auto {
+                doc.manual_edges.push(ends);
+            } else if label.is_empty() {
+                doc.edges.push(SavedEdge::Plain(ends));
+            } else {
+                doc.edges.push(SavedEdge::Labeled { from: ends[0], to: ends[1], label: label.clone() });
+            }
+        }
+        doc
+    }
+
+    /// Rebuild the graph, restoring each node at its saved position.
+    ///
+    /// # Errors
+    /// Returns `InvalidData` if an edge refers to a node that is not in the document.
+    pub fn into_graph(self) -> io::Result<LogGraph> {
+        let mut g = StableGraph::new();
+        let mut indices: Vec<(NodeIndex, Pos2)> = Vec::with_capacity(self.nodes.len());
+        for node in self.nodes {
+            indices.push((g.add_node(node.data), Pos2::new(node.x, node.y)));
+        }
+        let auto = self.edges.into_iter().map(|e| match e {
+            SavedEdge::Plain(ends) => (ends, LogEdgeData::wikilink(String::new())),
+            SavedEdge::Labeled { from, to, label } => ([from, to], LogEdgeData::wikilink(label)),
+        });
+        let manual = self.manual_edges.into_iter().map(|e| (e, LogEdgeData::default()));
+        for ([a, b], data) in auto.chain(manual) {
+            let (Some((from, _)), Some((to, _))) = (indices.get(a), indices.get(b)) else {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("edge {a} -> {b} refers to a missing node"),
+                ));
+            };
+            g.add_edge(*from, *to, data);
+        }
+
+        let mut graph = Graph::from(&g);
+        for (idx, pos) in indices {
+            if let Some(node) = graph.node_mut(idx) {
+                node.set_location(pos);
+            }
+        }
+        Ok(graph)
+    }
+}
+
+/// Write every note to `dir` as `{label}.md` with its content as the body,
+/// so wikilinks resolve when the folder is opened as an Obsidian vault.
+/// Links to a note whose file name had to differ from its label point at the
+/// file name instead, still showing the label. Returns the number of files
+/// written.
+///
+/// # Errors
+/// Fails with `AlreadyExists`, before writing anything, if one of the files
+/// is already in `dir`, and otherwise if the directory or a file cannot be
+/// written.
+pub fn export_vault(graph: &LogGraph, dir: &Path, wikilinks: &Regex) -> io::Result<usize> {
+    let mut taken = HashSet::new();
+    let mut stems = HashMap::new();
+    let mut notes = Vec::new();
+    for node in graph.g().node_weights() {
+        let data = node.payload();
+        let name = vault_file_name(&data.label, &mut taken);
+        stems.entry(data.label.as_str()).or_insert_with(|| name.trim_end_matches(".md").to_string());
+        notes.push((data, name));
+    }
+    fs::create_dir_all(dir)?;
+    if let Some((_, name)) = notes.iter().find(|(_, name)| dir.join(name).exists()) {
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{name} is already in that folder")));
+    }
+    for (data, name) in &notes {
+        fs::write(dir.join(name), vault_links(wikilinks, &data.content, &stems))?;
+    }
+    Ok(notes.len())
+}
+
+/// `content` with each wikilink whose target is exported under another file
+/// stem pointing at that stem, showing the link's alias or else the label.
+fn vault_links(wikilinks: &Regex, content: &str, stems: &HashMap<&str, String>) -> String {
+    wikilinks
+        .replace_all(content, |cap: &regex::Captures| {
+            let (target, alias) = split_wikilink(&cap[1]);
+            match stems.get(target) {
+                Some(stem) if stem != target => format!("[[{stem}|{}]]", alias.unwrap_or(target)),
+                _ => cap[0].to_string(),
+            }
+        })
+        .into_owned()
+}
+
+/// Label and content of every `.md` file directly inside `dir`, by file name.
+/// The label is the file name without its extension.
+///
+/// # Errors
+/// Fails if the directory or one of its Markdown files cannot be read.
+pub fn read_vault(dir: &Path) -> io::Result<Vec<(String, String)>> {
+    let mut notes = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if !path.is_file() || path.extension().is_none_or(|ext| !ext.eq_ignore_ascii_case("md")) {
+            continue;
+        }
+        let Some(label) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        notes.push((label.to_string(), fs::read_to_string(&path)?));
+    }
+    notes.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(notes)
+}
+
+/// `{label}.md` with characters that are unsafe in file names replaced, and
+/// ` (2)`, ` (3)`… appended when an earlier note already took the name.
+/// Names are compared case-insensitively, as on Windows and macOS.
+fn vault_file_name(label: &str, taken: &mut HashSet<String>) -> String {
+    const RESERVED: [&str; 22] = [
+        "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+        "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+    ];
+    let cleaned: String = label
+        .chars()
+        .map(|c| if c.is_control() || matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') { '_' } else { c })
+        .collect();
+    let mut stem = cleaned.trim().trim_end_matches('.').to_string();
+    if stem.is_empty() {
+        stem = "Untitled".to_string();
+    }
+    if RESERVED.iter().any(|r| r.eq_ignore_ascii_case(&stem)) {
+        stem.push('_');
+    }
+    let mut name = format!("{stem}.md");
+    let mut counter = 2;
+    while !taken.insert(name.to_lowercase()) {
+        name = format!("{stem} ({counter}).md");
+        counter += 1;
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tags_and_edge_kinds_round_trip() {
+        let mut g = StableGraph::new();
+        let mut tagged = LogNodeData::new("Home".to_string(), "#rust #notes".to_string());
+        tagged.tags = vec!["rust".to_string(), "notes".to_string()];
+        let a = g.add_node(tagged);
+        let b = g.add_node(LogNodeData::new("Ideas".to_string(), String::new()));
+        g.add_edge(a, b, LogEdgeData::wikilink("see also".to_string()));
+        g.add_edge(b, a, LogEdgeData::default());
+
+        let json = serde_json::to_string(&SavedGraph::from_graph(&Graph::from(&g))).unwrap();
+        let doc: SavedGraph = serde_json::from_str(&json).unwrap();
+        assert_eq!(doc.nodes[0].data.tags, ["rust", "notes"]);
+        assert!(doc.nodes[0].data.created > 0);
+        assert_eq!(doc.nodes[0].data.modified, doc.nodes[0].data.created);
+        assert!(doc.nodes[1].data.tags.is_empty());
+        assert_eq!((doc.edges.len(), doc.manual_edges.len()), (1, 1));
+        let graph = doc.into_graph().unwrap();
+        assert_eq!(graph.g().edge_count(), 2);
+        assert!(graph.g().edge_weights().any(|e| e.payload().label == "see also"));
+    }
+
+    #[test]
+    fn documents_without_tags_still_load() {
+        let json = r#"{"nodes":[{"label":"Home","content":"","x":0.0,"y":0.0}],"edges":[]}"#;
+        let doc: SavedGraph = serde_json::from_str(json).unwrap();
+        assert!(doc.nodes[0].data.tags.is_empty());
+        assert_eq!((doc.nodes[0].data.created, doc.nodes[0].data.modified), (0, 0));
+    }
+
+    #[test]
+    fn vault_file_names_are_safe_and_unique() {
+        let mut taken = HashSet::new();
+        assert_eq!(vault_file_name("Ideas", &mut taken), "Ideas.md");
+        assert_eq!(vault_file_name("ideas", &mut taken), "ideas (2).md");
+        assert_eq!(vault_file_name("a/b: c?", &mut taken), "a_b_ c_.md");
+        assert_eq!(vault_file_name("  ", &mut taken), "Untitled.md");
+        assert_eq!(vault_file_name("con", &mut taken), "con_.md");
+    }
+
+    #[test]
+    fn vault_export_rewrites_links_to_sanitised_names() {
+        let mut g = StableGraph::new();
+        g.add_node(LogNodeData::new("Home".to_string(), "See [[a/b: c?]], [[ a/b: c? |that]] and [[Home]].".to_string()));
+        g.add_node(LogNodeData::new("a/b: c?".to_string(), "Back to [[Home]]".to_string()));
+        let graph = Graph::from(&g);
+        let wikilinks = Regex::new(r"\[\[(.*?)\]\]").unwrap();
+        let dir = std::env::temp_dir().join(format!("logmark-vault-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        assert_eq!(export_vault(&graph, &dir, &wikilinks).unwrap(), 2);
+        let notes = read_vault(&dir).unwrap();
+        assert_eq!(notes[0], ("Home".to_string(), "See [[a_b_ c_|a/b: c?]], [[a_b_ c_|that]] and [[Home]].".to_string()));
+        assert_eq!(notes[1], ("a_b_ c_".to_string(), "Back to [[Home]]".to_string()));
+
+        // a second export into the same folder leaves the files alone
+        let err = export_vault(&graph, &dir, &wikilinks).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
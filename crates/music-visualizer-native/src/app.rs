@@ -1,22 +1,78 @@
 use std::sync::{Arc, Mutex};
 use eframe::egui::{self, Color32, Pos2, Rect};
-use crate::audio_analysis::AudioAnalysis;
-use crate::visualizer_config::VisualizerConfig;
-use crate::playlist::PlaylistState;
-use crate::particle::Particle;
+use crate::audio_analysis::{demo_buffer, rms, sorted_band_edges, AudioAnalysis, ChannelMode, ANALYSIS_SAMPLE_RATE};
+use crate::band_history::BandHistory;
+use crate::capture::{self, CaptureStream};
+use crate::decoded_track::DecodedTrack;
+use crate::midi::{self, MidiConnection, MidiEvent};
+use crate::osc::{OscArg, OscMessage, OscServer};
+use crate::recorder::{write_png, FrameRecorder};
+use crate::visualizer_config::{custom_presets_path, presets, save_custom_presets, AudioFeature, ConfigParam, CustomPreset, FractalKind, MAX_BASE_DEPTH, MAX_BRANCHES, MAX_STROBE_INTENSITY, MIN_BRANCHES, MidiCcMapping, MidiTarget, Palette, ParticleShape, ReactivityEntry, ReactivityMap, UpOrientation, VisualizerConfig, WaveformStyle};
+use crate::playlist::{normalization_gain_db, PlaylistState, SortKey};
+use crate::playback_clock::PlaybackClock;
+use crate::particle::{burst_count, burst_speed_scale, Particle};
 use crate::unknown_pleasures::UnknownPleasuresVisualizer;
+use crate::ui::{draw_vu_meter, FractalCache, FractalTrails};
 // `ui` helpers are accessed explicitly where needed; avoid glob import which was unused.
 use rodio::{OutputStream, OutputStreamHandle, Sink, Decoder, Source};
-use std::fs::File;
-use std::io::BufReader;
-use std::time::Instant;
+use std::fs::{self, File};
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::{Duration, Instant};
 use rfd::FileDialog;
-use hound;
+
+/// Keyboard bindings handled by `handle_shortcuts`, as shown in the `?` overlay.
+pub const SHORTCUTS: [(&str, &str); 10] = [
+    ("Space", "Play / pause"),
+    ("← / →", "Previous / next track"),
+    ("Shift + ← / →", "Seek 5 s back / forward"),
+    ("↑ / ↓", "Volume up / down"),
+    ("S", "Toggle shuffle"),
+    ("?", "Show / hide this help"),
+    ("Esc", "Close this help"),
+    ("F", "Freeze / unfreeze the visuals"),
+    ("F11", "Toggle presentation mode"),
+    ("Esc / mouse move", "Show the presentation exit button"),
+];
+const SEEK_STEP_SECS: f64 = 5.0;
+// TouchOSC's default outgoing port
+const DEFAULT_OSC_PORT: u16 = 9000;
+/// OSC addresses handled by `apply_osc`, as listed in the settings panel.
+pub const OSC_ADDRESSES: [(&str, &str); 9] = [
+    ("/visualizer/zoom", "0.01 – 10"),
+    ("/visualizer/width", "0.1 – 5"),
+    ("/visualizer/depth", "1 – 24"),
+    ("/visualizer/brightness", "0 – 2"),
+    ("/visualizer/rotation_speed", "0 – 10"),
+    ("/visualizer/glow", "0 – 2"),
+    ("/visualizer/beat_sensitivity", "1 – 3"),
+    ("/visualizer/mode", "0 fractal, 1 unknown pleasures, 2 radial (or the name)"),
+    ("/visualizer/fractal", "0 – 3 (or the name)"),
+];
+const VOLUME_STEP: f32 = 0.05;
+// how long the presentation exit overlay stays up after the last mouse move or Esc
+pub const PRESENTATION_OVERLAY_SECS: f64 = 2.5;
+// resolutions offered for the Unknown Pleasures PNG export
+const PNG_EXPORT_SIZES: [(&str, [usize; 2]); 4] = [
+    ("1920×1080", [1920, 1080]),
+    ("2560×1440", [2560, 1440]),
+    ("3840×2160 (4K)", [3840, 2160]),
+    ("4960×7016 (A4 poster)", [4960, 7016]),
+];
+// shown over the visuals while no capture device is open
+const NO_INPUT_BANNER: &str = "No audio input — using demo mode";
+// repaint rate while nothing plays and the input is silent, and the volume counted as silence
+const IDLE_FPS: u32 = 5;
+const IDLE_VOLUME: f32 = 0.001;
+// attract mode plays the demo signal at this fraction of its level
+const ATTRACT_LEVEL: f32 = 0.3;
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum VisualizerMode {
     Fractal,
     UnknownPleasures,
+    RadialSpectrum,
 }
 
 pub struct MusicVisualizerNativeApp {
@@ -26,28 +82,110 @@ pub struct MusicVisualizerNativeApp {
     pub rotation: f32,
     pub particles: Vec<Particle>,
     pub playlist: PlaylistState,
+    // text of the position being typed after double-clicking the elapsed time
+    pub seek_text: Option<String>,
     pub beat_flash: f32,
+    // fractal camera: screen offset of the origin and scale factor
+    pub pan: egui::Vec2,
+    pub zoom: f32,
     pub visualizer_mode: VisualizerMode,
     pub unknown_visualizer: UnknownPleasuresVisualizer,
+    // area the visualizer was last drawn into, used for exports
+    pub visual_rect: Rect,
     pub audio_data: Arc<Mutex<Vec<f32>>>,
     // demo mode uses generated audio if true
     pub demo_mode: bool,
     pub show_settings: bool,
+    pub show_shortcuts: bool, // keyboard help overlay, toggled with `?`
+    pub show_band_debug: bool, // scrolling raw/smoothed band plots for tuning reactivity
+    pub band_history: BandHistory,
+    // fullscreen visual with every panel hidden, toggled with F11
+    pub presentation_mode: bool,
+    // wall-clock time until which the presentation exit overlay is shown
+    pub presentation_overlay_until: f64,
+    // visuals held on the current frame while audio keeps playing, toggled with F
+    pub frozen: bool,
+    // `time` of the last sound, playback, key or pointer event
+    pub last_activity: f64,
+    // self-animating screensaver after `idle_timeout_secs` of nothing happening
+    pub attract_mode: bool,
+    pub playlist_filter: String, // case-insensitive track name filter
+    pub playback_rate: f32, // speed factor for file playback, 0.5..=2.0
+    #[cfg(feature = "pitch-preserve")]
+    pub preserve_pitch: bool,
     pub show_spectrum: bool,
     pub show_waveform: bool,
+    // held maximum of each spectrum bar, one entry per drawn bar
+    pub spectrum_peaks: Vec<f32>,
+    // eased copies of the spectrum bar levels and waveform samples actually drawn
+    pub spectrum_smoothed: Vec<f32>,
+    pub waveform_smoothed: Vec<f32>,
+    pub fractal_trails: FractalTrails,
+    // last fractal's shapes, replayed while its inputs hold still
+    pub fractal_cache: FractalCache,
     // system audio capture flag
     pub system_audio_mode: bool,
+    // live input stream feeding `audio_data`, and the devices offered in the picker
+    pub capture: Option<CaptureStream>,
+    pub input_devices: Vec<String>,
+    // last capture problem (missing device, disconnect), shown under the source picker
+    pub capture_message: Option<String>,
+    capture_error_tx: Sender<cpal::StreamError>,
+    capture_error_rx: Receiver<cpal::StreamError>,
+    // optional MIDI controller: note-ons trigger beats, CCs set `config.midi_cc_map` targets
+    pub midi: Option<MidiConnection>,
+    pub midi_ports: Vec<String>,
+    pub midi_message: Option<String>,
+    // most recent (controller, value), to help find a knob's CC number
+    pub midi_last_cc: Option<(u8, u8)>,
+    midi_tx: Sender<MidiEvent>,
+    midi_rx: Receiver<MidiEvent>,
+    // OSC control server; the listener thread fills `osc_inbox`, drained each frame
+    pub osc_port: u16,
+    pub osc_lan: bool, // listen on every interface rather than only localhost
+    pub osc: Option<OscServer>,
+    pub osc_message: Option<String>,
+    osc_inbox: Arc<Mutex<Vec<OscMessage>>>,
+    // PNG sequence of the visual, written to `record_dir` while `recorder` is set
+    pub record_dir: Option<PathBuf>,
+    // index into `PNG_EXPORT_SIZES` for the Unknown Pleasures poster export
+    pub png_export_size: usize,
+    pub recorder: Option<FrameRecorder>,
+    pub record_message: Option<String>,
+    // user-saved configs, persisted to `custom_presets_path()`
+    pub custom_presets: Vec<CustomPreset>,
+    pub preset_name: String,
+    pub preset_message: Option<String>,
+    // pasted config string waiting to be applied
+    pub share_input: String,
     // rodio output for file playback
     pub output_stream: Option<OutputStream>,
     pub output_stream_handle: Option<OutputStreamHandle>,
     pub current_sink: Option<Sink>,
-    // playback timing helpers for approximate tracking / seeking
-    pub playback_start_instant: Option<Instant>,
-    pub playback_seek_offset: f64,
+    // outgoing track while crossfading into `current_sink`, and when the fade began
+    pub fading_sink: Option<Sink>,
+    pub crossfade_start: Option<Instant>,
+    // volume the outgoing track started its fade from
+    fading_volume: f32,
+    // playback position, driven by the samples the output device consumes
+    pub clock: PlaybackClock,
+    // decoded samples of the current track, filled by a background thread
+    pub decoded_cache: Arc<Mutex<Option<DecodedTrack>>>,
+    // (path, seconds) from background duration scans of files without a length header
+    duration_tx: Sender<(String, f64)>,
+    duration_rx: Receiver<(String, f64)>,
+    // files queued for the loudness worker, and the (path, gain in dB) it sends back
+    loudness_jobs: Sender<PathBuf>,
+    loudness_rx: Receiver<(String, f32)>,
 }
 
 impl MusicVisualizerNativeApp {
     pub fn with_audio_data(audio_data: Arc<Mutex<Vec<f32>>>) -> Self {
+        let (duration_tx, duration_rx) = mpsc::channel();
+        let (loudness_tx, loudness_rx) = mpsc::channel();
+        let loudness_jobs = spawn_loudness_worker(loudness_tx);
+        let (capture_error_tx, capture_error_rx) = mpsc::channel();
+        let (midi_tx, midi_rx) = mpsc::channel();
         Self {
             audio: AudioAnalysis::new(),
             config: VisualizerConfig::default(),
@@ -55,20 +193,72 @@ impl MusicVisualizerNativeApp {
             rotation: 0.0,
             particles: Vec::new(),
             playlist: PlaylistState::default(),
+            seek_text: None,
             beat_flash: 0.0,
+            pan: egui::Vec2::ZERO,
+            zoom: 1.0,
             visualizer_mode: VisualizerMode::Fractal,
             unknown_visualizer: UnknownPleasuresVisualizer::new(),
+            visual_rect: Rect::from_min_size(Pos2::ZERO, egui::vec2(800.0, 600.0)),
             audio_data,
             show_settings: false,
+            show_shortcuts: false,
+            show_band_debug: false,
+            band_history: BandHistory::default(),
+            presentation_mode: false,
+            frozen: false,
+            last_activity: 0.0,
+            attract_mode: false,
+            presentation_overlay_until: 0.0,
+            playlist_filter: String::new(),
+            playback_rate: 1.0,
+            #[cfg(feature = "pitch-preserve")]
+            preserve_pitch: true,
             show_spectrum: false,
             show_waveform: false,
+            spectrum_peaks: Vec::new(),
+            spectrum_smoothed: Vec::new(),
+            waveform_smoothed: Vec::new(),
+            fractal_trails: FractalTrails::default(),
+            fractal_cache: FractalCache::default(),
             system_audio_mode: true,
+            capture: None,
+            input_devices: Vec::new(),
+            capture_message: None,
+            capture_error_tx,
+            capture_error_rx,
+            midi: None,
+            midi_ports: Vec::new(),
+            midi_message: None,
+            midi_last_cc: None,
+            midi_tx,
+            midi_rx,
+            osc_port: DEFAULT_OSC_PORT,
+            osc_lan: false,
+            osc: None,
+            osc_message: None,
+            osc_inbox: Arc::new(Mutex::new(Vec::new())),
+            record_dir: None,
+            recorder: None,
+            record_message: None,
+            png_export_size: 0,
+            custom_presets: Vec::new(),
+            preset_name: String::new(),
+            preset_message: None,
+            share_input: String::new(),
             demo_mode: true,
             output_stream: None,
             output_stream_handle: None,
             current_sink: None,
-            playback_start_instant: None,
-            playback_seek_offset: 0.0,
+            fading_sink: None,
+            fading_volume: 0.0,
+            crossfade_start: None,
+            clock: PlaybackClock::default(),
+            decoded_cache: Arc::new(Mutex::new(None)),
+            duration_tx,
+            duration_rx,
+            loudness_jobs,
+            loudness_rx,
         }
     }
 
@@ -87,17 +277,55 @@ impl MusicVisualizerNativeApp {
                     if ui.selectable_label(self.visualizer_mode == VisualizerMode::UnknownPleasures, "Unknown Pleasures").clicked() {
                         self.visualizer_mode = VisualizerMode::UnknownPleasures;
                     }
+                    if ui.selectable_label(self.visualizer_mode == VisualizerMode::RadialSpectrum, "Radial Spectrum").clicked() {
+                        self.visualizer_mode = VisualizerMode::RadialSpectrum;
+                    }
                 });
 
                 // Unknown Pleasures quick preset
                 if self.visualizer_mode == VisualizerMode::UnknownPleasures {
                     ui.add_space(4.0);
                     if ui.button("Apply 'Image' Preset").clicked() {
-                        self.config = VisualizerConfig::preset_unknown_pleasures_image();
+                        self.config.apply_preset(VisualizerConfig::preset_unknown_pleasures_image());
                     }
                 }
             });
 
+            ui.collapsing("🎨 Presets", |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    for (name, config) in presets() {
+                        if ui.button(name).clicked() { self.config.apply_preset(config); }
+                    }
+                });
+                let mut remove = None;
+                for (i, preset) in self.custom_presets.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        if ui.button(&preset.name).clicked() { self.config.apply_preset(preset.config.clone()); }
+                        if ui.small_button("🗑").on_hover_text("Delete preset").clicked() { remove = Some(i); }
+                    });
+                }
+                if let Some(i) = remove { self.delete_custom_preset(i); }
+                ui.horizontal(|ui| {
+                    ui.add(egui::TextEdit::singleline(&mut self.preset_name).hint_text("Preset name").desired_width(120.0));
+                    if ui.add_enabled(!self.preset_name.trim().is_empty(), egui::Button::new("💾 Save current")).clicked() { self.save_custom_preset(); }
+                });
+                if ui.button("📋 Copy Config").on_hover_text("Copy the current settings as a shareable string").clicked() {
+                    let shared = self.config.to_share_string();
+                    ui.output_mut(|o| o.copied_text = shared);
+                    self.preset_message = Some("Config copied to clipboard".to_string());
+                }
+                ui.horizontal(|ui| {
+                    ui.add(egui::TextEdit::singleline(&mut self.share_input).hint_text("Paste config string").desired_width(120.0));
+                    if ui.add_enabled(!self.share_input.trim().is_empty(), egui::Button::new("Paste Config")).clicked() {
+                        self.preset_message = Some(match VisualizerConfig::from_share_string(&self.share_input) {
+                            Ok(config) => { self.config = config; self.share_input.clear(); "Config applied".to_string() }
+                            Err(e) => e,
+                        });
+                    }
+                });
+                if let Some(msg) = &self.preset_message { ui.label(egui::RichText::new(msg).small()); }
+            });
+
             // ===== PLAYLIST SECTION =====
             ui.collapsing("🎶 Playlist", |ui| {
                 ui.horizontal(|ui| {
@@ -105,12 +333,12 @@ impl MusicVisualizerNativeApp {
                         self.trigger_file_input();
                     }
                     let shuffle_text = if self.playlist.is_shuffled { "🔀 On" } else { "🔀 Off" };
-                    if ui.button(shuffle_text).clicked() {
-                        self.playlist.is_shuffled = !self.playlist.is_shuffled;
-                        if self.playlist.is_shuffled {
-                            self.playlist.shuffle_playlist();
+                    if ui.button(shuffle_text).clicked() { self.toggle_shuffle(); }
+                    ui.menu_button("↕ Sort", |ui| {
+                        for (label, key, ascending) in [("Sort by name", SortKey::Name, true), ("Sort by duration ↑", SortKey::Duration, true), ("Sort by duration ↓", SortKey::Duration, false)] {
+                            if ui.button(label).clicked() { self.playlist.sort_tracks(key, ascending); ui.close_menu(); }
                         }
-                    }
+                    });
                 });
 
                 ui.label("Supported: MP3, WAV, OGG, FLAC, AAC, M4A");
@@ -126,7 +354,7 @@ impl MusicVisualizerNativeApp {
                         let current_time = PlaylistState::format_time(self.playlist.current_time);
                         let total_time = PlaylistState::format_time(self.playlist.duration);
                         ui.horizontal(|ui| {
-                            ui.label(&current_time);
+                            self.seek_time_ui(ui, &current_time);
                             let progress_response = ui.add(
                                 egui::ProgressBar::new(progress)
                                     .desired_width(ui.available_width() - 50.0)
@@ -139,8 +367,30 @@ impl MusicVisualizerNativeApp {
                                     self.seek_to(seek_time);
                                 }
                             }
+                            // A/B loop markers, with the looped stretch tinted between them
+                            if self.playlist.duration > 0.0 {
+                                let rect = progress_response.rect;
+                                let x = |t: f64| rect.left() + (t / self.playlist.duration).clamp(0.0, 1.0) as f32 * rect.width();
+                                let marker = egui::Color32::from_rgb(255, 200, 60);
+                                if let Some((a, b)) = self.playlist.loop_range() {
+                                    let span = egui::Rect::from_x_y_ranges(x(a)..=x(b), rect.y_range());
+                                    ui.painter().rect_filled(span, 2.0, marker.gamma_multiply(0.25));
+                                }
+                                for t in [track.loop_start, track.loop_end].into_iter().flatten() {
+                                    ui.painter().vline(x(t), rect.y_range(), egui::Stroke::new(2.0, marker));
+                                }
+                            }
                             ui.label(&total_time);
                         });
+                        ui.horizontal(|ui| {
+                            ui.label("Loop:");
+                            if ui.button("A").on_hover_text("Start the loop here").clicked() { self.playlist.set_loop_start(); }
+                            if ui.button("B").on_hover_text("End the loop here and start repeating").clicked() { self.playlist.set_loop_end(); }
+                            if ui.add_enabled(track.loop_start.is_some() || track.loop_end.is_some(), egui::Button::new("✖")).on_hover_text("Clear the loop").clicked() { self.playlist.clear_loop(); }
+                            if let Some((a, b)) = self.playlist.loop_range() {
+                                ui.weak(format!("{} – {}", PlaylistState::format_time(a), PlaylistState::format_time(b)));
+                            }
+                        });
 
                         ui.horizontal(|ui| {
                             if ui.button("⏮").clicked() { self.play_previous(); }
@@ -148,7 +398,9 @@ impl MusicVisualizerNativeApp {
                             if ui.button(play_pause).clicked() { self.toggle_playback(); }
                             if ui.button("⏭").clicked() { self.play_next(); }
                             if ui.button("⏹").clicked() { self.stop_playback(); }
+                            if ui.button(self.playlist.repeat.label()).clicked() { self.playlist.repeat = self.playlist.repeat.next(); }
                         });
+                        ui.horizontal(|ui| { ui.label("Crossfade (s):"); ui.add(egui::DragValue::new(&mut self.config.crossfade_secs).speed(0.1).clamp_range(0.0..=12.0)); });
 
                         ui.horizontal(|ui| {
                             ui.label("🔊");
@@ -156,6 +408,22 @@ impl MusicVisualizerNativeApp {
                                 self.update_volume();
                             }
                         });
+                        if ui.checkbox(&mut self.playlist.normalize_volume, "Normalize volume").on_hover_text("Level tracks to a common loudness").changed() {
+                            self.update_volume();
+                        }
+                        ui.horizontal(|ui| {
+                            ui.label("Speed:");
+                            let resp = ui.add(egui::Slider::new(&mut self.playback_rate, 0.5..=2.0).suffix("x"));
+                            // with pitch preservation each change restarts the source, so wait for the drag to end
+                            #[cfg(feature = "pitch-preserve")]
+                            let apply = if self.preserve_pitch { resp.drag_stopped() || (resp.changed() && !resp.dragged()) } else { resp.changed() };
+                            #[cfg(not(feature = "pitch-preserve"))]
+                            let apply = resp.changed();
+                            if apply { self.apply_playback_rate(); }
+                            if ui.small_button("1x").clicked() { self.playback_rate = 1.0; self.apply_playback_rate(); }
+                        });
+                        #[cfg(feature = "pitch-preserve")]
+                        if ui.checkbox(&mut self.preserve_pitch, "Preserve pitch").changed() { self.apply_playback_rate(); }
                     });
                 } else {
                     ui.colored_label(Color32::GRAY, "No track selected");
@@ -164,14 +432,25 @@ impl MusicVisualizerNativeApp {
                 ui.add_space(8.0);
                 if !self.playlist.tracks.is_empty() {
                     ui.label(format!("Tracks ({}):", self.playlist.tracks.len()));
+                    ui.horizontal(|ui| {
+                        ui.label("🔍");
+                        ui.add(egui::TextEdit::singleline(&mut self.playlist_filter).hint_text("Filter tracks…"));
+                        if !self.playlist_filter.is_empty() && ui.small_button("✖").clicked() { self.playlist_filter.clear(); }
+                    });
+                    let query = self.playlist_filter.to_lowercase();
                     let mut play_idx: Option<usize> = None;
                     let mut remove_idx: Option<usize> = None;
+                    let mut move_idx: Option<(usize, usize)> = None;
                     egui::ScrollArea::vertical().max_height(150.0).show(ui, |ui| {
-                        for (idx, track) in self.playlist.tracks.iter().enumerate() {
+                        // filter the enumerated list so rows keep their true playlist index
+                        let visible = self.playlist.tracks.iter().enumerate().filter(|(_, t)| query.is_empty() || t.name.to_lowercase().contains(&query));
+                        for (idx, track) in visible {
                             let is_current = self.playlist.current_index == Some(idx);
                             let bg = if is_current { Color32::from_rgba_unmultiplied(100,200,255,30) } else { Color32::TRANSPARENT };
-                            egui::Frame::group(&ui.style()).fill(bg).inner_margin(4.0).show(ui, |ui| {
+                            let row = egui::Frame::group(&ui.style()).fill(bg).inner_margin(4.0).show(ui, |ui| {
                                 ui.horizontal(|ui| {
+                                    // drag handle: drop onto another row to move the track there
+                                    ui.dnd_drag_source(egui::Id::new(("playlist_track", idx)), idx, |ui| { ui.label("☰"); });
                                     if is_current && self.playlist.is_playing { ui.label("▶"); } else { ui.label(format!("{}.", idx+1)); }
                                     let lbl = egui::Label::new(&track.name).sense(egui::Sense::click());
                                     if ui.add(lbl).clicked() { play_idx = Some(idx); }
@@ -180,15 +459,40 @@ impl MusicVisualizerNativeApp {
                                         ui.label(PlaylistState::format_time(track.duration));
                                     });
                                 });
-                            });
+                            }).response;
+                            if let Some(from) = row.dnd_hover_payload::<usize>().filter(|from| **from != idx) {
+                                // mark the edge the dragged track will land on
+                                let y = if *from > idx { row.rect.top() } else { row.rect.bottom() };
+                                ui.painter().hline(row.rect.x_range(), y, egui::Stroke::new(2.0, Color32::from_rgb(100, 200, 255)));
+                            }
+                            if let Some(from) = row.dnd_release_payload::<usize>() { move_idx = Some((*from, idx)); }
                         }
 
                     });
                     if let Some(i) = play_idx { self.play_track(i); }
                     if let Some(i) = remove_idx { self.remove_track(i); }
+                    if let Some((from, to)) = move_idx { self.playlist.reorder_track(from, to); }
                     ui.add_space(4.0);
-                    if ui.button("🗑 Clear Playlist").clicked() { self.clear_playlist(); }
                 }
+                ui.horizontal(|ui| {
+                    if !self.playlist.tracks.is_empty() {
+                        if ui.button("🗑 Clear Playlist").clicked() { self.clear_playlist(); }
+                        if ui.button("💾 Export Playlist").clicked() {
+                            if let Some(path) = FileDialog::new().add_filter("M3U playlist", &["m3u", "m3u8"]).set_file_name("playlist.m3u").save_file() {
+                                if let Err(e) = self.export_playlist_m3u(&path) {
+                                    eprintln!("Failed to export playlist to {}: {e}", path.display());
+                                }
+                            }
+                        }
+                    }
+                    if ui.button("📂 Import Playlist").clicked() {
+                        if let Some(path) = FileDialog::new().add_filter("M3U playlist", &["m3u", "m3u8"]).pick_file() {
+                            if let Err(e) = self.import_playlist_m3u(&path) {
+                                eprintln!("Failed to import playlist from {}: {e}", path.display());
+                            }
+                        }
+                    }
+                });
             });
 
             ui.separator();
@@ -199,6 +503,7 @@ impl MusicVisualizerNativeApp {
                 if ui.selectable_label(self.demo_mode, "Demo").clicked() { self.demo_mode = true; }
                 if ui.selectable_label(!self.demo_mode && !self.is_system_audio(), "Microphone").clicked() {
                     self.demo_mode = false;
+                    if self.is_system_audio() || self.capture.is_none() { self.select_input_device(None); }
                     self.set_system_audio(false);
                 }
                 if ui.selectable_label(self.is_system_audio(), "System Audio").clicked() {
@@ -207,13 +512,69 @@ impl MusicVisualizerNativeApp {
                     self.try_init_system_audio();
                 }
             });
+            if !self.demo_mode && !self.is_system_audio() {
+                let current = self.capture.as_ref().map(|c| c.device_name.clone());
+                let mut selected = current.clone();
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_label("Input").selected_text(current.as_deref().unwrap_or("None")).show_ui(ui, |ui| {
+                        for name in &self.input_devices { ui.selectable_value(&mut selected, Some(name.clone()), name); }
+                    });
+                    if ui.small_button("🔄").on_hover_text("Refresh device list").clicked() { self.input_devices = capture::input_device_names(); }
+                });
+                if let Some(c) = &self.capture {
+                    ui.weak(format!("{} Hz, {} ch → analysed at {} Hz", c.sample_rate, c.channels, ANALYSIS_SAMPLE_RATE));
+                }
+                if selected != current { self.select_input_device(selected); }
+            }
+            if let Some(msg) = &self.capture_message { ui.colored_label(Color32::YELLOW, msg); }
 
             // Audio levels
             ui.collapsing("📊 Audio Levels", |ui| {
-                ui.horizontal(|ui| { ui.label("Bass:"); ui.add(egui::ProgressBar::new(self.audio.smooth_bass).show_percentage()); });
-                ui.horizontal(|ui| { ui.label("Mid:"); ui.add(egui::ProgressBar::new(self.audio.smooth_mid).show_percentage()); });
-                ui.horizontal(|ui| { ui.label("Treble:"); ui.add(egui::ProgressBar::new(self.audio.smooth_treble).show_percentage()); });
-                ui.horizontal(|ui| { ui.label("Volume:"); ui.add(egui::ProgressBar::new(self.audio.smooth_volume).show_percentage()); });
+                // smoothed level as the bar, this frame's level as the peak
+                let a = &self.audio;
+                for (label, rms, peak) in [("Bass:", a.smooth_bass, a.bass), ("Mid:", a.smooth_mid, a.mid), ("Treble:", a.smooth_treble, a.treble), ("Volume:", a.smooth_volume, a.peak)] {
+                    ui.horizontal(|ui| {
+                        ui.add_sized([50.0, 14.0], egui::Label::new(label));
+                        let (rect, _) = ui.allocate_exact_size(egui::vec2(ui.available_width(), 14.0), egui::Sense::hover());
+                        draw_vu_meter(ui, ui.id().with(label), rect, rms, peak);
+                    });
+                }
+                ui.horizontal(|ui| {
+                    ui.label("Smoothing:");
+                    ui.add(egui::Slider::new(&mut self.config.band_smoothing, 0.0..=1.0)).on_hover_text("Lower is sluggish and smooth, higher is snappy");
+                });
+                ui.checkbox(&mut self.config.a_weighting, "A-weighting").on_hover_text("Weight bands and volume by perceived loudness");
+                ui.checkbox(&mut self.show_band_debug, "Band debug view").on_hover_text("Plot raw and smoothed bass/mid/treble with beat markers over the last few seconds");
+                ui.add_enabled_ui(self.audio.is_stereo(), |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Channels:");
+                        for mode in ChannelMode::ALL { ui.selectable_value(&mut self.audio.channel_mode, mode, mode.label()); }
+                    });
+                });
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.config.auto_gain, "Auto gain").on_hover_text("Scale bands to the recent peak level so quiet and loud tracks react alike");
+                    ui.add_enabled(self.config.auto_gain, egui::Slider::new(&mut self.config.auto_gain_strength, 0.0..=1.0).text("strength"));
+                });
+                ui.label("Band edges (Hz):");
+                let mut edges_changed = false;
+                ui.horizontal(|ui| {
+                    for edge in &mut self.config.band_edges_hz[1..5] {
+                        edges_changed |= ui.add(egui::DragValue::new(edge).speed(10.0)).changed();
+                    }
+                });
+                if edges_changed { self.config.band_edges_hz = sorted_band_edges(self.config.band_edges_hz); }
+                ui.label(format!("BPM: {:.0}", self.audio.bpm));
+                ui.horizontal(|ui| {
+                    ui.label("Beat sensitivity:");
+                    ui.add(egui::DragValue::new(&mut self.config.beat_sensitivity).speed(0.01))
+                        .on_hover_text("Bass must exceed its ~1s rolling average by this factor to count as a beat");
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Noise gate:");
+                    ui.add(egui::DragValue::new(&mut self.config.noise_gate).speed(0.001).clamp_range(0.0..=0.2))
+                        .on_hover_text("Input quieter than this RMS level is treated as silence");
+                    if self.audio.gated { ui.weak("(gated)"); }
+                });
                 if self.audio.beat { ui.colored_label(Color32::from_rgb(255,100,100), "🥁 BEAT!"); }
             });
 
@@ -224,15 +585,66 @@ impl MusicVisualizerNativeApp {
                 if self.visualizer_mode == VisualizerMode::UnknownPleasures {
                     ui.horizontal(|ui| { ui.label("Zoom:"); ui.add(egui::DragValue::new(&mut self.config.up_zoom).speed(0.01)); });
                     ui.horizontal(|ui| { ui.label("Isometric rotate:"); ui.checkbox(&mut self.config.up_isometric_rotate, "Enable"); ui.label("Angle:"); ui.add(egui::DragValue::new(&mut self.config.up_rotation_deg).speed(1.0)); });
-                    ui.horizontal(|ui| { ui.label("Line thickness:"); ui.add(egui::DragValue::new(&mut self.config.up_line_thickness).speed(0.1)); });
+                    ui.horizontal(|ui| { ui.label("Line thickness:"); ui.add(egui::DragValue::new(&mut self.config.up_line_thickness).speed(0.1)); ui.checkbox(&mut self.config.up_thickness_follows_zoom, "Scale with zoom").on_hover_text("Widen lines as you zoom in so they stay visible"); });
                     ui.horizontal(|ui| { ui.label("Line length:"); ui.add(egui::DragValue::new(&mut self.config.up_line_length).speed(0.05)); });
                     ui.horizontal(|ui| { ui.label("Perspective:"); ui.add(egui::DragValue::new(&mut self.config.up_perspective).speed(0.05)); });
-                    ui.horizontal(|ui| { ui.label("Vertical scale:"); ui.add(egui::DragValue::new(&mut self.config.up_vertical_scale).speed(0.1)); });
+                    ui.horizontal(|ui| {
+                        ui.label("Vertical scale:");
+                        ui.add_enabled(!self.config.up_auto_fit, egui::DragValue::new(&mut self.config.up_vertical_scale).speed(0.1));
+                        ui.checkbox(&mut self.config.up_auto_fit, "Fit to height").on_hover_text("Space the lines and scale their peaks so the stack fills the view without clipping");
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Orientation:");
+                        for orientation in UpOrientation::ALL { ui.selectable_value(&mut self.config.up_orientation, orientation, orientation.label()); }
+                    });
+                    ui.horizontal(|ui| { ui.label("Style:"); ui.checkbox(&mut self.config.up_fill_occlusion, "Filled (poster)"); });
+                    ui.checkbox(&mut self.config.up_segment_strokes, "Per-segment strokes").on_hover_text("Draw each segment separately instead of one smooth path per line; shows joints at sharp peaks");
+                    ui.horizontal(|ui| {
+                        ui.label("Bands:");
+                        ui.checkbox(&mut self.config.up_mel_scale, "Mel scale");
+                        ui.label("Curve exp:");
+                        ui.add_enabled(!self.config.up_mel_scale, egui::DragValue::new(&mut self.config.up_freq_curve_exponent).speed(0.1));
+                    });
+                    ui.horizontal(|ui| { ui.checkbox(&mut self.config.up_gradient, "Depth gradient"); ui.label("Top:"); ui.color_edit_button_srgba(&mut self.config.up_gradient_top); ui.label("Bottom:"); ui.color_edit_button_srgba(&mut self.config.up_gradient_bottom); });
+                    if ui.button("🖼 Export SVG").clicked() {
+                        if let Some(path) = FileDialog::new().add_filter("SVG", &["svg"]).set_file_name("unknown_pleasures.svg").save_file() {
+                            let svg = self.unknown_visualizer.to_svg(self.visual_rect, &self.config);
+                            if let Err(e) = fs::write(&path, svg) {
+                                eprintln!("Failed to export SVG: {e}");
+                            }
+                        }
+                    }
+                    ui.horizontal(|ui| {
+                        egui::ComboBox::from_id_source("up_png_size").selected_text(PNG_EXPORT_SIZES[self.png_export_size].0).show_ui(ui, |ui| {
+                            for (i, (label, _)) in PNG_EXPORT_SIZES.iter().enumerate() { ui.selectable_value(&mut self.png_export_size, i, *label); }
+                        });
+                        if ui.button("🖼 Export PNG").on_hover_text("Render the current frame at this resolution").clicked() {
+                            if let Some(path) = FileDialog::new().add_filter("PNG", &["png"]).set_file_name("unknown_pleasures.png").save_file() {
+                                let image = self.unknown_visualizer.to_image(self.visual_rect.height(), &self.config, PNG_EXPORT_SIZES[self.png_export_size].1);
+                                if let Err(e) = write_png(&path, &image) {
+                                    eprintln!("Failed to export PNG: {e}");
+                                }
+                            }
+                        }
+                    });
                 } else {
+                    egui::ComboBox::from_label("Shape").selected_text(self.config.fractal_kind.label()).show_ui(ui, |ui| {
+                        for kind in FractalKind::ALL { ui.selectable_value(&mut self.config.fractal_kind, kind, kind.label()); }
+                    });
                     ui.horizontal(|ui| { ui.label("Zoom:"); ui.add(egui::DragValue::new(&mut self.config.base_zoom).speed(0.01)); });
                     ui.horizontal(|ui| { ui.label("Width:"); ui.add(egui::DragValue::new(&mut self.config.base_width).speed(0.01)); });
-                    ui.horizontal(|ui| { ui.label("Depth:"); ui.add(egui::DragValue::new(&mut self.config.base_depth).speed(1.0)); });
+                    ui.horizontal(|ui| { ui.label("Depth:"); ui.add(egui::DragValue::new(&mut self.config.base_depth).speed(1.0).clamp_range(1..=MAX_BASE_DEPTH)); });
                     ui.horizontal(|ui| { ui.label("Brightness:"); ui.add(egui::DragValue::new(&mut self.config.base_brightness).speed(0.01)); });
+                    ui.horizontal(|ui| {
+                        ui.label("Branches:");
+                        ui.add(egui::DragValue::new(&mut self.config.fractal_branches).clamp_range(MIN_BRANCHES..=MAX_BRANCHES));
+                        ui.label("Spread:");
+                        ui.add(egui::DragValue::new(&mut self.config.fractal_spread_deg).clamp_range(0.0..=360.0).suffix("°"));
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Depth hue shift:");
+                        ui.add(egui::Slider::new(&mut self.config.fractal_depth_hue_shift, -0.25..=0.25)).on_hover_text("Hue rotation per branch level; 0 keeps one colour");
+                    });
                     if ui.small_button("Reset fractal defaults").clicked() { self.config.reset_fractal_to_default(); }
                 }
             });
@@ -243,10 +655,27 @@ impl MusicVisualizerNativeApp {
                     ui.horizontal(|ui| { ui.label("Mid influence:"); ui.add(egui::DragValue::new(&mut self.config.up_mid_mult).speed(0.01)); });
                     ui.horizontal(|ui| { ui.label("Treble influence:"); ui.add(egui::DragValue::new(&mut self.config.up_treble_mult).speed(0.01)); });
                 } else {
-                    ui.horizontal(|ui| { ui.label("Bass → Zoom:"); ui.add(egui::DragValue::new(&mut self.config.zoom_bass_mult).speed(0.01)); });
-                    ui.horizontal(|ui| { ui.label("Bass → Width:"); ui.add(egui::DragValue::new(&mut self.config.width_bass_mult).speed(0.01)); });
-                    ui.horizontal(|ui| { ui.label("Complexity → Depth:"); ui.add(egui::DragValue::new(&mut self.config.depth_complexity_mult).speed(0.1)); });
-                    ui.horizontal(|ui| { ui.label("Treble → Brightness:"); ui.add(egui::DragValue::new(&mut self.config.brightness_treble_mult).speed(0.01)); });
+                    let mut remove = None;
+                    for (i, entry) in self.config.reactivity.entries.iter_mut().enumerate() {
+                        ui.horizontal(|ui| {
+                            egui::ComboBox::from_id_source(("react_source", i)).width(90.0).selected_text(entry.source.label()).show_ui(ui, |ui| {
+                                for f in AudioFeature::ALL { ui.selectable_value(&mut entry.source, f, f.label()); }
+                            });
+                            ui.label("→");
+                            egui::ComboBox::from_id_source(("react_target", i)).width(90.0).selected_text(entry.target.label()).show_ui(ui, |ui| {
+                                for p in ConfigParam::ALL { ui.selectable_value(&mut entry.target, p, p.label()); }
+                            });
+                            ui.add(egui::DragValue::new(&mut entry.multiplier).speed(0.01));
+                            if ui.small_button("✖").clicked() { remove = Some(i); }
+                        });
+                    }
+                    if let Some(i) = remove { self.config.reactivity.entries.remove(i); }
+                    ui.horizontal(|ui| {
+                        if ui.small_button("➕ Add mapping").clicked() {
+                            self.config.reactivity.entries.push(ReactivityEntry { source: AudioFeature::Bass, target: ConfigParam::Zoom, multiplier: 0.1 });
+                        }
+                        if ui.small_button("Reset").clicked() { self.config.reactivity = ReactivityMap::default(); }
+                    });
                 }
             });
 
@@ -254,24 +683,160 @@ impl MusicVisualizerNativeApp {
                 ui.checkbox(&mut self.config.auto_rotate, "Auto Rotate");
                 ui.horizontal(|ui| { ui.label("Rotation Speed:"); ui.add(egui::DragValue::new(&mut self.config.rotation_speed).speed(0.01)); });
                 ui.checkbox(&mut self.config.pulse_on_beat, "Pulse on Beat");
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.config.beat_strobe, "Beat strobe");
+                    ui.color_edit_button_srgba(&mut self.config.strobe_color);
+                    ui.add_enabled(self.config.beat_strobe, egui::Slider::new(&mut self.config.strobe_intensity, 0.0..=MAX_STROBE_INTENSITY).text("Intensity"));
+                });
+                if self.config.beat_strobe { ui.colored_label(Color32::YELLOW, "⚠ Flashing light can trigger seizures in photosensitive viewers"); }
+                ui.horizontal(|ui| {
+                    ui.label("Particle gravity:");
+                    ui.add(egui::DragValue::new(&mut self.config.particle_gravity.x).speed(1.0).prefix("x: "));
+                    ui.add(egui::DragValue::new(&mut self.config.particle_gravity.y).speed(1.0).prefix("y: "));
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Particle shape:");
+                    for shape in ParticleShape::ALL { ui.selectable_value(&mut self.config.particle_shape, shape, shape.label()); }
+                });
                 ui.checkbox(&mut self.config.color_cycle, "Color Cycle");
                 ui.horizontal(|ui| { ui.label("Color Speed:"); ui.add(egui::DragValue::new(&mut self.config.color_cycle_speed).speed(0.01)); });
             });
 
             ui.collapsing("🖥️ Display", |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Palette:");
+                    egui::ComboBox::from_id_source("palette").selected_text("Choose…").show_ui(ui, |ui| {
+                        for palette in Palette::ALL {
+                            if ui.selectable_label(false, palette.label()).clicked() { self.config.apply_palette(palette); }
+                        }
+                    });
+                });
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.config.background_gradient, "Background gradient");
+                    ui.add_enabled_ui(self.config.background_gradient, |ui| { ui.label("Top:"); ui.color_edit_button_srgba(&mut self.config.background_top); ui.label("Bottom:"); ui.color_edit_button_srgba(&mut self.config.background_bottom); });
+                });
                 ui.checkbox(&mut self.show_spectrum, "Show Spectrum");
+                ui.horizontal(|ui| { ui.label("Spectrum bars:"); ui.add(egui::DragValue::new(&mut self.config.spectrum_bars).clamp_range(4..=256)); });
+                ui.checkbox(&mut self.config.spectrum_log_axis, "Log frequency axis");
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.config.spectrum_peak_hold, "Peak hold");
+                    ui.label("Decay/s:");
+                    ui.add_enabled(self.config.spectrum_peak_hold, egui::DragValue::new(&mut self.config.spectrum_peak_decay).speed(0.01).clamp_range(0.0..=10.0));
+                });
+                ui.horizontal(|ui| { ui.label("Spectrum smoothing:"); ui.add(egui::Slider::new(&mut self.config.spectrum_smoothing, 0.0..=0.95)); });
+                ui.horizontal(|ui| {
+                    ui.label("Max FPS:");
+                    ui.add(egui::DragValue::new(&mut self.config.max_fps).clamp_range(10..=240)).on_hover_text("Lower values save CPU; drops to a few FPS when paused and silent");
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Attract mode after:");
+                    ui.add(egui::DragValue::new(&mut self.config.idle_timeout_secs).clamp_range(0.0..=3600.0).suffix(" s"))
+                        .on_hover_text("Animate on its own after this long with nothing playing or heard; 0 turns it off");
+                });
+                ui.checkbox(&mut self.config.mirror_channels, "Mirror L/R channels")
+                    .on_hover_text(if self.audio.is_stereo() { "Left channel on the left, right on the right" } else { "Input is mono, so both halves match" });
                 ui.checkbox(&mut self.show_waveform, "Show Waveform");
+                ui.horizontal(|ui| {
+                    ui.label("Waveform style:");
+                    for style in WaveformStyle::ALL { ui.selectable_value(&mut self.config.waveform_style, style, style.label()); }
+                });
+                ui.horizontal(|ui| { ui.label("Waveform smoothing:"); ui.add(egui::Slider::new(&mut self.config.waveform_smoothing, 0.0..=0.95)); });
                 ui.horizontal(|ui| { ui.label("Glow:"); ui.add(egui::DragValue::new(&mut self.config.glow_intensity).speed(0.01)); });
+                ui.horizontal(|ui| {
+                    ui.label("Fractal trails:");
+                    ui.add(egui::Slider::new(&mut self.config.trail_amount, 0.0..=0.95))
+                        .on_hover_text("How much of the previous frame stays behind; 0 clears every frame");
+                });
+            });
+
+            ui.collapsing("🎹 MIDI", |ui| {
+                let current = self.midi.as_ref().map(|m| m.port_name.clone());
+                let mut selected = current.clone();
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_label("Device").selected_text(current.as_deref().unwrap_or("None")).show_ui(ui, |ui| {
+                        ui.selectable_value(&mut selected, None, "None");
+                        for name in &self.midi_ports { ui.selectable_value(&mut selected, Some(name.clone()), name); }
+                    });
+                    if ui.small_button("🔄").on_hover_text("Refresh device list").clicked() { self.midi_ports = midi::input_port_names(); }
+                });
+                if selected != current { self.select_midi_port(selected); }
+                if let Some(msg) = &self.midi_message { ui.colored_label(Color32::YELLOW, msg); }
+                ui.label("Notes trigger beats; knobs (CC) set:");
+                let mut remove = None;
+                for (i, mapping) in self.config.midi_cc_map.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.add(egui::DragValue::new(&mut mapping.controller).clamp_range(0..=127).prefix("CC "));
+                        ui.label("→");
+                        egui::ComboBox::from_id_source(("midi_target", i)).width(120.0).selected_text(mapping.target.label()).show_ui(ui, |ui| {
+                            for t in MidiTarget::ALL { ui.selectable_value(&mut mapping.target, t, t.label()); }
+                        });
+                        if ui.small_button("✖").clicked() { remove = Some(i); }
+                    });
+                }
+                if let Some(i) = remove { self.config.midi_cc_map.remove(i); }
+                ui.horizontal(|ui| {
+                    if ui.small_button("➕ Add mapping").clicked() {
+                        let controller = self.midi_last_cc.map_or(1, |(cc, _)| cc);
+                        self.config.midi_cc_map.push(MidiCcMapping { controller, target: MidiTarget::RotationSpeed });
+                    }
+                    if let Some((cc, value)) = self.midi_last_cc { ui.label(format!("Last: CC {cc} = {value}")); }
+                });
+            });
+
+            ui.collapsing("📡 OSC", |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("UDP port:");
+                    ui.add_enabled(self.osc.is_none(), egui::DragValue::new(&mut self.osc_port).clamp_range(1024..=65535));
+                    let mut listening = self.osc.is_some();
+                    if ui.checkbox(&mut listening, "Listen").changed() { self.set_osc_listening(listening); }
+                });
+                ui.add_enabled(self.osc.is_none(), egui::Checkbox::new(&mut self.osc_lan, "Accept from other devices"))
+                    .on_hover_text("Listen on the local network, e.g. for TouchOSC on a phone, instead of this computer only");
+                if let Some(osc) = &self.osc { ui.label(format!("Listening on {}", osc.addr)); }
+                if let Some(msg) = &self.osc_message { ui.label(egui::RichText::new(msg).small()); }
+                ui.collapsing("Addresses", |ui| {
+                    egui::Grid::new("osc_addresses").striped(true).show(ui, |ui| {
+                        for (address, range) in OSC_ADDRESSES { ui.monospace(address); ui.label(range); ui.end_row(); }
+                    });
+                });
+            });
+
+            ui.collapsing("🎥 Recording", |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Folder:");
+                    ui.label(self.record_dir.as_ref().map_or("none".to_string(), |d| d.display().to_string()));
+                    if ui.add_enabled(self.recorder.is_none(), egui::Button::new("📁 Choose…")).clicked() {
+                        if let Some(dir) = FileDialog::new().set_title("Save frames to").pick_folder() { self.record_dir = Some(dir); }
+                    }
+                });
+                ui.horizontal(|ui| {
+                    if self.recorder.is_some() {
+                        if ui.button("⏹ Stop").clicked() { self.stop_recording(); }
+                    } else if ui.add_enabled(self.record_dir.is_some(), egui::Button::new("⏺ Record")).clicked() {
+                        self.start_recording();
+                    }
+                    if let Some(rec) = &self.recorder {
+                        ui.label(format!("{} frames, {:.1} MB", rec.frames, rec.bytes_written() as f64 / 1_048_576.0));
+                        if rec.dropped > 0 { ui.label(format!("({} dropped)", rec.dropped)); }
+                    }
+                });
+                ui.label(egui::RichText::new("⚠ Every frame is saved as a full-size PNG, often over 1 MB each at 60 fps. Watch your free disk space.").small().weak());
+                if let Some(msg) = &self.record_message { ui.colored_label(Color32::YELLOW, msg); }
             });
 
             ui.separator();
-            if ui.button("🔄 Reset Settings").clicked() { self.config = VisualizerConfig::default(); }
+            if ui.button("🔄 Reset Settings").clicked() { self.config.apply_preset(VisualizerConfig::default()); }
         });
     }
 }
 
 impl Default for MusicVisualizerNativeApp {
     fn default() -> Self {
+        let (duration_tx, duration_rx) = mpsc::channel();
+        let (loudness_tx, loudness_rx) = mpsc::channel();
+        let loudness_jobs = spawn_loudness_worker(loudness_tx);
+        let (capture_error_tx, capture_error_rx) = mpsc::channel();
+        let (midi_tx, midi_rx) = mpsc::channel();
         Self {
             audio: AudioAnalysis::new(),
             config: VisualizerConfig::default(),
@@ -279,112 +844,227 @@ impl Default for MusicVisualizerNativeApp {
             rotation: 0.0,
             particles: Vec::new(),
             playlist: PlaylistState::default(),
+            seek_text: None,
             beat_flash: 0.0,
+            pan: egui::Vec2::ZERO,
+            zoom: 1.0,
             visualizer_mode: VisualizerMode::Fractal,
             unknown_visualizer: UnknownPleasuresVisualizer::new(),
+            visual_rect: Rect::from_min_size(Pos2::ZERO, egui::vec2(800.0, 600.0)),
             audio_data: Arc::new(Mutex::new(Vec::new())),
             show_settings: false,
+            show_shortcuts: false,
+            show_band_debug: false,
+            band_history: BandHistory::default(),
+            presentation_mode: false,
+            frozen: false,
+            last_activity: 0.0,
+            attract_mode: false,
+            presentation_overlay_until: 0.0,
+            playlist_filter: String::new(),
+            playback_rate: 1.0,
+            #[cfg(feature = "pitch-preserve")]
+            preserve_pitch: true,
             show_spectrum: false,
             show_waveform: false,
+            spectrum_peaks: Vec::new(),
+            spectrum_smoothed: Vec::new(),
+            waveform_smoothed: Vec::new(),
+            fractal_trails: FractalTrails::default(),
+            fractal_cache: FractalCache::default(),
             system_audio_mode: true,
+            capture: None,
+            input_devices: Vec::new(),
+            capture_message: None,
+            capture_error_tx,
+            capture_error_rx,
+            midi: None,
+            midi_ports: Vec::new(),
+            midi_message: None,
+            midi_last_cc: None,
+            midi_tx,
+            midi_rx,
+            osc_port: DEFAULT_OSC_PORT,
+            osc_lan: false,
+            osc: None,
+            osc_message: None,
+            osc_inbox: Arc::new(Mutex::new(Vec::new())),
+            record_dir: None,
+            recorder: None,
+            record_message: None,
+            png_export_size: 0,
+            custom_presets: Vec::new(),
+            preset_name: String::new(),
+            preset_message: None,
+            share_input: String::new(),
             demo_mode: true,
             output_stream: None,
             output_stream_handle: None,
             current_sink: None,
-            playback_start_instant: None,
-            playback_seek_offset: 0.0,
+            fading_sink: None,
+            fading_volume: 0.0,
+            crossfade_start: None,
+            clock: PlaybackClock::default(),
+            decoded_cache: Arc::new(Mutex::new(None)),
+            duration_tx,
+            duration_rx,
+            loudness_jobs,
+            loudness_rx,
         }
     }
 }
 
 impl eframe::App for MusicVisualizerNativeApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        let dt = ctx.input(|i| i.stable_dt);
+        // frozen visuals keep their clock, analysis and particles as they are
+        let dt = if self.frozen { 0.0 } else { ctx.input(|i| i.stable_dt) };
         self.time += dt as f64;
-    // Update audio analysis from CPAL buffer
-    let audio_buf = self.audio_data.lock().unwrap().clone();
-    self.audio.update_from_cpal(&audio_buf);
-    // Update playback timing/state for file playback
-    self.update_playback_state();
-    // Update rotation and particles driven by audio
-    let dt_f32 = dt as f32;
-    if self.config.auto_rotate {
-        // apply base rotation plus beat-influenced rotation multiplier
-        self.rotation += self.config.rotation_speed * dt_f32 + self.audio.beat_intensity * self.config.rotation_beat_mult;
-    }
-    // spawn particles on beat
-    if self.audio.beat {
-        let spawn_count = (self.config.particle_count / 10).max(1) as usize;
-        for _ in 0..spawn_count {
-            let angle = rand::random::<f32>() * std::f32::consts::TAU;
-            let speed = 30.0 + rand::random::<f32>() * 80.0;
-            let color = self.get_current_color();
-            self.particles.push(Particle::new(egui::Pos2::new(400.0, 300.0), angle, speed, color));
-        }
-    }
-    // update and cull particles
-    for p in &mut self.particles {
-        p.update(dt_f32);
-    }
-    let target = self.config.particle_count as usize;
-    self.particles.retain(|p| p.is_alive());
-    if self.particles.len() > target { self.particles.truncate(target); }
-        // Sidebar toggle
-        if !self.show_settings {
+        self.poll_capture_errors();
+        self.record_frame(ctx);
+        self.handle_shortcuts(ctx);
+        // Update audio analysis from CPAL buffer
+        // with no input device open the generated demo signal stands in for the capture
+        let mut audio_buf = if self.capture.is_some() { self.audio_data.lock().unwrap().clone() } else { demo_buffer(self.time, self.audio.sample_rate) };
+        if self.capture.is_none() { self.audio.channels = 1; }
+        // attract mode: after `idle_timeout_secs` with nothing playing, heard or touched,
+        // a quiet demo signal keeps the screen moving until something happens. only a real
+        // capture counts as sound, so the full-level stand-in never holds attract mode off
+        let heard = self.capture.is_some() && rms(&audio_buf) > IDLE_VOLUME;
+        if self.playlist.is_playing || heard || ctx.input(|i| !i.events.is_empty()) {
+            self.last_activity = self.time;
+        }
+        let timeout = self.config.idle_timeout_secs;
+        self.attract_mode = timeout > 0.0 && self.time - self.last_activity > timeout as f64;
+        if self.attract_mode {
+            // interleaved to match the capture, so its channel count stays valid
+            let channels = self.audio.channels.max(1) as usize;
+            audio_buf = demo_buffer(self.time, self.audio.sample_rate).iter().flat_map(|s| std::iter::repeat_n(s * ATTRACT_LEVEL, channels)).collect();
+        }
+        self.audio.beat_sensitivity = self.config.beat_sensitivity;
+        self.audio.set_smoothing(self.config.band_smoothing);
+        self.audio.set_band_edges(self.config.band_edges_hz);
+        self.audio.a_weighting = self.config.a_weighting;
+        self.audio.noise_gate = self.config.noise_gate;
+        self.audio.auto_gain = self.config.auto_gain;
+        self.audio.auto_gain_strength = self.config.auto_gain_strength;
+        if !self.frozen { self.audio.update_from_cpal(&audio_buf, self.time); }
+        if !self.frozen { self.band_history.push(&self.audio, self.time); }
+        // Update playback timing/state for file playback
+        self.update_playback_state();
+        // MIDI notes and knobs override the audio-driven beat and settings
+        self.poll_midi();
+        self.poll_osc();
+        // Update rotation and particles driven by audio
+        let dt_f32 = dt as f32;
+        if self.audio.beat && !self.frozen { self.beat_flash = 1.0; }
+        self.beat_flash *= 0.9_f32.powf(dt_f32 * 60.0);
+        if (self.config.auto_rotate || self.attract_mode) && !self.frozen {
+            // apply base rotation plus beat-influenced rotation multiplier
+            self.rotation += self.config.rotation_speed * dt_f32 + self.audio.beat_intensity * self.config.rotation_beat_mult;
+        }
+        // spawn particles on beat, more and faster the harder it hits
+        if self.audio.beat && !self.frozen {
+            let intensity = self.audio.beat_intensity;
+            for _ in 0..burst_count(self.config.particle_count, intensity) {
+                let angle = rand::random::<f32>() * std::f32::consts::TAU;
+                let speed = (30.0 + rand::random::<f32>() * 80.0) * burst_speed_scale(intensity);
+                let color = self.spectrum_particle_color(rand::random::<f32>());
+                self.particles.push(Particle::new(angle, speed, color, self.config.particle_shape));
+            }
+        }
+        // update and cull particles
+        for p in &mut self.particles {
+            p.update(dt_f32, self.config.particle_gravity);
+        }
+        let target = self.config.particle_count as usize;
+        self.particles.retain(|p| p.is_alive());
+        if self.particles.len() > target { self.particles.truncate(target); }
+        self.schedule_repaint(ctx);
+        // Sidebar toggle; presentation mode hides every panel
+        if !self.show_settings && !self.presentation_mode {
             egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
                 ui.horizontal(|ui| {
                     if ui.button("▶ Show Settings").clicked() {
                         self.show_settings = true;
                     }
+                    if ui.button("⛶ Present").on_hover_text("Hide all panels (F11)").clicked() {
+                        self.set_presentation_mode(ctx, true);
+                    }
+                    ui.toggle_value(&mut self.frozen, "❄ Freeze").on_hover_text("Hold the visuals while audio keeps playing (F)");
                 });
             });
         }
-        if self.show_settings {
+        if self.show_settings && !self.presentation_mode {
             egui::SidePanel::left("settings_panel")
                 .resizable(true)
                 .default_width(280.0)
                 .show(ctx, |ui| {
                     self.draw_settings_panel(ui);
-                    if ui.button("◀ Hide").clicked() {
-                        self.show_settings = false;
-                    }
+                    ui.horizontal(|ui| {
+                        if ui.button("◀ Hide").clicked() { self.show_settings = false; }
+                        if ui.button("⛶ Present").on_hover_text("Hide all panels (F11)").clicked() { self.set_presentation_mode(ctx, true); }
+                        ui.toggle_value(&mut self.frozen, "❄ Freeze").on_hover_text("Hold the visuals while audio keeps playing (F)");
+                    });
                 });
         }
-        egui::CentralPanel::default().show(ctx, |ui| {
+        if self.presentation_mode { self.draw_presentation_overlay(ctx); }
+        self.draw_shortcuts_help(ctx);
+        self.draw_band_debug(ctx);
+        // presentation mode gives the visual the whole window, edge to edge
+        let presenting = self.presentation_mode;
+        let central_frame = if presenting { egui::Frame::none() } else { egui::Frame::central_panel(&ctx.style()) };
+        egui::CentralPanel::default().frame(central_frame).show(ctx, |ui| {
             let available = ui.available_rect_before_wrap();
-            let spectrum_height = if self.show_spectrum { 80.0 } else { 0.0 };
-            let waveform_height = if self.show_waveform { 60.0 } else { 0.0 };
+            let show_spectrum = self.show_spectrum && !presenting;
+            let show_waveform = self.show_waveform && !presenting;
+            let spectrum_height = if show_spectrum { 80.0 } else { 0.0 };
+            let waveform_height = if show_waveform { 60.0 } else { 0.0 };
             let bottom_ui_height = spectrum_height + waveform_height;
             let fractal_rect = egui::Rect::from_min_max(
                 available.min,
                 Pos2::new(available.max.x, available.max.y - bottom_ui_height),
             );
+            self.visual_rect = fractal_rect;
+            let fractal_response = ui.allocate_rect(fractal_rect, egui::Sense::click_and_drag());
             match self.visualizer_mode {
-                VisualizerMode::Fractal => self.draw_fractal(ui, fractal_rect),
+                VisualizerMode::Fractal => {
+                    self.handle_camera_input(ui, &fractal_response);
+                    self.draw_fractal(ui, fractal_rect);
+                }
                 VisualizerMode::UnknownPleasures => {
-                    self.unknown_visualizer.draw(ui, fractal_rect, &self.audio, &self.config, self.time);
+                    self.unknown_visualizer.draw(ui, fractal_rect, &self.audio, &self.config, self.time, self.frozen);
                 }
+                VisualizerMode::RadialSpectrum => self.draw_radial_spectrum(ui, fractal_rect),
             }
             let painter = ui.painter();
             self.draw_particles(painter, fractal_rect.center());
-            // Playback/update UI: draw compact play controls in corner
-            let ctrl_rect = Rect::from_min_size(
-                Pos2::new(fractal_rect.right() - 220.0, fractal_rect.top() + 8.0),
-                egui::vec2(212.0, 48.0),
-            );
-            ui.allocate_rect(ctrl_rect, egui::Sense::hover());
-            ui.painter().rect_filled(ctrl_rect, 4.0, Color32::from_rgba_unmultiplied(0, 0, 0, 100));
-            ui.allocate_ui_at_rect(ctrl_rect.shrink(6.0), |ui| {
-                ui.horizontal(|ui| {
-                    if ui.small_button("⏮").clicked() { self.play_previous(); }
-                    let play_icon = if self.playlist.is_playing { "⏸" } else { "▶" };
-                    if ui.small_button(play_icon).clicked() { self.toggle_playback(); }
-                    if ui.small_button("⏭").clicked() { self.play_next(); }
-                    ui.separator();
-                    ui.label(format!("{}", self.playlist.get_current_track().map(|t| t.name.clone()).unwrap_or_else(|| "No track".to_string())));
+            self.draw_strobe(painter, fractal_rect);
+            if self.capture.is_none() && !presenting {
+                let galley = painter.layout_no_wrap(NO_INPUT_BANNER.to_string(), egui::FontId::proportional(14.0), Color32::YELLOW);
+                let banner = egui::Align2::CENTER_TOP.anchor_size(fractal_rect.center_top() + egui::vec2(0.0, 12.0), galley.size());
+                painter.rect_filled(banner.expand(4.0), 4.0, Color32::from_black_alpha(160));
+                painter.galley(banner.min, galley, Color32::YELLOW);
+            }
+            if !presenting {
+                // Playback/update UI: draw compact play controls in corner
+                let ctrl_rect = Rect::from_min_size(
+                    Pos2::new(fractal_rect.right() - 220.0, fractal_rect.top() + 8.0),
+                    egui::vec2(212.0, 48.0),
+                );
+                ui.allocate_rect(ctrl_rect, egui::Sense::hover());
+                ui.painter().rect_filled(ctrl_rect, 4.0, Color32::from_rgba_unmultiplied(0, 0, 0, 100));
+                ui.allocate_ui_at_rect(ctrl_rect.shrink(6.0), |ui| {
+                    ui.horizontal(|ui| {
+                        if ui.small_button("⏮").clicked() { self.play_previous(); }
+                        let play_icon = if self.playlist.is_playing { "⏸" } else { "▶" };
+                        if ui.small_button(play_icon).clicked() { self.toggle_playback(); }
+                        if ui.small_button("⏭").clicked() { self.play_next(); }
+                        ui.separator();
+                        ui.label(format!("{}", self.playlist.get_current_track().map(|t| t.name.clone()).unwrap_or_else(|| "No track".to_string())));
+                    });
                 });
-            });
-            if self.show_spectrum {
+            }
+            if show_spectrum {
                 let spectrum_rect = egui::Rect::from_min_max(
                     Pos2::new(available.min.x, available.max.y - bottom_ui_height),
                     Pos2::new(available.max.x, available.max.y - waveform_height),
@@ -392,7 +1072,7 @@ impl eframe::App for MusicVisualizerNativeApp {
                 ui.allocate_rect(spectrum_rect, egui::Sense::hover());
                 self.draw_spectrum(ui, spectrum_rect);
             }
-            if self.show_waveform {
+            if show_waveform {
                 let waveform_rect = egui::Rect::from_min_max(
                     Pos2::new(available.min.x, available.max.y - waveform_height),
                     available.max,
@@ -411,7 +1091,9 @@ impl MusicVisualizerNativeApp {
             for p in paths {
                 if let Some(name) = p.file_name().and_then(|s| s.to_str().map(|s| s.to_string())) {
                     let ext = p.extension().and_then(|s| s.to_str()).unwrap_or("").to_lowercase();
-                    self.playlist.tracks.push(crate::playlist::PlaylistTrack { name: name.clone(), path: p.to_string_lossy().to_string(), duration: 0.0, file_type: ext.clone() });
+                    let duration = self.probe_duration(&p);
+                    self.scan_loudness(&p);
+                    self.playlist.tracks.push(crate::playlist::PlaylistTrack { name: name.clone(), path: p.to_string_lossy().to_string(), duration, file_type: ext.clone(), gain_db: 0.0, ..Default::default() });
                 }
             }
             if self.playlist.current_index.is_none() && !self.playlist.tracks.is_empty() {
@@ -420,6 +1102,116 @@ impl MusicVisualizerNativeApp {
         }
     }
 
+    /// Write the playlist as an extended M3U file (`#EXTINF:{duration},{name}` + path).
+    pub fn export_playlist_m3u(&self, path: &Path) -> io::Result<()> {
+        let mut out = String::from("#EXTM3U\n");
+        for track in &self.playlist.tracks {
+            out.push_str(&format!("#EXTINF:{},{}\n{}\n", track.duration.round() as i64, track.name, track.path));
+        }
+        fs::write(path, out)
+    }
+
+    /// Append the tracks listed in an M3U file to the playlist. Relative entries
+    /// are resolved against the playlist's directory; missing files are skipped.
+    pub fn import_playlist_m3u(&mut self, path: &Path) -> io::Result<()> {
+        let text = fs::read_to_string(path)?;
+        let base = path.parent().unwrap_or_else(|| Path::new(""));
+        let mut pending_info: Option<(f64, String)> = None;
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line == "#EXTM3U" {
+                continue;
+            }
+            if let Some(info) = line.strip_prefix("#EXTINF:") {
+                let (duration, name) = info.split_once(',').unwrap_or((info, ""));
+                pending_info = Some((duration.trim().parse::<f64>().unwrap_or(0.0).max(0.0), name.trim().to_string()));
+                continue;
+            }
+            if line.starts_with('#') {
+                continue;
+            }
+            let (duration, name) = pending_info.take().unwrap_or_default();
+            let track_path = base.join(line);
+            if !track_path.is_file() {
+                eprintln!("Skipping missing playlist entry: {}", track_path.display());
+                continue;
+            }
+            let file_name = track_path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            let ext = track_path.extension().and_then(|s| s.to_str()).unwrap_or("").to_lowercase();
+            let duration = if duration > 0.0 { duration } else { self.probe_duration(&track_path) };
+            self.scan_loudness(&track_path);
+            self.playlist.tracks.push(crate::playlist::PlaylistTrack {
+                name: if name.is_empty() { file_name } else { name },
+                path: track_path.to_string_lossy().to_string(),
+                duration,
+                file_type: ext,
+                gain_db: 0.0,
+                ..Default::default()
+            });
+        }
+        if self.playlist.current_index.is_none() && !self.playlist.tracks.is_empty() {
+            self.playlist.current_index = Some(0);
+        }
+        if self.playlist.is_shuffled {
+            self.playlist.shuffle_playlist();
+        }
+        Ok(())
+    }
+
+    /// Length of an audio file in seconds. WAV is read from the header via
+    /// `hound`, other formats from the decoder; when the decoder can't tell
+    /// (e.g. MP3 without a Xing header) the file is scanned on a background
+    /// thread and the result arrives through `apply_scanned_durations`.
+    fn probe_duration(&self, path: &Path) -> f64 {
+        if let Ok(reader) = hound::WavReader::open(path) {
+            let spec = reader.spec();
+            return reader.duration() as f64 / spec.sample_rate.max(1) as f64;
+        }
+        let Ok(file) = File::open(path) else { return 0.0 };
+        let Ok(decoder) = Decoder::new(BufReader::new(file)) else { return 0.0 };
+        if let Some(dur) = decoder.total_duration() {
+            return dur.as_secs_f64();
+        }
+        let tx = self.duration_tx.clone();
+        let path = path.to_string_lossy().to_string();
+        std::thread::spawn(move || {
+            let samples_per_sec = decoder.sample_rate() as f64 * decoder.channels().max(1) as f64;
+            let secs = decoder.count() as f64 / samples_per_sec;
+            let _ = tx.send((path, secs));
+        });
+        0.0
+    }
+
+    /// Fill in durations finished by background scans.
+    fn apply_scanned_durations(&mut self) {
+        while let Ok((path, secs)) = self.duration_rx.try_recv() {
+            for track in self.playlist.tracks.iter_mut().filter(|t| t.path == path) {
+                track.duration = secs;
+            }
+            if self.playlist.get_current_track().is_some_and(|t| t.path == path) && self.playlist.duration <= 0.0 {
+                self.playlist.duration = secs;
+            }
+        }
+    }
+
+    /// Queue `path` for the loudness worker; the gain arrives through
+    /// `apply_scanned_loudness`.
+    fn scan_loudness(&self, path: &Path) {
+        let _ = self.loudness_jobs.send(path.to_path_buf());
+    }
+
+    /// Store gains finished by background loudness scans, re-levelling the
+    /// current track if its scan just landed.
+    fn apply_scanned_loudness(&mut self) {
+        while let Ok((path, gain_db)) = self.loudness_rx.try_recv() {
+            for track in self.playlist.tracks.iter_mut().filter(|t| t.path == path) {
+                track.gain_db = gain_db;
+            }
+            if self.crossfade_start.is_none() && self.playlist.get_current_track().is_some_and(|t| t.path == path) {
+                self.update_volume();
+            }
+        }
+    }
+
     fn ensure_output_stream(&mut self) {
         if self.output_stream.is_some() && self.output_stream_handle.is_some() {
             return;
@@ -438,6 +1230,8 @@ impl MusicVisualizerNativeApp {
         self.demo_mode = false;
 
         self.ensure_output_stream();
+        // a manual track change cuts any crossfade short
+        self.finish_crossfade();
 
         if let Some(handle) = &self.output_stream_handle {
             // Stop previous sink
@@ -448,36 +1242,87 @@ impl MusicVisualizerNativeApp {
             if let Ok(file) = File::open(&track.path) {
                 let buf = BufReader::new(file);
                 if let Ok(decoder) = Decoder::new(buf) {
-                    let sink = Sink::try_new(handle).unwrap();
+                    let Ok(sink) = Sink::try_new(handle) else { return };
                     // set volume
-                    sink.set_volume(self.playlist.volume);
-                    // try to get duration
-                    if let Some(dur) = decoder.total_duration() {
-                        self.playlist.duration = dur.as_secs_f64();
-                    }
-                    sink.append(decoder);
-                    self.playback_start_instant = Some(Instant::now());
-                    self.playback_seek_offset = 0.0;
+                    sink.set_volume(self.playlist.volume_for(&track));
+                    // prefer the decoder's duration, else what was probed when the file was added
+                    self.playlist.duration = decoder.total_duration().map_or(track.duration, |d| d.as_secs_f64());
+                    self.append_source(&sink, decoder, 0.0);
                     self.current_sink = Some(sink);
+                    self.cache_decoded_track(&track.path);
                 }
             }
         }
     }
 
+    /// Queue `source`, which starts `start` seconds into the track, at the
+    /// current playback rate.
+    fn append_source<S>(&mut self, sink: &Sink, source: S, start: f64)
+    where
+        S: Source<Item = i16> + Send + 'static,
+    {
+        self.clock.set_rate(self.playback_rate as f64);
+        // counted before any speed change, so the clock keeps reporting track time
+        let source = self.clock.track(source, start);
+        #[cfg(feature = "pitch-preserve")]
+        if self.preserve_pitch {
+            sink.set_speed(1.0);
+            sink.append(crate::time_stretch::TimeStretch::new(source, self.playback_rate));
+            return;
+        }
+        // rodio's speed resamples, so the pitch moves with the rate (2x is an octave up)
+        sink.set_speed(self.playback_rate);
+        sink.append(source);
+    }
+
+    /// Apply a changed `playback_rate` to the playing track.
+    pub fn apply_playback_rate(&mut self) {
+        #[cfg(feature = "pitch-preserve")]
+        if self.preserve_pitch {
+            // the stretch is fixed when the source is built, so restart it in place
+            if self.current_sink.is_some() {
+                let paused = !self.playlist.is_playing;
+                self.seek_to(self.playlist.current_time);
+                if paused { self.toggle_playback(); }
+            }
+            return;
+        }
+        self.clock.set_rate(self.playback_rate as f64);
+        if let Some(sink) = &self.current_sink {
+            sink.set_speed(self.playback_rate);
+        }
+    }
+
+    /// Decode `path` to PCM on a background thread so later seeks are
+    /// sample-accurate without re-decoding the file.
+    fn cache_decoded_track(&self, path: &str) {
+        if self.decoded_cache.lock().unwrap().as_ref().is_some_and(|d| d.path == path) {
+            return;
+        }
+        let cache = self.decoded_cache.clone();
+        let path = path.to_string();
+        std::thread::spawn(move || {
+            let Ok(file) = File::open(&path) else { return };
+            let Ok(decoder) = Decoder::new(BufReader::new(file)) else { return };
+            let channels = decoder.channels();
+            let sample_rate = decoder.sample_rate();
+            let samples = decoder.collect::<Vec<i16>>().into();
+            *cache.lock().unwrap() = Some(DecodedTrack { path, channels, sample_rate, samples });
+        });
+    }
+
     pub fn toggle_playback(&mut self) {
+        if self.playlist.is_playing {
+            self.finish_crossfade();
+        }
         if let Some(sink) = &self.current_sink {
             if self.playlist.is_playing {
                 sink.pause();
-                // adjust offset
-                if let Some(start) = self.playback_start_instant {
-                    let elapsed = start.elapsed().as_secs_f64();
-                    self.playback_seek_offset += elapsed;
-                }
-                self.playback_start_instant = None;
+                self.clock.pause();
                 self.playlist.is_playing = false;
             } else {
                 sink.play();
-                self.playback_start_instant = Some(Instant::now());
+                self.clock.resume();
                 self.playlist.is_playing = true;
             }
         } else if self.playlist.current_index.is_some() {
@@ -487,20 +1332,116 @@ impl MusicVisualizerNativeApp {
         }
     }
 
+    pub fn toggle_shuffle(&mut self) {
+        self.playlist.is_shuffled = !self.playlist.is_shuffled;
+        if self.playlist.is_shuffled { self.playlist.shuffle_playlist(); }
+    }
+
+    /// Playback hotkeys, listed in [`SHORTCUTS`]. Ignored while a text field has focus.
+    fn handle_shortcuts(&mut self, ctx: &egui::Context) {
+        if ctx.wants_keyboard_input() { return; }
+        use egui::{Key, Modifiers};
+        let (toggle_help, toggle_presentation, play, seek_back, seek_fwd, prev, next, louder, quieter, shuffle, freeze) = ctx.input_mut(|i| (
+            i.events.iter().any(|e| matches!(e, egui::Event::Text(t) if t == "?")),
+            i.consume_key(Modifiers::NONE, Key::F11),
+            i.consume_key(Modifiers::NONE, Key::Space),
+            i.consume_key(Modifiers::SHIFT, Key::ArrowLeft),
+            i.consume_key(Modifiers::SHIFT, Key::ArrowRight),
+            i.consume_key(Modifiers::NONE, Key::ArrowLeft),
+            i.consume_key(Modifiers::NONE, Key::ArrowRight),
+            i.consume_key(Modifiers::NONE, Key::ArrowUp),
+            i.consume_key(Modifiers::NONE, Key::ArrowDown),
+            i.consume_key(Modifiers::NONE, Key::S),
+            i.consume_key(Modifiers::NONE, Key::F),
+        ));
+        if toggle_help { self.show_shortcuts = !self.show_shortcuts; }
+        if self.show_shortcuts && ctx.input_mut(|i| i.consume_key(Modifiers::NONE, Key::Escape)) { self.show_shortcuts = false; }
+        if self.presentation_mode && ctx.input_mut(|i| i.consume_key(Modifiers::NONE, Key::Escape)) {
+            self.presentation_overlay_until = ctx.input(|i| i.time) + PRESENTATION_OVERLAY_SECS;
+        }
+        if toggle_presentation { self.set_presentation_mode(ctx, !self.presentation_mode); }
+        if play { self.toggle_playback(); }
+        if seek_back || seek_fwd {
+            let step = if seek_fwd { SEEK_STEP_SECS } else { -SEEK_STEP_SECS };
+            let end = if self.playlist.duration > 0.0 { self.playlist.duration } else { f64::MAX };
+            self.seek_to((self.playlist.current_time + step).min(end));
+        }
+        if prev { self.play_previous(); }
+        if next { self.play_next(); }
+        if louder || quieter {
+            let step = if louder { VOLUME_STEP } else { -VOLUME_STEP };
+            self.playlist.volume = (self.playlist.volume + step).clamp(0.0, 1.0);
+            self.update_volume();
+        }
+        if shuffle { self.toggle_shuffle(); }
+        if freeze { self.frozen = !self.frozen; }
+    }
+
+    /// Enter or leave presentation mode, taking the window fullscreen with it.
+    pub fn set_presentation_mode(&mut self, ctx: &egui::Context, on: bool) {
+        self.presentation_mode = on;
+        self.presentation_overlay_until = if on { ctx.input(|i| i.time) + PRESENTATION_OVERLAY_SECS } else { 0.0 };
+        ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(on));
+    }
+
     pub fn stop_playback(&mut self) {
         if let Some(sink) = self.current_sink.take() {
             sink.stop();
         }
+        self.finish_crossfade();
         self.playlist.is_playing = false;
         self.playlist.current_time = 0.0;
-        self.playback_start_instant = None;
-        self.playback_seek_offset = 0.0;
+        self.clock.reset();
     }
 
     pub fn play_next(&mut self) {
-        // try next index; if none, loop to 0
-        let next = self.playlist.get_next_index().or_else(|| if !self.playlist.tracks.is_empty() { Some(0) } else { None });
-        if let Some(idx) = next { self.play_track(idx); } else { self.stop_playback(); }
+        // try next index; wraps to the first track unless repeat is off
+        if let Some(idx) = self.playlist.get_manual_next_index() { self.play_track(idx); } else { self.stop_playback(); }
+    }
+
+    /// Advance when the current track finishes, honouring the repeat mode.
+    pub fn handle_track_end(&mut self) {
+        if let Some(idx) = self.playlist.get_index_after_end() { self.play_track(idx); } else { self.stop_playback(); }
+    }
+
+    /// Start the next track on a fresh sink while the current one keeps
+    /// playing; `update_crossfade` ramps the two volumes against each other.
+    fn start_crossfade(&mut self) {
+        let Some(next) = self.playlist.get_index_after_end() else { return };
+        let outgoing = self.current_sink.take();
+        self.fading_volume = self.playlist.current_volume();
+        self.play_track(next);
+        if let Some(sink) = &self.current_sink {
+            sink.set_volume(0.0);
+        }
+        self.fading_sink = outgoing;
+        self.crossfade_start = Some(Instant::now());
+    }
+
+    fn update_crossfade(&mut self) {
+        let Some(start) = self.crossfade_start else { return };
+        let t = (start.elapsed().as_secs_f32() / self.config.crossfade_secs.max(0.01)).min(1.0);
+        let faded_out = self.fading_sink.as_ref().is_none_or(|s| s.empty());
+        if t >= 1.0 || faded_out {
+            self.finish_crossfade();
+            return;
+        }
+        if let Some(sink) = &self.fading_sink {
+            sink.set_volume(self.fading_volume * (1.0 - t));
+        }
+        if let Some(sink) = &self.current_sink {
+            sink.set_volume(self.playlist.current_volume() * t);
+        }
+    }
+
+    /// Drop the outgoing track and restore full volume on the current one.
+    fn finish_crossfade(&mut self) {
+        if let Some(sink) = self.fading_sink.take() {
+            sink.stop();
+        }
+        if self.crossfade_start.take().is_some() {
+            self.update_volume();
+        }
     }
 
     pub fn play_previous(&mut self) {
@@ -511,73 +1452,70 @@ impl MusicVisualizerNativeApp {
         if let Some(prev) = self.playlist.get_prev_index() { self.play_track(prev); } else { self.stop_playback(); }
     }
 
+    /// The elapsed time label; double-click it to type a position (`mm:ss`)
+    /// and press Enter to seek there. Escape or clicking away cancels.
+    fn seek_time_ui(&mut self, ui: &mut egui::Ui, current_time: &str) {
+        let id = ui.make_persistent_id("seek_time");
+        let duration = self.playlist.duration;
+        let Some(text) = &mut self.seek_text else {
+            let label = ui.add(egui::Label::new(current_time).sense(egui::Sense::click())).on_hover_text("Double-click to type a position");
+            if label.double_clicked() {
+                self.seek_text = Some(current_time.to_string());
+                ui.memory_mut(|m| m.request_focus(id));
+            }
+            return;
+        };
+        let target = PlaylistState::parse_time(text).filter(|&t| duration <= 0.0 || t <= duration);
+        let mut edit = egui::TextEdit::singleline(text).id(id).desired_width(48.0);
+        if target.is_none() { edit = edit.text_color(ui.visuals().error_fg_color); }
+        let response = ui.add(edit).on_hover_text(format!("mm:ss, up to {}", PlaylistState::format_time(duration)));
+        if !response.lost_focus() { return; }
+        if ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+            // out-of-range or malformed input stays open for correction
+            let Some(time) = target else { response.request_focus(); return };
+            self.seek_to(time);
+        }
+        self.seek_text = None;
+    }
+
     pub fn seek_to(&mut self, time: f64) {
-        // Best-effort seeking: WAV files supported via `hound`; other formats restart at 0 and set time marker
-        if let Some(idx) = self.playlist.current_index {
-            let track = self.playlist.tracks[idx].clone();
-            let ext = track.file_type.to_lowercase();
-            // stop current sink
-            if let Some(sink) = self.current_sink.take() {
-                sink.stop();
-            }
-
-            self.ensure_output_stream();
-            if let Some(handle) = &self.output_stream_handle {
-                if ext == "wav" {
-                    // Use hound to seek samples
-                    if let Ok(mut reader) = hound::WavReader::open(&track.path) {
-                        let spec = reader.spec();
-                        let sample_rate = spec.sample_rate as u64;
-                        let channels = spec.channels as usize;
-                        let start_sample = (time * sample_rate as f64) as u64;
-                        // skip to start_sample * channels
-                        let total_samples = reader.duration();
-                        let mut samples_iter = reader.samples::<i16>();
-                        let to_skip = start_sample.saturating_mul(channels as u64);
-                        for _ in 0..to_skip { let _ = samples_iter.next(); }
-                        let samples: Vec<i16> = samples_iter.filter_map(Result::ok).collect();
-                        // Create a SamplesBuffer and play
-                        let source = rodio::buffer::SamplesBuffer::new(channels as u16, sample_rate as u32, samples);
-                        let sink = Sink::try_new(handle).unwrap();
-                        sink.set_volume(self.playlist.volume);
-                        sink.append(source);
-                        self.current_sink = Some(sink);
-                        self.playback_start_instant = Some(Instant::now());
-                        self.playback_seek_offset = time;
-                        // duration: calculate from total_samples
-                        if total_samples > 0 {
-                            self.playlist.duration = (total_samples as f64) / (sample_rate as f64 * channels as f64);
-                        }
-                        self.playlist.is_playing = true;
-                        self.playlist.current_time = time;
-                        return;
-                    }
-                }
+        // Sample-accurate seeking from the decoded cache once the background decode
+        // has finished; until then the decoder skips ahead with `skip_duration`.
+        let Some(idx) = self.playlist.current_index else { return };
+        let track = self.playlist.tracks[idx].clone();
+        let time = time.max(0.0);
+        // stop current sink
+        if let Some(sink) = self.current_sink.take() {
+            sink.stop();
+        }
+        self.finish_crossfade();
 
-                // Fallback: start from beginning and set offset marker (approximate)
-                if let Ok(file) = File::open(&track.path) {
-                    let buf = BufReader::new(file);
-                    if let Ok(decoder) = Decoder::new(buf) {
-                        let sink = Sink::try_new(handle).unwrap();
-                        sink.set_volume(self.playlist.volume);
-                        if let Some(dur) = decoder.total_duration() {
-                            self.playlist.duration = dur.as_secs_f64();
-                        }
-                        sink.append(decoder);
-                        self.current_sink = Some(sink);
-                        self.playback_start_instant = Some(Instant::now());
-                        self.playback_seek_offset = time; // we note desired offset but actual audio will start at 0
-                        self.playlist.is_playing = true;
-                        self.playlist.current_time = time;
-                    }
-                }
+        self.ensure_output_stream();
+        let Some(handle) = &self.output_stream_handle else { return };
+        let Ok(sink) = Sink::try_new(handle) else { return };
+        sink.set_volume(self.playlist.volume_for(&track));
+
+        let cached = self.decoded_cache.lock().unwrap().as_ref().filter(|d| d.path == track.path).map(|d| (d.source_from(time), d.duration_secs()));
+        if let Some((source, total)) = cached {
+            self.playlist.duration = total;
+            self.append_source(&sink, source, time);
+        } else {
+            let Ok(file) = File::open(&track.path) else { return };
+            let Ok(decoder) = Decoder::new(BufReader::new(file)) else { return };
+            if let Some(dur) = decoder.total_duration() {
+                self.playlist.duration = dur.as_secs_f64();
             }
+            self.append_source(&sink, decoder.skip_duration(Duration::from_secs_f64(time)), time);
+            self.cache_decoded_track(&track.path);
         }
+        self.current_sink = Some(sink);
+        self.playlist.is_playing = true;
+        self.playlist.current_time = time;
     }
 
     pub fn update_volume(&mut self) {
         if let Some(sink) = &self.current_sink {
-            sink.set_volume(self.playlist.volume);
+            sink.set_volume(self.playlist.current_volume());
         }
     }
 
@@ -602,21 +1540,228 @@ impl MusicVisualizerNativeApp {
     }
 
     pub fn update_playback_state(&mut self) {
-        // Update current_time based on start instant + seek offset; detect end using sink.empty()
+        self.apply_scanned_durations();
+        self.apply_scanned_loudness();
+        // Update current_time from the audio clock; detect end using sink.empty()
+        self.update_crossfade();
         if let Some(sink) = &self.current_sink {
-            let elapsed = if let Some(start) = self.playback_start_instant {
-                start.elapsed().as_secs_f64()
-            } else { 0.0 };
-            self.playlist.current_time = self.playback_seek_offset + elapsed;
-            if sink.empty() && self.playlist.is_playing {
+            self.clock.sync();
+            self.playlist.current_time = self.clock.position();
+            let remaining = self.playlist.duration - self.playlist.current_time;
+            let crossfade = self.config.crossfade_secs as f64;
+            // an A/B loop jumps back at B, or at the end of the track if B lies past it,
+            // and holds off the crossfade so B is always reached
+            let loop_range = self.playlist.loop_range();
+            let loop_back = loop_range
+                .filter(|&(_, b)| self.playlist.is_playing && (self.playlist.current_time >= b || sink.empty()))
+                .map(|(a, _)| a);
+            if let Some(a) = loop_back {
+                self.seek_to(a);
+            } else if crossfade > 0.0 && loop_range.is_none() && self.playlist.is_playing && self.crossfade_start.is_none()
+                && self.playlist.duration > crossfade && remaining <= crossfade && !sink.empty() {
+                self.start_crossfade();
+            } else if sink.empty() && self.playlist.is_playing {
                 // consider ended
-                self.play_next();
+                self.handle_track_end();
             }
         }
     }
 }
 
 impl MusicVisualizerNativeApp {
+    /// (Re)start capture from the named input device, or the default one for
+    /// `None`. The old stream is dropped first so the device is released.
+    pub fn select_input_device(&mut self, name: Option<String>) {
+        self.capture = None;
+        self.input_devices = capture::input_device_names();
+        match capture::start_cpal_stream(name.as_deref(), self.audio_data.clone(), self.capture_error_tx.clone()) {
+            Ok(stream) => {
+                self.audio.sample_rate = ANALYSIS_SAMPLE_RATE;
+                self.audio.channels = stream.channels;
+                self.capture_message = match name {
+                    Some(wanted) if wanted != stream.device_name => Some(format!("{wanted} not found; using {}", stream.device_name)),
+                    _ => None,
+                };
+                self.capture = Some(stream);
+            }
+            Err(e) => {
+                eprintln!("Failed to start audio capture: {e}");
+                self.demo_mode = true;
+                self.capture_message = Some(format!("{NO_INPUT_BANNER} ({e})"));
+            }
+        }
+    }
+
+    /// Open the named MIDI input, or close the current one for `None`.
+    pub fn select_midi_port(&mut self, name: Option<String>) {
+        self.midi = None;
+        self.midi_message = None;
+        let Some(name) = name else { return };
+        match midi::connect(&name, self.midi_tx.clone()) {
+            Ok(conn) => self.midi = Some(conn),
+            Err(e) => self.midi_message = Some(e),
+        }
+    }
+
+    fn poll_midi(&mut self) {
+        while let Ok(event) = self.midi_rx.try_recv() {
+            match event {
+                MidiEvent::NoteOn { velocity, .. } => {
+                    self.audio.beat = true;
+                    self.audio.beat_intensity = self.audio.beat_intensity.max(f32::from(velocity) / 127.0);
+                }
+                MidiEvent::ControlChange { controller, value } => {
+                    self.midi_last_cc = Some((controller, value));
+                    for mapping in self.config.midi_cc_map.clone().into_iter().filter(|m| m.controller == controller) {
+                        mapping.target.apply(&mut self.config, value);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Start or stop the OSC listener on `osc_port`, reachable from other
+    /// devices only when `osc_lan` is set.
+    pub fn set_osc_listening(&mut self, on: bool) {
+        self.osc = None;
+        self.osc_message = None;
+        if !on { return; }
+        match OscServer::start(self.osc_port, self.osc_lan, self.osc_inbox.clone()) {
+            Ok(server) => self.osc = Some(server),
+            Err(e) => self.osc_message = Some(e),
+        }
+    }
+
+    fn poll_osc(&mut self) {
+        let messages = std::mem::take(&mut *self.osc_inbox.lock().unwrap());
+        for msg in messages {
+            self.osc_message = Some(match self.apply_osc(&msg) {
+                Ok(()) => format!("Last: {} {:?}", msg.address, msg.args),
+                Err(e) => e,
+            });
+        }
+    }
+
+    /// Set the config field or mode named by an `OSC_ADDRESSES` entry,
+    /// clamping numbers to the listed range.
+    pub fn apply_osc(&mut self, msg: &OscMessage) -> Result<(), String> {
+        let number = || msg.number().ok_or_else(|| format!("{} needs a numeric argument", msg.address));
+        let name = match msg.args.first() { Some(OscArg::Str(s)) => Some(s.to_lowercase()), _ => None };
+        let c = &mut self.config;
+        match msg.address.as_str() {
+            "/visualizer/zoom" => c.base_zoom = number()?.clamp(0.01, 10.0),
+            "/visualizer/width" => c.base_width = number()?.clamp(0.1, 5.0),
+            "/visualizer/depth" => c.base_depth = number()?.round().clamp(1.0, MAX_BASE_DEPTH as f32) as u32,
+            "/visualizer/brightness" => c.base_brightness = number()?.clamp(0.0, 2.0),
+            "/visualizer/rotation_speed" => c.rotation_speed = number()?.clamp(0.0, 10.0),
+            "/visualizer/glow" => c.glow_intensity = number()?.clamp(0.0, 2.0),
+            "/visualizer/beat_sensitivity" => c.beat_sensitivity = number()?.clamp(1.0, 3.0),
+            "/visualizer/mode" => {
+                self.visualizer_mode = match (name.as_deref(), msg.number()) {
+                    (Some("fractal"), _) => VisualizerMode::Fractal,
+                    (Some("up" | "unknown_pleasures" | "unknown pleasures"), _) => VisualizerMode::UnknownPleasures,
+                    (None, Some(n)) if n.round() == 0.0 => VisualizerMode::Fractal,
+                    (Some("radial" | "radial_spectrum" | "radial spectrum"), _) => VisualizerMode::RadialSpectrum,
+                    (None, Some(n)) if n.round() == 1.0 => VisualizerMode::UnknownPleasures,
+                    (None, Some(n)) if n.round() == 2.0 => VisualizerMode::RadialSpectrum,
+                    _ => return Err(format!("Unknown mode {:?}", msg.args)),
+                };
+            }
+            "/visualizer/fractal" => {
+                let kind = match &name {
+                    Some(name) => FractalKind::ALL.into_iter().find(|k| k.label().to_lowercase() == *name),
+                    None => msg.number().and_then(|n| FractalKind::ALL.get(n.round().max(0.0) as usize).copied()),
+                };
+                c.fractal_kind = kind.ok_or_else(|| format!("Unknown fractal {:?}", msg.args))?;
+            }
+            other => return Err(format!("Unknown OSC address {other}")),
+        }
+        Ok(())
+    }
+
+    /// Ask for the next frame no sooner than `max_fps` allows, dropping to
+    /// `IDLE_FPS` while nothing plays, nothing records and the input is silent.
+    /// Input events still repaint immediately, so the UI stays responsive.
+    fn schedule_repaint(&self, ctx: &egui::Context) {
+        let idle = !self.playlist.is_playing && self.recorder.is_none() && self.audio.smooth_volume < IDLE_VOLUME;
+        let fps = if idle { IDLE_FPS } else { self.config.max_fps.max(1) };
+        ctx.request_repaint_after(Duration::from_secs_f32(1.0 / fps as f32));
+    }
+
+    /// Store the current config as a custom preset named `preset_name`,
+    /// replacing any preset of the same name, and write the list to disk.
+    pub fn save_custom_preset(&mut self) {
+        let name = self.preset_name.trim().to_string();
+        let preset = CustomPreset { name: name.clone(), config: self.config.clone() };
+        match self.custom_presets.iter_mut().find(|p| p.name == name) {
+            Some(existing) => *existing = preset,
+            None => self.custom_presets.push(preset),
+        }
+        self.write_custom_presets(format!("Saved preset \"{name}\""));
+    }
+
+    pub fn delete_custom_preset(&mut self, index: usize) {
+        let preset = self.custom_presets.remove(index);
+        self.write_custom_presets(format!("Deleted preset \"{}\"", preset.name));
+    }
+
+    fn write_custom_presets(&mut self, done: String) {
+        let path = custom_presets_path();
+        self.preset_message = Some(match save_custom_presets(&path, &self.custom_presets) {
+            Ok(()) => done,
+            Err(e) => format!("Failed to write {}: {e}", path.display()),
+        });
+    }
+
+    pub fn start_recording(&mut self) {
+        let Some(dir) = &self.record_dir else { return };
+        match FrameRecorder::start(dir) {
+            Ok(recorder) => { self.recorder = Some(recorder); self.record_message = None; }
+            Err(e) => self.record_message = Some(e),
+        }
+    }
+
+    pub fn stop_recording(&mut self) {
+        if let Some(rec) = self.recorder.take() {
+            self.record_message = Some(format!("Saved {} frames to {}", rec.frames, rec.dir.display()));
+        }
+    }
+
+    // hand last frame's screenshot, cropped to the visual, to the recorder and request the next one
+    fn record_frame(&mut self, ctx: &egui::Context) {
+        let Some(recorder) = &mut self.recorder else { return };
+        let shots: Vec<Arc<egui::ColorImage>> = ctx.input(|i| i.events.iter().filter_map(|e| match e {
+            egui::Event::Screenshot { image, .. } => Some(image.clone()),
+            _ => None,
+        }).collect());
+        let ppp = ctx.pixels_per_point();
+        for image in shots {
+            recorder.push(image.region(&self.visual_rect, Some(ppp)), self.time);
+        }
+        if let Some(err) = recorder.poll_error() {
+            self.recorder = None;
+            self.record_message = Some(err);
+            return;
+        }
+        ctx.send_viewport_cmd(egui::ViewportCommand::Screenshot);
+    }
+
+    // a vanished device falls back to the default input; other stream errors are only reported
+    fn poll_capture_errors(&mut self) {
+        while let Ok(err) = self.capture_error_rx.try_recv() {
+            if matches!(err, cpal::StreamError::DeviceNotAvailable) {
+                let lost = self.capture.as_ref().map(|c| c.device_name.clone()).unwrap_or_default();
+                self.set_system_audio(false);
+                self.select_input_device(None);
+                if self.capture.is_some() {
+                    self.capture_message = Some(format!("{lost} disconnected; switched to the default input"));
+                }
+            } else {
+                self.capture_message = Some(format!("Audio capture error: {err}"));
+            }
+        }
+    }
+
     pub fn is_system_audio(&self) -> bool {
         self.system_audio_mode
     }
@@ -625,10 +1770,39 @@ impl MusicVisualizerNativeApp {
         self.system_audio_mode = enabled;
     }
 
+    /// Switch capture to the system output mix. Where that isn't possible the
+    /// reason is shown and the app falls back to the microphone.
     pub fn try_init_system_audio(&mut self) {
-        // Native CPAL stream is configured in main; here we just log the attempt
-        eprintln!("System audio capture requested: {}", self.system_audio_mode);
+        self.capture = None;
+        match capture::start_loopback_stream(self.audio_data.clone(), self.capture_error_tx.clone()) {
+            Ok(stream) => {
+                self.audio.sample_rate = ANALYSIS_SAMPLE_RATE;
+                self.audio.channels = stream.channels;
+                self.capture_message = None;
+                self.capture = Some(stream);
+            }
+            Err(e) => {
+                eprintln!("System audio capture unavailable: {e}");
+                self.set_system_audio(false);
+                self.select_input_device(None);
+                self.capture_message = Some(format!("System audio unavailable: {e}"));
+            }
+        }
     }
 }
 
+/// Start the thread that decodes queued files one at a time and sends back
+/// their normalization gain, so a large import doesn't run a decoder per file.
+fn spawn_loudness_worker(results: Sender<(String, f32)>) -> Sender<PathBuf> {
+    let (jobs, queue) = mpsc::channel::<PathBuf>();
+    std::thread::spawn(move || {
+        for path in queue {
+            let Ok(file) = File::open(&path) else { continue };
+            let Ok(decoder) = Decoder::new(BufReader::new(file)) else { continue };
+            if results.send((path.to_string_lossy().to_string(), normalization_gain_db(decoder))).is_err() { return; }
+        }
+    });
+    jobs
+}
+
 // Drawing helpers and UI logic are in `src/ui.rs`.
@@ -1,3 +1,83 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+use rustfft::{num_complex::Complex, Fft, FftPlanner};
+
+/// Number of time-domain samples per FFT frame; yields `FFT_SIZE / 2` spectrum bins.
+pub const FFT_SIZE: usize = 512;
+/// Rate capture is resampled to before analysis, so the FFT window spans the
+/// same time and bins the same frequencies on every device.
+pub const ANALYSIS_SAMPLE_RATE: f32 = 44_100.0;
+// dB range mapped onto 0..255, mirroring the Web Audio AnalyserNode defaults
+const MIN_DB: f32 = -100.0;
+const MAX_DB: f32 = -30.0;
+/// Number of bass energy frames kept for adaptive beat detection (~1s at 43 fps).
+pub const BEAT_HISTORY_LEN: usize = 43;
+/// Seconds of beat history used for tempo estimation.
+pub const BPM_WINDOW_SECS: f64 = 10.0;
+// beats closer together than this (200 BPM) are treated as one onset
+const MIN_BEAT_INTERVAL_SECS: f64 = 0.3;
+// auto gain envelope: fraction of the gap closed per 60 Hz frame when the level rises / falls;
+// fast attack and slow release keep loud hits from pumping the gain
+const AUTO_GAIN_ATTACK: f32 = 0.5;
+const AUTO_GAIN_RELEASE: f32 = 0.005;
+// level the envelope is scaled to, and the smallest envelope divided by (so silence isn't amplified)
+const AUTO_GAIN_TARGET: f32 = 0.7;
+const AUTO_GAIN_FLOOR: f32 = 0.05;
+/// Classic band boundaries in Hz: bass | low-mid | mid | high-mid | treble.
+pub const DEFAULT_BAND_EDGES_HZ: [f32; 6] = [20.0, 250.0, 500.0, 2000.0, 4000.0, 20_000.0];
+
+/// `edges` clamped to non-negative and sorted, so each band always covers a
+/// non-negative range.
+pub fn sorted_band_edges(mut edges: [f32; 6]) -> [f32; 6] {
+    for e in &mut edges {
+        *e = e.max(0.0);
+    }
+    edges.sort_by(f32::total_cmp);
+    edges
+}
+
+/// A-weighting gain in dB at `hz` (IEC 61672), 0 dB at 1 kHz; models how
+/// much quieter the ear hears low and very high frequencies.
+pub fn a_weighting_db(hz: f32) -> f32 {
+    let f2 = (hz as f64).powi(2);
+    let ra = 12194f64.powi(2) * f2 * f2
+        / ((f2 + 20.6f64.powi(2)) * ((f2 + 107.7f64.powi(2)) * (f2 + 737.9f64.powi(2))).sqrt() * (f2 + 12194f64.powi(2)));
+    (20.0 * ra.max(1e-10).log10() + 2.0) as f32
+}
+
+/// Which channels of a multi-channel capture feed the main analysis.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ChannelMode {
+    // average of every channel
+    #[default]
+    MonoSum,
+    Left,
+    Right,
+}
+
+impl ChannelMode {
+    pub const ALL: [ChannelMode; 3] = [ChannelMode::MonoSum, ChannelMode::Left, ChannelMode::Right];
+    pub fn label(self) -> &'static str {
+        match self {
+            ChannelMode::MonoSum => "Mono sum",
+            ChannelMode::Left => "Left only",
+            ChannelMode::Right => "Right only",
+        }
+    }
+
+    /// One mono sample per frame of `interleaved` audio `channels` wide.
+    /// Left and right fall back to the only channel of mono input.
+    pub fn downmix(self, interleaved: &[f32], channels: usize) -> Vec<f32> {
+        let channels = channels.max(1);
+        let frames = interleaved.chunks_exact(channels);
+        match self {
+            ChannelMode::MonoSum => frames.map(|f| f.iter().sum::<f32>() / channels as f32).collect(),
+            ChannelMode::Left => frames.map(|f| f[0]).collect(),
+            ChannelMode::Right => frames.map(|f| f[1.min(channels - 1)]).collect(),
+        }
+    }
+}
+
 // Native audio analysis struct, adapted from WASM version
 // This implementation computes basic band averages and a simple spectral flux
 // so fields like low_mid, high_mid and spectral_flux are actively used.
@@ -20,35 +100,283 @@ pub struct AudioAnalysis {
     pub smooth_volume: f32,
     pub frequency_data: Vec<f32>,
     pub time_data: Vec<f32>,
+    // interleaved channels in the capture buffer; only the first two are analysed separately
+    pub channels: u16,
+    // how those channels are folded into the mono signal behind the main bands
+    pub channel_mode: ChannelMode,
+    // per-channel band levels and spectra; equal to the mono values for one-channel input
+    pub bass_left: f32,
+    pub bass_right: f32,
+    pub mid_left: f32,
+    pub mid_right: f32,
+    pub treble_left: f32,
+    pub treble_right: f32,
+    // the per-channel levels smoothed like the `smooth_*` mono levels
+    pub smooth_bass_left: f32,
+    pub smooth_bass_right: f32,
+    pub smooth_mid_left: f32,
+    pub smooth_mid_right: f32,
+    pub smooth_treble_left: f32,
+    pub smooth_treble_right: f32,
+    pub frequency_data_left: Vec<f32>,
+    pub frequency_data_right: Vec<f32>,
     // previous frequency snapshot used to compute spectral flux
     pub prev_frequency_data: Vec<f32>,
+    // sample rate of the capture device, used to map Hz to FFT bins
+    pub sample_rate: f32,
+    // ascending Hz boundaries of the five bands (bass, low_mid, mid, high_mid, treble)
+    band_edges_hz: [f32; 6],
+    // a beat fires when bass exceeds the rolling mean times this factor
+    pub beat_sensitivity: f32,
+    // fraction of the gap to the new level each `smooth_*` value closes per frame (0..1)
+    smoothing: f32,
+    // frames whose RMS (after DC removal) is below this are analysed as silence
+    pub noise_gate: f32,
+    // whether the gate silenced the latest frame
+    pub gated: bool,
+    // ring buffer of recent bass energies for adaptive beat detection
+    energy_history: VecDeque<f32>,
+    // estimated tempo in beats per minute (0 until enough beats are seen)
+    pub bpm: f32,
+    // timestamps of recent beat onsets, used for BPM estimation
+    beat_times: VecDeque<f64>,
+    // weight bins by the A-curve before band averages and volume (perceived loudness)
+    pub a_weighting: bool,
+    // per-bin A-weighting in dB, rebuilt when the bin count or sample rate changes
+    a_weights_db: Vec<f32>,
+    a_weights_rate: f32,
+    // normalize bands against a slow peak envelope so quiet and loud sources react alike;
+    // `auto_gain_strength` blends from raw (0) to fully normalized (1)
+    pub auto_gain: bool,
+    pub auto_gain_strength: f32,
+    gain_envelope: f32,
+    // `time` of the last envelope update, for scaling its rates by the frame time
+    gain_time: Option<f64>,
+    // FFT plan and Hann window, built lazily on first use
+    fft: Option<Arc<dyn Fft<f32>>>,
+    window: Vec<f32>,
 }
 
 impl AudioAnalysis {
     pub fn new() -> Self {
         Self {
-            frequency_data: vec![0.0; 256],
-            time_data: vec![0.0; 256],
-            prev_frequency_data: vec![0.0; 256],
+            frequency_data: vec![0.0; FFT_SIZE / 2],
+            time_data: vec![0.0; FFT_SIZE / 2],
+            prev_frequency_data: vec![0.0; FFT_SIZE / 2],
+            channels: 1,
+            frequency_data_left: vec![0.0; FFT_SIZE / 2],
+            frequency_data_right: vec![0.0; FFT_SIZE / 2],
+            sample_rate: ANALYSIS_SAMPLE_RATE,
+            band_edges_hz: DEFAULT_BAND_EDGES_HZ,
+            beat_sensitivity: 1.4,
+            smoothing: 0.15,
+            energy_history: VecDeque::with_capacity(BEAT_HISTORY_LEN),
             ..Default::default()
         }
     }
-    pub fn update_from_cpal(&mut self, buffer: &[f32]) {
-        // Fill time_data and frequency_data with buffer values
+
+    /// Set how far each `smooth_*` value closes the gap to the new level per
+    /// frame, clamped to 0..1: lower is sluggish and smooth, higher is snappy.
+    pub fn set_smoothing(&mut self, smoothing: f32) {
+        self.smoothing = smoothing.clamp(0.0, 1.0);
+    }
+
+    /// Set the Hz boundaries of the five analysis bands. Edges are sorted so
+    /// each band always covers a non-negative range.
+    pub fn set_band_edges(&mut self, edges: [f32; 6]) {
+        self.band_edges_hz = sorted_band_edges(edges);
+    }
+
+    /// Bin ranges for bass, low-mid, mid, high-mid and treble.
+    fn band_ranges(&self) -> [std::ops::Range<usize>; 5] {
+        let e = self.band_edges_hz.map(|hz| self.hz_to_bin(hz));
+        [e[0]..e[1], e[1]..e[2], e[2]..e[3], e[3]..e[4], e[4]..e[5]]
+    }
+
+    /// Average level (0..1) of each band in a 0..255 spectrum.
+    fn band_levels(&self, spectrum: &[f32]) -> [f32; 5] {
+        self.band_ranges().map(|r| {
+            if r.is_empty() || r.end > spectrum.len() { return 0.0; }
+            let s: f32 = spectrum[r.clone()].iter().sum();
+            s / ((r.end - r.start) as f32 * 255.0)
+        })
+    }
+
+    /// Precompute the A-weighting of each bin from its centre frequency.
+    fn refresh_a_weights(&mut self, bins: usize) {
+        if self.a_weights_db.len() == bins && self.a_weights_rate == self.sample_rate { return; }
+        let sample_rate = if self.sample_rate > 0.0 { self.sample_rate } else { 44_100.0 };
+        self.a_weights_db = (0..bins).map(|i| a_weighting_db(i as f32 * sample_rate / FFT_SIZE as f32)).collect();
+        self.a_weights_rate = self.sample_rate;
+    }
+
+    /// The 0..255 spectrum the bands are measured on. Bins are on a dB scale,
+    /// so with A-weighting on each weight is added as a dB offset, which is
+    /// the same as multiplying the bin magnitude by the linear gain.
+    fn weighted_spectrum(&mut self, spectrum: &[f32]) -> Vec<f32> {
+        if !self.a_weighting { return spectrum.to_vec(); }
+        self.refresh_a_weights(spectrum.len());
+        let per_db = 255.0 / (MAX_DB - MIN_DB);
+        spectrum.iter().zip(&self.a_weights_db).map(|(v, w)| (v + w * per_db).clamp(0.0, 255.0)).collect()
+    }
+
+    /// Factor applied to the RMS volume with A-weighting on: the ratio of
+    /// weighted to unweighted spectral power, as an amplitude.
+    fn a_weighted_volume_gain(&mut self, spectrum: &[f32]) -> f32 {
+        if !self.a_weighting { return 1.0; }
+        self.refresh_a_weights(spectrum.len());
+        let power = |v: f32| 10f32.powf((MIN_DB + v / 255.0 * (MAX_DB - MIN_DB)) / 10.0);
+        let (mut total, mut weighted) = (0.0, 0.0);
+        for (&v, &w) in spectrum.iter().zip(&self.a_weights_db) {
+            total += power(v);
+            weighted += power(v) * 10f32.powf(w / 10.0);
+        }
+        if total > 0.0 { (weighted / total).sqrt() } else { 1.0 }
+    }
+
+    pub fn is_stereo(&self) -> bool {
+        self.channels >= 2
+    }
+
+    /// Convert a frequency in Hz to the index of the FFT bin containing it.
+    pub fn hz_to_bin(&self, hz: f32) -> usize {
+        let sample_rate = if self.sample_rate > 0.0 { self.sample_rate } else { 44_100.0 };
+        ((hz * FFT_SIZE as f32 / sample_rate).round().max(0.0) as usize).min(FFT_SIZE / 2)
+    }
+
+    /// Follow the loudest band with a fast-attack, slow-release envelope, then
+    /// scale `bands` by it. Bands pass through untouched while auto gain is off.
+    fn apply_auto_gain(&mut self, bands: [f32; 5], time: f64) -> [f32; 5] {
+        // a stalled or restarted clock counts as one frame, at most a quarter second
+        let dt = self.gain_time.map_or(1.0 / 60.0, |last| (time - last).clamp(0.0, 0.25)) as f32;
+        self.gain_time = Some(time);
+        if !self.auto_gain { return bands; }
+        let level = bands.iter().copied().fold(0.0, f32::max);
+        let rate = if level > self.gain_envelope { AUTO_GAIN_ATTACK } else { AUTO_GAIN_RELEASE };
+        let rate = 1.0 - (1.0 - rate).powf(dt * 60.0);
+        self.gain_envelope += (level - self.gain_envelope) * rate;
+        self.scale_by_auto_gain(bands)
+    }
+
+    // scale bands so the current envelope sits at AUTO_GAIN_TARGET, without moving the envelope
+    fn scale_by_auto_gain(&self, bands: [f32; 5]) -> [f32; 5] {
+        if !self.auto_gain { return bands; }
+        let gain = AUTO_GAIN_TARGET / self.gain_envelope.max(AUTO_GAIN_FLOOR);
+        let strength = self.auto_gain_strength.clamp(0.0, 1.0);
+        bands.map(|b| b + ((b * gain).min(1.0) - b) * strength)
+    }
+
+    /// Adaptive beat detection: flag a beat when the instantaneous bass energy
+    /// exceeds the rolling mean by `beat_sensitivity` and stands out from the
+    /// recent variance, then push the energy into the history ring buffer.
+    fn detect_beat(&mut self, bass: f32) {
+        let n = self.energy_history.len();
+        if n >= BEAT_HISTORY_LEN / 2 {
+            let mean = self.energy_history.iter().sum::<f32>() / n as f32;
+            let variance = self.energy_history.iter().map(|e| (e - mean) * (e - mean)).sum::<f32>() / n as f32;
+            self.beat = bass > mean * self.beat_sensitivity && bass - mean > variance.sqrt();
+            self.beat_intensity = if self.beat { ((bass - mean) / mean.max(1e-3)).min(1.0) } else { 0.0 };
+        } else {
+            self.beat = false;
+            self.beat_intensity = 0.0;
+        }
+        if n >= BEAT_HISTORY_LEN {
+            self.energy_history.pop_front();
+        }
+        self.energy_history.push_back(bass);
+    }
+
+    /// Record beat onsets at `time` (seconds) and estimate the tempo from a
+    /// histogram of inter-beat intervals over the last `BPM_WINDOW_SECS`,
+    /// folded into the 60–200 BPM range. Reports 0 until enough beats arrive.
+    fn update_bpm(&mut self, time: f64) {
+        if self.beat && self.beat_times.back().is_none_or(|&t| time - t >= MIN_BEAT_INTERVAL_SECS) {
+            self.beat_times.push_back(time);
+        }
+        while self.beat_times.front().is_some_and(|&t| time - t > BPM_WINDOW_SECS) {
+            self.beat_times.pop_front();
+        }
+        if self.beat_times.len() < 4 {
+            self.bpm = 0.0;
+            return;
+        }
+        let candidates: Vec<f32> = self.beat_times.iter().zip(self.beat_times.iter().skip(1))
+            .map(|(a, b)| {
+                let mut bpm = (60.0 / (b - a)) as f32;
+                while bpm < 60.0 { bpm *= 2.0; }
+                while bpm > 200.0 { bpm /= 2.0; }
+                bpm
+            })
+            .collect();
+        // 1-BPM buckets; score each bucket by its +/-2 BPM neighbourhood
+        let mut histogram = [0u32; 141];
+        for bpm in &candidates {
+            histogram[(bpm.round() as usize).clamp(60, 200) - 60] += 1;
+        }
+        let score = |b: usize| histogram[b.saturating_sub(2)..(b + 3).min(histogram.len())].iter().sum::<u32>();
+        let best = (0..histogram.len()).max_by_key(|&b| score(b)).unwrap_or(0) as f32 + 60.0;
+        let near: Vec<f32> = candidates.into_iter().filter(|bpm| (bpm - best).abs() <= 2.5).collect();
+        self.bpm = if near.is_empty() { best } else { near.iter().sum::<f32>() / near.len() as f32 };
+    }
+
+    /// Hann-window the most recent `FFT_SIZE` samples (zero-padding shorter
+    /// buffers) and return the magnitude spectrum scaled to 0..255.
+    fn compute_spectrum(&mut self, buffer: &[f32]) -> Vec<f32> {
+        let fft = self
+            .fft
+            .get_or_insert_with(|| FftPlanner::new().plan_fft_forward(FFT_SIZE))
+            .clone();
+        if self.window.len() != FFT_SIZE {
+            self.window = (0..FFT_SIZE)
+                .map(|i| {
+                    0.5 - 0.5 * (std::f32::consts::TAU * i as f32 / (FFT_SIZE - 1) as f32).cos()
+                })
+                .collect();
+        }
+        let frame = &buffer[buffer.len().saturating_sub(FFT_SIZE)..];
+        let mut bins: Vec<Complex<f32>> = self
+            .window
+            .iter()
+            .enumerate()
+            .map(|(i, w)| Complex::new(frame.get(i).copied().unwrap_or(0.0) * w, 0.0))
+            .collect();
+        fft.process(&mut bins);
+        // normalize by the window's coherent gain so a full-scale sine reads ~0 dB
+        let norm = 2.0 / self.window.iter().sum::<f32>();
+        bins[..FFT_SIZE / 2]
+            .iter()
+            .map(|c| {
+                let db = 20.0 * (c.norm() * norm).max(1e-10).log10();
+                ((db - MIN_DB) / (MAX_DB - MIN_DB) * 255.0).clamp(0.0, 255.0)
+            })
+            .collect()
+    }
+
+    /// Analyse one capture buffer of interleaved frames (`self.channels` wide).
+    /// Bands, beats and the main spectrum use the mono downmix; stereo input
+    /// additionally gets left/right spectra and band levels.
+    pub fn update_from_cpal(&mut self, interleaved: &[f32], time: f64) {
+        let channels = self.channels.max(1) as usize;
+        let mut buffer = self.channel_mode.downmix(interleaved, channels);
         let len = buffer.len();
         if len == 0 {
             return;
         }
-        // For now, treat buffer as time domain
-        self.time_data = buffer.iter().cloned().collect();
-        // Simple RMS volume
-        let rms = (buffer.iter().map(|x| x * x).sum::<f32>() / len as f32).sqrt();
-        self.volume = rms;
-        self.smooth_volume = self.smooth_volume + (rms - self.smooth_volume) * 0.15;
+        // a biased input would otherwise read as constant low-frequency energy
+        remove_dc_offset(&mut buffer);
+        self.gated = rms(&buffer) < self.noise_gate;
+        if self.gated {
+            buffer.fill(0.0);
+        }
+        self.time_data = buffer.clone();
         // Peak detection
         self.peak = buffer.iter().map(|x| x.abs()).fold(0.0, f32::max);
-        // Simulate frequency_data (for now, just copy time_data)
-        let new_freq: Vec<f32> = buffer.iter().map(|x| (x.abs() * 255.0).min(255.0)).collect();
+        let new_freq = self.compute_spectrum(&buffer);
+        // Simple RMS volume, scaled by the A-curve when enabled
+        let rms = rms(&buffer) * self.a_weighted_volume_gain(&new_freq);
+        self.volume = rms;
+        let smoothing = self.smoothing.clamp(0.0, 1.0);
+        self.smooth_volume = self.smooth_volume + (rms - self.smooth_volume) * smoothing;
         // compute spectral flux against previous frame
         let mut flux = 0.0f32;
         let prev = &self.prev_frequency_data;
@@ -58,36 +386,250 @@ impl AudioAnalysis {
             if diff > 0.0 { flux += diff; }
         }
         self.spectral_flux = flux;
+        // Spectral centroid (normalized 0..1 over the bin range)
+        let total_energy: f32 = new_freq.iter().sum();
+        if total_energy > 0.0 {
+            let weighted: f32 = new_freq.iter().enumerate().map(|(i, v)| i as f32 * v).sum();
+            self.spectral_centroid = weighted / total_energy / new_freq.len() as f32;
+        }
         self.frequency_data = new_freq;
-        // Basic band analysis
-        let band_size = len / 8; // finer division
-        let bass_range = 0..band_size.max(1);
-        let low_mid_range = band_size..(band_size * 2).max(band_size + 1);
-        let mid_range = (band_size * 2)..(band_size * 4).max((band_size * 2) + 1);
-        let high_mid_range = (band_size * 4)..(band_size * 6).min(len);
-        let treble_range = (band_size * 6)..len;
-
-        let avg = |r: std::ops::Range<usize>| -> f32 {
-            if r.is_empty() { return 0.0; }
-            let s: f32 = buffer[r.clone()].iter().map(|x| x.abs()).sum();
-            s / (r.end - r.start) as f32
-        };
-
-        self.bass = avg(bass_range.clone());
-        self.low_mid = avg(low_mid_range.clone());
-        self.mid = avg(mid_range.clone());
-        self.high_mid = avg(high_mid_range.clone());
-        self.treble = avg(treble_range.clone());
+        // Band analysis on the spectrum, using the configured Hz boundaries
+        let weighted = self.weighted_spectrum(&self.frequency_data.clone());
+        let levels = self.band_levels(&weighted);
+        let [bass, low_mid, mid, high_mid, treble] = self.apply_auto_gain(levels, time);
+        self.bass = bass;
+        self.low_mid = low_mid;
+        self.mid = mid;
+        self.high_mid = high_mid;
+        self.treble = treble;
         // Smooth bands
-        self.smooth_bass = self.smooth_bass + (self.bass - self.smooth_bass) * 0.15;
-        self.smooth_mid = self.smooth_mid + (self.mid - self.smooth_mid) * 0.15;
-        self.smooth_treble = self.smooth_treble + (self.treble - self.smooth_treble) * 0.15;
-        // Beat detection (simple energy spike)
-        let energy_jump = self.bass - self.smooth_bass;
-        self.beat = energy_jump > 0.1 && self.bass > 0.3;
-        self.beat_intensity = if self.beat { energy_jump.min(1.0) } else { 0.0 };
+        self.smooth_bass = self.smooth_bass + (self.bass - self.smooth_bass) * smoothing;
+        self.smooth_mid = self.smooth_mid + (self.mid - self.smooth_mid) * smoothing;
+        self.smooth_treble = self.smooth_treble + (self.treble - self.smooth_treble) * smoothing;
+        // Beat detection against the rolling energy history
+        self.detect_beat(self.bass);
+        self.update_bpm(time);
+
+        self.update_channels(interleaved, channels);
 
         // store frequency snapshot for next frame spectral flux calculation
         self.prev_frequency_data = self.frequency_data.clone();
     }
+
+    /// Per-channel spectra and bands. Mono input just mirrors the mono analysis.
+    fn update_channels(&mut self, interleaved: &[f32], channels: usize) {
+        if channels < 2 {
+            self.frequency_data_left = self.frequency_data.clone();
+            self.frequency_data_right = self.frequency_data.clone();
+            (self.bass_left, self.mid_left, self.treble_left) = (self.bass, self.mid, self.treble);
+            (self.bass_right, self.mid_right, self.treble_right) = (self.bass, self.mid, self.treble);
+            (self.smooth_bass_left, self.smooth_mid_left, self.smooth_treble_left) = (self.smooth_bass, self.smooth_mid, self.smooth_treble);
+            (self.smooth_bass_right, self.smooth_mid_right, self.smooth_treble_right) = (self.smooth_bass, self.smooth_mid, self.smooth_treble);
+            return;
+        }
+        let gated = self.gated;
+        let channel = |c: usize| -> Vec<f32> {
+            let mut samples: Vec<f32> = interleaved.chunks_exact(channels).map(|f| f[c]).collect();
+            remove_dc_offset(&mut samples);
+            if gated { samples.fill(0.0); }
+            samples
+        };
+        let (left, right) = (channel(0), channel(1));
+        self.frequency_data_left = self.compute_spectrum(&left);
+        self.frequency_data_right = self.compute_spectrum(&right);
+        let weighted = self.weighted_spectrum(&self.frequency_data_left.clone());
+        let [bass, _, mid, _, treble] = self.scale_by_auto_gain(self.band_levels(&weighted));
+        (self.bass_left, self.mid_left, self.treble_left) = (bass, mid, treble);
+        let weighted = self.weighted_spectrum(&self.frequency_data_right.clone());
+        let [bass, _, mid, _, treble] = self.scale_by_auto_gain(self.band_levels(&weighted));
+        (self.bass_right, self.mid_right, self.treble_right) = (bass, mid, treble);
+        let smoothing = self.smoothing.clamp(0.0, 1.0);
+        for (smooth, level) in [
+            (&mut self.smooth_bass_left, self.bass_left),
+            (&mut self.smooth_mid_left, self.mid_left),
+            (&mut self.smooth_treble_left, self.treble_left),
+            (&mut self.smooth_bass_right, self.bass_right),
+            (&mut self.smooth_mid_right, self.mid_right),
+            (&mut self.smooth_treble_right, self.treble_right),
+        ] {
+            *smooth += (level - *smooth) * smoothing;
+        }
+    }
+}
+
+/// `FFT_SIZE` mono samples of synthetic audio ending at `time`: a 120 BPM
+/// kick over a wobbling mid tone and hi-hat hiss. Stands in for the capture
+/// buffer when no input device is open, so the visuals still move.
+pub fn demo_buffer(time: f64, sample_rate: f32) -> Vec<f32> {
+    const BEAT_SECS: f64 = 0.5;
+    let rate = sample_rate.max(1.0) as f64;
+    let mid_level = 0.15 + 0.1 * (time * 2.3).sin();
+    let treble_level = 0.05 + 0.04 * (time * 5.1).sin();
+    (0..FFT_SIZE).map(|i| {
+        let t = time - (FFT_SIZE - i) as f64 / rate;
+        let since_beat = t.rem_euclid(BEAT_SECS);
+        let kick = (-since_beat * 12.0).exp() * (t * 55.0 * std::f64::consts::TAU).sin() * 0.8;
+        let mid = mid_level * (t * 660.0 * std::f64::consts::TAU).sin();
+        // a cheap deterministic "noise" for the hats
+        let hiss = treble_level * ((t * 7_919.0 * std::f64::consts::TAU).sin() * (t * 10_007.0 * std::f64::consts::TAU).sin());
+        (kick + mid + hiss) as f32
+    }).collect()
+}
+
+fn remove_dc_offset(samples: &mut [f32]) {
+    let mean = samples.iter().sum::<f32>() / samples.len().max(1) as f32;
+    samples.iter_mut().for_each(|s| *s -= mean);
+}
+
+/// Root-mean-square level of `samples`.
+pub fn rms(samples: &[f32]) -> f32 {
+    (samples.iter().map(|x| x * x).sum::<f32>() / samples.len().max(1) as f32).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn downmix_follows_channel_mode() {
+        let stereo = [1.0, 0.0, 0.5, -0.5];
+        assert_eq!(ChannelMode::MonoSum.downmix(&stereo, 2), vec![0.5, 0.0]);
+        assert_eq!(ChannelMode::Left.downmix(&stereo, 2), vec![1.0, 0.5]);
+        assert_eq!(ChannelMode::Right.downmix(&stereo, 2), vec![0.0, -0.5]);
+        assert_eq!(ChannelMode::Right.downmix(&[0.25, 0.75], 1), vec![0.25, 0.75]);
+    }
+
+    #[test]
+    fn demo_buffer_is_one_audible_frame() {
+        for sample_rate in [22_050.0, 44_100.0, 48_000.0] {
+            let buffer = demo_buffer(3.2, sample_rate);
+            assert_eq!(buffer.len(), FFT_SIZE);
+            assert!(buffer.iter().all(|s| s.is_finite() && s.abs() <= 1.2));
+            assert!(rms(&buffer) > 0.05, "{sample_rate}");
+        }
+        // an unknown rate still fills a frame rather than dividing by zero
+        assert_eq!(demo_buffer(3.2, 0.0).iter().filter(|s| s.is_finite()).count(), FFT_SIZE);
+        // the frame ends at `time`: right after a kick it is louder than just before the next one
+        let frame_secs = FFT_SIZE as f64 / 44_100.0;
+        assert!(rms(&demo_buffer(0.5 + frame_secs, 44_100.0)) > rms(&demo_buffer(0.999, 44_100.0)) * 2.0);
+    }
+
+    #[test]
+    fn band_edges_are_kept_sorted() {
+        let mut a = AudioAnalysis::new();
+        a.set_band_edges([20.0, 4000.0, 500.0, -5.0, 250.0, 20_000.0]);
+        assert_eq!(a.band_edges_hz, [0.0, 20.0, 250.0, 500.0, 4000.0, 20_000.0]);
+        assert!(a.band_ranges().iter().all(|r| r.start <= r.end));
+    }
+
+    #[test]
+    fn a_weighting_attenuates_bass_more_than_mids() {
+        assert!(a_weighting_db(1000.0).abs() < 0.5);
+        assert!(a_weighting_db(50.0) < -25.0);
+
+        let flat = vec![200.0; FFT_SIZE / 2];
+        let mut a = AudioAnalysis::new();
+        let spectrum = a.weighted_spectrum(&flat);
+        let plain = a.band_levels(&spectrum);
+        a.a_weighting = true;
+        let spectrum = a.weighted_spectrum(&flat);
+        let weighted = a.band_levels(&spectrum);
+        // bass loses far more than the 500-2000 Hz mid band
+        assert!(weighted[0] < plain[0] * 0.8);
+        assert!((weighted[2] - plain[2]).abs() < plain[2] * 0.1);
+        assert!(a.a_weighted_volume_gain(&flat) < 1.0);
+    }
+
+    #[test]
+    fn noise_gate_silences_offset_hum() {
+        let mut a = AudioAnalysis::new();
+        a.noise_gate = 0.01;
+        // a large DC offset with faint hum on top, over enough frames to arm beat detection
+        for frame in 0..BEAT_HISTORY_LEN {
+            let buffer: Vec<f32> = (0..FFT_SIZE)
+                .map(|i| 0.4 + 0.002 * (i as f32 * 0.05 + frame as f32).sin())
+                .collect();
+            a.update_from_cpal(&buffer, frame as f64 / 60.0);
+            assert!(a.gated);
+            assert!(!a.beat);
+            assert_eq!(a.beat_intensity, 0.0);
+            assert_eq!([a.bass, a.low_mid, a.mid, a.high_mid, a.treble], [0.0; 5]);
+            assert_eq!(a.volume, 0.0);
+            assert!(a.frequency_data.iter().all(|&v| v == 0.0));
+        }
+
+        // the offset alone doesn't count as signal once removed
+        a.noise_gate = 0.0;
+        a.update_from_cpal(&[0.4; FFT_SIZE], 1.0);
+        assert!(a.peak < 1e-4);
+    }
+
+    #[test]
+    fn auto_gain_levels_loud_and_quiet_sources() {
+        // 1 kHz sine, loud then 20 dB quieter
+        let sine = |amp: f32| -> Vec<f32> { (0..FFT_SIZE).map(|i| amp * (std::f32::consts::TAU * 1000.0 * i as f32 / 44_100.0).sin()).collect() };
+        let run = |auto_gain: bool| {
+            let mut a = AudioAnalysis::new();
+            a.auto_gain = auto_gain;
+            a.auto_gain_strength = 1.0;
+            let (loud_buf, quiet_buf) = (sine(0.5), sine(0.05));
+            for frame in 0..200 { a.update_from_cpal(&loud_buf, frame as f64 / 60.0); }
+            let loud = a.mid;
+            a.update_from_cpal(&quiet_buf, 200.0 / 60.0);
+            let first_quiet = a.mid;
+            for frame in 0..1500 { a.update_from_cpal(&quiet_buf, (201 + frame) as f64 / 60.0); }
+            (loud, first_quiet, a.mid)
+        };
+
+        let (loud, _, quiet) = run(false);
+        assert!(quiet < loud * 0.8);
+
+        let (loud, first_quiet, quiet) = run(true);
+        // the loudest band sits at the target; the 1 kHz mid band is close to it
+        assert!(loud > 0.5 && loud < AUTO_GAIN_TARGET + 0.01);
+        // the envelope falls slowly, so a sudden drop isn't boosted straight back up
+        assert!(first_quiet < loud * 0.8);
+        assert!((quiet - loud).abs() < loud * 0.1);
+    }
+
+    #[test]
+    fn auto_gain_envelope_ignores_frame_rate() {
+        let buffer: Vec<f32> = (0..FFT_SIZE).map(|i| 0.05 * (std::f32::consts::TAU * 1000.0 * i as f32 / 44_100.0).sin()).collect();
+        let envelope_after_a_second = |fps: u32| {
+            let mut a = AudioAnalysis::new();
+            (a.auto_gain, a.gain_envelope) = (true, 1.0);
+            // the slow release, falling from a loud passage to this quieter tone
+            for frame in 0..=fps { a.update_from_cpal(&buffer, frame as f64 / fps as f64); }
+            a.gain_envelope
+        };
+        let (slow, fast) = (envelope_after_a_second(30), envelope_after_a_second(144));
+        assert!(slow < 0.9);
+        assert!((slow - fast).abs() < 0.01, "{slow} vs {fast}");
+    }
+
+    #[test]
+    fn full_smoothing_tracks_the_instant_value() {
+        let mut a = AudioAnalysis::new();
+        a.set_smoothing(1.0);
+        let buffer: Vec<f32> = (0..FFT_SIZE).map(|i| 0.5 * (std::f32::consts::TAU * 100.0 * i as f32 / 44_100.0).sin()).collect();
+        a.update_from_cpal(&buffer, 0.0);
+        assert!(a.bass > 0.0);
+        assert_eq!(a.smooth_bass, a.bass);
+        assert_eq!(a.smooth_volume, a.volume);
+        // out-of-range values act like the nearest end
+        a.set_smoothing(3.0);
+        a.update_from_cpal(&buffer, 0.1);
+        assert_eq!(a.smooth_bass, a.bass);
+    }
+
+    #[test]
+    fn bpm_estimate_follows_a_120_bpm_beat_train() {
+        let mut a = AudioAnalysis::new();
+        // 10 s at 60 fps with a beat every 30 frames, one of them missed
+        for frame in 0..600 {
+            a.beat = frame % 30 == 0 && frame != 270;
+            a.update_bpm(frame as f64 / 60.0);
+        }
+        assert!((a.bpm - 120.0).abs() < 1.0, "bpm {}", a.bpm);
+    }
 }
@@ -0,0 +1,219 @@
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::Sample;
+use crate::audio_analysis::{ANALYSIS_SAMPLE_RATE, FFT_SIZE};
+
+/// A running input stream keeping the latest interleaved frames, resampled to
+/// `ANALYSIS_SAMPLE_RATE`, in the shared buffer. Dropping it stops the capture.
+pub struct CaptureStream {
+    _stream: cpal::Stream,
+    pub device_name: String,
+    // the device's own rate; the shared buffer is always at `ANALYSIS_SAMPLE_RATE`
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+/// Names of the default host's input devices, for the device picker.
+pub fn input_device_names() -> Vec<String> {
+    cpal::default_host()
+        .input_devices()
+        .map(|devices| devices.filter_map(|d| d.name().ok()).collect())
+        .unwrap_or_default()
+}
+
+/// Start capturing from the input device called `device_name`, or from the
+/// default input when it is `None` or no longer present. `audio_data` is
+/// resized to hold `FFT_SIZE` interleaved frames at `ANALYSIS_SAMPLE_RATE`,
+/// whatever the device's rate and buffer size; stream errors (such as the
+/// device being unplugged) are forwarded to `errors`.
+pub fn start_cpal_stream(
+    device_name: Option<&str>,
+    audio_data: Arc<Mutex<Vec<f32>>>,
+    errors: Sender<cpal::StreamError>,
+) -> Result<CaptureStream, String> {
+    let host = cpal::default_host();
+    let named = device_name.and_then(|name| {
+        host.input_devices().ok()?.find(|d| d.name().is_ok_and(|n| n == name))
+    });
+    let device = named.or_else(|| host.default_input_device()).ok_or("No input device available")?;
+    let config = device.default_input_config().map_err(|e| device_error(&device, e))?;
+    build_capture(device, config, audio_data, errors)
+}
+
+/// Capture what the system is playing, feeding the same buffer as
+/// [`start_cpal_stream`].
+///
+/// - Windows: WASAPI loopback on the default output device.
+/// - Linux: a PulseAudio/PipeWire monitor source, when the host exposes one
+///   as an input device (its name contains "monitor").
+/// - macOS: a virtual loopback input such as BlackHole or Soundflower, which
+///   must be installed and routed separately.
+///
+/// Elsewhere, or when no such device exists, this returns an error to show.
+pub fn start_loopback_stream(
+    audio_data: Arc<Mutex<Vec<f32>>>,
+    errors: Sender<cpal::StreamError>,
+) -> Result<CaptureStream, String> {
+    let host = cpal::default_host();
+    if cfg!(target_os = "windows") {
+        // WASAPI turns an input stream on an output device into loopback capture
+        let device = host.default_output_device().ok_or("No output device to capture")?;
+        let config = device.default_output_config().map_err(|e| device_error(&device, e))?;
+        return build_capture(device, config, audio_data, errors);
+    }
+    let device = host
+        .input_devices()
+        .map_err(|e| e.to_string())?
+        .find(|d| d.name().is_ok_and(|n| is_loopback_name(&n)))
+        .ok_or(if cfg!(target_os = "macos") {
+            "No loopback input found; install BlackHole or Soundflower and route output to it"
+        } else if cfg!(target_os = "linux") {
+            "No monitor source found; expose one as an input device (e.g. via pavucontrol)"
+        } else {
+            "System audio capture is not supported on this platform"
+        })?;
+    let config = device.default_input_config().map_err(|e| device_error(&device, e))?;
+    build_capture(device, config, audio_data, errors)
+}
+
+// input devices that carry the system mix rather than a microphone
+fn is_loopback_name(name: &str) -> bool {
+    let name = name.to_lowercase();
+    ["monitor", "loopback", "blackhole", "soundflower"].iter().any(|hint| name.contains(hint))
+}
+
+fn device_error(device: &cpal::Device, e: impl std::fmt::Display) -> String {
+    format!("{}: {e}", device.name().unwrap_or_else(|_| "Unknown device".to_string()))
+}
+
+fn build_capture(
+    device: cpal::Device,
+    config: cpal::SupportedStreamConfig,
+    audio_data: Arc<Mutex<Vec<f32>>>,
+    errors: Sender<cpal::StreamError>,
+) -> Result<CaptureStream, String> {
+    let device_name = device.name().unwrap_or_else(|_| "Unknown device".to_string());
+    let sample_rate = config.sample_rate().0;
+    let channels = config.channels().max(1);
+    audio_data.lock().unwrap().resize(FFT_SIZE * channels as usize, 0.0);
+    let err_fn = move |err| {
+        eprintln!("CPAL stream error: {}", err);
+        let _ = errors.send(err);
+    };
+    let timeout = None; // Option<Duration>
+    let mut resampler = Resampler::new(sample_rate, ANALYSIS_SAMPLE_RATE as u32, channels);
+    let mut resampled = Vec::new();
+    // every format goes through the resampler into the rolling analysis window
+    let mut feed = move |samples: &[f32]| {
+        resampled.clear();
+        resampler.process(samples, &mut resampled);
+        push_latest(&mut audio_data.lock().unwrap(), &resampled);
+    };
+    let stream = match config.sample_format() {
+        cpal::SampleFormat::F32 => device.build_input_stream(
+            &config.into(),
+            move |data: &[f32], _| feed(data),
+            err_fn,
+            timeout,
+        ),
+        cpal::SampleFormat::I16 => device.build_input_stream(
+            &config.into(),
+            move |data: &[i16], _| {
+                let samples: Vec<f32> = data.iter().map(|s| s.to_sample::<f32>()).collect();
+                feed(&samples);
+            },
+            err_fn,
+            timeout,
+        ),
+        cpal::SampleFormat::U16 => device.build_input_stream(
+            &config.into(),
+            move |data: &[u16], _| {
+                // unsigned samples are offset binary; to_sample recentres them on zero
+                let samples: Vec<f32> = data.iter().map(|s| s.to_sample::<f32>()).collect();
+                feed(&samples);
+            },
+            err_fn,
+            timeout,
+        ),
+        other => return Err(format!("{device_name}: unsupported sample format {other:?}")),
+    }
+    .map_err(|e| format!("{device_name}: {e}"))?;
+    stream.play().map_err(|e| format!("{device_name}: {e}"))?;
+    Ok(CaptureStream { _stream: stream, device_name, sample_rate, channels })
+}
+
+// slide `samples` in at the end of `audio`, dropping the oldest, so the buffer
+// always holds the newest frames however the callbacks are sized. Callbacks
+// carry whole frames, so channel interleaving stays aligned.
+fn push_latest(audio: &mut [f32], samples: &[f32]) {
+    let len = audio.len();
+    if samples.len() >= len {
+        audio.copy_from_slice(&samples[samples.len() - len..]);
+    } else {
+        audio.copy_within(samples.len().., 0);
+        audio[len - samples.len()..].copy_from_slice(samples);
+    }
+}
+
+/// Streaming linear-interpolation resampler for interleaved frames. It keeps
+/// the last frame and the read position between calls, so consecutive
+/// buffers of any size join without clicks.
+pub struct Resampler {
+    // input frames advanced per output frame
+    step: f64,
+    channels: usize,
+    // position of the next output frame in the current input; -1 is `last`
+    pos: f64,
+    last: Vec<f32>,
+}
+
+impl Resampler {
+    pub fn new(from_rate: u32, to_rate: u32, channels: u16) -> Self {
+        let channels = channels.max(1) as usize;
+        Self { step: from_rate.max(1) as f64 / to_rate.max(1) as f64, channels, pos: 0.0, last: vec![0.0; channels] }
+    }
+
+    /// Append `input` resampled to the target rate onto `out`.
+    pub fn process(&mut self, input: &[f32], out: &mut Vec<f32>) {
+        let ch = self.channels;
+        let frames = input.len() / ch;
+        if frames == 0 { return; }
+        let frame = |i: isize| if i < 0 { &self.last[..] } else { &input[i as usize * ch..(i as usize + 1) * ch] };
+        // each output frame needs the input frame after it, so stop one short of the end
+        while self.pos < (frames - 1) as f64 {
+            let i = self.pos.floor();
+            let frac = (self.pos - i) as f32;
+            let (a, b) = (frame(i as isize), frame(i as isize + 1));
+            out.extend(a.iter().zip(b).map(|(a, b)| a + (b - a) * frac));
+            self.pos += self.step;
+        }
+        self.pos -= frames as f64;
+        self.last.copy_from_slice(&input[(frames - 1) * ch..frames * ch]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resampler_converts_rate_across_buffers() {
+        // equal rates pass frames through; the newest waits for the next buffer
+        let mut same = Resampler::new(48_000, 48_000, 2);
+        let mut out = Vec::new();
+        same.process(&[1.0, -1.0, 2.0, -2.0], &mut out);
+        same.process(&[3.0, -3.0], &mut out);
+        assert_eq!(out, vec![1.0, -1.0, 2.0, -2.0]);
+
+        // halving the rate keeps every other frame, however the input is split
+        let ramp: Vec<f32> = (0..100).map(|i| i as f32).collect();
+        let mut half = Resampler::new(88_200, 44_100, 1);
+        let mut out = Vec::new();
+        for chunk in ramp.chunks(7) {
+            half.process(chunk, &mut out);
+        }
+        assert_eq!(out.len(), 50);
+        assert!(out.windows(2).all(|w| (w[1] - w[0] - 2.0).abs() < 1e-4));
+    }
+}
@@ -0,0 +1,69 @@
+use eframe::egui::Color32;
+
+/// Opaque colour from hue, saturation and lightness, each 0..1.
+pub fn hsl_to_rgb(h: f32, s: f32, l: f32) -> Color32 {
+    let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
+    let x = c * (1.0 - ((h * 6.0) % 2.0 - 1.0).abs());
+    let m = l - c / 2.0;
+    let (r, g, b) = match (h * 6.0) as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    Color32::from_rgb(
+        ((r + m) * 255.0) as u8,
+        ((g + m) * 255.0) as u8,
+        ((b + m) * 255.0) as u8,
+    )
+}
+
+/// Inverse of [`hsl_to_rgb`]: hue, saturation and lightness of `color`, each 0..1.
+pub fn rgb_to_hsl(color: Color32) -> (f32, f32, f32) {
+    let [r, g, b] = [color.r(), color.g(), color.b()].map(|c| c as f32 / 255.0);
+    let (max, min) = (r.max(g).max(b), r.min(g).min(b));
+    let l = (max + min) / 2.0;
+    let d = max - min;
+    if d <= f32::EPSILON {
+        return (0.0, 0.0, l);
+    }
+    let s = d / (1.0 - (2.0 * l - 1.0).abs());
+    let h = if max == r {
+        ((g - b) / d).rem_euclid(6.0)
+    } else if max == g {
+        (b - r) / d + 2.0
+    } else {
+        (r - g) / d + 4.0
+    };
+    (h / 6.0, s, l)
+}
+
+// per-channel linear blend from `a` (t = 0) to `b` (t = 1)
+pub fn lerp_color(a: Color32, b: Color32, t: f32) -> Color32 {
+    let mix = |x: u8, y: u8| (x as f32 + (y as f32 - x as f32) * t.clamp(0.0, 1.0)).round() as u8;
+    Color32::from_rgb(mix(a.r(), b.r()), mix(a.g(), b.g()), mix(a.b(), b.b()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hsl_round_trips_rgb() {
+        for r in (0..=255).step_by(15) {
+            for g in (0..=255).step_by(15) {
+                for b in (0..=255).step_by(15) {
+                    let color = Color32::from_rgb(r as u8, g as u8, b as u8);
+                    let (h, s, l) = rgb_to_hsl(color);
+                    let back = hsl_to_rgb(h, s, l);
+                    // hsl_to_rgb truncates, so a channel may come back one lower
+                    for (before, after) in color.to_array().into_iter().zip(back.to_array()) {
+                        assert!(before.abs_diff(after) <= 1, "{color:?} -> {back:?}");
+                    }
+                }
+            }
+        }
+    }
+}
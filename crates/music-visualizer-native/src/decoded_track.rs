@@ -0,0 +1,91 @@
+use std::sync::Arc;
+use std::time::Duration;
+use rodio::Source;
+
+/// Fully decoded PCM of one track, kept so seeks can start at an exact sample.
+pub struct DecodedTrack {
+    pub path: String,
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub samples: Arc<[i16]>,
+}
+
+impl DecodedTrack {
+    /// Length of the track in seconds.
+    pub fn duration_secs(&self) -> f64 {
+        self.samples.len() as f64 / (self.sample_rate as f64 * self.channels.max(1) as f64)
+    }
+
+    /// A source playing the track from `time` seconds in. It shares the
+    /// decoded samples, so seeking copies nothing.
+    pub fn source_from(&self, time: f64) -> DecodedSource {
+        let channels = self.channels.max(1) as usize;
+        let start = ((time.max(0.0) * self.sample_rate as f64) as usize * channels).min(self.samples.len());
+        DecodedSource { samples: self.samples.clone(), pos: start, channels: self.channels, sample_rate: self.sample_rate }
+    }
+}
+
+/// Interleaved samples of a [`DecodedTrack`], read from an offset onwards.
+pub struct DecodedSource {
+    samples: Arc<[i16]>,
+    pos: usize,
+    channels: u16,
+    sample_rate: u32,
+}
+
+impl Iterator for DecodedSource {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        let sample = self.samples.get(self.pos).copied()?;
+        self.pos += 1;
+        Some(sample)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = self.samples.len() - self.pos;
+        (left, Some(left))
+    }
+}
+
+impl Source for DecodedSource {
+    fn current_frame_len(&self) -> Option<usize> { Some(self.samples.len() - self.pos) }
+    fn channels(&self) -> u16 { self.channels }
+    fn sample_rate(&self) -> u32 { self.sample_rate }
+    fn total_duration(&self) -> Option<Duration> {
+        let frames = (self.samples.len() - self.pos) / self.channels.max(1) as usize;
+        Some(Duration::from_secs_f64(frames as f64 / self.sample_rate as f64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stereo_ramp(frames: usize) -> DecodedTrack {
+        let samples: Vec<i16> = (0..frames * 2).map(|i| i as i16).collect();
+        DecodedTrack { path: "ramp".into(), channels: 2, sample_rate: 10, samples: samples.into() }
+    }
+
+    #[test]
+    fn source_starts_on_the_frame_at_the_seek_time() {
+        let track = stereo_ramp(40);
+        assert_eq!(track.duration_secs(), 4.0);
+        let source = track.source_from(1.5);
+        assert_eq!(source.total_duration(), Some(Duration::from_secs_f64(2.5)));
+        // frame 15 of a stereo track starts at sample 30, on the left channel
+        let rest: Vec<i16> = source.collect();
+        assert_eq!(rest.len(), 50);
+        assert_eq!(rest[0], 30);
+        assert_eq!(*rest.last().unwrap(), 79);
+    }
+
+    #[test]
+    fn seeking_shares_the_samples() {
+        let track = stereo_ramp(40);
+        let _source = track.source_from(1.0);
+        assert_eq!(Arc::strong_count(&track.samples), 2);
+        // past the end is an empty source rather than a panic
+        assert_eq!(track.source_from(10.0).count(), 0);
+    }
+}
@@ -0,0 +1,51 @@
+use std::sync::mpsc::Sender;
+use midir::{Ignore, MidiInput, MidiInputConnection};
+
+const CLIENT_NAME: &str = "music-visualizer";
+
+/// MIDI message the visuals react to; everything else is ignored.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MidiEvent {
+    NoteOn { note: u8, velocity: u8 },
+    ControlChange { controller: u8, value: u8 },
+}
+
+impl MidiEvent {
+    /// Decode a raw channel message on any channel. A note-on with velocity 0
+    /// is a note-off by convention and yields `None`.
+    pub fn parse(message: &[u8]) -> Option<Self> {
+        match *message {
+            [status, note, velocity] if status & 0xF0 == 0x90 && velocity > 0 => Some(MidiEvent::NoteOn { note, velocity }),
+            [status, controller, value] if status & 0xF0 == 0xB0 => Some(MidiEvent::ControlChange { controller, value }),
+            _ => None,
+        }
+    }
+}
+
+/// An open MIDI input port forwarding decoded events. Dropping it closes the port.
+pub struct MidiConnection {
+    _connection: MidiInputConnection<()>,
+    pub port_name: String,
+}
+
+/// Names of the available MIDI input ports, for the device picker.
+pub fn input_port_names() -> Vec<String> {
+    let Ok(input) = MidiInput::new(CLIENT_NAME) else { return Vec::new() };
+    input.ports().iter().filter_map(|p| input.port_name(p).ok()).collect()
+}
+
+/// Open the input port called `port_name` and send its note-on and CC
+/// messages to `events`.
+pub fn connect(port_name: &str, events: Sender<MidiEvent>) -> Result<MidiConnection, String> {
+    let mut input = MidiInput::new(CLIENT_NAME).map_err(|e| format!("MIDI unavailable: {e}"))?;
+    input.ignore(Ignore::All);
+    let port = input.ports().into_iter()
+        .find(|p| input.port_name(p).is_ok_and(|n| n == port_name))
+        .ok_or_else(|| format!("MIDI device {port_name} not found"))?;
+    let connection = input
+        .connect(&port, "visualizer-in", move |_stamp, message, _| {
+            if let Some(event) = MidiEvent::parse(message) { let _ = events.send(event); }
+        }, ())
+        .map_err(|e| format!("Can't open {port_name}: {e}"))?;
+    Ok(MidiConnection { _connection: connection, port_name: port_name.to_string() })
+}
@@ -0,0 +1,187 @@
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+// how often the listener wakes from `recv` to check whether it should stop
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+// largest datagram accepted; OSC over UDP rarely comes close
+const MAX_PACKET: usize = 4096;
+
+/// One OSC argument. Types outside int32/float32/string/bool are skipped.
+#[derive(Clone, Debug, PartialEq)]
+pub enum OscArg {
+    Int(i32),
+    Float(f32),
+    Str(String),
+    Bool(bool),
+}
+
+impl OscArg {
+    pub fn as_f32(&self) -> Option<f32> {
+        match self {
+            OscArg::Int(i) => Some(*i as f32),
+            OscArg::Float(f) => Some(*f),
+            OscArg::Str(s) => s.trim().parse().ok(),
+            OscArg::Bool(b) => Some(if *b { 1.0 } else { 0.0 }),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct OscMessage {
+    pub address: String,
+    pub args: Vec<OscArg>,
+}
+
+impl OscMessage {
+    /// First argument as a number, if there is one.
+    pub fn number(&self) -> Option<f32> {
+        self.args.first().and_then(OscArg::as_f32)
+    }
+}
+
+/// Decode a UDP payload into its messages, flattening bundles. Malformed
+/// packets yield whatever was decoded before the problem.
+pub fn parse_packet(buf: &[u8]) -> Vec<OscMessage> {
+    let mut out = Vec::new();
+    parse_into(buf, &mut out);
+    out
+}
+
+fn parse_into(buf: &[u8], out: &mut Vec<OscMessage>) {
+    if let Some(mut rest) = buf.strip_prefix(b"#bundle\0") {
+        // skip the 8-byte time tag; elements are size-prefixed packets
+        rest = rest.get(8..).unwrap_or_default();
+        while let Some((size, tail)) = read_i32(rest) {
+            let Some(element) = tail.get(..size.max(0) as usize) else { return };
+            parse_into(element, out);
+            rest = &tail[element.len()..];
+        }
+    } else if let Some(msg) = parse_message(buf) {
+        out.push(msg);
+    }
+}
+
+fn parse_message(buf: &[u8]) -> Option<OscMessage> {
+    let (address, mut rest) = read_string(buf)?;
+    if !address.starts_with('/') {
+        return None;
+    }
+    let (tags, tail) = read_string(rest).unwrap_or((",".to_string(), &[]));
+    rest = tail;
+    let mut args = Vec::new();
+    for tag in tags.strip_prefix(',')?.chars() {
+        match tag {
+            'i' => { let (v, tail) = read_i32(rest)?; args.push(OscArg::Int(v)); rest = tail; }
+            'f' => { let (v, tail) = read_i32(rest)?; args.push(OscArg::Float(f32::from_bits(v as u32))); rest = tail; }
+            's' => { let (v, tail) = read_string(rest)?; args.push(OscArg::Str(v)); rest = tail; }
+            'T' => args.push(OscArg::Bool(true)),
+            'F' => args.push(OscArg::Bool(false)),
+            // unsupported types still have to be stepped over
+            'N' | 'I' => {}
+            'c' | 'r' | 'm' => rest = rest.get(4..)?,
+            'h' | 'd' | 't' => rest = rest.get(8..)?,
+            'S' => rest = read_string(rest)?.1,
+            'b' => { let (len, tail) = read_i32(rest)?; rest = tail.get(padded(len.max(0) as usize)..)?; }
+            // the size of an unknown type is unknown too, so nothing after it can be read
+            _ => break,
+        }
+    }
+    Some(OscMessage { address, args })
+}
+
+// OSC strings are NUL-terminated and padded to a multiple of four bytes
+fn read_string(buf: &[u8]) -> Option<(String, &[u8])> {
+    let end = buf.iter().position(|&b| b == 0)?;
+    let text = std::str::from_utf8(&buf[..end]).ok()?.to_string();
+    Some((text, buf.get(padded(end + 1)..).unwrap_or_default()))
+}
+
+fn read_i32(buf: &[u8]) -> Option<(i32, &[u8])> {
+    let bytes: [u8; 4] = buf.get(..4)?.try_into().ok()?;
+    Some((i32::from_be_bytes(bytes), &buf[4..]))
+}
+
+fn padded(len: usize) -> usize {
+    len.div_ceil(4) * 4
+}
+
+/// UDP listener pushing decoded messages into a shared inbox the app drains
+/// each frame. Dropping it stops the thread and frees the port.
+pub struct OscServer {
+    pub addr: SocketAddr,
+    running: Arc<AtomicBool>,
+}
+
+impl OscServer {
+    /// Listen on `port`: on every interface when `lan` is set, so a phone or
+    /// another machine can reach it, otherwise on localhost only.
+    pub fn start(port: u16, lan: bool, inbox: Arc<Mutex<Vec<OscMessage>>>) -> Result<Self, String> {
+        let host = if lan { Ipv4Addr::UNSPECIFIED } else { Ipv4Addr::LOCALHOST };
+        let socket = UdpSocket::bind((host, port)).map_err(|e| format!("Can't listen on UDP port {port}: {e}"))?;
+        let addr = socket.local_addr().map_err(|e| e.to_string())?;
+        socket.set_read_timeout(Some(POLL_INTERVAL)).map_err(|e| e.to_string())?;
+        let running = Arc::new(AtomicBool::new(true));
+        let alive = running.clone();
+        thread::spawn(move || {
+            let mut buf = [0u8; MAX_PACKET];
+            while alive.load(Ordering::Relaxed) {
+                // timeouts land here too, which is what lets the loop see `alive`
+                let Ok(len) = socket.recv(&mut buf) else { continue };
+                let messages = parse_packet(&buf[..len]);
+                if !messages.is_empty() {
+                    inbox.lock().unwrap().extend(messages);
+                }
+            }
+        });
+        Ok(Self { addr, running })
+    }
+}
+
+impl Drop for OscServer {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_messages_and_bundles() {
+        // "/visualizer/zoom" ,f 0.5 — address padded to 20 bytes, tags to 4
+        let mut msg = b"/visualizer/zoom\0\0\0\0,f\0\0".to_vec();
+        msg.extend(0.5f32.to_be_bytes());
+        assert_eq!(parse_packet(&msg), vec![OscMessage { address: "/visualizer/zoom".into(), args: vec![OscArg::Float(0.5)] }]);
+
+        let mut bundle = b"#bundle\0".to_vec();
+        bundle.extend([0, 0, 0, 0, 0, 0, 0, 1]);
+        bundle.extend((msg.len() as i32).to_be_bytes());
+        bundle.extend(&msg);
+        bundle.extend(28i32.to_be_bytes());
+        bundle.extend(b"/visualizer/mode\0\0\0\0,s\0\0up\0\0");
+        let parsed = parse_packet(&bundle);
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[1].args, vec![OscArg::Str("up".into())]);
+    }
+
+    #[test]
+    fn steps_over_four_byte_types_and_stops_at_unknown_ones() {
+        // ,cif: a char, then the int and float that follow it
+        let mut msg = b"/visualizer/zoom\0\0\0\0,cif\0\0\0\0".to_vec();
+        msg.extend(65i32.to_be_bytes());
+        msg.extend(7i32.to_be_bytes());
+        msg.extend(0.5f32.to_be_bytes());
+        assert_eq!(parse_packet(&msg)[0].args, vec![OscArg::Int(7), OscArg::Float(0.5)]);
+
+        // ,ixf: the float can't be found past an unknown type
+        let mut msg = b"/visualizer/zoom\0\0\0\0,ixf\0\0\0\0".to_vec();
+        msg.extend(7i32.to_be_bytes());
+        msg.extend(1i32.to_be_bytes());
+        msg.extend(0.5f32.to_be_bytes());
+        assert_eq!(parse_packet(&msg)[0].args, vec![OscArg::Int(7)]);
+    }
+}
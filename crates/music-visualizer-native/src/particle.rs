@@ -1,27 +1,33 @@
 use eframe::egui::{Color32, Pos2, Vec2};
+use crate::visualizer_config::ParticleShape;
 
 #[derive(Clone)]
 pub struct Particle {
+    // offset from the visualizer centre, which may move between frames
     pub pos: Pos2,
     pub vel: Vec2,
     pub life: f32,
     pub max_life: f32,
     pub size: f32,
     pub color: Color32,
+    pub shape: ParticleShape,
 }
 
 impl Particle {
-    pub fn new(center: Pos2, angle: f32, speed: f32, color: Color32) -> Self {
+    pub fn new(angle: f32, speed: f32, color: Color32, shape: ParticleShape) -> Self {
         Self {
-            pos: center,
+            pos: Pos2::ZERO,
             vel: Vec2::new(angle.cos() * speed, angle.sin() * speed),
             life: 1.0,
             max_life: 1.0,
             size: 3.0 + rand::random::<f32>() * 5.0,
             color,
+            shape,
         }
     }
-    pub fn update(&mut self, dt: f32) {
+    /// `gravity` is an acceleration in screen units per second squared.
+    pub fn update(&mut self, dt: f32, gravity: Vec2) {
+        self.vel += gravity * dt;
         self.pos += self.vel * dt;
         self.vel *= 0.98;
         self.life -= dt / self.max_life;
@@ -29,4 +35,50 @@ impl Particle {
     pub fn is_alive(&self) -> bool {
         self.life > 0.0
     }
+    /// Where to draw the particle when the visualizer is centred on `center`.
+    pub fn screen_pos(&self, center: Pos2) -> Pos2 {
+        center + self.pos.to_vec2()
+    }
+}
+
+/// Particles spawned by a beat of `intensity` (0..1): one for the softest
+/// beat up to a tenth of `particle_count` for the hardest.
+pub fn burst_count(particle_count: u32, intensity: f32) -> usize {
+    let full = (particle_count / 10).max(1) as f32;
+    (1.0 + (full - 1.0) * intensity.clamp(0.0, 1.0)).round() as usize
+}
+
+/// Launch speed multiplier for a beat of `intensity`: half speed for the
+/// softest beat, full speed for the hardest.
+pub fn burst_speed_scale(intensity: f32) -> f32 {
+    0.5 + 0.5 * intensity.clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eframe::egui::Rect;
+    use crate::ui::particle_shape;
+
+    #[test]
+    fn burst_count_follows_beat_intensity() {
+        assert_eq!(burst_count(50, 0.0), 1);
+        assert_eq!(burst_count(50, 1.0), 5);
+        assert_eq!(burst_count(3, 1.0), 1);
+    }
+
+    #[test]
+    fn particle_renders_at_the_spawn_center() {
+        // a small panel well away from the old fixed (400, 300) centre
+        let rect = Rect::from_min_size(Pos2::new(900.0, 40.0), Vec2::new(320.0, 180.0));
+        let mut p = Particle::new(0.0, 120.0, Color32::WHITE, ParticleShape::Circle);
+        for _ in 0..30 {
+            p.update(1.0 / 60.0, Vec2::ZERO);
+        }
+        let travelled = p.pos.to_vec2();
+        assert!(travelled.x > 30.0);
+        let drawn = particle_shape(p.shape, p.screen_pos(rect.center()), p.size * p.life, p.vel, p.color).visual_bounding_rect().center();
+        assert!(drawn.distance(rect.center() + travelled) < 1e-3);
+        assert!(rect.contains(drawn));
+    }
 }
@@ -0,0 +1,130 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use rodio::{Sample, Source};
+
+// never extrapolate further than this past the last audio callback, so a
+// stalled device doesn't let the position run ahead
+const MAX_INTERPOLATION_SECS: f64 = 0.1;
+
+/// Source wrapper that counts every sample the output device pulls, so the
+/// playback position follows the real audio clock rather than wall time.
+pub struct CountingSource<S> {
+    inner: S,
+    played: Arc<AtomicU64>,
+}
+
+impl<S> Iterator for CountingSource<S>
+where
+    S: Source,
+    S::Item: Sample,
+{
+    type Item = S::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let sample = self.inner.next();
+        if sample.is_some() {
+            self.played.fetch_add(1, Ordering::Relaxed);
+        }
+        sample
+    }
+}
+
+impl<S> Source for CountingSource<S>
+where
+    S: Source,
+    S::Item: Sample,
+{
+    fn current_frame_len(&self) -> Option<usize> { self.inner.current_frame_len() }
+    fn channels(&self) -> u16 { self.inner.channels() }
+    fn sample_rate(&self) -> u32 { self.inner.sample_rate() }
+    fn total_duration(&self) -> Option<Duration> { self.inner.total_duration() }
+}
+
+/// Playback position estimate: interpolates with `Instant` between audio
+/// callbacks and snaps to the played-sample count whenever it advances.
+pub struct PlaybackClock {
+    played: Arc<AtomicU64>,
+    // interleaved samples per second of the current source
+    samples_per_sec: f64,
+    // track position at which the current source starts
+    base: f64,
+    last_played: u64,
+    // position at `anchor`, and when it was taken (None while paused)
+    position: f64,
+    anchor: Option<Instant>,
+    // track seconds per wall-clock second, for interpolation
+    rate: f64,
+}
+
+impl Default for PlaybackClock {
+    fn default() -> Self {
+        Self {
+            played: Arc::default(),
+            samples_per_sec: 0.0,
+            base: 0.0,
+            last_played: 0,
+            position: 0.0,
+            anchor: None,
+            rate: 1.0,
+        }
+    }
+}
+
+impl PlaybackClock {
+    /// Reset the clock for a new source starting at `start_secs` and wrap the
+    /// source so it reports the samples it hands to the device.
+    pub fn track<S>(&mut self, source: S, start_secs: f64) -> CountingSource<S>
+    where
+        S: Source,
+        S::Item: Sample,
+    {
+        self.played = Arc::new(AtomicU64::new(0));
+        self.samples_per_sec = source.sample_rate() as f64 * source.channels().max(1) as f64;
+        self.base = start_secs;
+        self.last_played = 0;
+        self.position = start_secs;
+        self.anchor = Some(Instant::now());
+        CountingSource { inner: source, played: self.played.clone() }
+    }
+
+    pub fn pause(&mut self) {
+        self.position = self.position();
+        self.anchor = None;
+    }
+
+    pub fn resume(&mut self) {
+        self.anchor = Some(Instant::now());
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self { rate: self.rate, ..Self::default() };
+    }
+
+    /// Set the playback speed factor. The sample count already measures track
+    /// time; this only scales the interpolation between callbacks.
+    pub fn set_rate(&mut self, rate: f64) {
+        self.position = self.position();
+        if self.anchor.is_some() {
+            self.anchor = Some(Instant::now());
+        }
+        self.rate = rate;
+    }
+
+    /// Re-anchor to the sample count if the device consumed more audio since
+    /// the last call. Call once per frame.
+    pub fn sync(&mut self) {
+        let played = self.played.load(Ordering::Relaxed);
+        if played != self.last_played && self.samples_per_sec > 0.0 {
+            self.last_played = played;
+            self.position = self.base + played as f64 / self.samples_per_sec;
+            if self.anchor.is_some() {
+                self.anchor = Some(Instant::now());
+            }
+        }
+    }
+
+    pub fn position(&self) -> f64 {
+        self.position + self.anchor.map_or(0.0, |a| a.elapsed().as_secs_f64().min(MAX_INTERPOLATION_SECS) * self.rate)
+    }
+}
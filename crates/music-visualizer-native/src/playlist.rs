@@ -4,6 +4,68 @@ pub struct PlaylistTrack {
     pub path: String,
     pub duration: f64,
     pub file_type: String,
+    // loudness correction from `normalization_gain_db`; 0 until the background scan finishes
+    pub gain_db: f32,
+    // A/B repeat points in seconds; playback jumps back to A on reaching B once both are set
+    pub loop_start: Option<f64>,
+    pub loop_end: Option<f64>,
+}
+
+// integrated RMS level tracks are normalized to, and the most a track is boosted or cut
+const LOUDNESS_TARGET_DBFS: f32 = -18.0;
+const MAX_NORMALIZE_GAIN_DB: f32 = 12.0;
+
+/// Gain in dB that brings the RMS level of `samples` to `LOUDNESS_TARGET_DBFS`,
+/// limited to ±`MAX_NORMALIZE_GAIN_DB`. Silence gets no correction.
+pub fn normalization_gain_db(samples: impl Iterator<Item = i16>) -> f32 {
+    let (mut sum, mut count) = (0.0f64, 0u64);
+    for s in samples {
+        let x = s as f64 / i16::MAX as f64;
+        sum += x * x;
+        count += 1;
+    }
+    if count == 0 || sum == 0.0 {
+        return 0.0;
+    }
+    let rms_db = 10.0 * (sum / count as f64).log10() as f32;
+    (LOUDNESS_TARGET_DBFS - rms_db).clamp(-MAX_NORMALIZE_GAIN_DB, MAX_NORMALIZE_GAIN_DB)
+}
+
+/// What happens when the current track finishes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RepeatMode {
+    /// Stop after the last track.
+    Off,
+    /// Replay the current track.
+    One,
+    /// Loop back to the first track after the last one.
+    #[default]
+    All,
+}
+
+impl RepeatMode {
+    pub fn next(self) -> Self {
+        match self {
+            RepeatMode::Off => RepeatMode::All,
+            RepeatMode::All => RepeatMode::One,
+            RepeatMode::One => RepeatMode::Off,
+        }
+    }
+    pub fn label(self) -> &'static str {
+        match self {
+            RepeatMode::Off => "🔁 Off",
+            RepeatMode::All => "🔁 All",
+            RepeatMode::One => "🔂 One",
+        }
+    }
+}
+
+/// Field `PlaylistState::sort_tracks` orders by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortKey {
+    /// Track name, ignoring case.
+    Name,
+    Duration,
 }
 
 #[derive(Clone)]
@@ -12,10 +74,13 @@ pub struct PlaylistState {
     pub current_index: Option<usize>,
     pub is_playing: bool,
     pub is_shuffled: bool,
+    pub repeat: RepeatMode,
     pub shuffle_order: Vec<usize>,
     pub current_time: f64,
     pub duration: f64,
     pub volume: f32,
+    // scale each track by its `gain_db` so quiet and loud files play at a similar level
+    pub normalize_volume: bool,
 }
 
 impl Default for PlaylistState {
@@ -25,10 +90,12 @@ impl Default for PlaylistState {
             current_index: None,
             is_playing: false,
             is_shuffled: false,
+            repeat: RepeatMode::default(),
             shuffle_order: Vec::new(),
             current_time: 0.0,
             duration: 0.0,
             volume: 0.8,
+            normalize_volume: false,
         }
     }
 }
@@ -37,6 +104,49 @@ impl PlaylistState {
     pub fn get_current_track(&self) -> Option<&PlaylistTrack> {
         self.current_index.and_then(|idx| self.tracks.get(idx))
     }
+    /// Sink volume for `track`: the user volume, corrected by the track's gain
+    /// when normalization is on. Boosts stop at full scale so quiet tracks
+    /// never clip.
+    pub fn volume_for(&self, track: &PlaylistTrack) -> f32 {
+        if self.normalize_volume { (self.volume * 10f32.powf(track.gain_db / 20.0)).min(1.0) } else { self.volume }
+    }
+    pub fn current_volume(&self) -> f32 {
+        self.get_current_track().map_or(self.volume, |t| self.volume_for(t))
+    }
+    /// The current track's A/B loop as `(start, end)`, once both points are set
+    /// with B after A.
+    pub fn loop_range(&self) -> Option<(f64, f64)> {
+        let track = self.get_current_track()?;
+        match (track.loop_start, track.loop_end) {
+            (Some(a), Some(b)) if b > a => Some((a, b)),
+            _ => None,
+        }
+    }
+    /// Set the current track's A point at the playback position; a B point at
+    /// or before it is dropped.
+    pub fn set_loop_start(&mut self) {
+        let time = self.current_time;
+        let Some(track) = self.current_index.and_then(|i| self.tracks.get_mut(i)) else { return };
+        track.loop_start = Some(time);
+        if track.loop_end.is_some_and(|b| b <= time) { track.loop_end = None; }
+    }
+    /// Set the current track's B point at the playback position, if it comes
+    /// after A (or A is unset, which loops from the start).
+    pub fn set_loop_end(&mut self) {
+        let time = self.current_time;
+        let Some(track) = self.current_index.and_then(|i| self.tracks.get_mut(i)) else { return };
+        if track.loop_start.unwrap_or(0.0) < time {
+            track.loop_start.get_or_insert(0.0);
+            track.loop_end = Some(time);
+        }
+    }
+    /// Drop the current track's A and B points.
+    pub fn clear_loop(&mut self) {
+        if let Some(track) = self.current_index.and_then(|i| self.tracks.get_mut(i)) {
+            track.loop_start = None;
+            track.loop_end = None;
+        }
+    }
     pub fn get_progress(&self) -> f32 {
         if self.duration > 0.0 {
             (self.current_time / self.duration) as f32
@@ -49,6 +159,21 @@ impl PlaylistState {
         let secs = (seconds % 60.0) as u32;
         format!("{:02}:{:02}", mins, secs)
     }
+    /// Read a typed position as seconds: `ss`, `mm:ss` or `h:mm:ss`, with an
+    /// optional fraction on the last field.
+    pub fn parse_time(text: &str) -> Option<f64> {
+        let fields: Vec<&str> = text.trim().split(':').collect();
+        if fields.len() > 3 { return None; }
+        let mut seconds = 0.0;
+        for (i, field) in fields.iter().enumerate() {
+            let value: f64 = field.trim().parse().ok().filter(|v: &f64| v.is_finite() && *v >= 0.0)?;
+            // only the leading field may run past 59, and only the last may have a fraction
+            if i > 0 && value >= 60.0 { return None; }
+            if i + 1 < fields.len() && field.contains('.') { return None; }
+            seconds = seconds * 60.0 + value;
+        }
+        Some(seconds)
+    }
     pub fn shuffle_playlist(&mut self) {
         let len = self.tracks.len();
         if len == 0 { return; }
@@ -91,5 +216,168 @@ impl PlaylistState {
             None => Some(0),
         }
     }
-    // Add shuffle and navigation logic here as needed
+    /// First track in play order (respects the shuffle order).
+    pub fn get_first_index(&self) -> Option<usize> {
+        if self.tracks.is_empty() { return None; }
+        if self.is_shuffled && !self.shuffle_order.is_empty() { Some(self.shuffle_order[0]) } else { Some(0) }
+    }
+
+    /// Track to play after a manual "next": wraps around unless repeat is off.
+    pub fn get_manual_next_index(&self) -> Option<usize> {
+        match self.repeat {
+            RepeatMode::Off => self.get_next_index(),
+            RepeatMode::One | RepeatMode::All => self.get_next_index().or_else(|| self.get_first_index()),
+        }
+    }
+
+    /// Track to play when the current one finishes on its own.
+    pub fn get_index_after_end(&self) -> Option<usize> {
+        match self.repeat {
+            RepeatMode::Off => self.get_next_index(),
+            RepeatMode::One => self.current_index,
+            RepeatMode::All => self.get_next_index().or_else(|| self.get_first_index()),
+        }
+    }
+
+    /// Sort the tracks, keeping `current_index` on the same track. A stable
+    /// sort, so equal keys keep their order; the shuffle order is redrawn.
+    pub fn sort_tracks(&mut self, key: SortKey, ascending: bool) {
+        let mut order: Vec<usize> = (0..self.tracks.len()).collect();
+        order.sort_by(|&a, &b| {
+            let (a, b) = (&self.tracks[a], &self.tracks[b]);
+            let ord = match key {
+                SortKey::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+                SortKey::Duration => a.duration.total_cmp(&b.duration),
+            };
+            if ascending { ord } else { ord.reverse() }
+        });
+        self.current_index = self.current_index.and_then(|cur| order.iter().position(|&i| i == cur));
+        let mut old: Vec<Option<PlaylistTrack>> = std::mem::take(&mut self.tracks).into_iter().map(Some).collect();
+        self.tracks = order.iter().filter_map(|&i| old[i].take()).collect();
+        if self.is_shuffled { self.shuffle_playlist(); } else { self.shuffle_order.clear(); }
+    }
+
+    /// Move the track at `from` so it ends up at `to`, shifting the ones in
+    /// between. `current_index` and `shuffle_order` keep referring to the
+    /// same tracks, so the play order is unchanged.
+    pub fn reorder_track(&mut self, from: usize, to: usize) {
+        let len = self.tracks.len();
+        if from >= len || to >= len || from == to { return; }
+        let track = self.tracks.remove(from);
+        self.tracks.insert(to, track);
+        let remap = |i: usize| {
+            if i == from { to }
+            else if from < to && (from + 1..=to).contains(&i) { i - 1 }
+            else if to < from && (to..from).contains(&i) { i + 1 }
+            else { i }
+        };
+        self.current_index = self.current_index.map(remap);
+        for i in &mut self.shuffle_order { *i = remap(*i); }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn playlist(names: &[&str]) -> PlaylistState {
+        let tracks = names.iter().map(|n| PlaylistTrack { name: n.to_string(), ..Default::default() }).collect();
+        PlaylistState { tracks, ..Default::default() }
+    }
+
+    fn names(p: &PlaylistState) -> Vec<&str> {
+        p.tracks.iter().map(|t| t.name.as_str()).collect()
+    }
+
+    #[test]
+    fn reorder_track_keeps_current_track() {
+        let mut p = playlist(&["a", "b", "c", "d"]);
+        p.current_index = Some(2);
+        p.reorder_track(0, 3);
+        assert_eq!(names(&p), ["b", "c", "d", "a"]);
+        assert_eq!(p.get_current_track().unwrap().name, "c");
+        p.reorder_track(3, 0);
+        assert_eq!(names(&p), ["a", "b", "c", "d"]);
+        assert_eq!(p.get_current_track().unwrap().name, "c");
+        p.reorder_track(2, 0);
+        assert_eq!(p.current_index, Some(0));
+    }
+
+    #[test]
+    fn sort_tracks_follows_the_current_track() {
+        let mut p = playlist(&["b", "C", "a"]);
+        for (t, d) in p.tracks.iter_mut().zip([30.0, 10.0, 20.0]) { t.duration = d; }
+        p.current_index = Some(0);
+        p.sort_tracks(SortKey::Name, true);
+        assert_eq!(names(&p), ["a", "b", "C"]);
+        assert_eq!(p.get_current_track().unwrap().name, "b");
+        p.sort_tracks(SortKey::Duration, false);
+        assert_eq!(names(&p), ["b", "a", "C"]);
+        assert_eq!(p.current_index, Some(0));
+        p.sort_tracks(SortKey::Duration, true);
+        assert_eq!(names(&p), ["C", "a", "b"]);
+        assert_eq!(p.get_current_track().unwrap().name, "b");
+    }
+
+    #[test]
+    fn reorder_track_preserves_shuffle_play_order() {
+        let mut p = playlist(&["a", "b", "c", "d"]);
+        p.is_shuffled = true;
+        p.shuffle_order = vec![3, 1, 0, 2];
+        p.reorder_track(1, 3);
+        let order: Vec<&str> = p.shuffle_order.iter().map(|&i| p.tracks[i].name.as_str()).collect();
+        assert_eq!(order, ["d", "b", "a", "c"]);
+    }
+
+    #[test]
+    fn parse_time_reads_typed_positions() {
+        assert_eq!(PlaylistState::parse_time("90"), Some(90.0));
+        assert_eq!(PlaylistState::parse_time(" 01:30 "), Some(90.0));
+        assert_eq!(PlaylistState::parse_time("1:02:03.5"), Some(3723.5));
+        assert_eq!(PlaylistState::parse_time(&PlaylistState::format_time(754.0)), Some(754.0));
+        for bad in ["", "1:", "1:60", "-5", "a:10", "1:2:3:4", "1.5:30", "1.0:30", "1:02.5:03"] {
+            assert_eq!(PlaylistState::parse_time(bad), None, "{bad}");
+        }
+    }
+
+    #[test]
+    fn loop_points_stay_ordered() {
+        let mut p = playlist(&["a"]);
+        p.current_index = Some(0);
+        p.current_time = 10.0;
+        p.set_loop_end();
+        assert_eq!(p.loop_range(), Some((0.0, 10.0)));
+        p.current_time = 4.0;
+        p.set_loop_start();
+        assert_eq!(p.loop_range(), Some((4.0, 10.0)));
+        // B before A is refused; A after B drops B
+        p.current_time = 2.0;
+        p.set_loop_end();
+        assert_eq!(p.loop_range(), Some((4.0, 10.0)));
+        p.current_time = 12.0;
+        p.set_loop_start();
+        assert_eq!(p.loop_range(), None);
+        p.clear_loop();
+        assert_eq!((p.tracks[0].loop_start, p.tracks[0].loop_end), (None, None));
+    }
+
+    #[test]
+    fn normalization_gain_targets_rms_level() {
+        // full-scale square wave sits at 0 dBFS RMS, so it is cut as far as allowed
+        let loud = (0..4800).map(|i| if i % 2 == 0 { i16::MAX } else { -i16::MAX });
+        assert_eq!(normalization_gain_db(loud), -MAX_NORMALIZE_GAIN_DB);
+        // a square wave at a tenth of full scale is -20 dBFS, 2 dB under the target
+        let quiet = (0..4800).map(|i| if i % 2 == 0 { 3277 } else { -3277 });
+        assert!((normalization_gain_db(quiet) - 2.0).abs() < 0.01);
+        assert_eq!(normalization_gain_db(std::iter::repeat_n(0, 100)), 0.0);
+
+        let mut p = playlist(&["a"]);
+        p.tracks[0].gain_db = 6.0;
+        p.current_index = Some(0);
+        assert_eq!(p.current_volume(), p.volume);
+        p.normalize_volume = true;
+        assert_eq!(p.current_volume(), 1.0);
+        p.volume = 0.4;
+        assert!((p.current_volume() - p.volume * 1.995).abs() < 0.01);
+    }
 }
@@ -0,0 +1,72 @@
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::thread;
+use eframe::egui::ColorImage;
+
+// frames waiting for the writer thread; when it falls behind, newer frames are dropped
+const QUEUE_FRAMES: usize = 8;
+
+/// Writes captured frames as a numbered PNG sequence on a background thread.
+/// Dropping it stops recording once the queued frames are written.
+pub struct FrameRecorder {
+    pub dir: PathBuf,
+    pub frames: u64,
+    pub dropped: u64,
+    bytes_written: Arc<AtomicU64>,
+    frame_tx: SyncSender<(PathBuf, ColorImage)>,
+    error_rx: Receiver<String>,
+}
+
+impl FrameRecorder {
+    /// Create `dir` if needed and start the writer thread.
+    pub fn start(dir: &Path) -> Result<Self, String> {
+        fs::create_dir_all(dir).map_err(|e| format!("Can't create {}: {e}", dir.display()))?;
+        let (frame_tx, frame_rx) = mpsc::sync_channel::<(PathBuf, ColorImage)>(QUEUE_FRAMES);
+        let (error_tx, error_rx) = mpsc::channel();
+        let bytes_written = Arc::new(AtomicU64::new(0));
+        let bytes = bytes_written.clone();
+        thread::spawn(move || {
+            for (path, image) in frame_rx {
+                match write_png(&path, &image) {
+                    Ok(()) => { bytes.fetch_add(fs::metadata(&path).map_or(0, |m| m.len()), Ordering::Relaxed); }
+                    Err(e) => { let _ = error_tx.send(format!("Failed to write {}: {e}", path.display())); }
+                }
+            }
+        });
+        Ok(Self { dir: dir.to_path_buf(), frames: 0, dropped: 0, bytes_written, frame_tx, error_rx })
+    }
+
+    /// Queue `image` as the next frame, named `frame_{number}_{time}s.png`
+    /// after the frame counter and the app time in seconds.
+    pub fn push(&mut self, image: ColorImage, time: f64) {
+        let path = self.dir.join(format!("frame_{:06}_{:.3}s.png", self.frames, time));
+        match self.frame_tx.try_send((path, image)) {
+            Ok(()) => self.frames += 1,
+            Err(TrySendError::Full(_)) => self.dropped += 1,
+            Err(TrySendError::Disconnected(_)) => {}
+        }
+    }
+
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written.load(Ordering::Relaxed)
+    }
+
+    /// Oldest write error not yet reported, if any.
+    pub fn poll_error(&self) -> Option<String> {
+        self.error_rx.try_recv().ok()
+    }
+}
+
+/// Encode `image` as an 8-bit RGBA PNG at `path`.
+pub fn write_png(path: &Path, image: &ColorImage) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| e.to_string())?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), image.width() as u32, image.height() as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+    writer.write_image_data(image.as_raw()).map_err(|e| e.to_string())
+}
@@ -0,0 +1,143 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+use rodio::Source;
+
+// analysis window per channel (~46 ms at 44.1 kHz); output hops are half of it
+const WINDOW_FRAMES: usize = 2048;
+const SYNTHESIS_HOP: usize = WINDOW_FRAMES / 2;
+
+/// Overlap-add time stretch: changes tempo by `rate` without changing pitch.
+///
+/// Hann-windowed frames are read from the input every `rate * SYNTHESIS_HOP`
+/// frames and written out every `SYNTHESIS_HOP`, so at 50% overlap the
+/// windows sum to unity gain. Without phase alignment (WSOLA / phase vocoder)
+/// tonal material picks up some phasiness, which is fine for a visualizer.
+pub struct TimeStretch<S> {
+    inner: S,
+    channels: usize,
+    analysis_hop: usize,
+    window: Vec<f32>,
+    input: VecDeque<f32>,
+    // second half of the previous windowed frame, waiting to be summed
+    overlap: Vec<f32>,
+    output: VecDeque<f32>,
+    inner_done: bool,
+}
+
+impl<S: Source<Item = i16>> TimeStretch<S> {
+    pub fn new(inner: S, rate: f32) -> Self {
+        let channels = inner.channels().max(1) as usize;
+        let window = (0..WINDOW_FRAMES)
+            .map(|i| 0.5 - 0.5 * (std::f32::consts::TAU * i as f32 / WINDOW_FRAMES as f32).cos())
+            .collect();
+        Self {
+            inner,
+            channels,
+            analysis_hop: ((SYNTHESIS_HOP as f32 * rate.max(0.01)).round() as usize).max(1),
+            window,
+            input: VecDeque::new(),
+            overlap: vec![0.0; SYNTHESIS_HOP * channels],
+            output: VecDeque::new(),
+            inner_done: false,
+        }
+    }
+
+    // read, window and overlap-add one frame; false once the input is used up
+    fn process_frame(&mut self) -> bool {
+        let frame_len = WINDOW_FRAMES * self.channels;
+        while self.input.len() < frame_len && !self.inner_done {
+            match self.inner.next() {
+                Some(s) => self.input.push_back(s as f32 / i16::MAX as f32),
+                None => self.inner_done = true,
+            }
+        }
+        if self.input.is_empty() {
+            return false;
+        }
+        self.input.resize(frame_len.max(self.input.len()), 0.0);
+
+        let half = SYNTHESIS_HOP * self.channels;
+        for (i, &sample) in self.input.iter().take(frame_len).enumerate() {
+            let windowed = sample * self.window[i / self.channels];
+            if i < half {
+                self.output.push_back(self.overlap[i] + windowed);
+            } else {
+                self.overlap[i - half] = windowed;
+            }
+        }
+        let hop = (self.analysis_hop * self.channels).min(self.input.len());
+        self.input.drain(..hop);
+        if self.inner_done && self.input.iter().all(|&s| s == 0.0) {
+            self.input.clear();
+        }
+        true
+    }
+}
+
+impl<S: Source<Item = i16>> Iterator for TimeStretch<S> {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        if self.output.is_empty() && !self.process_frame() {
+            // flush the tail of the last frame once
+            if self.overlap.iter().all(|&s| s == 0.0) {
+                return None;
+            }
+            self.output.extend(self.overlap.iter().copied());
+            self.overlap.fill(0.0);
+        }
+        self.output.pop_front().map(|s| (s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)
+    }
+}
+
+impl<S: Source<Item = i16>> Source for TimeStretch<S> {
+    fn current_frame_len(&self) -> Option<usize> { None }
+    fn channels(&self) -> u16 { self.channels as u16 }
+    fn sample_rate(&self) -> u32 { self.inner.sample_rate() }
+    fn total_duration(&self) -> Option<Duration> { None }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::buffer::SamplesBuffer;
+    use rustfft::{num_complex::Complex, FftPlanner};
+
+    const RATE: u32 = 44_100;
+
+    fn sine(hz: f32, secs: f32) -> SamplesBuffer<i16> {
+        let samples = (0..(RATE as f32 * secs) as usize)
+            .map(|i| ((std::f32::consts::TAU * hz * i as f32 / RATE as f32).sin() * 0.5 * i16::MAX as f32) as i16)
+            .collect::<Vec<_>>();
+        SamplesBuffer::new(1, RATE, samples)
+    }
+
+    // frequency of the strongest FFT bin over `samples`
+    fn dominant_hz(samples: &[i16]) -> f32 {
+        let mut buffer: Vec<Complex<f32>> = samples.iter().map(|&s| Complex::new(s as f32, 0.0)).collect();
+        FftPlanner::new().plan_fft_forward(buffer.len()).process(&mut buffer);
+        let peak = (1..buffer.len() / 2).max_by(|&a, &b| buffer[a].norm().total_cmp(&buffer[b].norm())).unwrap();
+        peak as f32 * RATE as f32 / buffer.len() as f32
+    }
+
+    #[test]
+    fn output_length_scales_with_rate() {
+        let input_len = RATE as usize * 2;
+        for rate in [0.5, 1.0, 2.0] {
+            let out = TimeStretch::new(sine(440.0, 2.0), rate).count() as f32;
+            let expected = input_len as f32 / rate;
+            // the last window's tail adds up to one window of slack
+            assert!((out - expected).abs() <= WINDOW_FRAMES as f32, "rate {rate}: {out} vs {expected}");
+        }
+    }
+
+    #[test]
+    fn sine_keeps_its_pitch() {
+        for rate in [0.5, 2.0] {
+            let out: Vec<i16> = TimeStretch::new(sine(440.0, 2.0), rate).collect();
+            let middle = &out[out.len() / 2 - 4096..out.len() / 2 + 4096];
+            let hz = dominant_hz(middle);
+            assert!((hz - 440.0).abs() < 10.0, "rate {rate}: {hz} Hz");
+        }
+    }
+}
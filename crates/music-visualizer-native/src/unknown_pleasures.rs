@@ -1,80 +1,383 @@
-use eframe::egui::{self, Color32, Pos2, Rect, Stroke};
+use eframe::egui::{self, Color32, ColorImage, Pos2, Rect, Stroke, Vec2};
+use tiny_skia::{FillRule, LineCap, LineJoin, Paint, Path, PathBuilder, Pixmap, Transform};
+use crate::color::lerp_color;
 use crate::audio_analysis::AudioAnalysis;
-use crate::visualizer_config::VisualizerConfig;
+use crate::visualizer_config::{UpOrientation, VisualizerConfig};
 // TAU is referenced via fully-qualified path in this file; avoid an unused import.
 
+// in auto-fit mode a full-amplitude peak reaches this many line spacings
+const AUTO_FIT_PEAK_LINES: f32 = 3.0;
+// extra headroom for the per-sample jitter on top of the peak
+const AUTO_FIT_JITTER_ALLOWANCE: f32 = 1.15;
+
 pub struct UnknownPleasuresVisualizer {
     last_amplitudes: Vec<f32>,
+    // right-channel amplitudes in mirror mode; the right half of each line follows these
+    last_amplitudes_right: Vec<f32>,
+    // animation phase of the last drawn frame, so exports match the screen
+    last_phase: f32,
+}
+
+/// Screen-space geometry of one Unknown Pleasures line.
+struct UpLine {
+    points: Vec<Pos2>,
+    // projection of each point onto the bottom edge, closing the area under the line
+    floor: Vec<Pos2>,
+    thickness: f32,
+    color: Color32,
 }
 
 impl UnknownPleasuresVisualizer {
     pub fn new() -> Self {
-        Self { last_amplitudes: Vec::new() }
+        Self { last_amplitudes: Vec::new(), last_amplitudes_right: Vec::new(), last_phase: 0.0 }
     }
-    pub fn draw(&mut self, ui: &mut egui::Ui, rect: Rect, audio: &AudioAnalysis, cfg: &VisualizerConfig, time: f64) {
+    /// Smooth towards the current band levels and draw. While `frozen` the
+    /// held amplitudes and phase are redrawn as they are.
+    pub fn draw(&mut self, ui: &mut egui::Ui, rect: Rect, audio: &AudioAnalysis, cfg: &VisualizerConfig, time: f64, frozen: bool) {
         let painter = ui.painter();
-        let bands = cfg.up_max_lines as usize;
-        let freq_len = audio.frequency_data.len().max(1);
-        let samples = cfg.up_samples as usize;
+        let bands = cfg.up_line_count();
         if self.last_amplitudes.len() < bands {
             self.last_amplitudes.resize(bands, 0.0);
         }
-        let width = rect.width();
-        let phase = (time as f32) * 2.0;
-        let center = rect.center();
+        if self.last_amplitudes_right.len() < bands {
+            self.last_amplitudes_right.resize(bands, 0.0);
+        }
+        if frozen {
+            self.paint(painter, rect, cfg);
+            return;
+        }
+        self.last_phase = (time as f32) * 2.0;
+        let smoothing = cfg.up_smoothing.clamp(0.0, 1.0);
+        let channels = if cfg.mirror_channels {
+            [
+                (&audio.frequency_data_left, (audio.smooth_bass_left, audio.smooth_mid_left, audio.smooth_treble_left)),
+                (&audio.frequency_data_right, (audio.smooth_bass_right, audio.smooth_mid_right, audio.smooth_treble_right)),
+            ]
+        } else {
+            let mono = (audio.smooth_bass, audio.smooth_mid, audio.smooth_treble);
+            [(&audio.frequency_data, mono), (&audio.frequency_data, mono)]
+        };
         for i in 0..bands {
-            let z = i as f32 / bands as f32;
-            let f0 = (i as f32) / (bands as f32);
-            let f1 = ((i + 1) as f32) / (bands as f32);
-            let exp = cfg.up_freq_curve_exponent.max(0.001);
-            let idx0 = ((f0.powf(exp)) * (freq_len as f32)).floor() as usize;
-            let idx1 = ((f1.powf(exp)) * (freq_len as f32)).floor() as usize;
-            let start = idx0.min(freq_len - 1);
-            let mut end = idx1.min(freq_len);
-            if end <= start { end = (start + 1).min(freq_len); }
-            let mut sum = 0.0;
-            for b in start..end {
-                sum += audio.frequency_data[b];
+            for (amps, (spectrum, levels)) in [&mut self.last_amplitudes, &mut self.last_amplitudes_right].into_iter().zip(channels) {
+                let raw_amp = band_amplitude(spectrum, i, bands, cfg, audio.sample_rate, levels);
+                let last = amps[i];
+                amps[i] = last + (raw_amp - last) * smoothing;
             }
-            let base_amp = (sum as f32) / ((end - start) as f32 * 255.0);
-            let raw_amp = base_amp * (1.0
-                + cfg.up_bass_mult * audio.smooth_bass
-                + cfg.up_mid_mult * audio.smooth_mid
-                + cfg.up_treble_mult * audio.smooth_treble);
-            let last = self.last_amplitudes[i];
-            let smoothing = cfg.up_smoothing.clamp(0.0, 1.0);
-            let amp = last + (raw_amp - last) * smoothing;
-            self.last_amplitudes[i] = amp;
-            let perspective = 1.0 - z * cfg.up_perspective;
-            let line_thickness = (cfg.up_line_thickness * perspective).max(0.3);
-            let alpha = (200.0 * (1.0 - z)).max(40.0) as u8;
-            let spacing = rect.height() / (bands as f32 * 0.9);
-            let baseline = rect.bottom() - (i as f32 * spacing) + z * (rect.height() * -0.2);
-            let amp_scale = cfg.up_vertical_scale * 100.0 * amp * perspective;
-            let color = if cfg.up_monochrome {
-                Color32::from_rgba_unmultiplied(255, 255, 255, alpha)
+        }
+        self.paint(painter, rect, cfg);
+    }
+
+    // the whole frame goes to the painter as one batch: a path per line (or a
+    // shape per segment) plus its occlusion fill
+    fn paint(&self, painter: &egui::Painter, rect: Rect, cfg: &VisualizerConfig) {
+        let mut shapes = Vec::with_capacity(cfg.up_line_count() * 2);
+        for line in self.lines(rect, cfg) {
+            if cfg.up_fill_occlusion {
+                shapes.push(egui::Shape::mesh(occlusion_mesh(&line.points, &line.floor, cfg.background_color)));
+            }
+            let stroke = Stroke::new(line.thickness, line.color);
+            if cfg.up_segment_strokes {
+                // separate segments leave room for per-segment colors, at the cost of joints at sharp peaks
+                shapes.extend(line.points.windows(2).map(|w| egui::Shape::line_segment([w[0], w[1]], stroke)));
             } else {
-                let base = cfg.base_color;
-                Color32::from_rgba_unmultiplied(base.r(), base.g(), base.b(), alpha)
-            };
-            let mut prev: Option<Pos2> = None;
-            let angle_rad = cfg.up_rotation_deg.to_radians();
-            let (ca, sa) = if cfg.up_isometric_rotate { (angle_rad.cos(), angle_rad.sin()) } else { (1.0f32, 0.0f32) };
-            for s in 0..samples {
-                let t = s as f32 / (samples - 1) as f32;
-                let local_x = (t - 0.5) * width * cfg.up_line_length * cfg.up_zoom;
-                let freq_mod = 1.0 + (z * 6.0);
-                let carrier = (t * std::f32::consts::TAU * freq_mod + phase * (1.0 + z)).sin();
-                let jitter = ((t * 50.0).sin() * 0.15 + (t * 12.0).cos() * 0.08) * (1.0 - z) * 0.6;
-                let local_y = (baseline - center.y) - (carrier * amp_scale * (1.0 + jitter));
-                let rx = local_x * ca - local_y * sa;
-                let ry = local_x * sa + local_y * ca;
-                let pt = Pos2::new(center.x + rx, center.y + ry);
-                if let Some(p0) = prev {
-                    painter.line_segment([p0, pt], Stroke::new(line_thickness, color));
+                // one feathered path per line joins the segments cleanly
+                shapes.push(egui::Shape::line(line.points, stroke));
+            }
+        }
+        painter.extend(shapes);
+    }
+
+    /// The last drawn frame as a standalone SVG document sized to `rect`,
+    /// using the same geometry as `draw`.
+    pub fn to_svg(&self, rect: Rect, cfg: &VisualizerConfig) -> String {
+        let (w, h) = (rect.width(), rect.height());
+        let bg = cfg.background_color;
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w:.0}\" height=\"{h:.0}\" viewBox=\"0 0 {w:.2} {h:.2}\">\n\
+             <rect width=\"100%\" height=\"100%\" fill=\"{}\"/>\n",
+            svg_color(bg),
+        );
+        let coords = |pts: &mut dyn Iterator<Item = &Pos2>| {
+            pts.map(|p| format!("{:.2},{:.2}", p.x - rect.left(), p.y - rect.top())).collect::<Vec<_>>().join(" ")
+        };
+        for line in self.lines(rect, cfg) {
+            if cfg.up_fill_occlusion {
+                let outline = coords(&mut line.points.iter().chain(line.floor.iter().rev()));
+                svg.push_str(&format!("<polygon points=\"{outline}\" fill=\"{}\"/>\n", svg_color(bg)));
+            }
+            svg.push_str(&format!(
+                "<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-opacity=\"{:.3}\" stroke-width=\"{:.2}\" stroke-linejoin=\"round\"/>\n",
+                coords(&mut line.points.iter()),
+                svg_color(line.color),
+                line.color.a() as f32 / 255.0,
+                line.thickness,
+            ));
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    /// The last drawn frame rendered offscreen at `size` pixels, whatever the
+    /// window size. Geometry is laid out as on a screen rect of the export's
+    /// aspect ratio and `screen_height` tall, then scaled up, so amplitudes and
+    /// line weights keep their proportions.
+    pub fn to_image(&self, screen_height: f32, cfg: &VisualizerConfig, size: [usize; 2]) -> ColorImage {
+        let scale = size[1] as f32 / screen_height.max(1.0);
+        let layout = Rect::from_min_size(Pos2::ZERO, Vec2::new(size[0] as f32, size[1] as f32) / scale);
+        let Some(mut pixmap) = Pixmap::new(size[0] as u32, size[1] as u32) else {
+            return ColorImage::new(size, cfg.background_color);
+        };
+        pixmap.fill(skia_color(cfg.background_color));
+        // the same outline and polyline as the SVG, so the two exports agree
+        let transform = Transform::from_scale(scale, scale);
+        let mut paint = Paint::default();
+        for line in self.lines(layout, cfg) {
+            if cfg.up_fill_occlusion {
+                if let Some(outline) = skia_path(line.points.iter().chain(line.floor.iter().rev()), true) {
+                    paint.set_color(skia_color(cfg.background_color));
+                    pixmap.fill_path(&outline, &paint, FillRule::Winding, transform, None);
                 }
-                prev = Some(pt);
             }
+            if let Some(path) = skia_path(line.points.iter(), false) {
+                paint.set_color(skia_color(line.color));
+                let stroke = tiny_skia::Stroke { width: line.thickness, line_cap: LineCap::Round, line_join: LineJoin::Round, ..Default::default() };
+                pixmap.stroke_path(&path, &paint, &stroke, transform, None);
+            }
+        }
+        ColorImage::from_rgba_premultiplied(size, pixmap.data())
+    }
+
+    // every line of the frame laid out in `rect`, back to front so filled lines
+    // hide the farther ones behind their peaks
+    fn lines<'a>(&'a self, rect: Rect, cfg: &'a VisualizerConfig) -> impl Iterator<Item = UpLine> + 'a {
+        let bands = cfg.up_line_count().min(self.last_amplitudes.len());
+        (0..bands).rev().map(move |i| self.line_geometry(rect, cfg, i))
+    }
+
+    /// Perspective, rotation and waveform for line `i` at its smoothed amplitude.
+    fn line_geometry(&self, rect: Rect, cfg: &VisualizerConfig, i: usize) -> UpLine {
+        let rect = line_space(rect, cfg);
+        let bands = cfg.up_line_count();
+        let samples = cfg.up_sample_count();
+        let mut amp_left = self.last_amplitudes.get(i).copied().unwrap_or(0.0);
+        let mut amp_right = self.last_amplitudes_right.get(i).copied().unwrap_or(amp_left);
+        if cfg.up_auto_fit {
+            amp_left = amp_left.min(1.0);
+            amp_right = amp_right.min(1.0);
+        }
+        let width = rect.width();
+        let phase = self.last_phase;
+        let center = rect.center();
+        let z = i as f32 / bands as f32;
+        let perspective = 1.0 - z * cfg.up_perspective;
+        let zoom = if cfg.up_thickness_follows_zoom { cfg.up_zoom } else { 1.0 };
+        let thickness = (cfg.up_line_thickness * perspective * zoom).max(0.3);
+        let alpha = (200.0 * (1.0 - z)).max(40.0) as u8;
+        let (baseline, unit_scale) = line_placement(rect, cfg, i, bands);
+        let amp_scale = unit_scale * perspective;
+        let color = if cfg.up_gradient {
+            let c = lerp_color(cfg.up_gradient_bottom, cfg.up_gradient_top, z);
+            Color32::from_rgba_unmultiplied(c.r(), c.g(), c.b(), alpha)
+        } else if cfg.up_monochrome {
+            Color32::from_rgba_unmultiplied(255, 255, 255, alpha)
+        } else {
+            let base = cfg.base_color;
+            Color32::from_rgba_unmultiplied(base.r(), base.g(), base.b(), alpha)
+        };
+        let mut points: Vec<Pos2> = Vec::with_capacity(samples);
+        let mut floor: Vec<Pos2> = Vec::with_capacity(samples);
+        let angle_rad = cfg.up_rotation_deg.to_radians();
+        let (ca, sa) = if cfg.up_isometric_rotate { (angle_rad.cos(), angle_rad.sin()) } else { (1.0f32, 0.0f32) };
+        for s in 0..samples {
+            let t = s as f32 / (samples - 1) as f32;
+            // blend the channels across the middle fifth so the seam doesn't kink
+            let blend = ((t - 0.4) / 0.2).clamp(0.0, 1.0);
+            let amp = amp_left + (amp_right - amp_left) * blend * blend * (3.0 - 2.0 * blend);
+            let local_x = (t - 0.5) * width * cfg.up_line_length * cfg.up_zoom;
+            let freq_mod = 1.0 + (z * 6.0);
+            let carrier = (t * std::f32::consts::TAU * freq_mod + phase * (1.0 + z)).sin();
+            let jitter = ((t * 50.0).sin() * 0.15 + (t * 12.0).cos() * 0.08) * (1.0 - z) * 0.6;
+            let local_y = (baseline - center.y) - (carrier * amp_scale * amp * (1.0 + jitter));
+            let rx = local_x * ca - local_y * sa;
+            let ry = local_x * sa + local_y * ca;
+            points.push(orient(center, rx, ry, cfg));
+            let floor_y = rect.bottom() - center.y;
+            floor.push(orient(center, local_x * ca - floor_y * sa, local_x * sa + floor_y * ca, cfg));
+        }
+        UpLine { points, floor, thickness, color }
+    }
+}
+
+// the rect lines are laid out in: `rect`, or for vertical lines the same centre
+// with the sides swapped so length follows the tall axis and spacing the wide one
+fn line_space(rect: Rect, cfg: &VisualizerConfig) -> Rect {
+    match cfg.up_orientation {
+        UpOrientation::Horizontal => rect,
+        UpOrientation::Vertical => Rect::from_center_size(rect.center(), Vec2::new(rect.height(), rect.width())),
+    }
+}
+
+// screen position of a point `along` its line and `across` the stack from
+// `center`; vertical lines run down the screen and stack from the left
+fn orient(center: Pos2, along: f32, across: f32, cfg: &VisualizerConfig) -> Pos2 {
+    match cfg.up_orientation {
+        UpOrientation::Horizontal => Pos2::new(center.x + along, center.y + across),
+        UpOrientation::Vertical => Pos2::new(center.x - across, center.y + along),
+    }
+}
+
+// baseline of line `i` and the height a unit amplitude draws before perspective.
+// manual mode spreads the lines over the rect and pushes far ones up for depth;
+// auto-fit sizes both from the rect height and line count so the unrotated
+// stack, peaks included, never clips
+fn line_placement(rect: Rect, cfg: &VisualizerConfig, i: usize, bands: usize) -> (f32, f32) {
+    if cfg.up_auto_fit {
+        let headroom = AUTO_FIT_PEAK_LINES * AUTO_FIT_JITTER_ALLOWANCE;
+        let spacing = rect.height() / (bands.saturating_sub(1) as f32 + 2.0 * headroom);
+        (rect.bottom() - (headroom + i as f32) * spacing, AUTO_FIT_PEAK_LINES * spacing)
+    } else {
+        let z = i as f32 / bands as f32;
+        let spacing = rect.height() / (bands as f32 * 0.9);
+        (rect.bottom() - (i as f32 * spacing) + z * (rect.height() * -0.2), cfg.up_vertical_scale * 100.0)
+    }
+}
+
+// lowest frequency of the Mel-spaced bands
+const MEL_MIN_HZ: f32 = 20.0;
+
+fn hz_to_mel(hz: f32) -> f32 {
+    2595.0 * (1.0 + hz / 700.0).log10()
+}
+
+fn mel_to_hz(mel: f32) -> f32 {
+    700.0 * (10f32.powf(mel / 2595.0) - 1.0)
+}
+
+// bin range of line `i`: equal steps in Mel between MEL_MIN_HZ and Nyquist, or
+// the `up_freq_curve_exponent` power curve over the bins
+fn band_bins(i: usize, bands: usize, freq_len: usize, cfg: &VisualizerConfig, sample_rate: f32) -> std::ops::Range<usize> {
+    let f0 = (i as f32) / (bands as f32);
+    let f1 = ((i + 1) as f32) / (bands as f32);
+    let (t0, t1) = if cfg.up_mel_scale {
+        let nyquist = if sample_rate > 0.0 { sample_rate / 2.0 } else { 22_050.0 };
+        let (lo, hi) = (hz_to_mel(MEL_MIN_HZ), hz_to_mel(nyquist));
+        let to_fraction = |f: f32| mel_to_hz(lo + (hi - lo) * f) / nyquist;
+        (to_fraction(f0), to_fraction(f1))
+    } else {
+        let exp = cfg.up_freq_curve_exponent.max(0.001);
+        (f0.powf(exp), f1.powf(exp))
+    };
+    let idx0 = (t0 * (freq_len as f32)).floor() as usize;
+    let idx1 = (t1 * (freq_len as f32)).floor() as usize;
+    let start = idx0.min(freq_len - 1);
+    let mut end = idx1.min(freq_len);
+    if end <= start { end = (start + 1).min(freq_len); }
+    start..end
+}
+
+// mean 0..1 level of line `i`'s slice of `spectrum`, boosted by the band levels
+fn band_amplitude(spectrum: &[f32], i: usize, bands: usize, cfg: &VisualizerConfig, sample_rate: f32, (bass, mid, treble): (f32, f32, f32)) -> f32 {
+    let range = band_bins(i, bands, spectrum.len().max(1), cfg, sample_rate);
+    let len = range.len();
+    let sum: f32 = spectrum.get(range).map_or(0.0, |s| s.iter().sum());
+    let base_amp = sum / (len as f32 * 255.0);
+    base_amp * (1.0 + cfg.up_bass_mult * bass + cfg.up_mid_mult * mid + cfg.up_treble_mult * treble)
+}
+
+// `#rrggbb`; alpha is emitted separately as an opacity attribute
+fn svg_color(c: Color32) -> String {
+    // unmultiplied, or the opacity attribute would darken translucent lines a second time
+    let [r, g, b, _] = c.to_srgba_unmultiplied();
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+/// Opaque mesh covering the area between a line and its bottom-edge
+/// projection. Built from per-segment quads since the outline is not convex.
+fn occlusion_mesh(points: &[Pos2], floor: &[Pos2], color: Color32) -> egui::Mesh {
+    let mut mesh = egui::Mesh::default();
+    for (p, f) in points.iter().zip(floor) {
+        mesh.colored_vertex(*p, color);
+        mesh.colored_vertex(*f, color);
+    }
+    for k in 0..points.len().saturating_sub(1) as u32 {
+        let (a, b) = (2 * k, 2 * k + 2);
+        mesh.add_triangle(a, a + 1, b);
+        mesh.add_triangle(b, a + 1, b + 1);
+    }
+    mesh
+}
+
+fn skia_color(c: Color32) -> tiny_skia::Color {
+    let [r, g, b, a] = c.to_srgba_unmultiplied();
+    tiny_skia::Color::from_rgba8(r, g, b, a)
+}
+
+// `points` joined into one path, closed into a polygon when `close` is set
+fn skia_path<'a>(mut points: impl Iterator<Item = &'a Pos2>, close: bool) -> Option<Path> {
+    let first = points.next()?;
+    let mut builder = PathBuilder::new();
+    builder.move_to(first.x, first.y);
+    for p in points {
+        builder.line_to(p.x, p.y);
+    }
+    if close {
+        builder.close();
+    }
+    builder.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::visualizer_config::{MIN_UP_LINES, MIN_UP_SAMPLES};
+
+    #[test]
+    fn degenerate_line_counts_stay_finite() {
+        let cfg = VisualizerConfig { up_samples: 1, up_max_lines: 0, ..Default::default() };
+        let audio = AudioAnalysis::new();
+        let amp = band_amplitude(&audio.frequency_data, 0, cfg.up_line_count(), &cfg, audio.sample_rate, (0.5, 0.5, 0.5));
+        assert!(amp.is_finite());
+        let mut vis = UnknownPleasuresVisualizer::new();
+        vis.last_amplitudes = vec![0.8; MIN_UP_LINES as usize];
+        vis.last_phase = 1.0;
+        let rect = Rect::from_min_size(Pos2::ZERO, Vec2::new(800.0, 600.0));
+        let lines: Vec<UpLine> = vis.lines(rect, &cfg).collect();
+        assert_eq!(lines.len(), MIN_UP_LINES as usize);
+        for line in &lines {
+            assert_eq!(line.points.len(), MIN_UP_SAMPLES as usize);
+            assert!(line.thickness.is_finite());
+            assert!(line.points.iter().chain(&line.floor).all(|p| p.x.is_finite() && p.y.is_finite()));
+        }
+    }
+
+    #[test]
+    fn auto_fit_keeps_lines_inside_rect() {
+        let rect = Rect::from_min_size(Pos2::ZERO, Vec2::new(800.0, 600.0));
+        for (max_lines, up_orientation) in [(1, UpOrientation::Horizontal), (20, UpOrientation::Horizontal), (200, UpOrientation::Vertical)] {
+            let cfg = VisualizerConfig { up_auto_fit: true, up_max_lines: max_lines, up_vertical_scale: 10.0, up_orientation, ..Default::default() };
+            let mut vis = UnknownPleasuresVisualizer::new();
+            vis.last_amplitudes = vec![5.0; max_lines as usize];
+            vis.last_phase = 1.0;
+            let lines: Vec<UpLine> = vis.lines(rect, &cfg).collect();
+            assert_eq!(lines.len(), cfg.up_line_count());
+            assert!(lines.iter().flat_map(|l| &l.points).all(|&p| rect.expand(0.01).contains(p)), "{max_lines} {up_orientation:?} lines overflow");
         }
     }
+
+    #[test]
+    fn png_export_scales_the_frame_to_its_size() {
+        let cfg = VisualizerConfig { up_max_lines: MIN_UP_LINES, up_auto_fit: true, up_line_length: 0.5, up_fill_occlusion: true, ..Default::default() };
+        let mut vis = UnknownPleasuresVisualizer::new();
+        vis.last_amplitudes = vec![0.0; MIN_UP_LINES as usize];
+        let image = vis.to_image(180.0, &cfg, [640, 360]);
+        assert_eq!(image.size, [640, 360]);
+        // a flat half-width line across the middle, background beside it
+        assert_eq!(image.pixels[0], cfg.background_color);
+        let pixels = &image.pixels;
+        let column = |x: usize| (0..360).map(move |y| pixels[y * 640 + x]);
+        assert!(column(320).any(|p| p != cfg.background_color));
+        assert!(column(0).all(|p| p == cfg.background_color));
+        // nothing to draw into is an empty image rather than a panic
+        assert_eq!(vis.to_image(180.0, &cfg, [0, 0]).size, [0, 0]);
+    }
 }
@@ -1,29 +1,361 @@
-use eframe::egui::Color32;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use eframe::egui::{Color32, Vec2};
+use serde::{Deserialize, Serialize};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use crate::audio_analysis::{sorted_band_edges, AudioAnalysis, DEFAULT_BAND_EDGES_HZ};
+use crate::color::hsl_to_rgb;
 
-#[derive(Clone)]
+/// Upper bound on `base_depth` accepted from the UI and remote control.
+pub const MAX_BASE_DEPTH: u32 = 24;
+// range of `fractal_branches`, the children per tree node
+pub const MIN_BRANCHES: u32 = 2;
+pub const MAX_BRANCHES: u32 = 5;
+/// Start of every string made by `VisualizerConfig::to_share_string`; the
+/// number is bumped if the encoding ever changes.
+pub const SHARE_PREFIX: &str = "mv1:";
+/// Ceiling on the strobe's peak opacity whatever `strobe_intensity` says, so
+/// the flash never goes fully opaque (photosensitivity).
+pub const MAX_STROBE_INTENSITY: f32 = 0.5;
+// fewest points per Unknown Pleasures line (a line needs two ends) and fewest lines
+pub const MIN_UP_SAMPLES: u32 = 2;
+pub const MIN_UP_LINES: u32 = 1;
+
+/// Shape drawn by the fractal visualizer.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum FractalKind {
+    #[default]
+    BinaryTree,
+    Koch,
+    Sierpinski,
+    FractalFern,
+}
+
+impl FractalKind {
+    pub const ALL: [FractalKind; 4] = [FractalKind::BinaryTree, FractalKind::Koch, FractalKind::Sierpinski, FractalKind::FractalFern];
+    pub fn label(self) -> &'static str {
+        match self {
+            FractalKind::BinaryTree => "Binary tree",
+            FractalKind::Koch => "Koch snowflake",
+            FractalKind::Sierpinski => "Sierpinski triangle",
+            FractalKind::FractalFern => "Fractal fern",
+        }
+    }
+}
+
+/// How the waveform strip renders the time-domain buffer.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WaveformStyle {
+    #[default]
+    Line,
+    // per-column min/max peaks with an RMS band, like an audio editor
+    Envelope,
+}
+
+impl WaveformStyle {
+    pub const ALL: [WaveformStyle; 2] = [WaveformStyle::Line, WaveformStyle::Envelope];
+    pub fn label(self) -> &'static str {
+        match self {
+            WaveformStyle::Line => "Line",
+            WaveformStyle::Envelope => "Envelope",
+        }
+    }
+}
+
+/// Which way the Unknown Pleasures lines run.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UpOrientation {
+    // left to right, stacked bottom (near) to top (far)
+    #[default]
+    Horizontal,
+    // top to bottom, stacked left (near) to right (far), for portrait displays
+    Vertical,
+}
+
+impl UpOrientation {
+    pub const ALL: [UpOrientation; 2] = [UpOrientation::Horizontal, UpOrientation::Vertical];
+    pub fn label(self) -> &'static str {
+        match self {
+            UpOrientation::Horizontal => "Horizontal",
+            UpOrientation::Vertical => "Vertical",
+        }
+    }
+}
+
+/// Shape drawn for each beat particle.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ParticleShape {
+    #[default]
+    Circle,
+    Square,
+    Star,
+    // a streak along the particle's direction of travel
+    Line,
+}
+
+impl ParticleShape {
+    pub const ALL: [ParticleShape; 4] = [ParticleShape::Circle, ParticleShape::Square, ParticleShape::Star, ParticleShape::Line];
+    pub fn label(self) -> &'static str {
+        match self {
+            ParticleShape::Circle => "Circle",
+            ParticleShape::Square => "Square",
+            ParticleShape::Star => "Star",
+            ParticleShape::Line => "Line",
+        }
+    }
+}
+
+/// Ready-made colour scheme applied by [`VisualizerConfig::apply_palette`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Palette {
+    Sunset,
+    Ocean,
+    Mono,
+    Neon,
+    Fire,
+}
+
+impl Palette {
+    pub const ALL: [Palette; 5] = [Palette::Sunset, Palette::Ocean, Palette::Mono, Palette::Neon, Palette::Fire];
+    pub fn label(self) -> &'static str {
+        match self {
+            Palette::Sunset => "Sunset",
+            Palette::Ocean => "Ocean",
+            Palette::Mono => "Mono",
+            Palette::Neon => "Neon",
+            Palette::Fire => "Fire",
+        }
+    }
+
+    // (main hue, accent hue, saturation); every colour of the palette is derived from these
+    fn hues(self) -> (f32, f32, f32) {
+        match self {
+            Palette::Sunset => (0.95, 0.08, 0.85),
+            Palette::Ocean => (0.55, 0.45, 0.75),
+            Palette::Mono => (0.0, 0.0, 0.0),
+            Palette::Neon => (0.83, 0.5, 1.0),
+            Palette::Fire => (0.02, 0.12, 0.95),
+        }
+    }
+}
+
+/// Audio measurement that can drive a fractal parameter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AudioFeature {
+    Bass,
+    Mid,
+    Treble,
+    Volume,
+    Centroid,
+    Flux,
+}
+
+impl AudioFeature {
+    pub const ALL: [AudioFeature; 6] = [AudioFeature::Bass, AudioFeature::Mid, AudioFeature::Treble, AudioFeature::Volume, AudioFeature::Centroid, AudioFeature::Flux];
+    pub fn label(self) -> &'static str {
+        match self {
+            AudioFeature::Bass => "Bass",
+            AudioFeature::Mid => "Mid",
+            AudioFeature::Treble => "Treble",
+            AudioFeature::Volume => "Volume",
+            AudioFeature::Centroid => "Complexity",
+            AudioFeature::Flux => "Flux",
+        }
+    }
+    pub fn value(self, audio: &AudioAnalysis) -> f32 {
+        match self {
+            AudioFeature::Bass => audio.smooth_bass,
+            AudioFeature::Mid => audio.smooth_mid,
+            AudioFeature::Treble => audio.smooth_treble,
+            AudioFeature::Volume => audio.smooth_volume,
+            AudioFeature::Centroid => audio.spectral_centroid,
+            AudioFeature::Flux => audio.spectral_flux,
+        }
+    }
+}
+
+/// Fractal parameter that audio features push away from its base value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConfigParam {
+    Zoom,
+    Width,
+    Depth,
+    Brightness,
+}
+
+impl ConfigParam {
+    pub const ALL: [ConfigParam; 4] = [ConfigParam::Zoom, ConfigParam::Width, ConfigParam::Depth, ConfigParam::Brightness];
+    pub fn label(self) -> &'static str {
+        match self {
+            ConfigParam::Zoom => "Zoom",
+            ConfigParam::Width => "Width",
+            ConfigParam::Depth => "Depth",
+            ConfigParam::Brightness => "Brightness",
+        }
+    }
+}
+
+/// One `source → target` wire: adds `source * multiplier` to the target's base value.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ReactivityEntry {
+    pub source: AudioFeature,
+    pub target: ConfigParam,
+    pub multiplier: f32,
+}
+
+/// User-editable audio→parameter wiring. The default reproduces the classic
+/// bass→zoom, bass→width, complexity→depth and treble→brightness mapping.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ReactivityMap {
+    pub entries: Vec<ReactivityEntry>,
+}
+
+impl Default for ReactivityMap {
+    fn default() -> Self {
+        let entry = |source, target, multiplier| ReactivityEntry { source, target, multiplier };
+        Self {
+            entries: vec![
+                entry(AudioFeature::Bass, ConfigParam::Zoom, 0.1),
+                entry(AudioFeature::Bass, ConfigParam::Width, 0.3),
+                entry(AudioFeature::Centroid, ConfigParam::Depth, 4.0),
+                entry(AudioFeature::Treble, ConfigParam::Brightness, 0.4),
+            ],
+        }
+    }
+}
+
+impl ReactivityMap {
+    /// Sum of every entry driving `target` for the current audio frame.
+    pub fn offset(&self, target: ConfigParam, audio: &AudioAnalysis) -> f32 {
+        self.entries.iter().filter(|e| e.target == target).map(|e| e.source.value(audio) * e.multiplier).sum()
+    }
+}
+
+/// Config setting a mapped MIDI control-change knob overwrites, with the
+/// knob's travel spread over the range given on each variant.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MidiTarget {
+    /// `rotation_speed`, 0 to 5
+    RotationSpeed,
+    /// `glow_intensity`, 0 to 2
+    GlowIntensity,
+    /// `color_cycle_speed`, 0 to 1
+    ColorCycleSpeed,
+    /// `beat_sensitivity`, 1 to 3
+    BeatSensitivity,
+    /// `particle_count`, 0 to 200
+    ParticleCount,
+}
+
+impl MidiTarget {
+    pub const ALL: [MidiTarget; 5] = [MidiTarget::RotationSpeed, MidiTarget::GlowIntensity, MidiTarget::ColorCycleSpeed, MidiTarget::BeatSensitivity, MidiTarget::ParticleCount];
+    pub fn label(self) -> &'static str {
+        match self {
+            MidiTarget::RotationSpeed => "Rotation speed",
+            MidiTarget::GlowIntensity => "Glow",
+            MidiTarget::ColorCycleSpeed => "Color speed",
+            MidiTarget::BeatSensitivity => "Beat sensitivity",
+            MidiTarget::ParticleCount => "Particle count",
+        }
+    }
+    /// Set the parameter from a 0..=127 CC value spread over its useful range.
+    pub fn apply(self, cfg: &mut VisualizerConfig, value: u8) {
+        let t = f32::from(value.min(127)) / 127.0;
+        match self {
+            MidiTarget::RotationSpeed => cfg.rotation_speed = t * 5.0,
+            MidiTarget::GlowIntensity => cfg.glow_intensity = t * 2.0,
+            MidiTarget::ColorCycleSpeed => cfg.color_cycle_speed = t,
+            MidiTarget::BeatSensitivity => cfg.beat_sensitivity = 1.0 + t * 2.0,
+            MidiTarget::ParticleCount => cfg.particle_count = (t * 200.0).round() as u32,
+        }
+    }
+}
+
+/// One `CC number → setting` wire for MIDI controllers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MidiCcMapping {
+    pub controller: u8,
+    pub target: MidiTarget,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct VisualizerConfig {
+    pub fractal_kind: FractalKind,
     pub base_zoom: f32,
     pub base_width: f32,
     pub base_depth: u32,
     pub base_brightness: f32,
-    pub zoom_bass_mult: f32,
-    pub width_bass_mult: f32,
-    pub depth_complexity_mult: f32,
-    pub brightness_treble_mult: f32,
+    // children per tree node, fanned evenly across `fractal_spread_deg`
+    pub fractal_branches: u32,
+    pub fractal_spread_deg: f32,
+    // hue rotation per binary-tree recursion level, in turns; 0 keeps a single hue
+    pub fractal_depth_hue_shift: f32,
+    pub reactivity: ReactivityMap,
+    // MIDI CC knobs that set parameters; note-ons always trigger beats
+    pub midi_cc_map: Vec<MidiCcMapping>,
     pub rotation_beat_mult: f32,
+    pub beat_sensitivity: f32,
+    // fraction of the gap to the new level the smoothed bands close per frame (0..1)
+    pub band_smoothing: f32,
+    // ascending Hz boundaries of the five analysis bands (bass, low_mid, mid, high_mid, treble)
+    pub band_edges_hz: [f32; 6],
+    // weight bands and volume by perceived loudness
+    pub a_weighting: bool,
+    // RMS below which input is treated as silence (about -46 dBFS by default)
+    pub noise_gate: f32,
+    // normalize bands against a slow peak envelope; strength 0 = raw, 1 = fully normalized
+    pub auto_gain: bool,
+    pub auto_gain_strength: f32,
     pub auto_rotate: bool,
     pub rotation_speed: f32,
     pub pulse_on_beat: bool,
+    // flash the whole view on beats, in any mode
+    pub beat_strobe: bool,
+    pub strobe_color: Color32,
+    // peak opacity of the flash, capped at `MAX_STROBE_INTENSITY`
+    pub strobe_intensity: f32,
     pub color_cycle: bool,
     pub color_cycle_speed: f32,
     pub base_color: Color32,
     pub accent_color: Color32,
     pub background_color: Color32,
+    // fractal backdrop fades from `background_top` to `background_bottom` instead of the flat colour
+    pub background_gradient: bool,
+    pub background_top: Color32,
+    pub background_bottom: Color32,
     pub glow_intensity: f32,
+    // fraction of the previous fractal frame kept each 60 Hz frame; 0 clears every frame
+    pub trail_amount: f32,
+    // repaint rate cap while audio is playing; idle, silent frames run slower still
+    pub max_fps: u32,
+    // silence with nothing playing before attract mode starts; 0 never
+    pub idle_timeout_secs: f32,
     pub particle_count: u32,
+    // acceleration applied to beat particles, in screen units/s² (positive y falls)
+    pub particle_gravity: Vec2,
+    pub particle_shape: ParticleShape,
+    pub waveform_style: WaveformStyle,
+    pub spectrum_bars: u32,
+    // spread bars along a power curve so low frequencies get more of them
+    pub spectrum_log_axis: bool,
+    // mark each bar's recent maximum, falling by `spectrum_peak_decay` of full height per second
+    pub spectrum_peak_hold: bool,
+    pub spectrum_peak_decay: f32,
+    // fraction of the gap to the new level kept each frame; 0 draws raw data
+    pub spectrum_smoothing: f32,
+    pub waveform_smoothing: f32,
     pub up_line_thickness: f32,
+    // widen lines with `up_zoom` so zoomed-in lines stay visible
+    pub up_thickness_follows_zoom: bool,
+    // stroke each segment on its own instead of one path per line (joints show at sharp peaks)
+    pub up_segment_strokes: bool,
     pub up_perspective: f32,
     pub up_vertical_scale: f32,
+    // size line spacing and peak height from the viewport instead of `up_vertical_scale`
+    pub up_auto_fit: bool,
+    pub up_orientation: UpOrientation,
     pub up_line_length: f32,
     pub up_zoom: f32,
     pub up_isometric_rotate: bool,
@@ -34,35 +366,82 @@ pub struct VisualizerConfig {
     pub up_max_lines: u32,
     pub up_samples: u32,
     pub up_freq_curve_exponent: f32,
+    // Mel-spaced line frequencies instead of the exponent curve
+    pub up_mel_scale: bool,
     pub up_monochrome: bool,
     pub up_smoothing: f32,
+    // fill under each line with the background so nearer peaks hide farther lines
+    pub up_fill_occlusion: bool,
+    // color lines by depth, blending from the bottom (front) color to the top (back) color
+    pub up_gradient: bool,
+    pub up_gradient_top: Color32,
+    pub up_gradient_bottom: Color32,
+    // seconds of overlap between consecutive tracks; 0 cuts straight to the next
+    pub crossfade_secs: f32,
+    // draw the left channel on the left half and the right channel on the right half
+    pub mirror_channels: bool,
 }
 
 impl Default for VisualizerConfig {
     fn default() -> Self {
         Self {
+            fractal_kind: FractalKind::BinaryTree,
             base_zoom: 0.1,
             base_width: 1.0,
             base_depth: 16,
             base_brightness: 0.8,
-            zoom_bass_mult: 0.1,
-            width_bass_mult: 0.3,
-            depth_complexity_mult: 4.0,
-            brightness_treble_mult: 0.4,
+            fractal_branches: 2,
+            fractal_spread_deg: 90.0,
+            fractal_depth_hue_shift: 0.0,
+            reactivity: ReactivityMap::default(),
+            // CC 1 is the mod wheel on most keyboards
+            midi_cc_map: vec![
+                MidiCcMapping { controller: 1, target: MidiTarget::RotationSpeed },
+                MidiCcMapping { controller: 2, target: MidiTarget::GlowIntensity },
+            ],
             rotation_beat_mult: 0.1,
+            beat_sensitivity: 1.4,
+            band_smoothing: 0.15,
+            band_edges_hz: DEFAULT_BAND_EDGES_HZ,
+            a_weighting: false,
+            noise_gate: 0.005,
+            auto_gain: false,
+            auto_gain_strength: 1.0,
             auto_rotate: true,
             rotation_speed: 1.0,
             pulse_on_beat: true,
+            beat_strobe: false,
+            strobe_color: Color32::WHITE,
+            strobe_intensity: 0.3,
             color_cycle: true,
             color_cycle_speed: 0.1,
             base_color: Color32::from_rgb(100, 200, 255),
             accent_color: Color32::from_rgb(255, 100, 200),
             background_color: Color32::from_rgb(10, 10, 20),
+            background_gradient: false,
+            background_top: Color32::from_rgb(28, 20, 56),
+            background_bottom: Color32::from_rgb(4, 4, 12),
             glow_intensity: 0.5,
+            trail_amount: 0.0,
+            max_fps: 60,
+            idle_timeout_secs: 60.0,
             particle_count: 50,
+            particle_gravity: Vec2::ZERO,
+            particle_shape: ParticleShape::Circle,
+            waveform_style: WaveformStyle::Line,
+            spectrum_bars: 64,
+            spectrum_log_axis: false,
+            spectrum_peak_hold: false,
+            spectrum_peak_decay: 0.5,
+            spectrum_smoothing: 0.5,
+            waveform_smoothing: 0.0,
             up_line_thickness: 1.5,
+            up_thickness_follows_zoom: false,
+            up_segment_strokes: false,
             up_perspective: 0.6,
             up_vertical_scale: 1.0,
+            up_auto_fit: false,
+            up_orientation: UpOrientation::Horizontal,
             up_line_length: 1.0,
             up_zoom: 1.0,
             up_isometric_rotate: false,
@@ -73,8 +452,15 @@ impl Default for VisualizerConfig {
             up_max_lines: 80,
             up_samples: 120,
             up_freq_curve_exponent: 2.5,
+            up_mel_scale: false,
             up_monochrome: true,
             up_smoothing: 0.15,
+            up_fill_occlusion: false,
+            up_gradient: false,
+            up_gradient_top: Color32::from_rgb(255, 60, 160),
+            up_gradient_bottom: Color32::from_rgb(60, 200, 255),
+            crossfade_secs: 0.0,
+            mirror_channels: false,
         }
     }
 }
@@ -100,6 +486,7 @@ impl VisualizerConfig {
         c.up_samples = 180;
         c.up_freq_curve_exponent = 3.2;
         c.up_smoothing = 0.22;
+        c.up_fill_occlusion = true;
 
         // Reduce other visual distractions
         c.pulse_on_beat = false;
@@ -107,6 +494,86 @@ impl VisualizerConfig {
         c
     }
 
+    /// Take on the look of `preset` (a built-in, a saved preset or the
+    /// defaults) while keeping the MIDI mapping, crossfade, idle timeout and
+    /// frame-rate cap, which belong to this setup rather than to a look.
+    pub fn apply_preset(&mut self, preset: VisualizerConfig) {
+        let kept = std::mem::take(self);
+        *self = VisualizerConfig {
+            midi_cc_map: kept.midi_cc_map,
+            crossfade_secs: kept.crossfade_secs,
+            idle_timeout_secs: kept.idle_timeout_secs,
+            max_fps: kept.max_fps,
+            ..preset
+        };
+    }
+
+    /// Set the fractal, background and Unknown Pleasures gradient colours from
+    /// `palette`. Colour cycling is turned off so the palette stays visible.
+    pub fn apply_palette(&mut self, palette: Palette) {
+        let (main, accent, sat) = palette.hues();
+        self.base_color = hsl_to_rgb(main, sat, 0.65);
+        self.accent_color = hsl_to_rgb(accent, sat, 0.6);
+        self.background_color = hsl_to_rgb(main, sat * 0.5, 0.05);
+        self.background_top = hsl_to_rgb(accent, sat * 0.5, 0.12);
+        self.background_bottom = hsl_to_rgb(main, sat * 0.5, 0.03);
+        self.up_gradient_top = hsl_to_rgb(accent, sat, 0.55);
+        self.up_gradient_bottom = hsl_to_rgb(main, sat, 0.55);
+        self.color_cycle = false;
+    }
+
+    /// `up_max_lines`, at least `MIN_UP_LINES` whatever a preset or share string says.
+    pub fn up_line_count(&self) -> usize {
+        self.up_max_lines.max(MIN_UP_LINES) as usize
+    }
+
+    /// `up_samples`, at least `MIN_UP_SAMPLES` so lines never divide by zero.
+    pub fn up_sample_count(&self) -> usize {
+        self.up_samples.max(MIN_UP_SAMPLES) as usize
+    }
+
+    /// Pull every setting that has a range in the UI back inside it, so a
+    /// hand-edited share string can't ask for more than the controls allow.
+    pub fn clamp_to_ui_ranges(&mut self) {
+        self.crossfade_secs = self.crossfade_secs.clamp(0.0, 12.0);
+        self.auto_gain_strength = self.auto_gain_strength.clamp(0.0, 1.0);
+        self.noise_gate = self.noise_gate.clamp(0.0, 0.2);
+        self.band_smoothing = self.band_smoothing.clamp(0.0, 1.0);
+        self.band_edges_hz = sorted_band_edges(self.band_edges_hz);
+        self.base_depth = self.base_depth.clamp(1, MAX_BASE_DEPTH);
+        self.fractal_branches = self.fractal_branches.clamp(MIN_BRANCHES, MAX_BRANCHES);
+        self.fractal_spread_deg = self.fractal_spread_deg.clamp(0.0, 360.0);
+        self.fractal_depth_hue_shift = self.fractal_depth_hue_shift.clamp(-0.25, 0.25);
+        self.strobe_intensity = self.strobe_intensity.clamp(0.0, MAX_STROBE_INTENSITY);
+        self.trail_amount = self.trail_amount.clamp(0.0, 0.95);
+        self.spectrum_bars = self.spectrum_bars.clamp(4, 256);
+        self.spectrum_peak_decay = self.spectrum_peak_decay.clamp(0.0, 10.0);
+        self.spectrum_smoothing = self.spectrum_smoothing.clamp(0.0, 0.95);
+        self.waveform_smoothing = self.waveform_smoothing.clamp(0.0, 0.95);
+        self.max_fps = self.max_fps.clamp(10, 240);
+        self.idle_timeout_secs = self.idle_timeout_secs.clamp(0.0, 3600.0);
+        self.up_max_lines = self.up_max_lines.max(MIN_UP_LINES);
+        self.up_samples = self.up_samples.max(MIN_UP_SAMPLES);
+    }
+
+    /// Compact text form of the whole config for pasting into chat or a
+    /// forum: `SHARE_PREFIX` followed by the URL-safe base64 of its JSON.
+    pub fn to_share_string(&self) -> String {
+        let json = serde_json::to_string(self).unwrap_or_default();
+        format!("{SHARE_PREFIX}{}", URL_SAFE_NO_PAD.encode(json))
+    }
+
+    /// Parse a string made by [`Self::to_share_string`]. Surrounding
+    /// whitespace is ignored; anything else that doesn't decode to a config
+    /// is rejected with a message for the UI.
+    pub fn from_share_string(s: &str) -> Result<Self, String> {
+        let encoded = s.trim().strip_prefix(SHARE_PREFIX).ok_or("Not a visualizer config string")?;
+        let json = URL_SAFE_NO_PAD.decode(encoded).map_err(|_| "Config string is damaged or incomplete")?;
+        let mut config: Self = serde_json::from_slice(&json).map_err(|e| format!("Config string is invalid: {e}"))?;
+        config.clamp_to_ui_ranges();
+        Ok(config)
+    }
+
     /// Reset only the fractal-related parameters to their default values
     pub fn reset_fractal_to_default(&mut self) {
         let d = VisualizerConfig::default();
@@ -114,11 +581,204 @@ impl VisualizerConfig {
         self.base_width = d.base_width;
         self.base_depth = d.base_depth;
         self.base_brightness = d.base_brightness;
+        self.fractal_branches = d.fractal_branches;
+        self.fractal_spread_deg = d.fractal_spread_deg;
+        self.fractal_depth_hue_shift = d.fractal_depth_hue_shift;
 
-        self.zoom_bass_mult = d.zoom_bass_mult;
-        self.width_bass_mult = d.width_bass_mult;
-        self.depth_complexity_mult = d.depth_complexity_mult;
-        self.brightness_treble_mult = d.brightness_treble_mult;
+        self.reactivity = d.reactivity;
         self.rotation_beat_mult = d.rotation_beat_mult;
     }
 }
+
+/// Built-in looks offered in the Presets section, ahead of the user's own.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VisualizerPreset {
+    Classic,
+    EmberTree,
+    NeonSnowflake,
+    OceanFern,
+    MonoSierpinski,
+    UnknownPleasuresImage,
+    UnknownPleasuresNeon,
+}
+
+impl VisualizerPreset {
+    pub const ALL: [VisualizerPreset; 7] = [
+        VisualizerPreset::Classic, VisualizerPreset::EmberTree, VisualizerPreset::NeonSnowflake, VisualizerPreset::OceanFern,
+        VisualizerPreset::MonoSierpinski, VisualizerPreset::UnknownPleasuresImage, VisualizerPreset::UnknownPleasuresNeon,
+    ];
+    pub fn label(self) -> &'static str {
+        match self {
+            VisualizerPreset::Classic => "Classic",
+            VisualizerPreset::EmberTree => "Ember tree",
+            VisualizerPreset::NeonSnowflake => "Neon snowflake",
+            VisualizerPreset::OceanFern => "Ocean fern",
+            VisualizerPreset::MonoSierpinski => "Mono Sierpinski",
+            VisualizerPreset::UnknownPleasuresImage => "Unknown Pleasures: image",
+            VisualizerPreset::UnknownPleasuresNeon => "Unknown Pleasures: neon",
+        }
+    }
+
+    pub fn config(self) -> VisualizerConfig {
+        let mut c = VisualizerConfig::default();
+        match self {
+            VisualizerPreset::Classic => {}
+            VisualizerPreset::EmberTree => {
+                c.apply_palette(Palette::Fire);
+                c.base_depth = 12;
+                c.trail_amount = 0.6;
+                c.glow_intensity = 0.8;
+                c.rotation_speed = 0.5;
+            }
+            VisualizerPreset::NeonSnowflake => {
+                c.apply_palette(Palette::Neon);
+                c.fractal_kind = FractalKind::Koch;
+                c.base_depth = 4;
+                c.glow_intensity = 1.2;
+                c.particle_shape = ParticleShape::Star;
+            }
+            VisualizerPreset::OceanFern => {
+                c.apply_palette(Palette::Ocean);
+                c.fractal_kind = FractalKind::FractalFern;
+                c.trail_amount = 0.3;
+                c.auto_rotate = false;
+                c.particle_gravity = Vec2::new(0.0, 60.0);
+            }
+            VisualizerPreset::MonoSierpinski => {
+                c.apply_palette(Palette::Mono);
+                c.fractal_kind = FractalKind::Sierpinski;
+                c.glow_intensity = 0.0;
+                c.pulse_on_beat = false;
+            }
+            VisualizerPreset::UnknownPleasuresImage => c = VisualizerConfig::preset_unknown_pleasures_image(),
+            VisualizerPreset::UnknownPleasuresNeon => {
+                c = VisualizerConfig::preset_unknown_pleasures_image();
+                c.apply_palette(Palette::Neon);
+                c.up_monochrome = false;
+                c.up_gradient = true;
+                c.up_mel_scale = true;
+            }
+        }
+        c
+    }
+}
+
+/// Every built-in preset with its display name, in menu order.
+pub fn presets() -> Vec<(&'static str, VisualizerConfig)> {
+    VisualizerPreset::ALL.iter().map(|p| (p.label(), p.config())).collect()
+}
+
+/// A config the user saved under a name, listed after the built-in presets.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CustomPreset {
+    pub name: String,
+    pub config: VisualizerConfig,
+}
+
+/// File the custom presets live in: `.music-visualizer/presets.json` under
+/// `%APPDATA%` on Windows and the home directory elsewhere.
+pub fn custom_presets_path() -> PathBuf {
+    let base = std::env::var_os("APPDATA").or_else(|| std::env::var_os("HOME")).map(PathBuf::from).unwrap_or_default();
+    base.join(".music-visualizer").join("presets.json")
+}
+
+/// Custom presets saved at `path`; a missing or unreadable file yields none.
+pub fn load_custom_presets(path: &Path) -> Vec<CustomPreset> {
+    fs::read_to_string(path).ok().and_then(|json| serde_json::from_str(&json).ok()).unwrap_or_default()
+}
+
+pub fn save_custom_presets(path: &Path, presets: &[CustomPreset]) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(presets)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn share_string_values_are_clamped() {
+        let config = VisualizerConfig {
+            base_depth: 999,
+            up_samples: 0,
+            up_max_lines: 0,
+            strobe_intensity: 5.0,
+            fractal_branches: 40,
+            ..Default::default()
+        };
+        let back = VisualizerConfig::from_share_string(&config.to_share_string()).unwrap();
+        assert_eq!(back.base_depth, MAX_BASE_DEPTH);
+        assert_eq!((back.up_samples, back.up_max_lines), (MIN_UP_SAMPLES, MIN_UP_LINES));
+        assert_eq!(back.strobe_intensity, MAX_STROBE_INTENSITY);
+        assert_eq!(back.fractal_branches, MAX_BRANCHES);
+    }
+
+    #[test]
+    fn band_settings_travel_in_share_strings() {
+        let config = VisualizerConfig { band_smoothing: 0.4, band_edges_hz: [30.0, 200.0, 600.0, 1500.0, 5000.0, 16_000.0], a_weighting: true, ..Default::default() };
+        let back = VisualizerConfig::from_share_string(&config.to_share_string()).unwrap();
+        assert_eq!(back.band_smoothing, 0.4);
+        assert_eq!(back.band_edges_hz, config.band_edges_hz);
+        assert!(back.a_weighting);
+        // strings from before the fields existed fall back to the defaults
+        let old = format!("{SHARE_PREFIX}{}", URL_SAFE_NO_PAD.encode(r#"{"base_depth":8}"#));
+        let back = VisualizerConfig::from_share_string(&old).unwrap();
+        assert_eq!((back.base_depth, back.band_smoothing, back.band_edges_hz), (8, 0.15, DEFAULT_BAND_EDGES_HZ));
+        assert!(!back.a_weighting);
+    }
+
+    #[test]
+    fn reactivity_offset_sums_the_entries_for_a_target() {
+        let mut audio = AudioAnalysis::new();
+        (audio.smooth_bass, audio.smooth_treble, audio.spectral_centroid) = (0.5, 0.25, 0.2);
+        let map = ReactivityMap::default();
+        assert!((map.offset(ConfigParam::Zoom, &audio) - 0.05).abs() < 1e-6);
+        assert!((map.offset(ConfigParam::Width, &audio) - 0.15).abs() < 1e-6);
+        assert!((map.offset(ConfigParam::Depth, &audio) - 0.8).abs() < 1e-6);
+        assert!((map.offset(ConfigParam::Brightness, &audio) - 0.1).abs() < 1e-6);
+
+        let entry = |source, multiplier| ReactivityEntry { source, target: ConfigParam::Zoom, multiplier };
+        let map = ReactivityMap { entries: vec![entry(AudioFeature::Bass, 2.0), entry(AudioFeature::Treble, -1.0)] };
+        assert!((map.offset(ConfigParam::Zoom, &audio) - 0.75).abs() < 1e-6);
+        assert_eq!(map.offset(ConfigParam::Depth, &audio), 0.0);
+    }
+
+    #[test]
+    fn presets_keep_the_setup_settings() {
+        let mut config = VisualizerConfig {
+            midi_cc_map: vec![MidiCcMapping { controller: 7, target: MidiTarget::ParticleCount }],
+            crossfade_secs: 4.0,
+            idle_timeout_secs: 5.0,
+            max_fps: 30,
+            ..Default::default()
+        };
+        config.apply_preset(VisualizerPreset::NeonSnowflake.config());
+        assert_eq!(config.fractal_kind, FractalKind::Koch);
+        assert_eq!(config.midi_cc_map, vec![MidiCcMapping { controller: 7, target: MidiTarget::ParticleCount }]);
+        assert_eq!((config.crossfade_secs, config.idle_timeout_secs, config.max_fps), (4.0, 5.0, 30));
+        config.apply_preset(VisualizerConfig::default());
+        assert_eq!(config.fractal_kind, FractalKind::BinaryTree);
+        assert_eq!(config.crossfade_secs, 4.0);
+    }
+
+    #[test]
+    fn custom_presets_round_trip_through_their_file() {
+        let dir = std::env::temp_dir().join(format!("music-visualizer-presets-test-{}", std::process::id()));
+        let path = dir.join("nested").join("presets.json");
+        assert!(load_custom_presets(&path).is_empty());
+        let presets = vec![
+            CustomPreset { name: "Mine".into(), config: VisualizerPreset::EmberTree.config() },
+            CustomPreset { name: "Lines".into(), config: VisualizerConfig::preset_unknown_pleasures_image() },
+        ];
+        save_custom_presets(&path, &presets).unwrap();
+        let loaded = load_custom_presets(&path);
+        let _ = fs::remove_dir_all(&dir);
+        assert_eq!(loaded.len(), 2);
+        for (saved, back) in presets.iter().zip(&loaded) {
+            assert_eq!(saved.name, back.name);
+            assert_eq!(saved.config.to_share_string(), back.config.to_share_string());
+        }
+    }
+}
@@ -1,8 +1,67 @@
 use std::cell::RefCell;
+use std::collections::VecDeque;
 use std::rc::Rc;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
 
+/// Number of bass energy frames kept for adaptive beat detection (~1s at 43 fps).
+pub const BEAT_HISTORY_LEN: usize = 43;
+/// Seconds of beat history used for tempo estimation.
+pub const BPM_WINDOW_SECS: f64 = 10.0;
+// beats closer together than this (200 BPM) are treated as one onset
+const MIN_BEAT_INTERVAL_SECS: f64 = 0.3;
+/// Classic band boundaries in Hz: bass | low-mid | mid | high-mid | treble.
+pub const DEFAULT_BAND_EDGES_HZ: [f32; 6] = [20.0, 250.0, 500.0, 2000.0, 4000.0, 20_000.0];
+
+/// `edges` clamped to non-negative and sorted, so each band always covers a
+/// non-negative range.
+pub fn sorted_band_edges(mut edges: [f32; 6]) -> [f32; 6] {
+    for e in &mut edges {
+        *e = e.max(0.0);
+    }
+    edges.sort_by(f32::total_cmp);
+    edges
+}
+
+// Auto gain envelope: fraction of the gap closed per 60 Hz frame when the level rises / falls.
+// Fast attack and slow release keep loud hits from pumping the gain.
+const AUTO_GAIN_ATTACK: f32 = 0.5;
+const AUTO_GAIN_RELEASE: f32 = 0.005;
+// Level the envelope is scaled to, and the smallest envelope divided by (so silence isn't amplified)
+const AUTO_GAIN_TARGET: f32 = 0.7;
+const AUTO_GAIN_FLOOR: f32 = 0.05;
+
+// The demo's own smoothing; its synthetic bands are already smooth, so it stays put
+// whatever `smoothing` is set to for real input
+const DEMO_SMOOTHING: f32 = 0.1;
+
+// Byte spectrum range, matching the AnalyserNode's default min/max decibels
+const ANALYSER_MIN_DB: f32 = -100.0;
+const ANALYSER_MAX_DB: f32 = -30.0;
+
+/// A-weighting gain in dB at `hz` (IEC 61672), 0 dB at 1 kHz; models how
+/// much quieter the ear hears low and very high frequencies.
+pub fn a_weighting_db(hz: f32) -> f32 {
+    let f2 = (hz as f64).powi(2);
+    let ra = 12194f64.powi(2) * f2 * f2
+        / ((f2 + 20.6f64.powi(2))
+            * ((f2 + 107.7f64.powi(2)) * (f2 + 737.9f64.powi(2))).sqrt()
+            * (f2 + 12194f64.powi(2)));
+    (20.0 * ra.max(1e-10).log10() + 2.0) as f32
+}
+
+/// Root-mean-square level of an AnalyserNode time-domain buffer, whose bytes
+/// are centred on 128; 0.0 is silence and 1.0 a full-scale square wave.
+pub fn time_domain_rms(time_data: &[u8]) -> f32 {
+    let sum: f32 = time_data.iter()
+        .map(|&x| {
+            let centered = (x as f32) - 128.0;
+            centered * centered
+        })
+        .sum();
+    (sum / time_data.len().max(1) as f32).sqrt() / 128.0
+}
+
 // Audio analysis data extracted from Web Audio API
 #[derive(Clone, Default)]
 pub struct AudioAnalysis {
@@ -20,6 +79,11 @@ pub struct AudioAnalysis {
     // Beat detection
     pub beat: bool,        // True when beat detected
     pub beat_intensity: f32,
+    pub beat_sensitivity: f32,   // bass must exceed rolling mean by this factor
+    smoothing: f32,              // fraction of the gap each `smooth_*` value closes per frame (0..1)
+    energy_history: VecDeque<f32>, // ring buffer of recent bass energies
+    pub bpm: f32,                  // estimated tempo (0 until enough beats)
+    beat_times: VecDeque<f64>,     // recent beat onset timestamps
 
     // Spectral features
     pub spectral_centroid: f32,
@@ -34,6 +98,28 @@ pub struct AudioAnalysis {
     // Raw frequency data
     pub frequency_data: Vec<u8>,
     pub time_data: Vec<u8>,
+
+    // Band configuration: sample rate of the analysed stream and the
+    // ascending Hz boundaries of the five bands
+    pub sample_rate: f32,
+    band_edges_hz: [f32; 6],
+
+    // Weight bins by the A-curve before band averages and volume (perceived loudness)
+    pub a_weighting: bool,
+    // Per-bin A-weighting in dB, rebuilt when the bin count or sample rate changes
+    a_weights_db: Vec<f32>,
+    a_weights_rate: f32,
+
+    // Normalize bands against a slow peak envelope so quiet and loud sources react alike;
+    // `auto_gain_strength` blends from raw (0) to fully normalized (1)
+    pub auto_gain: bool,
+    pub auto_gain_strength: f32,
+    gain_envelope: f32,
+    // `time` of the last envelope update, for scaling its rates by the frame time
+    gain_time: Option<f64>,
+
+    // Phase offsets of the demo signal's band wobbles, derived from the demo seed
+    demo_phases: [f64; 5],
 }
 
 impl AudioAnalysis {
@@ -41,11 +127,171 @@ impl AudioAnalysis {
         Self {
             frequency_data: vec![0u8; 256],
             time_data: vec![0u8; 256],
+            sample_rate: 44_100.0,
+            band_edges_hz: DEFAULT_BAND_EDGES_HZ,
+            beat_sensitivity: 1.4,
+            smoothing: 0.15,
+            energy_history: VecDeque::with_capacity(BEAT_HISTORY_LEN),
             ..Default::default()
         }
     }
 
-    pub fn update_from_fft(&mut self, frequency_data: &[u8], time_data: &[u8]) {
+    /// Shift the demo signal's band wobbles by phases derived from `seed`, so
+    /// different seeds give different (but repeatable) demos. Seed 0 is the
+    /// classic demo.
+    pub fn set_demo_seed(&mut self, seed: u64) {
+        let mut state = seed;
+        for phase in &mut self.demo_phases {
+            if seed == 0 {
+                *phase = 0.0;
+                continue;
+            }
+            // SplitMix64 step
+            state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            z ^= z >> 31;
+            *phase = z as f64 / u64::MAX as f64 * std::f64::consts::TAU;
+        }
+    }
+
+    /// Set how far each `smooth_*` value closes the gap to the new level per
+    /// frame, clamped to 0..1: lower is sluggish and smooth, higher is snappy.
+    pub fn set_smoothing(&mut self, smoothing: f32) {
+        self.smoothing = smoothing.clamp(0.0, 1.0);
+    }
+
+    /// Set the Hz boundaries of the five analysis bands. Edges are sorted so
+    /// each band always covers a non-negative range.
+    pub fn set_band_edges(&mut self, edges: [f32; 6]) {
+        self.band_edges_hz = sorted_band_edges(edges);
+    }
+
+    /// Convert a frequency in Hz to a bin index for a spectrum of `bin_count`
+    /// bins (the analyser's FFT size is twice the bin count).
+    pub fn hz_to_bin(&self, hz: f32, bin_count: usize) -> usize {
+        let sample_rate = if self.sample_rate > 0.0 { self.sample_rate } else { 44_100.0 };
+        let fft_size = (bin_count * 2) as f32;
+        ((hz * fft_size / sample_rate).round().max(0.0) as usize).min(bin_count)
+    }
+
+    /// Follow the loudest band with a fast-attack, slow-release envelope and
+    /// scale `bands` so that envelope sits at `AUTO_GAIN_TARGET`. Bands pass
+    /// through untouched while auto gain is off.
+    fn apply_auto_gain(&mut self, bands: [f32; 5], time: f64) -> [f32; 5] {
+        // A stalled or restarted clock counts as one frame, at most a quarter second
+        let dt = self.gain_time.map_or(1.0 / 60.0, |last| (time - last).clamp(0.0, 0.25)) as f32;
+        self.gain_time = Some(time);
+        if !self.auto_gain {
+            return bands;
+        }
+        let level = bands.iter().copied().fold(0.0, f32::max);
+        let rate = if level > self.gain_envelope { AUTO_GAIN_ATTACK } else { AUTO_GAIN_RELEASE };
+        let rate = 1.0 - (1.0 - rate).powf(dt * 60.0);
+        self.gain_envelope += (level - self.gain_envelope) * rate;
+        let gain = AUTO_GAIN_TARGET / self.gain_envelope.max(AUTO_GAIN_FLOOR);
+        let strength = self.auto_gain_strength.clamp(0.0, 1.0);
+        bands.map(|b| b + ((b * gain).min(1.0) - b) * strength)
+    }
+
+    /// Adaptive beat detection: flag a beat when the instantaneous bass energy
+    /// exceeds the rolling mean by `beat_sensitivity` and stands out from the
+    /// recent variance, then push the energy into the history ring buffer.
+    fn detect_beat(&mut self, bass: f32) {
+        let n = self.energy_history.len();
+        if n >= BEAT_HISTORY_LEN / 2 {
+            let mean = self.energy_history.iter().sum::<f32>() / n as f32;
+            let variance = self.energy_history.iter().map(|e| (e - mean) * (e - mean)).sum::<f32>() / n as f32;
+            self.beat = bass > mean * self.beat_sensitivity && bass - mean > variance.sqrt();
+            self.beat_intensity = if self.beat { ((bass - mean) / mean.max(1e-3)).min(1.0) } else { 0.0 };
+        } else {
+            self.beat = false;
+            self.beat_intensity = 0.0;
+        }
+        if n >= BEAT_HISTORY_LEN {
+            self.energy_history.pop_front();
+        }
+        self.energy_history.push_back(bass);
+    }
+
+    /// Record beat onsets at `time` (seconds) and estimate the tempo from a
+    /// histogram of inter-beat intervals over the last `BPM_WINDOW_SECS`,
+    /// folded into the 60–200 BPM range. Reports 0 until enough beats arrive.
+    fn update_bpm(&mut self, time: f64) {
+        if self.beat && self.beat_times.back().is_none_or(|&t| time - t >= MIN_BEAT_INTERVAL_SECS) {
+            self.beat_times.push_back(time);
+        }
+        while self.beat_times.front().is_some_and(|&t| time - t > BPM_WINDOW_SECS) {
+            self.beat_times.pop_front();
+        }
+        if self.beat_times.len() < 4 {
+            self.bpm = 0.0;
+            return;
+        }
+        let candidates: Vec<f32> = self.beat_times.iter().zip(self.beat_times.iter().skip(1))
+            .map(|(a, b)| {
+                let mut bpm = (60.0 / (b - a)) as f32;
+                while bpm < 60.0 { bpm *= 2.0; }
+                while bpm > 200.0 { bpm /= 2.0; }
+                bpm
+            })
+            .collect();
+        // 1-BPM buckets; score each bucket by its +/-2 BPM neighbourhood
+        let mut histogram = [0u32; 141];
+        for bpm in &candidates {
+            histogram[(bpm.round() as usize).clamp(60, 200) - 60] += 1;
+        }
+        let score = |b: usize| histogram[b.saturating_sub(2)..(b + 3).min(histogram.len())].iter().sum::<u32>();
+        let best = (0..histogram.len()).max_by_key(|&b| score(b)).unwrap_or(0) as f32 + 60.0;
+        let near: Vec<f32> = candidates.into_iter().filter(|bpm| (bpm - best).abs() <= 2.5).collect();
+        self.bpm = if near.is_empty() { best } else { near.iter().sum::<f32>() / near.len() as f32 };
+    }
+
+    /// Precompute the A-weighting of each bin from its centre frequency.
+    fn refresh_a_weights(&mut self, bins: usize) {
+        if self.a_weights_db.len() == bins && self.a_weights_rate == self.sample_rate {
+            return;
+        }
+        let sample_rate = if self.sample_rate > 0.0 { self.sample_rate } else { 44_100.0 };
+        // Bins span 0..Nyquist
+        let bin_hz = sample_rate / (2 * bins.max(1)) as f32;
+        self.a_weights_db = (0..bins).map(|i| a_weighting_db(i as f32 * bin_hz)).collect();
+        self.a_weights_rate = self.sample_rate;
+    }
+
+    /// Per-bin weights for a 0..255 spectrum, in the byte scale. Bins are on a
+    /// dB scale, so adding the weight as an offset is the same as multiplying
+    /// the bin magnitude by the linear gain. All zero with A-weighting off.
+    fn byte_weights(&mut self, bins: usize) -> Vec<f32> {
+        if !self.a_weighting {
+            return vec![0.0; bins];
+        }
+        self.refresh_a_weights(bins);
+        let per_db = 255.0 / (ANALYSER_MAX_DB - ANALYSER_MIN_DB);
+        self.a_weights_db.iter().map(|w| w * per_db).collect()
+    }
+
+    /// Factor applied to the RMS volume with A-weighting on: the ratio of
+    /// weighted to unweighted spectral power, as an amplitude.
+    fn a_weighted_volume_gain(&mut self, spectrum: &[u8]) -> f32 {
+        if !self.a_weighting {
+            return 1.0;
+        }
+        self.refresh_a_weights(spectrum.len());
+        let power = |v: u8| {
+            let db = ANALYSER_MIN_DB + v as f32 / 255.0 * (ANALYSER_MAX_DB - ANALYSER_MIN_DB);
+            10f32.powf(db / 10.0)
+        };
+        let (mut total, mut weighted) = (0.0, 0.0);
+        for (&v, &w) in spectrum.iter().zip(&self.a_weights_db) {
+            total += power(v);
+            weighted += power(v) * 10f32.powf(w / 10.0);
+        }
+        if total > 0.0 { (weighted / total).sqrt() } else { 1.0 }
+    }
+
+    pub fn update_from_fft(&mut self, frequency_data: &[u8], time_data: &[u8], time: f64) {
         self.frequency_data = frequency_data.to_vec();
         self.time_data = time_data.to_vec();
 
@@ -54,35 +300,37 @@ impl AudioAnalysis {
             return;
         }
 
-        // Calculate frequency bands
-        let bass_range = 0..len / 16;
-        let low_mid_range = len / 16..len / 8;
-        let mid_range = len / 8..len / 4;
-        let high_mid_range = len / 4..len / 2;
-        let treble_range = len / 2..len;
+        // Calculate frequency bands from the configured Hz boundaries
+        let e = self.band_edges_hz.map(|hz| self.hz_to_bin(hz, len));
+        let bass_range = e[0]..e[1];
+        let low_mid_range = e[1]..e[2];
+        let mid_range = e[2]..e[3];
+        let high_mid_range = e[3]..e[4];
+        let treble_range = e[4]..e[5];
 
+        let weights = self.byte_weights(len);
         let calc_band_avg = |range: std::ops::Range<usize>| -> f32 {
             if range.is_empty() {
                 return 0.0;
             }
-            let sum: u32 = frequency_data[range.clone()].iter().map(|&x| x as u32).sum();
-            (sum as f32) / (range.len() as f32 * 255.0)
+            let sum: f32 = frequency_data[range.clone()]
+                .iter()
+                .zip(&weights[range.clone()])
+                .map(|(&x, w)| (x as f32 + w).clamp(0.0, 255.0))
+                .sum();
+            sum / (range.len() as f32 * 255.0)
         };
 
-        let new_bass = calc_band_avg(bass_range);
-        let new_low_mid = calc_band_avg(low_mid_range);
-        let new_mid = calc_band_avg(mid_range);
-        let new_high_mid = calc_band_avg(high_mid_range);
-        let new_treble = calc_band_avg(treble_range);
+        let [new_bass, new_low_mid, new_mid, new_high_mid, new_treble] = self.apply_auto_gain([
+            calc_band_avg(bass_range),
+            calc_band_avg(low_mid_range),
+            calc_band_avg(mid_range),
+            calc_band_avg(high_mid_range),
+            calc_band_avg(treble_range),
+        ], time);
 
         // Calculate volume (RMS)
-        let rms: f32 = (time_data.iter()
-            .map(|&x| {
-                let centered = (x as f32) - 128.0;
-                centered * centered
-            })
-            .sum::<f32>() / time_data.len() as f32)
-            .sqrt() / 128.0;
+        let rms = time_domain_rms(time_data) * self.a_weighted_volume_gain(frequency_data);
 
         // Peak detection
         let peak = time_data.iter()
@@ -90,11 +338,9 @@ impl AudioAnalysis {
             .max_by(|a, b| a.partial_cmp(b).unwrap())
             .unwrap_or(0.0) / 128.0;
 
-        // Beat detection (energy spike in bass)
-        let bass_threshold = 0.6;
-        let energy_jump = new_bass - self.smooth_bass;
-        self.beat = energy_jump > 0.1 && new_bass > bass_threshold;
-        self.beat_intensity = if self.beat { energy_jump.min(1.0) } else { 0.0 };
+        // Beat detection against the rolling bass energy history
+        self.detect_beat(new_bass);
+        self.update_bpm(time);
 
         // Spectral centroid (brightness)
         let total_energy: f32 = frequency_data.iter().map(|&x| x as f32).sum();
@@ -117,7 +363,7 @@ impl AudioAnalysis {
         self.spectral_flux = flux;
 
         // Smooth transitions
-        let smoothing = 0.15;
+        let smoothing = self.smoothing.clamp(0.0, 1.0);
         self.smooth_bass = self.smooth_bass + (new_bass - self.smooth_bass) * smoothing;
         self.smooth_mid = self.smooth_mid + (new_mid - self.smooth_mid) * smoothing;
         self.smooth_treble = self.smooth_treble + (new_treble - self.smooth_treble) * smoothing;
@@ -133,30 +379,43 @@ impl AudioAnalysis {
         self.peak = peak;
     }
 
+    /// Feed a silent frame so levels fall off smoothly, e.g. while a
+    /// source that cannot be analysed is paused.
+    pub fn simulate_silence(&mut self, time: f64) {
+        let frequency_data = vec![0u8; self.frequency_data.len().max(1)];
+        let time_data = vec![128u8; self.time_data.len().max(1)];
+        self.update_from_fft(&frequency_data, &time_data, time);
+    }
+
     // Demo mode with simulated audio
-    pub fn simulate_demo(&mut self, time: f64) {
+    /// Fill every field with a synthetic signal: a kick at `bpm` whose level is
+    /// scaled by `bass_intensity`, over wobbling mid and treble bands scaled by
+    /// `treble_intensity`. 120 BPM at intensity 1 is the classic demo.
+    pub fn simulate_demo(&mut self, time: f64, bpm: f32, bass_intensity: f32, treble_intensity: f32) {
         // Simulate bass beat
-        let beat_freq = 2.0; // BPM / 60
+        let beat_freq = bpm.max(1.0) as f64 / 60.0;
         let beat_phase = (time * beat_freq * std::f64::consts::TAU).sin();
         let beat_envelope = ((beat_phase + 1.0) / 2.0).powf(4.0) as f32;
 
-        self.bass = 0.3 + beat_envelope * 0.5;
-        self.low_mid = 0.25 + (time * 1.5).sin() as f32 * 0.15;
-        self.mid = 0.3 + (time * 2.3).sin() as f32 * 0.2;
-        self.high_mid = 0.2 + (time * 3.7).sin() as f32 * 0.15;
-        self.treble = 0.15 + (time * 5.1).sin() as f32 * 0.1;
+        self.bass = (0.3 + beat_envelope * 0.5 * bass_intensity).clamp(0.0, 1.0);
+        let [p0, p1, p2, p3, p4] = self.demo_phases;
+        self.low_mid = 0.25 + (time * 1.5 + p0).sin() as f32 * 0.15;
+        self.mid = 0.3 + (time * 2.3 + p1).sin() as f32 * 0.2;
+        self.high_mid = (0.2 + (time * 3.7 + p2).sin() as f32 * 0.15) * treble_intensity;
+        self.treble = (0.15 + (time * 5.1 + p3).sin() as f32 * 0.1) * treble_intensity;
 
         self.volume = 0.4 + beat_envelope * 0.3;
         self.peak = self.volume * 1.2;
 
         self.beat = beat_envelope > 0.8;
         self.beat_intensity = if self.beat { beat_envelope } else { 0.0 };
+        self.update_bpm(time);
 
-        self.spectral_centroid = 0.5 + (time * 0.5).sin() as f32 * 0.3;
+        self.spectral_centroid = 0.5 + (time * 0.5 + p4).sin() as f32 * 0.3;
         self.spectral_flux = beat_envelope * 0.5;
 
         // Smooth values
-        let smoothing = 0.1;
+        let smoothing = DEMO_SMOOTHING;
         self.smooth_bass = self.smooth_bass + (self.bass - self.smooth_bass) * smoothing;
         self.smooth_mid = self.smooth_mid + (self.mid - self.smooth_mid) * smoothing;
         self.smooth_treble = self.smooth_treble + (self.treble - self.smooth_treble) * smoothing;
@@ -166,7 +425,7 @@ impl AudioAnalysis {
         for i in 0..self.frequency_data.len() {
             let freq_norm = i as f64 / self.frequency_data.len() as f64;
             let value = ((1.0 - freq_norm).powf(2.0) * self.bass as f64 * 200.0
-                + (time * (10.0 + i as f64 * 0.5)).sin().abs() * 50.0) as u8;
+                + (time * (10.0 + i as f64 * 0.5)).sin().abs() * 50.0 * (treble_intensity as f64 * freq_norm + 1.0 - freq_norm)) as u8;
             self.frequency_data[i] = value;
         }
 
@@ -179,14 +438,41 @@ impl AudioAnalysis {
     }
 }
 
+/// Byte spectrum of `len` bins that is silent apart from the given
+/// `(bin, magnitude)` peaks, for feeding `update_from_fft` known energy in
+/// tests. Bins past the end are ignored.
+#[cfg(test)]
+pub fn synthetic_spectrum(len: usize, peaks: &[(usize, u8)]) -> Vec<u8> {
+    let mut spectrum = vec![0u8; len];
+    for &(bin, magnitude) in peaks {
+        if let Some(slot) = spectrum.get_mut(bin) {
+            *slot = magnitude;
+        }
+    }
+    spectrum
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn bass_only_spectrum_lands_in_bass() {
+        const BINS: usize = 1024;
+        let mut a = AudioAnalysis::new();
+        let bass_bins = a.hz_to_bin(DEFAULT_BAND_EDGES_HZ[0], BINS)..a.hz_to_bin(DEFAULT_BAND_EDGES_HZ[1], BINS);
+        let peaks: Vec<(usize, u8)> = bass_bins.map(|bin| (bin, 230)).collect();
+        let spectrum = synthetic_spectrum(BINS, &peaks);
+        a.update_from_fft(&spectrum, &[128u8; 2048], 0.0);
+        assert!(a.bass > 0.85, "bass {}", a.bass);
+        assert!(a.treble < 0.01, "treble {}", a.treble);
+        assert!(a.mid < 0.01 && a.high_mid < 0.01, "mid {} high mid {}", a.mid, a.high_mid);
+    }
+
     #[test]
     fn simulate_demo_updates_fields() {
         let mut a = AudioAnalysis::new();
-        a.simulate_demo(0.1);
+        a.simulate_demo(0.1, 120.0, 1.0, 1.0);
         // After simulation some fields should be non-zero and within expected ranges
         assert!(a.bass >= 0.0 && a.bass <= 1.0);
         assert!(a.volume >= 0.0 && a.volume <= 1.5);
@@ -194,14 +480,64 @@ mod tests {
         assert!(a.time_data.len() > 0);
     }
 
+    #[test]
+    fn demo_seed_is_repeatable() {
+        let demo = |seed: Option<u64>| {
+            let mut a = AudioAnalysis::new();
+            if let Some(seed) = seed {
+                a.set_demo_seed(seed);
+            }
+            a.simulate_demo(1.3, 120.0, 1.0, 1.0);
+            (a.mid, a.treble, a.spectral_centroid)
+        };
+        assert_eq!(demo(Some(7)), demo(Some(7)));
+        assert_ne!(demo(Some(7)), demo(Some(8)));
+        // Seed 0 is the unseeded classic demo
+        assert_eq!(demo(Some(0)), demo(None));
+    }
+
+    #[test]
+    fn faster_demo_bpm_gives_more_beats() {
+        let beats_in_ten_seconds = |bpm: f32| {
+            let mut a = AudioAnalysis::new();
+            let mut count = 0;
+            let mut was_beat = false;
+            for frame in 0..600 {
+                a.simulate_demo(frame as f64 / 60.0, bpm, 1.0, 1.0);
+                if a.beat && !was_beat {
+                    count += 1;
+                }
+                was_beat = a.beat;
+            }
+            count
+        };
+
+        let slow = beats_in_ten_seconds(60.0);
+        let fast = beats_in_ten_seconds(180.0);
+        assert!((9..=11).contains(&slow), "{slow} beats at 60 BPM");
+        assert!(fast > slow * 2, "{fast} beats at 180 BPM vs {slow} at 60");
+    }
+
     #[test]
     fn update_from_fft_handles_empty() {
         let mut a = AudioAnalysis::new();
         // Should not panic on empty slices
-        a.update_from_fft(&[], &[]);
+        a.update_from_fft(&[], &[], 0.0);
         assert_eq!(a.frequency_data.len(), 0);
         assert_eq!(a.time_data.len(), 0);
     }
+
+    #[test]
+    fn demo_keeps_its_own_smoothing() {
+        let mut a = AudioAnalysis::new();
+        a.set_smoothing(1.0);
+        a.update_from_fft(&[180u8; 256], &[128u8; 512], 0.0);
+        let before = a.smooth_bass;
+        a.simulate_demo(1.0, 120.0, 1.0, 1.0);
+        assert!((a.smooth_bass - (before + (a.bass - before) * DEMO_SMOOTHING)).abs() < 1e-6);
+        a.set_smoothing(3.0);
+        assert_eq!(a.smoothing, 1.0);
+    }
 }
 
 // Web Audio wrapper (placeholder for future expansion)
@@ -221,8 +557,70 @@ impl Default for WebAudio {
     }
 }
 
-// Initialize Web Audio API
-pub async fn init_web_audio(audio_data: Rc<RefCell<(Vec<u8>, Vec<u8>)>>) -> Result<(), JsValue> {
+/// FFT sizes offered in the settings; the AnalyserNode accepts powers of two.
+pub const FFT_SIZES: [u32; 4] = [256, 512, 1024, 2048];
+
+/// AnalyserNode settings shared by the microphone and file-playback paths.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WebAudioConfig {
+    pub fft_size: u32,
+    pub smoothing_time_constant: f64,
+    pub poll_interval_ms: i32, // How often the byte spectrum is copied out
+}
+
+impl Default for WebAudioConfig {
+    fn default() -> Self {
+        Self {
+            fft_size: 512,
+            smoothing_time_constant: 0.8,
+            poll_interval_ms: 16, // ~60fps
+        }
+    }
+}
+
+impl WebAudioConfig {
+    /// Push the FFT size and smoothing to a live analyser. Poll buffers follow
+    /// the new size on their next tick.
+    pub fn apply(&self, analyser: &web_sys::AnalyserNode) {
+        analyser.set_fft_size(self.fft_size);
+        analyser.set_smoothing_time_constant(self.smoothing_time_constant);
+    }
+}
+
+/// Copy `analyser`'s frequency and time data into `audio_data` every
+/// `interval_ms`. Buffers are sized from the analyser on each tick, so a
+/// changed FFT size takes effect without restarting the poll.
+pub fn start_analyser_polling(
+    window: &web_sys::Window,
+    analyser: Rc<RefCell<Option<web_sys::AnalyserNode>>>,
+    audio_data: Rc<RefCell<(Vec<u8>, Vec<u8>)>>,
+    interval_ms: i32,
+) {
+    let callback = Closure::wrap(Box::new(move || {
+        if let Some(ref analyser) = *analyser.borrow() {
+            let mut freq_data = vec![0u8; analyser.frequency_bin_count() as usize];
+            let mut time_data = vec![0u8; analyser.fft_size() as usize];
+
+            analyser.get_byte_frequency_data(&mut freq_data);
+            analyser.get_byte_time_domain_data(&mut time_data);
+
+            *audio_data.borrow_mut() = (freq_data, time_data);
+        }
+    }) as Box<dyn Fn()>);
+
+    window.set_interval_with_callback_and_timeout_and_arguments_0(
+        callback.as_ref().unchecked_ref(),
+        interval_ms,
+    ).ok();
+    callback.forget(); // Leak the closure to keep it alive
+}
+
+// Initialize Web Audio API; the microphone analyser is stored in `analyser_slot`
+pub async fn init_web_audio(
+    audio_data: Rc<RefCell<(Vec<u8>, Vec<u8>)>>,
+    analyser_slot: Rc<RefCell<Option<web_sys::AnalyserNode>>>,
+    config: WebAudioConfig,
+) -> Result<(), JsValue> {
     let window = web_sys::window().ok_or("No window")?;
     let navigator = window.navigator();
     let media_devices = navigator.media_devices()?;
@@ -238,41 +636,14 @@ pub async fn init_web_audio(audio_data: Rc<RefCell<(Vec<u8>, Vec<u8>)>>) -> Resu
     // Create audio context and analyser
     let audio_ctx = web_sys::AudioContext::new()?;
     let analyser = audio_ctx.create_analyser()?;
-    analyser.set_fft_size(512);
-    analyser.set_smoothing_time_constant(0.8);
+    config.apply(&analyser);
 
     let source = audio_ctx.create_media_stream_source(&stream)?;
     source.connect_with_audio_node(&analyser)?;
 
-    // Store analyser for later use (simplified - in production would use more robust pattern)
-    let freq_data_length = analyser.frequency_bin_count() as usize;
-    let time_data_length = analyser.fft_size() as usize;
-
-    // Set up animation frame callback
-    let audio_data_clone = audio_data.clone();
-    let analyser_clone = analyser.clone();
-
-    let callback = Closure::wrap(Box::new(move || {
-        let mut freq_data = vec![0u8; freq_data_length];
-        let mut time_data = vec![0u8; time_data_length];
-
-        analyser_clone.get_byte_frequency_data(&mut freq_data);
-        analyser_clone.get_byte_time_domain_data(&mut time_data);
-
-        *audio_data_clone.borrow_mut() = (freq_data, time_data);
-    }) as Box<dyn Fn()>);
-
-    // Start polling audio data
-    let window_clone = window.clone();
-    fn request_frame(window: &web_sys::Window, callback: &Closure<dyn Fn()>) {
-        window.set_interval_with_callback_and_timeout_and_arguments_0(
-            callback.as_ref().unchecked_ref(),
-            16, // ~60fps
-        ).ok();
-    }
-
-    request_frame(&window_clone, &callback);
-    callback.forget(); // Leak the closure to keep it alive
+    // Keep the analyser so settings changes can reconfigure it
+    *analyser_slot.borrow_mut() = Some(analyser);
+    start_analyser_polling(&window, analyser_slot, audio_data, config.poll_interval_ms);
 
     Ok(())
 }
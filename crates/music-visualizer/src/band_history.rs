@@ -0,0 +1,85 @@
+use std::collections::VecDeque;
+use eframe::egui::{self, Color32, Pos2, Stroke};
+use crate::audio::AudioAnalysis;
+
+/// Seconds of band history kept and plotted.
+pub const BAND_HISTORY_SECS: f64 = 5.0;
+// Height of each band's plot in the debug window
+const PLOT_HEIGHT: f32 = 48.0;
+
+const BANDS: [(&str, Color32); 3] = [
+    ("Bass", Color32::from_rgb(255, 110, 90)),
+    ("Mid", Color32::from_rgb(120, 220, 120)),
+    ("Treble", Color32::from_rgb(110, 170, 255)),
+];
+
+#[derive(Clone, Copy)]
+struct BandFrame {
+    time: f64,
+    raw: [f32; 3],
+    smooth: [f32; 3],
+    beat: bool,
+}
+
+/// Rolling record of the raw and smoothed bass/mid/treble levels and beats
+/// over the last `BAND_HISTORY_SECS`, for the band debug view.
+#[derive(Default)]
+pub struct BandHistory {
+    frames: VecDeque<BandFrame>,
+}
+
+impl BandHistory {
+    /// Record this frame's analysis at `time` and drop frames that have
+    /// scrolled out of the window.
+    pub fn push(&mut self, audio: &AudioAnalysis, time: f64) {
+        // A clock that went backwards leaves nothing worth plotting
+        if self.frames.back().is_some_and(|f| f.time > time) {
+            self.frames.clear();
+        }
+        self.frames.push_back(BandFrame {
+            time,
+            raw: [audio.bass, audio.mid, audio.treble],
+            smooth: [audio.smooth_bass, audio.smooth_mid, audio.smooth_treble],
+            beat: audio.beat,
+        });
+        while self.frames.front().is_some_and(|f| time - f.time > BAND_HISTORY_SECS) {
+            self.frames.pop_front();
+        }
+    }
+
+    /// One scrolling plot per band, newest on the right: the raw level faint,
+    /// the smoothed level bright, and a vertical marker wherever a beat fired.
+    pub fn draw(&self, ui: &mut egui::Ui) {
+        let now = self.frames.back().map_or(0.0, |f| f.time);
+        for (band, (label, color)) in BANDS.into_iter().enumerate() {
+            ui.label(label);
+            let (rect, _) = ui.allocate_exact_size(
+                egui::vec2(ui.available_width(), PLOT_HEIGHT),
+                egui::Sense::hover(),
+            );
+            let painter = ui.painter_at(rect);
+            painter.rect_filled(rect, 2.0, Color32::from_gray(20));
+            let to_screen = |time: f64, level: f32| {
+                let age = ((now - time) / BAND_HISTORY_SECS) as f32;
+                Pos2::new(rect.right() - age * rect.width(), rect.bottom() - level.clamp(0.0, 1.0) * rect.height())
+            };
+
+            for frame in self.frames.iter().filter(|f| f.beat) {
+                let x = to_screen(frame.time, 0.0).x;
+                painter.line_segment(
+                    [Pos2::new(x, rect.top()), Pos2::new(x, rect.bottom())],
+                    Stroke::new(1.0, Color32::from_white_alpha(60)),
+                );
+            }
+            let raw = self.frames.iter().map(|f| to_screen(f.time, f.raw[band])).collect();
+            painter.add(egui::Shape::line(raw, Stroke::new(1.0, color.gamma_multiply(0.4))));
+            let smooth = self.frames.iter().map(|f| to_screen(f.time, f.smooth[band])).collect();
+            painter.add(egui::Shape::line(smooth, Stroke::new(1.5, color)));
+        }
+        ui.label(
+            egui::RichText::new("Faint: raw level · bright: smoothed · white: beats")
+                .small()
+                .weak(),
+        );
+    }
+}
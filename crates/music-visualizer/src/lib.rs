@@ -1,14 +1,20 @@
 use eframe::egui::{self, Color32, Pos2, Rect, Vec2};
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
 use wasm_bindgen_futures::spawn_local;
+use serde::{Deserialize, Serialize};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
 mod audio;
+mod band_history;
+mod color;
 mod ui;
 mod unknown_pleasures;
-use crate::audio::{AudioAnalysis, WebAudio, init_web_audio};
+use crate::audio::{sorted_band_edges, time_domain_rms, AudioAnalysis, DEFAULT_BAND_EDGES_HZ, WebAudio, WebAudioConfig, FFT_SIZES, init_web_audio, start_analyser_polling};
+use crate::band_history::BandHistory;
 use crate::unknown_pleasures::UnknownPleasuresVisualizer;
+use crate::ui::{FractalCache, FractalTrails};
 
 // Playlist track information
 #[derive(Clone, Default)]
@@ -17,6 +23,43 @@ pub struct PlaylistTrack {
     pub duration: f64,      // Duration in seconds
     pub file_type: String,  // mp3, wav, ogg, flac, etc.
     pub url: String,        // Object URL or external URL
+    pub bpm: Option<f32>,   // Tempo measured while the file played, if it has
+}
+
+// What happens when the current track finishes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RepeatMode {
+    /// Stop after the last track.
+    Off,
+    /// Replay the current track.
+    One,
+    /// Loop back to the first track after the last one.
+    #[default]
+    All,
+}
+
+impl RepeatMode {
+    pub fn next(self) -> Self {
+        match self {
+            RepeatMode::Off => RepeatMode::All,
+            RepeatMode::All => RepeatMode::One,
+            RepeatMode::One => RepeatMode::Off,
+        }
+    }
+    pub fn label(self) -> &'static str {
+        match self {
+            RepeatMode::Off => "🔁 Off",
+            RepeatMode::All => "🔁 All",
+            RepeatMode::One => "🔂 One",
+        }
+    }
+}
+
+/// Field `PlaylistState::sort_tracks` orders by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortKey {
+    Name, // Track name, ignoring case
+    Duration,
 }
 
 // Playlist and playback state
@@ -26,6 +69,7 @@ pub struct PlaylistState {
     pub current_index: Option<usize>,
     pub is_playing: bool,
     pub is_shuffled: bool,
+    pub repeat: RepeatMode,
     pub shuffle_order: Vec<usize>,
     pub current_time: f64,
     pub duration: f64,
@@ -39,6 +83,7 @@ impl Default for PlaylistState {
             current_index: None,
             is_playing: false,
             is_shuffled: false,
+            repeat: RepeatMode::default(),
             shuffle_order: Vec::new(),
             current_time: 0.0,
             duration: 0.0,
@@ -66,7 +111,30 @@ impl PlaylistState {
         format!("{:02}:{:02}", mins, secs)
     }
     
-    pub fn shuffle_playlist(&mut self) {
+    /// Read a typed position as seconds: `ss`, `mm:ss` or `h:mm:ss`, with an
+    /// optional fraction on the last field.
+    pub fn parse_time(text: &str) -> Option<f64> {
+        let fields: Vec<&str> = text.trim().split(':').collect();
+        if fields.len() > 3 {
+            return None;
+        }
+        let mut seconds = 0.0;
+        for (i, field) in fields.iter().enumerate() {
+            let value: f64 = field.trim().parse().ok().filter(|v: &f64| v.is_finite() && *v >= 0.0)?;
+            // Only the leading field may run past 59, and only the last may have a fraction
+            if i > 0 && value >= 60.0 {
+                return None;
+            }
+            if i + 1 < fields.len() && field.contains('.') {
+                return None;
+            }
+            seconds = seconds * 60.0 + value;
+        }
+        Some(seconds)
+    }
+    
+    /// Draw a new shuffle order, advancing `seed` (see [`rand_float`]).
+    pub fn shuffle_playlist(&mut self, seed: &mut u64) {
         let len = self.tracks.len();
         if len == 0 {
             return;
@@ -75,7 +143,7 @@ impl PlaylistState {
         self.shuffle_order = (0..len).collect();
         // Simple Fisher-Yates shuffle using our rand function
         for i in (1..len).rev() {
-            let j = (rand_float() * (i + 1) as f32) as usize;
+            let j = (rand_float(seed) * (i + 1) as f32) as usize;
             self.shuffle_order.swap(i, j);
         }
     }
@@ -131,30 +199,389 @@ impl PlaylistState {
             None => Some(0),
         }
     }
+    
+    /// First track in play order (respects the shuffle order).
+    pub fn get_first_index(&self) -> Option<usize> {
+        if self.tracks.is_empty() {
+            return None;
+        }
+        if self.is_shuffled && !self.shuffle_order.is_empty() {
+            Some(self.shuffle_order[0])
+        } else {
+            Some(0)
+        }
+    }
+    
+    /// Track to play after a manual "next": wraps around unless repeat is off.
+    pub fn get_manual_next_index(&self) -> Option<usize> {
+        match self.repeat {
+            RepeatMode::Off => self.get_next_index(),
+            RepeatMode::One | RepeatMode::All => self.get_next_index().or_else(|| self.get_first_index()),
+        }
+    }
+    
+    /// Track to play when the current one finishes on its own.
+    pub fn get_index_after_end(&self) -> Option<usize> {
+        match self.repeat {
+            RepeatMode::Off => self.get_next_index(),
+            RepeatMode::One => self.current_index,
+            RepeatMode::All => self.get_next_index().or_else(|| self.get_first_index()),
+        }
+    }
+    
+    /// Sort the tracks, keeping `current_index` on the same track. A stable
+    /// sort, so equal keys keep their order; the shuffle order is redrawn
+    /// from `seed`.
+    pub fn sort_tracks(&mut self, key: SortKey, ascending: bool, seed: &mut u64) {
+        let mut order: Vec<usize> = (0..self.tracks.len()).collect();
+        order.sort_by(|&a, &b| {
+            let (a, b) = (&self.tracks[a], &self.tracks[b]);
+            let ord = match key {
+                SortKey::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+                SortKey::Duration => a.duration.total_cmp(&b.duration),
+            };
+            if ascending { ord } else { ord.reverse() }
+        });
+        
+        self.current_index = self.current_index.and_then(|cur| order.iter().position(|&i| i == cur));
+        let mut old: Vec<Option<PlaylistTrack>> = std::mem::take(&mut self.tracks).into_iter().map(Some).collect();
+        self.tracks = order.iter().filter_map(|&i| old[i].take()).collect();
+        if self.is_shuffled {
+            self.shuffle_playlist(seed);
+        } else {
+            self.shuffle_order.clear();
+        }
+    }
+    
+    /// Move the track at `from` so it ends up at `to`, shifting the ones in
+    /// between. `current_index` and `shuffle_order` keep referring to the
+    /// same tracks, so the play order is unchanged.
+    pub fn reorder_track(&mut self, from: usize, to: usize) {
+        let len = self.tracks.len();
+        if from >= len || to >= len || from == to {
+            return;
+        }
+        let track = self.tracks.remove(from);
+        self.tracks.insert(to, track);
+        
+        let remap = |i: usize| {
+            if i == from {
+                to
+            } else if from < to && (from + 1..=to).contains(&i) {
+                i - 1
+            } else if to < from && (to..from).contains(&i) {
+                i + 1
+            } else {
+                i
+            }
+        };
+        self.current_index = self.current_index.map(remap);
+        for i in &mut self.shuffle_order {
+            *i = remap(*i);
+        }
+    }
 }
 
 // Audio logic moved to `src/audio.rs`.
 
+/// Shape drawn by the fractal visualizer.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum FractalKind {
+    #[default]
+    BinaryTree,
+    Koch,
+    Sierpinski,
+    FractalFern,
+}
+
+impl FractalKind {
+    pub const ALL: [FractalKind; 4] = [
+        FractalKind::BinaryTree,
+        FractalKind::Koch,
+        FractalKind::Sierpinski,
+        FractalKind::FractalFern,
+    ];
+    
+    pub fn label(self) -> &'static str {
+        match self {
+            FractalKind::BinaryTree => "Binary tree",
+            FractalKind::Koch => "Koch snowflake",
+            FractalKind::Sierpinski => "Sierpinski triangle",
+            FractalKind::FractalFern => "Fractal fern",
+        }
+    }
+}
+
+/// How the waveform strip renders the time-domain buffer.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WaveformStyle {
+    #[default]
+    Line,
+    Envelope, // per-column min/max peaks with an RMS band, like an audio editor
+}
+
+impl WaveformStyle {
+    pub const ALL: [WaveformStyle; 2] = [WaveformStyle::Line, WaveformStyle::Envelope];
+    
+    pub fn label(self) -> &'static str {
+        match self {
+            WaveformStyle::Line => "Line",
+            WaveformStyle::Envelope => "Envelope",
+        }
+    }
+}
+
+/// Shape drawn for each beat particle.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ParticleShape {
+    #[default]
+    Circle,
+    Square,
+    Star,
+    Line, // A streak along the particle's direction of travel
+}
+
+impl ParticleShape {
+    pub const ALL: [ParticleShape; 4] = [
+        ParticleShape::Circle,
+        ParticleShape::Square,
+        ParticleShape::Star,
+        ParticleShape::Line,
+    ];
+    
+    pub fn label(self) -> &'static str {
+        match self {
+            ParticleShape::Circle => "Circle",
+            ParticleShape::Square => "Square",
+            ParticleShape::Star => "Star",
+            ParticleShape::Line => "Line",
+        }
+    }
+}
+
+/// Which way the Unknown Pleasures lines run.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UpOrientation {
+    #[default]
+    Horizontal, // Lines run left to right, stacked bottom (near) to top (far)
+    Vertical,   // Lines run top to bottom, stacked left (near) to right (far), for portrait displays
+}
+
+impl UpOrientation {
+    pub const ALL: [UpOrientation; 2] = [UpOrientation::Horizontal, UpOrientation::Vertical];
+    
+    pub fn label(self) -> &'static str {
+        match self {
+            UpOrientation::Horizontal => "Horizontal",
+            UpOrientation::Vertical => "Vertical",
+        }
+    }
+}
+
+/// Ready-made colour scheme applied by [`VisualizerConfig::apply_palette`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Palette {
+    Sunset,
+    Ocean,
+    Mono,
+    Neon,
+    Fire,
+}
+
+impl Palette {
+    pub const ALL: [Palette; 5] = [
+        Palette::Sunset,
+        Palette::Ocean,
+        Palette::Mono,
+        Palette::Neon,
+        Palette::Fire,
+    ];
+    
+    pub fn label(self) -> &'static str {
+        match self {
+            Palette::Sunset => "Sunset",
+            Palette::Ocean => "Ocean",
+            Palette::Mono => "Mono",
+            Palette::Neon => "Neon",
+            Palette::Fire => "Fire",
+        }
+    }
+    
+    // (main hue, accent hue, saturation); every colour of the palette is derived from these
+    fn hues(self) -> (f32, f32, f32) {
+        match self {
+            Palette::Sunset => (0.95, 0.08, 0.85),
+            Palette::Ocean => (0.55, 0.45, 0.75),
+            Palette::Mono => (0.0, 0.0, 0.0),
+            Palette::Neon => (0.83, 0.5, 1.0),
+            Palette::Fire => (0.02, 0.12, 0.95),
+        }
+    }
+}
+
+/// Audio measurement that can drive a fractal parameter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AudioFeature {
+    Bass,
+    Mid,
+    Treble,
+    Volume,
+    Centroid,
+    Flux,
+}
+
+impl AudioFeature {
+    pub const ALL: [AudioFeature; 6] = [
+        AudioFeature::Bass,
+        AudioFeature::Mid,
+        AudioFeature::Treble,
+        AudioFeature::Volume,
+        AudioFeature::Centroid,
+        AudioFeature::Flux,
+    ];
+    
+    pub fn label(self) -> &'static str {
+        match self {
+            AudioFeature::Bass => "Bass",
+            AudioFeature::Mid => "Mid",
+            AudioFeature::Treble => "Treble",
+            AudioFeature::Volume => "Volume",
+            AudioFeature::Centroid => "Complexity",
+            AudioFeature::Flux => "Flux",
+        }
+    }
+    
+    pub fn value(self, audio: &AudioAnalysis) -> f32 {
+        match self {
+            AudioFeature::Bass => audio.smooth_bass,
+            AudioFeature::Mid => audio.smooth_mid,
+            AudioFeature::Treble => audio.smooth_treble,
+            AudioFeature::Volume => audio.smooth_volume,
+            AudioFeature::Centroid => audio.spectral_centroid,
+            AudioFeature::Flux => audio.spectral_flux,
+        }
+    }
+}
+
+/// Fractal parameter that audio features push away from its base value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConfigParam {
+    Zoom,
+    Width,
+    Depth,
+    Brightness,
+}
+
+impl ConfigParam {
+    pub const ALL: [ConfigParam; 4] = [
+        ConfigParam::Zoom,
+        ConfigParam::Width,
+        ConfigParam::Depth,
+        ConfigParam::Brightness,
+    ];
+    
+    pub fn label(self) -> &'static str {
+        match self {
+            ConfigParam::Zoom => "Zoom",
+            ConfigParam::Width => "Width",
+            ConfigParam::Depth => "Depth",
+            ConfigParam::Brightness => "Brightness",
+        }
+    }
+}
+
+/// One `source → target` wire: adds `source * multiplier` to the target's base value.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ReactivityEntry {
+    pub source: AudioFeature,
+    pub target: ConfigParam,
+    pub multiplier: f32,
+}
+
+/// User-editable audio→parameter wiring. The default reproduces the classic
+/// bass→zoom, bass→width, complexity→depth and treble→brightness mapping.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ReactivityMap {
+    pub entries: Vec<ReactivityEntry>,
+}
+
+impl Default for ReactivityMap {
+    fn default() -> Self {
+        let entry = |source, target, multiplier| ReactivityEntry { source, target, multiplier };
+        Self {
+            entries: vec![
+                entry(AudioFeature::Bass, ConfigParam::Zoom, 0.1),
+                entry(AudioFeature::Bass, ConfigParam::Width, 0.3),
+                entry(AudioFeature::Centroid, ConfigParam::Depth, 4.0),
+                entry(AudioFeature::Treble, ConfigParam::Brightness, 0.4),
+            ],
+        }
+    }
+}
+
+impl ReactivityMap {
+    /// Sum of every entry driving `target` for the current audio frame.
+    pub fn offset(&self, target: ConfigParam, audio: &AudioAnalysis) -> f32 {
+        self.entries
+            .iter()
+            .filter(|e| e.target == target)
+            .map(|e| e.source.value(audio) * e.multiplier)
+            .sum()
+    }
+}
+
+/// Upper bound on `base_depth` accepted from the UI.
+pub const MAX_BASE_DEPTH: u32 = 24;
+/// Range of `fractal_branches`, the children per tree node.
+pub const MIN_BRANCHES: u32 = 2;
+pub const MAX_BRANCHES: u32 = 5;
+/// Start of every string made by `VisualizerConfig::to_share_string`; the
+/// number is bumped if the encoding ever changes.
+pub const SHARE_PREFIX: &str = "mv1:";
+/// Ceiling on the strobe's peak opacity whatever `strobe_intensity` says, so
+/// the flash never goes fully opaque (photosensitivity).
+pub const MAX_STROBE_INTENSITY: f32 = 0.5;
+/// Fewest points per Unknown Pleasures line; a line needs two ends.
+pub const MIN_UP_SAMPLES: u32 = 2;
+/// Fewest Unknown Pleasures lines drawn.
+pub const MIN_UP_LINES: u32 = 1;
+
 // Configuration for visualizer
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct VisualizerConfig {
     // Base fractal parameters
+    pub fractal_kind: FractalKind,
     pub base_zoom: f32,
     pub base_width: f32,
     pub base_depth: u32,
     pub base_brightness: f32,
+    pub fractal_branches: u32, // Children per tree node, fanned evenly across `fractal_spread_deg`
+    pub fractal_spread_deg: f32,
+    pub fractal_depth_hue_shift: f32, // Hue rotation per binary-tree recursion level, in turns; 0 keeps a single hue
     
-    // Audio reactivity multipliers
-    pub zoom_bass_mult: f32,
-    pub width_bass_mult: f32,
-    pub depth_complexity_mult: f32,
-    pub brightness_treble_mult: f32,
+    // Audio reactivity
+    pub reactivity: ReactivityMap,
     pub rotation_beat_mult: f32,
+    pub beat_sensitivity: f32,
+    pub band_smoothing: f32, // Fraction of the gap to the new level the smoothed bands close per frame (0..1)
+    pub band_edges_hz: [f32; 6], // Ascending Hz boundaries of the five analysis bands
+    pub a_weighting: bool, // Weight bands and volume by perceived loudness
+    // Demo signal: kick tempo and how hard the bass and treble bands hit
+    pub demo_bpm: f32,
+    pub demo_bass_intensity: f32,
+    pub demo_treble_intensity: f32,
+    pub demo_follow_track: bool, // Use the loaded track's tempo and length when the demo stands in for it
+    pub auto_gain: bool, // Normalize bands against a slow peak envelope
+    pub auto_gain_strength: f32, // 0 = raw bands, 1 = fully normalized
     
     // Animation
     pub auto_rotate: bool,
     pub rotation_speed: f32,
     pub pulse_on_beat: bool,
+    pub beat_strobe: bool, // Flash the whole view on beats, in any mode
+    pub strobe_color: Color32,
+    pub strobe_intensity: f32, // Peak opacity of the flash, capped at `MAX_STROBE_INTENSITY`
     pub color_cycle: bool,
     pub color_cycle_speed: f32,
     
@@ -162,12 +589,31 @@ pub struct VisualizerConfig {
     pub base_color: Color32,
     pub accent_color: Color32,
     pub background_color: Color32,
+    pub background_gradient: bool, // Fractal backdrop fades from `background_top` to `background_bottom` instead of the flat colour
+    pub background_top: Color32,
+    pub background_bottom: Color32,
     pub glow_intensity: f32,
+    pub trail_amount: f32, // fraction of the previous fractal frame kept each 60 Hz frame; 0 clears every frame
+    pub max_fps: u32, // Repaint rate cap while audio is playing; idle, silent frames run slower still
+    pub idle_timeout_secs: f32, // Silence with nothing playing before attract mode starts; 0 never
     pub particle_count: u32,
+    pub particle_gravity: Vec2, // acceleration applied to beat particles, in screen units/s² (positive y falls)
+    pub particle_shape: ParticleShape,
+    pub waveform_style: WaveformStyle,
+    pub spectrum_bars: u32,
+    pub spectrum_log_axis: bool, // spread bars along a power curve so low frequencies get more of them
+    pub spectrum_peak_hold: bool,
+    pub spectrum_peak_decay: f32, // fraction of full height a held peak falls per second
+    pub spectrum_smoothing: f32, // fraction of the gap to the new level kept each frame; 0 draws raw data
+    pub waveform_smoothing: f32,
     // Unknown Pleasures visualizer parameters
     pub up_line_thickness: f32,
+    pub up_thickness_follows_zoom: bool, // widen lines with `up_zoom` so zoomed-in lines stay visible
+    pub up_segment_strokes: bool, // stroke each segment on its own instead of one path per line
     pub up_perspective: f32,
     pub up_vertical_scale: f32,
+    pub up_auto_fit: bool, // derive line spacing and height from the viewport instead of `up_vertical_scale`
+    pub up_orientation: UpOrientation,
     pub up_line_length: f32,
     pub up_zoom: f32,
     pub up_isometric_rotate: bool,
@@ -180,38 +626,76 @@ pub struct VisualizerConfig {
     pub up_max_lines: u32,
     pub up_samples: u32,
     pub up_freq_curve_exponent: f32,
+    pub up_mel_scale: bool, // Mel-spaced line frequencies instead of the exponent curve
     pub up_monochrome: bool,
     pub up_smoothing: f32,
+    pub up_fill_occlusion: bool, // filled "poster" look instead of wireframe
+    pub up_gradient: bool, // color lines by depth between the two colors below
+    pub up_gradient_top: Color32,
+    pub up_gradient_bottom: Color32,
 }
 
 impl Default for VisualizerConfig {
     fn default() -> Self {
         Self {
+            fractal_kind: FractalKind::BinaryTree,
             base_zoom: 0.1,
             base_width: 1.0,
             base_depth: 16,
             base_brightness: 0.8,
+            fractal_branches: 2,
+            fractal_spread_deg: 90.0,
+            fractal_depth_hue_shift: 0.0,
             
-            zoom_bass_mult: 0.1,
-            width_bass_mult: 0.3,
-            depth_complexity_mult: 4.0,
-            brightness_treble_mult: 0.4,
+            reactivity: ReactivityMap::default(),
             rotation_beat_mult: 0.1,
+            beat_sensitivity: 1.4,
+            band_smoothing: 0.15,
+            band_edges_hz: DEFAULT_BAND_EDGES_HZ,
+            a_weighting: false,
+            demo_bpm: 120.0,
+            demo_bass_intensity: 1.0,
+            demo_treble_intensity: 1.0,
+            demo_follow_track: false,
+            auto_gain: false,
+            auto_gain_strength: 1.0,
             
             auto_rotate: true,
             rotation_speed: 1.0,
             pulse_on_beat: true,
+            beat_strobe: false,
+            strobe_color: Color32::WHITE,
+            strobe_intensity: 0.3,
             color_cycle: true,
             color_cycle_speed: 0.1,
             
             base_color: Color32::from_rgb(100, 200, 255),
             accent_color: Color32::from_rgb(255, 100, 200),
             background_color: Color32::from_rgb(10, 10, 20),
+            background_gradient: false,
+            background_top: Color32::from_rgb(28, 20, 56),
+            background_bottom: Color32::from_rgb(4, 4, 12),
             glow_intensity: 0.5,
+            trail_amount: 0.0,
+            max_fps: 60,
+            idle_timeout_secs: 60.0,
             particle_count: 50,
+            particle_gravity: Vec2::ZERO,
+            particle_shape: ParticleShape::Circle,
+            waveform_style: WaveformStyle::Line,
+            spectrum_bars: 64,
+            spectrum_log_axis: false,
+            spectrum_peak_hold: false,
+            spectrum_peak_decay: 0.5,
+            spectrum_smoothing: 0.5,
+            waveform_smoothing: 0.0,
             up_line_thickness: 1.5,
+            up_thickness_follows_zoom: false,
+            up_segment_strokes: false,
             up_perspective: 0.6,
             up_vertical_scale: 1.0,
+            up_auto_fit: false,
+            up_orientation: UpOrientation::Horizontal,
             up_line_length: 1.0,
             up_zoom: 1.0,
             up_isometric_rotate: false,
@@ -222,8 +706,13 @@ impl Default for VisualizerConfig {
             up_max_lines: 80,
             up_samples: 120,
             up_freq_curve_exponent: 2.5,
+            up_mel_scale: false,
             up_monochrome: true,
             up_smoothing: 0.15,
+            up_fill_occlusion: false,
+            up_gradient: false,
+            up_gradient_top: Color32::from_rgb(255, 60, 160),
+            up_gradient_bottom: Color32::from_rgb(60, 200, 255),
         }
         }
     }
@@ -249,6 +738,7 @@ impl Default for VisualizerConfig {
             c.up_samples = 180;
             c.up_freq_curve_exponent = 3.2;
             c.up_smoothing = 0.22;
+            c.up_fill_occlusion = true;
 
             // Reduce other visual distractions
             c.pulse_on_beat = false;
@@ -256,6 +746,86 @@ impl Default for VisualizerConfig {
             c
         }
 
+        /// Take on the look of `preset` (a built-in, a saved preset or the
+        /// defaults) while keeping the idle timeout and frame-rate cap, which
+        /// belong to this setup rather than to a look.
+        pub fn apply_preset(&mut self, preset: VisualizerConfig) {
+            let kept = std::mem::take(self);
+            *self = VisualizerConfig {
+                idle_timeout_secs: kept.idle_timeout_secs,
+                max_fps: kept.max_fps,
+                ..preset
+            };
+        }
+
+        /// Set the fractal, background and Unknown Pleasures gradient colours from
+        /// `palette`. Colour cycling is turned off so the palette stays visible.
+        pub fn apply_palette(&mut self, palette: Palette) {
+            let (main, accent, sat) = palette.hues();
+            self.base_color = color::hsl_to_rgb(main, sat, 0.65);
+            self.accent_color = color::hsl_to_rgb(accent, sat, 0.6);
+            self.background_color = color::hsl_to_rgb(main, sat * 0.5, 0.05);
+            self.background_top = color::hsl_to_rgb(accent, sat * 0.5, 0.12);
+            self.background_bottom = color::hsl_to_rgb(main, sat * 0.5, 0.03);
+            self.up_gradient_top = color::hsl_to_rgb(accent, sat, 0.55);
+            self.up_gradient_bottom = color::hsl_to_rgb(main, sat, 0.55);
+            self.color_cycle = false;
+        }
+
+        /// `up_max_lines`, raised to `MIN_UP_LINES` for configs saved before it
+        /// was enforced.
+        pub fn up_line_count(&self) -> usize {
+            self.up_max_lines.max(MIN_UP_LINES) as usize
+        }
+
+        /// `up_samples`, raised to `MIN_UP_SAMPLES` so lines never divide by zero.
+        pub fn up_sample_count(&self) -> usize {
+            self.up_samples.max(MIN_UP_SAMPLES) as usize
+        }
+
+        /// Pull every setting that has a range in the UI back inside it, so a
+        /// hand-edited share string can't ask for more than the controls allow.
+        pub fn clamp_to_ui_ranges(&mut self) {
+            self.auto_gain_strength = self.auto_gain_strength.clamp(0.0, 1.0);
+            self.band_smoothing = self.band_smoothing.clamp(0.0, 1.0);
+            self.band_edges_hz = sorted_band_edges(self.band_edges_hz);
+            self.demo_bpm = self.demo_bpm.clamp(40.0, 220.0);
+            self.demo_bass_intensity = self.demo_bass_intensity.clamp(0.0, 2.0);
+            self.demo_treble_intensity = self.demo_treble_intensity.clamp(0.0, 2.0);
+            self.base_depth = self.base_depth.clamp(1, MAX_BASE_DEPTH);
+            self.fractal_branches = self.fractal_branches.clamp(MIN_BRANCHES, MAX_BRANCHES);
+            self.fractal_spread_deg = self.fractal_spread_deg.clamp(0.0, 360.0);
+            self.fractal_depth_hue_shift = self.fractal_depth_hue_shift.clamp(-0.25, 0.25);
+            self.strobe_intensity = self.strobe_intensity.clamp(0.0, MAX_STROBE_INTENSITY);
+            self.trail_amount = self.trail_amount.clamp(0.0, 0.95);
+            self.spectrum_bars = self.spectrum_bars.clamp(4, 256);
+            self.spectrum_peak_decay = self.spectrum_peak_decay.clamp(0.0, 10.0);
+            self.spectrum_smoothing = self.spectrum_smoothing.clamp(0.0, 0.95);
+            self.waveform_smoothing = self.waveform_smoothing.clamp(0.0, 0.95);
+            self.max_fps = self.max_fps.clamp(10, 240);
+            self.idle_timeout_secs = self.idle_timeout_secs.clamp(0.0, 3600.0);
+            self.up_max_lines = self.up_max_lines.max(MIN_UP_LINES);
+            self.up_samples = self.up_samples.max(MIN_UP_SAMPLES);
+        }
+
+        /// Compact text form of the whole config for pasting into chat or a
+        /// forum: `SHARE_PREFIX` followed by the URL-safe base64 of its JSON.
+        pub fn to_share_string(&self) -> String {
+            let json = serde_json::to_string(self).unwrap_or_default();
+            format!("{SHARE_PREFIX}{}", URL_SAFE_NO_PAD.encode(json))
+        }
+
+        /// Parse a string made by [`Self::to_share_string`]. Surrounding
+        /// whitespace is ignored; anything else that doesn't decode to a config
+        /// is rejected with a message for the UI.
+        pub fn from_share_string(s: &str) -> Result<Self, String> {
+            let encoded = s.trim().strip_prefix(SHARE_PREFIX).ok_or("Not a visualizer config string")?;
+            let json = URL_SAFE_NO_PAD.decode(encoded).map_err(|_| "Config string is damaged or incomplete")?;
+            let mut config: Self = serde_json::from_slice(&json).map_err(|e| format!("Config string is invalid: {e}"))?;
+            config.clamp_to_ui_ranges();
+            Ok(config)
+        }
+
         /// Reset only the fractal-related parameters to their default values
         pub fn reset_fractal_to_default(&mut self) {
         let d = VisualizerConfig::default();
@@ -263,39 +833,238 @@ impl Default for VisualizerConfig {
         self.base_width = d.base_width;
         self.base_depth = d.base_depth;
         self.base_brightness = d.base_brightness;
+        self.fractal_branches = d.fractal_branches;
+        self.fractal_spread_deg = d.fractal_spread_deg;
+        self.fractal_depth_hue_shift = d.fractal_depth_hue_shift;
 
-        self.zoom_bass_mult = d.zoom_bass_mult;
-        self.width_bass_mult = d.width_bass_mult;
-        self.depth_complexity_mult = d.depth_complexity_mult;
-        self.brightness_treble_mult = d.brightness_treble_mult;
+        self.reactivity = d.reactivity;
         self.rotation_beat_mult = d.rotation_beat_mult;
     }
 }
 
+/// Built-in looks offered in the Presets section, ahead of the user's own.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VisualizerPreset {
+    Classic,
+    EmberTree,
+    NeonSnowflake,
+    OceanFern,
+    MonoSierpinski,
+    UnknownPleasuresImage,
+    UnknownPleasuresNeon,
+}
+
+impl VisualizerPreset {
+    pub const ALL: [VisualizerPreset; 7] = [
+        VisualizerPreset::Classic,
+        VisualizerPreset::EmberTree,
+        VisualizerPreset::NeonSnowflake,
+        VisualizerPreset::OceanFern,
+        VisualizerPreset::MonoSierpinski,
+        VisualizerPreset::UnknownPleasuresImage,
+        VisualizerPreset::UnknownPleasuresNeon,
+    ];
+    
+    pub fn label(self) -> &'static str {
+        match self {
+            VisualizerPreset::Classic => "Classic",
+            VisualizerPreset::EmberTree => "Ember tree",
+            VisualizerPreset::NeonSnowflake => "Neon snowflake",
+            VisualizerPreset::OceanFern => "Ocean fern",
+            VisualizerPreset::MonoSierpinski => "Mono Sierpinski",
+            VisualizerPreset::UnknownPleasuresImage => "Unknown Pleasures: image",
+            VisualizerPreset::UnknownPleasuresNeon => "Unknown Pleasures: neon",
+        }
+    }
+    
+    pub fn config(self) -> VisualizerConfig {
+        let mut c = VisualizerConfig::default();
+        match self {
+            VisualizerPreset::Classic => {}
+            VisualizerPreset::EmberTree => {
+                c.apply_palette(Palette::Fire);
+                c.base_depth = 12;
+                c.trail_amount = 0.6;
+                c.glow_intensity = 0.8;
+                c.rotation_speed = 0.5;
+            }
+            VisualizerPreset::NeonSnowflake => {
+                c.apply_palette(Palette::Neon);
+                c.fractal_kind = FractalKind::Koch;
+                c.base_depth = 4;
+                c.glow_intensity = 1.2;
+                c.particle_shape = ParticleShape::Star;
+            }
+            VisualizerPreset::OceanFern => {
+                c.apply_palette(Palette::Ocean);
+                c.fractal_kind = FractalKind::FractalFern;
+                c.trail_amount = 0.3;
+                c.auto_rotate = false;
+                c.particle_gravity = Vec2::new(0.0, 60.0);
+            }
+            VisualizerPreset::MonoSierpinski => {
+                c.apply_palette(Palette::Mono);
+                c.fractal_kind = FractalKind::Sierpinski;
+                c.glow_intensity = 0.0;
+                c.pulse_on_beat = false;
+            }
+            VisualizerPreset::UnknownPleasuresImage => c = VisualizerConfig::preset_unknown_pleasures_image(),
+            VisualizerPreset::UnknownPleasuresNeon => {
+                c = VisualizerConfig::preset_unknown_pleasures_image();
+                c.apply_palette(Palette::Neon);
+                c.up_monochrome = false;
+                c.up_gradient = true;
+                c.up_mel_scale = true;
+            }
+        }
+        c
+    }
+}
+
+/// Every built-in preset with its display name, in menu order.
+pub fn presets() -> Vec<(&'static str, VisualizerConfig)> {
+    VisualizerPreset::ALL.iter().map(|p| (p.label(), p.config())).collect()
+}
+
+/// A config the user saved under a name, listed after the built-in presets.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CustomPreset {
+    pub name: String,
+    pub config: VisualizerConfig,
+}
+
+// localStorage key holding the custom presets as a JSON array
+const CUSTOM_PRESETS_KEY: &str = "music-visualizer.presets";
+
+/// Custom presets saved in localStorage; missing or unreadable data yields none.
+pub fn load_custom_presets() -> Vec<CustomPreset> {
+    web_sys::window()
+        .and_then(|w| w.local_storage().ok().flatten())
+        .and_then(|storage| storage.get_item(CUSTOM_PRESETS_KEY).ok().flatten())
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_custom_presets(presets: &[CustomPreset]) -> Result<(), String> {
+    let storage = web_sys::window()
+        .and_then(|w| w.local_storage().ok().flatten())
+        .ok_or("localStorage is not available")?;
+    let json = serde_json::to_string(presets).map_err(|e| e.to_string())?;
+    storage
+        .set_item(CUSTOM_PRESETS_KEY, &json)
+        .map_err(|_| "Browser storage is full or blocked".to_string())
+}
+
+/// Keyboard bindings handled by `handle_shortcuts`, as shown in the `?` overlay.
+pub const SHORTCUTS: [(&str, &str); 10] = [
+    ("Space", "Play / pause"),
+    ("← / →", "Previous / next track"),
+    ("Shift + ← / →", "Seek 5 s back / forward"),
+    ("↑ / ↓", "Volume up / down"),
+    ("S", "Toggle shuffle"),
+    ("?", "Show / hide this help"),
+    ("Esc", "Close this help"),
+    ("F", "Freeze / unfreeze the visuals"),
+    ("F11", "Toggle presentation mode"),
+    ("Esc / mouse move", "Show the presentation exit button"),
+];
+const SEEK_STEP_SECS: f64 = 5.0;
+const VOLUME_STEP: f32 = 0.05;
+/// How long the presentation exit overlay stays up after the last mouse move or Esc.
+const PRESENTATION_OVERLAY_SECS: f64 = 2.5;
+/// Repaint rate while nothing plays and the input is silent, and the volume counted as silence.
+const IDLE_FPS: u32 = 5;
+const IDLE_VOLUME: f32 = 0.001;
+/// Intro and outro over which the demo eases in and out when it follows a track.
+const DEMO_TRACK_FADE_SECS: f64 = 8.0;
+/// Demo level at the very start and end of a followed track.
+const DEMO_TRACK_FADE_FLOOR: f32 = 0.3;
+// Attract mode's stand-in signal: a slow pulse at a fraction of the demo's strength
+const ATTRACT_BPM: f32 = 60.0;
+const ATTRACT_LEVEL: f32 = 0.3;
+
+/// Shown for YouTube tracks, whose audio the visualizer cannot read.
+const YOUTUBE_CORS_NOTE: &str = "YouTube audio can't be analysed: the embedded player is cross-origin, \
+    so the browser (CORS) keeps Web Audio from reading it. The visualizer follows the player's \
+    play/pause state with simulated audio instead.";
+const YOUTUBE_CONTAINER_STYLE: &str = "position:fixed; bottom:10px; right:10px; z-index:9999; \
+    background:#222; border-radius:8px; padding:5px; box-shadow: 0 4px 12px rgba(0,0,0,0.5);";
+const YOUTUBE_CLOSE_STYLE: &str = "position:absolute; top:-8px; right:-8px; background:#ff4444; \
+    color:white; border:none; border-radius:50%; width:24px; height:24px; cursor:pointer; font-size:14px;";
+// postMessage target for commands to the embedded player
+const YOUTUBE_ORIGIN: &str = "https://www.youtube.com";
+
+/// Playback state reported by the embedded YouTube player over the IFrame
+/// API's postMessage channel.
+#[derive(Clone, Copy, Default)]
+struct YoutubePlayback {
+    connected: bool, // The player has sent at least one message
+    playing: bool,
+    ended: bool,
+    current_time: f64,
+    duration: f64,
+}
+
+impl YoutubePlayback {
+    /// Apply one decoded message from the player. Understands `onReady`,
+    /// `onStateChange` and the periodic `infoDelivery` updates.
+    fn apply_message(&mut self, msg: &JsValue) {
+        let get = |obj: &JsValue, key: &str| js_sys::Reflect::get(obj, &key.into()).ok();
+        let Some(event) = get(msg, "event").and_then(|e| e.as_string()) else {
+            return;
+        };
+        let info = get(msg, "info").unwrap_or(JsValue::UNDEFINED);
+        let state = match event.as_str() {
+            "onReady" => None,
+            "onStateChange" => info.as_f64(),
+            "infoDelivery" => {
+                if let Some(t) = get(&info, "currentTime").and_then(|t| t.as_f64()) {
+                    self.current_time = t;
+                }
+                if let Some(d) = get(&info, "duration").and_then(|d| d.as_f64()) {
+                    self.duration = d;
+                }
+                get(&info, "playerState").and_then(|s| s.as_f64())
+            }
+            _ => return,
+        };
+        self.connected = true;
+        if let Some(state) = state {
+            // -1 unstarted, 0 ended, 1 playing, 2 paused, 3 buffering, 5 cued
+            self.playing = state == 1.0;
+            self.ended |= state == 0.0;
+        }
+    }
+}
+
 // Particle for beat effects
 #[derive(Clone)]
 struct Particle {
-    pos: Pos2,
+    pos: Pos2, // Offset from the visualizer centre, which may move between frames
     vel: Vec2,
     life: f32,
     max_life: f32,
     size: f32,
     color: Color32,
+    shape: ParticleShape,
 }
 
 impl Particle {
-    fn new(center: Pos2, angle: f32, speed: f32, color: Color32) -> Self {
+    fn new(angle: f32, speed: f32, size: f32, color: Color32, shape: ParticleShape) -> Self {
         Self {
-            pos: center,
+            pos: Pos2::ZERO,
             vel: Vec2::new(angle.cos() * speed, angle.sin() * speed),
             life: 1.0,
             max_life: 1.0,
-            size: 3.0 + rand_float() * 5.0,
+            size,
             color,
+            shape,
         }
     }
     
-    fn update(&mut self, dt: f32) {
+    // Gravity is an acceleration in screen units per second squared
+    fn update(&mut self, dt: f32, gravity: Vec2) {
+        self.vel += gravity * dt;
         self.pos += self.vel * dt;
         self.vel *= 0.98; // Friction
         self.life -= dt / self.max_life;
@@ -304,24 +1073,36 @@ impl Particle {
     fn is_alive(&self) -> bool {
         self.life > 0.0
     }
+    
+    /// Where to draw the particle when the visualizer is centred on `center`.
+    fn screen_pos(&self, center: Pos2) -> Pos2 {
+        center + self.pos.to_vec2()
+    }
+}
+
+/// Particles spawned by a beat of `intensity` (0..1): a single one for the
+/// softest beat up to a tenth of `particle_count` for the hardest.
+fn burst_count(particle_count: u32, intensity: f32) -> usize {
+    let full = (particle_count / 10).max(1) as f32;
+    (1.0 + (full - 1.0) * intensity.clamp(0.0, 1.0)).round() as usize
+}
+
+/// Launch speed multiplier for a beat of `intensity`: half speed for the
+/// softest beat, full speed for the hardest.
+fn burst_speed_scale(intensity: f32) -> f32 {
+    0.5 + 0.5 * intensity.clamp(0.0, 1.0)
 }
 
-// Simple random function for WASM
-fn rand_float() -> f32 {
+// Simple random function for WASM: a 0..1 value from `seed`, which it
+// advances, so the same seed always gives the same sequence
+fn rand_float(seed: &mut u64) -> f32 {
     use std::collections::hash_map::DefaultHasher;
     use std::hash::{Hash, Hasher};
     
-    thread_local! {
-        static SEED: RefCell<u64> = RefCell::new(12345);
-    }
-    
-    SEED.with(|seed| {
-        let mut s = seed.borrow_mut();
-        let mut hasher = DefaultHasher::new();
-        s.hash(&mut hasher);
-        *s = hasher.finish();
-        *s as f32 / u64::MAX as f32
-    })
+    let mut hasher = DefaultHasher::new();
+    seed.hash(&mut hasher);
+    *seed = hasher.finish();
+    *seed as f32 / u64::MAX as f32
 }
 
 // Web audio types and initialization moved to `src/audio.rs`.
@@ -331,6 +1112,7 @@ fn rand_float() -> f32 {
 pub enum VisualizerMode {
     Fractal,
     UnknownPleasures,
+    RadialSpectrum,
 }
 
 pub struct MusicVisualizerApp {
@@ -343,6 +1125,7 @@ pub struct MusicVisualizerApp {
     time: f64,
     rotation: f32,
     particles: Vec<Particle>,
+    rng_seed: u64, // State of `rand_float` for particles and shuffling
     
     // Audio data shared with JS callback
     audio_data: Rc<RefCell<(Vec<u8>, Vec<u8>)>>,
@@ -350,18 +1133,44 @@ pub struct MusicVisualizerApp {
     
     // Playlist state (shared for file input callback)
     playlist: PlaylistState,
+    seek_text: Option<String>, // Text of the position being typed after double-clicking the elapsed time
     pending_tracks: Rc<RefCell<Vec<(String, String, String)>>>, // (name, type, url)
+    loaded_durations: Rc<RefCell<Vec<(String, f64)>>>, // (url, seconds) from metadata probes
     audio_element: Rc<RefCell<Option<web_sys::HtmlAudioElement>>>,
     audio_context: Rc<RefCell<Option<web_sys::AudioContext>>>,
     analyser_node: Rc<RefCell<Option<web_sys::AnalyserNode>>>,
+    mic_analyser: Rc<RefCell<Option<web_sys::AnalyserNode>>>,
+    web_audio_config: WebAudioConfig, // FFT size, smoothing and poll rate of both analysers
     file_audio_initialized: Rc<RefCell<bool>>,
     
     // UI state
     demo_mode: bool,
     show_spectrum: bool,
     show_waveform: bool,
+    spectrum_peaks: Vec<f32>, // held maximum of each spectrum bar
+    spectrum_smoothed: Vec<f32>, // eased bar levels actually drawn
+    waveform_smoothed: Vec<f32>, // eased -1..1 waveform samples actually drawn
+    fractal_trails: FractalTrails, // fading copy of recent fractal frames
+    fractal_cache: FractalCache, // last fractal's shapes, replayed while its inputs hold still
+    custom_presets: Vec<CustomPreset>, // User-saved configs, mirrored in localStorage
+    preset_name: String,
+    preset_message: Option<String>,
+    share_input: String, // Pasted config string waiting to be applied
     show_settings: bool,
+    show_shortcuts: bool, // Keyboard help overlay, toggled with `?`
+    show_band_debug: bool, // Scrolling raw/smoothed band plots for tuning reactivity
+    band_history: BandHistory,
+    presentation_mode: bool, // Fullscreen visual with every panel hidden, toggled with F11
+    presentation_overlay_until: f64, // Wall-clock time until which the exit overlay is shown
+    fullscreen_exited: Rc<Cell<bool>>, // Set by the `fullscreenchange` listener when the page leaves fullscreen
+    fullscreen_listener_installed: bool,
+    frozen: bool, // Visuals held on the current frame while audio keeps playing, toggled with F
+    last_activity: f64, // `time` of the last sound, playback, key or pointer event
+    attract_mode: bool, // Self-animating screensaver after `idle_timeout_secs` of nothing happening
     beat_flash: f32,
+    // Fractal camera: screen offset of the origin and scale factor
+    pan: egui::Vec2,
+    zoom: f32,
     // Option: when switching back to Fractal, reset fractal params to defaults
     restore_fractal_on_back: bool,
     // Current visualizer mode
@@ -372,8 +1181,17 @@ pub struct MusicVisualizerApp {
     system_audio_mode: Option<bool>,
     // YouTube URL input buffer
     youtube_url_input: String,
-    // YouTube error message
-    youtube_error: Rc<RefCell<Option<String>>>,
+    // Case-insensitive filter over playlist track names
+    playlist_filter: String,
+    // Problem with the pasted URL or the current stream, shown under the URL box
+    url_error: Rc<RefCell<Option<String>>>,
+    // Keeps the audio element's current `error` callback alive
+    audio_error_handler: RefCell<Option<Closure<dyn FnMut()>>>,
+    // Player state forwarded by the window message listener
+    youtube_playback: Rc<RefCell<YoutubePlayback>>,
+    youtube_listener_installed: bool,
+    // Demo clock that follows the video's position while it plays
+    youtube_clock: f64,
 }
 
 impl Default for MusicVisualizerApp {
@@ -385,25 +1203,56 @@ impl Default for MusicVisualizerApp {
             time: 0.0,
             rotation: 0.0,
             particles: Vec::new(),
+            rng_seed: 12345,
             audio_data: Rc::new(RefCell::new((vec![0u8; 256], vec![0u8; 256]))),
             audio_initialized: Rc::new(RefCell::new(false)),
             playlist: PlaylistState::default(),
+            seek_text: None,
             pending_tracks: Rc::new(RefCell::new(Vec::new())),
+            loaded_durations: Rc::new(RefCell::new(Vec::new())),
             audio_element: Rc::new(RefCell::new(None)),
             audio_context: Rc::new(RefCell::new(None)),
             analyser_node: Rc::new(RefCell::new(None)),
+            mic_analyser: Rc::new(RefCell::new(None)),
+            web_audio_config: WebAudioConfig::default(),
             file_audio_initialized: Rc::new(RefCell::new(false)),
             demo_mode: true,
             show_spectrum: true,
             show_waveform: true,
+            spectrum_peaks: Vec::new(),
+            spectrum_smoothed: Vec::new(),
+            waveform_smoothed: Vec::new(),
+            fractal_trails: FractalTrails::default(),
+            fractal_cache: FractalCache::default(),
+            custom_presets: load_custom_presets(),
+            preset_name: String::new(),
+            preset_message: None,
+            share_input: String::new(),
             show_settings: true,
+            show_shortcuts: false,
+            show_band_debug: false,
+            band_history: BandHistory::default(),
+            presentation_mode: false,
+            presentation_overlay_until: 0.0,
+            fullscreen_exited: Rc::new(Cell::new(false)),
+            fullscreen_listener_installed: false,
+            frozen: false,
+            last_activity: 0.0,
+            attract_mode: false,
             beat_flash: 0.0,
+            pan: egui::Vec2::ZERO,
+            zoom: 1.0,
             restore_fractal_on_back: false,
             visualizer_mode: VisualizerMode::Fractal,
             unknown_visualizer: UnknownPleasuresVisualizer::new(),
             system_audio_mode: Some(false),
             youtube_url_input: String::new(),
-            youtube_error: Rc::new(RefCell::new(None)),
+            playlist_filter: String::new(),
+            url_error: Rc::new(RefCell::new(None)),
+            audio_error_handler: RefCell::new(None),
+            youtube_playback: Rc::new(RefCell::new(YoutubePlayback::default())),
+            youtube_listener_installed: false,
+            youtube_clock: 0.0,
         }
     }
 }
@@ -420,9 +1269,11 @@ impl MusicVisualizerApp {
         
         let audio_data = self.audio_data.clone();
         let audio_initialized = self.audio_initialized.clone();
+        let mic_analyser = self.mic_analyser.clone();
+        let config = self.web_audio_config;
         
         spawn_local(async move {
-            match init_web_audio(audio_data.clone()).await {
+            match init_web_audio(audio_data.clone(), mic_analyser, config).await {
                 Ok(_) => {
                     *audio_initialized.borrow_mut() = true;
                     web_sys::console::log_1(&"Audio initialized successfully!".into());
@@ -434,20 +1285,135 @@ impl MusicVisualizerApp {
         });
     }
     
+    /// Store the current config as a custom preset named `preset_name`,
+    /// replacing any preset of the same name, and write the list to localStorage.
+    fn save_custom_preset(&mut self) {
+        let name = self.preset_name.trim().to_string();
+        let preset = CustomPreset { name: name.clone(), config: self.config.clone() };
+        match self.custom_presets.iter_mut().find(|p| p.name == name) {
+            Some(existing) => *existing = preset,
+            None => self.custom_presets.push(preset),
+        }
+        self.write_custom_presets(format!("Saved preset \"{name}\""));
+    }
+    
+    fn delete_custom_preset(&mut self, index: usize) {
+        let preset = self.custom_presets.remove(index);
+        self.write_custom_presets(format!("Deleted preset \"{}\"", preset.name));
+    }
+    
+    fn write_custom_presets(&mut self, done: String) {
+        self.preset_message = Some(match save_custom_presets(&self.custom_presets) {
+            Ok(()) => done,
+            Err(e) => format!("Failed to save presets: {e}"),
+        });
+    }
+    
+    /// Drive the analysis from the synthetic demo signal, styled by the config.
+    /// With `demo_follow_track`, a loaded track's measured tempo replaces the
+    /// demo BPM, and when `time` is the track position the level eases in and
+    /// out over its intro and outro.
+    fn simulate_demo(&mut self, time: f64, track_position: bool) {
+        let c = &self.config;
+        let track = self.playlist.get_current_track().filter(|_| c.demo_follow_track);
+        let bpm = track.and_then(|t| t.bpm).unwrap_or(c.demo_bpm);
+        let level = match track {
+            Some(t) if track_position && t.duration > 0.0 => {
+                let edge = time.min(t.duration - time).max(0.0) / DEMO_TRACK_FADE_SECS;
+                DEMO_TRACK_FADE_FLOOR + (1.0 - DEMO_TRACK_FADE_FLOOR) * edge.min(1.0) as f32
+            }
+            _ => 1.0,
+        };
+        self.audio.simulate_demo(time, bpm, c.demo_bass_intensity * level, c.demo_treble_intensity * level);
+    }
+    
+    /// Make the demo repeatable, e.g. for automated screenshots: reseeds the
+    /// demo signal's phases and the particle randomness.
+    pub fn set_demo_seed(&mut self, seed: u64) {
+        self.audio.set_demo_seed(seed);
+        self.rng_seed = seed;
+    }
+    
+    /// Ask for the next frame no sooner than `max_fps` allows, dropping to
+    /// `IDLE_FPS` while nothing plays and the input is silent. Input events
+    /// still repaint immediately, so the UI stays responsive.
+    fn schedule_repaint(&self, ctx: &egui::Context) {
+        let idle = !self.playlist.is_playing && !self.demo_mode && self.audio.smooth_volume < IDLE_VOLUME;
+        let fps = if idle { IDLE_FPS } else { self.config.max_fps.max(1) };
+        ctx.request_repaint_after(std::time::Duration::from_secs_f32(1.0 / fps as f32));
+    }
+    
+    /// Reconfigure whichever analysers exist after an FFT size or smoothing change.
+    fn apply_web_audio_config(&self) {
+        for slot in [&self.analyser_node, &self.mic_analyser] {
+            if let Some(ref analyser) = *slot.borrow() {
+                self.web_audio_config.apply(analyser);
+            }
+        }
+    }
+    
     fn update_audio(&mut self, dt: f32) {
+        self.audio.beat_sensitivity = self.config.beat_sensitivity;
+        self.audio.set_smoothing(self.config.band_smoothing);
+        self.audio.set_band_edges(self.config.band_edges_hz);
+        self.audio.a_weighting = self.config.a_weighting;
+        self.audio.auto_gain = self.config.auto_gain;
+        self.audio.auto_gain_strength = self.config.auto_gain_strength;
+
         // Check if playing from file
         let is_file_playing = self.playlist.is_playing && *self.file_audio_initialized.borrow();
+        let is_youtube = self.current_track_is_youtube();
+        let mic_live = !is_youtube && !is_file_playing && !self.demo_mode && *self.audio_initialized.borrow();
         
-        if is_file_playing {
-            // Use audio data from file playback
+        // Attract mode: after `idle_timeout_secs` with nothing playing or heard,
+        // a gentle demo signal keeps the screen moving until something happens.
+        // Only a live microphone counts as sound, judged before anything is
+        // analysed so each frame feeds the beat and BPM history once. Before the
+        // mic starts, the full demo stand-in is not sound either, so attract mode
+        // takes over from it just as it does on native with no capture device.
+        let heard = mic_live && time_domain_rms(&self.audio_data.borrow().1) > IDLE_VOLUME;
+        if self.playlist.is_playing || self.demo_mode || heard {
+            self.last_activity = self.time;
+        }
+        let timeout = self.config.idle_timeout_secs;
+        self.attract_mode = timeout > 0.0 && self.time - self.last_activity > timeout as f64;
+        
+        if self.attract_mode {
+            self.audio.simulate_demo(self.time, ATTRACT_BPM, ATTRACT_LEVEL, ATTRACT_LEVEL);
+        } else if is_youtube {
+            // The embed can't be analysed, so drive the demo signal from the player's clock
+            let yt = *self.youtube_playback.borrow();
+            if !yt.connected {
+                self.simulate_demo(self.time, false);
+            } else if yt.playing {
+                self.youtube_clock += dt as f64;
+                if (self.youtube_clock - yt.current_time).abs() > 0.5 {
+                    self.youtube_clock = yt.current_time;
+                }
+                self.simulate_demo(self.youtube_clock, true);
+            } else {
+                self.audio.simulate_silence(self.time);
+            }
+        } else if is_file_playing {
+            // Use audio data from file playback, mapping bands with the context's sample rate
+            if let Some(ref ctx) = *self.audio_context.borrow() {
+                self.audio.sample_rate = ctx.sample_rate();
+            }
             let data = self.audio_data.borrow();
-            self.audio.update_from_fft(&data.0, &data.1);
-        } else if self.demo_mode || !*self.audio_initialized.borrow() {
-            self.audio.simulate_demo(self.time);
-        } else {
+            self.audio.update_from_fft(&data.0, &data.1, self.time);
+            drop(data);
+            // Remember the tempo so the demo can stand in for this track later
+            if self.audio.bpm > 0.0 {
+                if let Some(track) = self.playlist.current_index.and_then(|i| self.playlist.tracks.get_mut(i)) {
+                    track.bpm = Some(self.audio.bpm);
+                }
+            }
+        } else if mic_live {
             // Use microphone audio data
             let data = self.audio_data.borrow();
-            self.audio.update_from_fft(&data.0, &data.1);
+            self.audio.update_from_fft(&data.0, &data.1, self.time);
+        } else {
+            self.simulate_demo(self.time, false);
         }
         
         // Beat flash decay
@@ -460,26 +1426,29 @@ impl MusicVisualizerApp {
     fn update_animation(&mut self, dt: f32) {
         self.time += dt as f64;
         
-        // Rotation
-        if self.config.auto_rotate {
+        // Rotation, which attract mode always keeps going
+        if self.config.auto_rotate || self.attract_mode {
             let beat_boost = if self.audio.beat { self.config.rotation_beat_mult } else { 0.0 };
             self.rotation += (self.config.rotation_speed + beat_boost) * dt;
         }
         
-        // Spawn particles on beat (disabled for Unknown Pleasures mode)
+        // Spawn particles on beat (disabled for Unknown Pleasures mode), more
+        // and faster the harder the beat
         if self.audio.beat && self.config.pulse_on_beat && self.visualizer_mode != VisualizerMode::UnknownPleasures {
-            let center = Pos2::new(400.0, 300.0); // Will be updated in render
-            let color = self.get_current_color();
-            for _ in 0..5 {
-                let angle = rand_float() * std::f32::consts::TAU;
-                let speed = 100.0 + rand_float() * 200.0;
-                self.particles.push(Particle::new(center, angle, speed, color));
+            let intensity = self.audio.beat_intensity;
+            for _ in 0..burst_count(self.config.particle_count, intensity) {
+                let angle = rand_float(&mut self.rng_seed) * std::f32::consts::TAU;
+                let speed = (100.0 + rand_float(&mut self.rng_seed) * 200.0) * burst_speed_scale(intensity);
+                let size = 3.0 + rand_float(&mut self.rng_seed) * 5.0;
+                let t = rand_float(&mut self.rng_seed);
+                let color = self.spectrum_particle_color(t);
+                self.particles.push(Particle::new(angle, speed, size, color, self.config.particle_shape));
             }
         }
         
         // Update particles
         for p in &mut self.particles {
-            p.update(dt);
+            p.update(dt, self.config.particle_gravity);
         }
         self.particles.retain(|p| p.is_alive());
         
@@ -512,6 +1481,9 @@ impl MusicVisualizerApp {
                         if ui.selectable_label(self.visualizer_mode == VisualizerMode::UnknownPleasures, "Unknown Pleasures").clicked() {
                             self.visualizer_mode = VisualizerMode::UnknownPleasures;
                         }
+                        if ui.selectable_label(self.visualizer_mode == VisualizerMode::RadialSpectrum, "Radial Spectrum").clicked() {
+                            self.visualizer_mode = VisualizerMode::RadialSpectrum;
+                        }
                     });
 
                 // If Unknown Pleasures is selected, show mode-specific params
@@ -528,19 +1500,34 @@ impl MusicVisualizerApp {
                     });
                     ui.horizontal(|ui| {
                         ui.label("Vertical scale:");
-                        ui.add(egui::DragValue::new(&mut self.config.up_vertical_scale).speed(0.1));
+                        ui.add_enabled(
+                            !self.config.up_auto_fit,
+                            egui::DragValue::new(&mut self.config.up_vertical_scale).speed(0.1),
+                        );
+                        ui.checkbox(&mut self.config.up_auto_fit, "Fit to height")
+                            .on_hover_text("Space the lines and scale their peaks so the stack fills the view without clipping");
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Orientation:");
+                        for orientation in UpOrientation::ALL {
+                            ui.selectable_value(&mut self.config.up_orientation, orientation, orientation.label());
+                        }
                     });
                     ui.horizontal(|ui| {
                         ui.label("Max lines:");
-                        ui.add(egui::DragValue::new(&mut self.config.up_max_lines).speed(1.0));
+                        ui.add(egui::DragValue::new(&mut self.config.up_max_lines).speed(1.0).range(MIN_UP_LINES..=u32::MAX));
                     });
                     ui.horizontal(|ui| {
                         ui.label("Samples per line:");
-                        ui.add(egui::DragValue::new(&mut self.config.up_samples).speed(1.0));
+                        ui.add(egui::DragValue::new(&mut self.config.up_samples).speed(1.0).range(MIN_UP_SAMPLES..=u32::MAX));
                     });
+                    ui.checkbox(&mut self.config.up_mel_scale, "Mel-scale bands");
                     ui.horizontal(|ui| {
                         ui.label("Freq curve exp:");
-                        ui.add(egui::DragValue::new(&mut self.config.up_freq_curve_exponent).speed(0.1));
+                        ui.add_enabled(
+                            !self.config.up_mel_scale,
+                            egui::DragValue::new(&mut self.config.up_freq_curve_exponent).speed(0.1),
+                        );
                     });
                     ui.horizontal(|ui| {
                         ui.label("Smoothing:");
@@ -552,13 +1539,92 @@ impl MusicVisualizerApp {
                             // toggle
                         }
                     });
+                    ui.horizontal(|ui| {
+                        ui.label("Style:");
+                        ui.checkbox(&mut self.config.up_fill_occlusion, "Filled (poster)");
+                    });
+                    ui.checkbox(&mut self.config.up_segment_strokes, "Per-segment strokes")
+                        .on_hover_text("Draw each segment separately instead of one smooth path per line; shows joints at sharp peaks");
+                    ui.checkbox(&mut self.config.up_gradient, "Depth gradient");
+                    ui.horizontal(|ui| {
+                        ui.label("Top:");
+                        ui.color_edit_button_srgba(&mut self.config.up_gradient_top);
+                        ui.label("Bottom:");
+                        ui.color_edit_button_srgba(&mut self.config.up_gradient_bottom);
+                    });
                     ui.add_space(4.0);
                     if ui.button("Apply 'Image' Preset").clicked() {
-                        self.config = VisualizerConfig::preset_unknown_pleasures_image();
+                        self.config.apply_preset(VisualizerConfig::preset_unknown_pleasures_image());
                     }
                 }
             });
             
+            // ===== PRESETS SECTION =====
+            ui.collapsing("🎨 Presets", |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    for (name, config) in presets() {
+                        if ui.button(name).clicked() {
+                            self.config.apply_preset(config);
+                        }
+                    }
+                });
+                
+                // User presets, each with a delete button
+                let mut remove = None;
+                for (i, preset) in self.custom_presets.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        if ui.button(&preset.name).clicked() {
+                            self.config.apply_preset(preset.config.clone());
+                        }
+                        if ui.small_button("🗑").on_hover_text("Delete preset").clicked() {
+                            remove = Some(i);
+                        }
+                    });
+                }
+                if let Some(i) = remove {
+                    self.delete_custom_preset(i);
+                }
+                
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.preset_name)
+                            .hint_text("Preset name")
+                            .desired_width(120.0),
+                    );
+                    let can_save = !self.preset_name.trim().is_empty();
+                    if ui.add_enabled(can_save, egui::Button::new("💾 Save current")).clicked() {
+                        self.save_custom_preset();
+                    }
+                });
+                
+                // Exchange looks as text instead of files
+                if ui.button("📋 Copy Config").on_hover_text("Copy the current settings as a shareable string").clicked() {
+                    ui.ctx().copy_text(self.config.to_share_string());
+                    self.preset_message = Some("Config copied to clipboard".to_string());
+                }
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.share_input)
+                            .hint_text("Paste config string")
+                            .desired_width(120.0),
+                    );
+                    let can_paste = !self.share_input.trim().is_empty();
+                    if ui.add_enabled(can_paste, egui::Button::new("Paste Config")).clicked() {
+                        self.preset_message = Some(match VisualizerConfig::from_share_string(&self.share_input) {
+                            Ok(config) => {
+                                self.config = config;
+                                self.share_input.clear();
+                                "Config applied".to_string()
+                            }
+                            Err(e) => e,
+                        });
+                    }
+                });
+                if let Some(msg) = &self.preset_message {
+                    ui.label(egui::RichText::new(msg).small());
+                }
+            });
+            
             // ===== PLAYLIST SECTION =====
             ui.collapsing("🎶 Playlist", |ui| {
                 // Add music button
@@ -570,11 +1636,23 @@ impl MusicVisualizerApp {
                     // Shuffle button
                     let shuffle_text = if self.playlist.is_shuffled { "🔀 On" } else { "🔀 Off" };
                     if ui.button(shuffle_text).clicked() {
-                        self.playlist.is_shuffled = !self.playlist.is_shuffled;
-                        if self.playlist.is_shuffled {
-                            self.playlist.shuffle_playlist();
-                        }
+                        self.toggle_shuffle();
                     }
+                    
+                    // Sort menu
+                    ui.menu_button("↕ Sort", |ui| {
+                        let options = [
+                            ("Sort by name", SortKey::Name, true),
+                            ("Sort by duration ↑", SortKey::Duration, true),
+                            ("Sort by duration ↓", SortKey::Duration, false),
+                        ];
+                        for (label, key, ascending) in options {
+                            if ui.button(label).clicked() {
+                                self.playlist.sort_tracks(key, ascending, &mut self.rng_seed);
+                                ui.close();
+                            }
+                        }
+                    });
                 });
                 
                 ui.label("Supported: MP3, WAV, OGG, FLAC, AAC, M4A");
@@ -590,58 +1668,41 @@ impl MusicVisualizerApp {
                     );
                 });
                 ui.horizontal(|ui| {
-                    if ui.button("▶ Play").clicked() && !self.youtube_url_input.trim().is_empty() {
-                        let url = self.youtube_url_input.clone();
-                        let is_youtube = url.contains("youtube.com") || url.contains("youtu.be");
-                        let (name, file_type) = if is_youtube {
-                            let name = if let Some(id) = extract_youtube_id(&url) {
-                                format!("YouTube - {}", id)
-                            } else {
-                                "YouTube".to_string()
-                            };
-                            (name, "youtube".to_string())
-                        } else {
-                            // Direct audio URL
-                            let name = url.split('/').last().unwrap_or("Audio").to_string();
-                            let ext = url.split('.').last().unwrap_or("mp3").to_lowercase();
-                            (name, ext)
-                        };
-                        
-                        self.playlist.tracks.push(PlaylistTrack {
-                            name,
-                            duration: 0.0,
-                            file_type,
-                            url,
-                        });
-                        let idx = self.playlist.tracks.len().saturating_sub(1);
-                        self.play_track(idx);
-                        self.youtube_url_input.clear();
-                    }
-                    if ui.button("➕ Queue").clicked() && !self.youtube_url_input.trim().is_empty() {
-                        let url = self.youtube_url_input.clone();
-                        let is_youtube = url.contains("youtube.com") || url.contains("youtu.be");
-                        let (name, file_type) = if is_youtube {
-                            let name = if let Some(id) = extract_youtube_id(&url) {
-                                format!("YouTube - {}", id)
-                            } else {
-                                "YouTube".to_string()
-                            };
-                            (name, "youtube".to_string())
-                        } else {
-                            let name = url.split('/').last().unwrap_or("Audio").to_string();
-                            let ext = url.split('.').last().unwrap_or("mp3").to_lowercase();
-                            (name, ext)
-                        };
-                        
-                        self.playlist.tracks.push(PlaylistTrack {
-                            name,
-                            duration: 0.0,
-                            file_type,
-                            url,
-                        });
-                        self.youtube_url_input.clear();
+                    let play = ui.button("▶ Play").clicked();
+                    let queue = ui.button("➕ Queue").clicked();
+                    if (play || queue) && !self.youtube_url_input.trim().is_empty() {
+                        let url = self.youtube_url_input.trim().to_string();
+                        match classify_url(&url) {
+                            Ok((name, file_type)) => {
+                                *self.url_error.borrow_mut() = None;
+                                if queue && file_type != "youtube" {
+                                    self.load_track_duration(&url);
+                                }
+                                self.playlist.tracks.push(PlaylistTrack {
+                                    name,
+                                    duration: 0.0,
+                                    file_type,
+                                    url,
+                                    bpm: None,
+                                });
+                                if play {
+                                    let idx = self.playlist.tracks.len().saturating_sub(1);
+                                    self.play_track(idx);
+                                }
+                                self.youtube_url_input.clear();
+                            }
+                            Err(msg) => *self.url_error.borrow_mut() = Some(msg),
+                        }
                     }
                 });
+                
+                // Show URL or playback problems, including the YouTube analysis note
+                if let Some(err) = self.url_error.borrow().as_ref() {
+                    ui.colored_label(Color32::from_rgb(255, 100, 100), format!("⚠ {}", err));
+                    if self.current_track_is_youtube() {
+                        ui.label("Try pasting a direct audio URL instead.");
+                    }
+                }
                 ui.add_space(4.0);
                 
                 // Current track info and progress
@@ -655,7 +1716,7 @@ impl MusicVisualizerApp {
                         let total_time = PlaylistState::format_time(self.playlist.duration);
                         
                         ui.horizontal(|ui| {
-                            ui.label(&current_time);
+                            self.seek_time_ui(ui, &current_time);
                             
                             // Interactive progress slider
                             let duration = self.playlist.duration.max(1.0); // avoid divide by zero
@@ -694,6 +1755,11 @@ impl MusicVisualizerApp {
                             if ui.button("⏹").clicked() {
                                 self.stop_playback();
                             }
+
+                            // Repeat mode (cycles Off → All → One)
+                            if ui.button(self.playlist.repeat.label()).clicked() {
+                                self.playlist.repeat = self.playlist.repeat.next();
+                            }
                         });
                         
                         // Volume control
@@ -704,12 +1770,7 @@ impl MusicVisualizerApp {
                             }
                         });
                     });
-                    
-                    // Show YouTube info/error if any
-                    if let Some(err) = self.youtube_error.borrow().as_ref() {
-                        ui.colored_label(Color32::from_rgb(255, 100, 100), format!("⚠ {}", err));
-                        ui.label("Try pasting a direct audio URL instead.");
-                    }
+
                 } else {
                     ui.colored_label(Color32::GRAY, "No track selected");
                 }
@@ -719,15 +1780,31 @@ impl MusicVisualizerApp {
                 // Track list
                 if !self.playlist.tracks.is_empty() {
                     ui.label(format!("Tracks ({}):", self.playlist.tracks.len()));
+                    ui.horizontal(|ui| {
+                        ui.label("🔍");
+                        ui.add(egui::TextEdit::singleline(&mut self.playlist_filter).hint_text("Filter tracks…"));
+                        if !self.playlist_filter.is_empty() && ui.small_button("✖").clicked() {
+                            self.playlist_filter.clear();
+                        }
+                    });
+                    let query = self.playlist_filter.to_lowercase();
                     
                     let mut track_to_play: Option<usize> = None;
                     let mut track_to_remove: Option<usize> = None;
+                    let mut track_to_move: Option<(usize, usize)> = None;
                     
                     egui::ScrollArea::vertical()
                         .max_height(150.0)
                         .id_salt("playlist_scroll")
                         .show(ui, |ui| {
-                            for (idx, track) in self.playlist.tracks.iter().enumerate() {
+                            // Filter the enumerated list so rows keep their true playlist index
+                            let visible = self
+                                .playlist
+                                .tracks
+                                .iter()
+                                .enumerate()
+                                .filter(|(_, t)| query.is_empty() || t.name.to_lowercase().contains(&query));
+                            for (idx, track) in visible {
                                 let is_current = self.playlist.current_index == Some(idx);
                                 let bg_color = if is_current {
                                     Color32::from_rgba_unmultiplied(100, 200, 255, 30)
@@ -735,11 +1812,16 @@ impl MusicVisualizerApp {
                                     Color32::TRANSPARENT
                                 };
                                 
-                                egui::Frame::new()
+                                let row = egui::Frame::new()
                                     .fill(bg_color)
                                     .inner_margin(4.0)
                                     .show(ui, |ui| {
                                         ui.horizontal(|ui| {
+                                            // Drag handle: drop onto another row to move the track there
+                                            ui.dnd_drag_source(egui::Id::new(("playlist_track", idx)), idx, |ui| {
+                                                ui.label("☰");
+                                            });
+                                            
                                             // Track number/playing indicator
                                             if is_current && self.playlist.is_playing {
                                                 ui.label("▶");
@@ -763,7 +1845,21 @@ impl MusicVisualizerApp {
                                                 ui.label(PlaylistState::format_time(track.duration));
                                             });
                                         });
-                                    });
+                                    })
+                                    .response;
+                                
+                                if let Some(from) = row.dnd_hover_payload::<usize>().filter(|from| **from != idx) {
+                                    // Mark the edge the dragged track will land on
+                                    let y = if *from > idx { row.rect.top() } else { row.rect.bottom() };
+                                    ui.painter().hline(
+                                        row.rect.x_range(),
+                                        y,
+                                        egui::Stroke::new(2.0, Color32::from_rgb(100, 200, 255)),
+                                    );
+                                }
+                                if let Some(from) = row.dnd_release_payload::<usize>() {
+                                    track_to_move = Some((*from, idx));
+                                }
                             }
                         });
                     
@@ -774,6 +1870,9 @@ impl MusicVisualizerApp {
                     if let Some(idx) = track_to_remove {
                         self.remove_track(idx);
                     }
+                    if let Some((from, to)) = track_to_move {
+                        self.playlist.reorder_track(from, to);
+                    }
                     
                     // Clear all button
                     ui.add_space(4.0);
@@ -807,25 +1906,96 @@ impl MusicVisualizerApp {
                 ui.colored_label(Color32::YELLOW, "⏳ Initializing microphone...");
             }
             
+            // Demo signal character
+            if self.demo_mode {
+                ui.horizontal(|ui| {
+                    ui.label("Demo BPM:");
+                    ui.add(egui::DragValue::new(&mut self.config.demo_bpm).range(40.0..=220.0).speed(1.0));
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Bass:");
+                    ui.add(egui::Slider::new(&mut self.config.demo_bass_intensity, 0.0..=2.0));
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Treble:");
+                    ui.add(egui::Slider::new(&mut self.config.demo_treble_intensity, 0.0..=2.0));
+                });
+                ui.checkbox(&mut self.config.demo_follow_track, "Follow loaded track")
+                    .on_hover_text("Use the current track's measured tempo instead of the demo BPM");
+            }
+            
             ui.separator();
             
             // Audio levels
             ui.collapsing("📊 Audio Levels", |ui| {
+                // Smoothed level as the bar, this frame's level as the peak
+                let a = &self.audio;
+                for (label, rms, peak) in [
+                    ("Bass:", a.smooth_bass, a.bass),
+                    ("Mid:", a.smooth_mid, a.mid),
+                    ("Treble:", a.smooth_treble, a.treble),
+                    ("Volume:", a.smooth_volume, a.peak),
+                ] {
+                    ui.horizontal(|ui| {
+                        ui.add_sized([50.0, 14.0], egui::Label::new(label));
+                        let (rect, _) = ui.allocate_exact_size(egui::vec2(ui.available_width(), 14.0), egui::Sense::hover());
+                        ui::draw_vu_meter(ui, ui.id().with(label), rect, rms, peak);
+                    });
+                }
                 ui.horizontal(|ui| {
-                    ui.label("Bass:");
-                    ui.add(egui::ProgressBar::new(self.audio.smooth_bass).show_percentage());
+                    ui.label("Smoothing:");
+                    ui.add(egui::Slider::new(&mut self.config.band_smoothing, 0.0..=1.0))
+                        .on_hover_text("Lower is sluggish and smooth, higher is snappy");
                 });
+                ui.checkbox(&mut self.config.a_weighting, "A-weighting")
+                    .on_hover_text("Weight bands and volume by perceived loudness");
+                ui.checkbox(&mut self.show_band_debug, "Band debug view")
+                    .on_hover_text("Plot raw and smoothed bass/mid/treble with beat markers over the last few seconds");
                 ui.horizontal(|ui| {
-                    ui.label("Mid:");
-                    ui.add(egui::ProgressBar::new(self.audio.smooth_mid).show_percentage());
+                    ui.checkbox(&mut self.config.auto_gain, "Auto gain")
+                        .on_hover_text("Scale bands to the recent peak level so quiet and loud tracks react alike");
+                    ui.add_enabled(
+                        self.config.auto_gain,
+                        egui::Slider::new(&mut self.config.auto_gain_strength, 0.0..=1.0).text("strength"),
+                    );
                 });
+                let mut analyser_changed = false;
                 ui.horizontal(|ui| {
-                    ui.label("Treble:");
-                    ui.add(egui::ProgressBar::new(self.audio.smooth_treble).show_percentage());
+                    ui.label("FFT size:");
+                    for size in FFT_SIZES {
+                        analyser_changed |= ui
+                            .selectable_value(&mut self.web_audio_config.fft_size, size, size.to_string())
+                            .on_hover_text(
+                                "Larger FFTs give finer frequency detail (more distinct Unknown Pleasures lines) \
+                                 but react more slowly and cost more CPU",
+                            )
+                            .changed();
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Analyser smoothing:");
+                    analyser_changed |= ui
+                        .add(egui::Slider::new(&mut self.web_audio_config.smoothing_time_constant, 0.0..=0.99))
+                        .changed();
+                });
+                if analyser_changed {
+                    self.apply_web_audio_config();
+                }
+                ui.label("Band edges (Hz):");
+                let mut edges_changed = false;
+                ui.horizontal(|ui| {
+                    for edge in &mut self.config.band_edges_hz[1..5] {
+                        edges_changed |= ui.add(egui::DragValue::new(edge).speed(10.0)).changed();
+                    }
                 });
+                if edges_changed {
+                    self.config.band_edges_hz = sorted_band_edges(self.config.band_edges_hz);
+                }
+                ui.label(format!("BPM: {:.0}", self.audio.bpm));
                 ui.horizontal(|ui| {
-                    ui.label("Volume:");
-                    ui.add(egui::ProgressBar::new(self.audio.smooth_volume).show_percentage());
+                    ui.label("Beat sensitivity:");
+                    ui.add(egui::DragValue::new(&mut self.config.beat_sensitivity).speed(0.01))
+                        .on_hover_text("Bass must exceed its ~1s rolling average by this factor to count as a beat");
                 });
                 if self.audio.beat {
                     ui.colored_label(Color32::from_rgb(255, 100, 100), "🥁 BEAT!");
@@ -851,6 +2021,8 @@ impl MusicVisualizerApp {
                     ui.horizontal(|ui| {
                         ui.label("Line thickness:");
                         ui.add(egui::DragValue::new(&mut self.config.up_line_thickness).speed(0.1));
+                        ui.checkbox(&mut self.config.up_thickness_follows_zoom, "Scale with zoom")
+                            .on_hover_text("Widen lines as you zoom in so they stay visible");
                     });
                     ui.horizontal(|ui| {
                         ui.label("Line length:");
@@ -862,9 +2034,27 @@ impl MusicVisualizerApp {
                     });
                     ui.horizontal(|ui| {
                         ui.label("Vertical scale:");
-                        ui.add(egui::DragValue::new(&mut self.config.up_vertical_scale).speed(0.1));
+                        ui.add_enabled(
+                            !self.config.up_auto_fit,
+                            egui::DragValue::new(&mut self.config.up_vertical_scale).speed(0.1),
+                        );
+                        ui.checkbox(&mut self.config.up_auto_fit, "Fit to height")
+                            .on_hover_text("Space the lines and scale their peaks so the stack fills the view without clipping");
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Orientation:");
+                        for orientation in UpOrientation::ALL {
+                            ui.selectable_value(&mut self.config.up_orientation, orientation, orientation.label());
+                        }
                     });
                 } else {
+                    egui::ComboBox::from_label("Shape")
+                        .selected_text(self.config.fractal_kind.label())
+                        .show_ui(ui, |ui| {
+                            for kind in FractalKind::ALL {
+                                ui.selectable_value(&mut self.config.fractal_kind, kind, kind.label());
+                            }
+                        });
                     ui.horizontal(|ui| {
                         ui.label("Zoom:");
                         ui.add(egui::DragValue::new(&mut self.config.base_zoom).speed(0.01));
@@ -875,12 +2065,23 @@ impl MusicVisualizerApp {
                     });
                     ui.horizontal(|ui| {
                         ui.label("Depth:");
-                        ui.add(egui::DragValue::new(&mut self.config.base_depth).speed(1.0));
+                        ui.add(egui::DragValue::new(&mut self.config.base_depth).speed(1.0).range(1..=MAX_BASE_DEPTH));
                     });
                     ui.horizontal(|ui| {
                         ui.label("Brightness:");
                         ui.add(egui::DragValue::new(&mut self.config.base_brightness).speed(0.01));
                     });
+                    ui.horizontal(|ui| {
+                        ui.label("Branches:");
+                        ui.add(egui::DragValue::new(&mut self.config.fractal_branches).range(MIN_BRANCHES..=MAX_BRANCHES));
+                        ui.label("Spread:");
+                        ui.add(egui::DragValue::new(&mut self.config.fractal_spread_deg).range(0.0..=360.0).suffix("°"));
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Depth hue shift:");
+                        ui.add(egui::Slider::new(&mut self.config.fractal_depth_hue_shift, -0.25..=0.25))
+                            .on_hover_text("Hue rotation per branch level; 0 keeps one colour");
+                    });
 
                     ui.add_space(4.0);
                     ui.horizontal(|ui| {
@@ -905,21 +2106,46 @@ impl MusicVisualizerApp {
                         ui.add(egui::DragValue::new(&mut self.config.up_treble_mult).speed(0.01));
                     });
                 } else {
+                    let mut remove = None;
+                    for (i, entry) in self.config.reactivity.entries.iter_mut().enumerate() {
+                        ui.horizontal(|ui| {
+                            egui::ComboBox::from_id_salt(("react_source", i))
+                                .width(90.0)
+                                .selected_text(entry.source.label())
+                                .show_ui(ui, |ui| {
+                                    for f in AudioFeature::ALL {
+                                        ui.selectable_value(&mut entry.source, f, f.label());
+                                    }
+                                });
+                            ui.label("→");
+                            egui::ComboBox::from_id_salt(("react_target", i))
+                                .width(90.0)
+                                .selected_text(entry.target.label())
+                                .show_ui(ui, |ui| {
+                                    for p in ConfigParam::ALL {
+                                        ui.selectable_value(&mut entry.target, p, p.label());
+                                    }
+                                });
+                            ui.add(egui::DragValue::new(&mut entry.multiplier).speed(0.01));
+                            if ui.small_button("✖").clicked() {
+                                remove = Some(i);
+                            }
+                        });
+                    }
+                    if let Some(i) = remove {
+                        self.config.reactivity.entries.remove(i);
+                    }
                     ui.horizontal(|ui| {
-                        ui.label("Bass → Zoom:");
-                        ui.add(egui::DragValue::new(&mut self.config.zoom_bass_mult).speed(0.01));
-                    });
-                    ui.horizontal(|ui| {
-                        ui.label("Bass → Width:");
-                        ui.add(egui::DragValue::new(&mut self.config.width_bass_mult).speed(0.01));
-                    });
-                    ui.horizontal(|ui| {
-                        ui.label("Complexity → Depth:");
-                        ui.add(egui::DragValue::new(&mut self.config.depth_complexity_mult).speed(0.1));
-                    });
-                    ui.horizontal(|ui| {
-                        ui.label("Treble → Brightness:");
-                        ui.add(egui::DragValue::new(&mut self.config.brightness_treble_mult).speed(0.01));
+                        if ui.small_button("➕ Add mapping").clicked() {
+                            self.config.reactivity.entries.push(ReactivityEntry {
+                                source: AudioFeature::Bass,
+                                target: ConfigParam::Zoom,
+                                multiplier: 0.1,
+                            });
+                        }
+                        if ui.small_button("Reset").clicked() {
+                            self.config.reactivity = ReactivityMap::default();
+                        }
                     });
                 }
             });
@@ -932,6 +2158,28 @@ impl MusicVisualizerApp {
                     ui.add(egui::DragValue::new(&mut self.config.rotation_speed).speed(0.01));
                 });
                 ui.checkbox(&mut self.config.pulse_on_beat, "Pulse on Beat");
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.config.beat_strobe, "Beat strobe");
+                    ui.color_edit_button_srgba(&mut self.config.strobe_color);
+                    ui.add_enabled(
+                        self.config.beat_strobe,
+                        egui::Slider::new(&mut self.config.strobe_intensity, 0.0..=MAX_STROBE_INTENSITY).text("Intensity"),
+                    );
+                });
+                if self.config.beat_strobe {
+                    ui.colored_label(Color32::YELLOW, "⚠ Flashing light can trigger seizures in photosensitive viewers");
+                }
+                ui.horizontal(|ui| {
+                    ui.label("Particle gravity:");
+                    ui.add(egui::DragValue::new(&mut self.config.particle_gravity.x).speed(1.0).prefix("x: "));
+                    ui.add(egui::DragValue::new(&mut self.config.particle_gravity.y).speed(1.0).prefix("y: "));
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Particle shape:");
+                    for shape in ParticleShape::ALL {
+                        ui.selectable_value(&mut self.config.particle_shape, shape, shape.label());
+                    }
+                });
                 ui.checkbox(&mut self.config.color_cycle, "Color Cycle");
                 ui.horizontal(|ui| {
                     ui.label("Color Speed:");
@@ -941,19 +2189,82 @@ impl MusicVisualizerApp {
             
             // Display options
             ui.collapsing("🖥️ Display", |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Palette:");
+                    egui::ComboBox::from_id_salt("palette")
+                        .selected_text("Choose…")
+                        .show_ui(ui, |ui| {
+                            for palette in Palette::ALL {
+                                if ui.selectable_label(false, palette.label()).clicked() {
+                                    self.config.apply_palette(palette);
+                                }
+                            }
+                        });
+                });
+                ui.checkbox(&mut self.config.background_gradient, "Background gradient");
+                ui.add_enabled_ui(self.config.background_gradient, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Top:");
+                        ui.color_edit_button_srgba(&mut self.config.background_top);
+                        ui.label("Bottom:");
+                        ui.color_edit_button_srgba(&mut self.config.background_bottom);
+                    });
+                });
                 ui.checkbox(&mut self.show_spectrum, "Show Spectrum");
+                ui.horizontal(|ui| {
+                    ui.label("Spectrum bars:");
+                    ui.add(egui::DragValue::new(&mut self.config.spectrum_bars).range(4..=256));
+                });
+                ui.checkbox(&mut self.config.spectrum_log_axis, "Log frequency axis");
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.config.spectrum_peak_hold, "Peak hold");
+                    ui.label("Decay/s:");
+                    ui.add_enabled(
+                        self.config.spectrum_peak_hold,
+                        egui::DragValue::new(&mut self.config.spectrum_peak_decay).speed(0.01).range(0.0..=10.0),
+                    );
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Spectrum smoothing:");
+                    ui.add(egui::Slider::new(&mut self.config.spectrum_smoothing, 0.0..=0.95));
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Max FPS:");
+                    ui.add(egui::DragValue::new(&mut self.config.max_fps).range(10..=240))
+                        .on_hover_text("Lower values save CPU; drops to a few FPS when paused and silent");
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Attract mode after:");
+                    ui.add(egui::DragValue::new(&mut self.config.idle_timeout_secs).range(0.0..=3600.0).suffix(" s"))
+                        .on_hover_text("Animate on its own after this long with nothing playing or heard; 0 turns it off");
+                });
                 ui.checkbox(&mut self.show_waveform, "Show Waveform");
+                ui.horizontal(|ui| {
+                    ui.label("Waveform style:");
+                    for style in WaveformStyle::ALL {
+                        ui.selectable_value(&mut self.config.waveform_style, style, style.label());
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Waveform smoothing:");
+                    ui.add(egui::Slider::new(&mut self.config.waveform_smoothing, 0.0..=0.95));
+                });
                 ui.horizontal(|ui| {
                     ui.label("Glow:");
                     ui.add(egui::DragValue::new(&mut self.config.glow_intensity).speed(0.01));
                 });
+                ui.horizontal(|ui| {
+                    ui.label("Fractal trails:");
+                    ui.add(egui::Slider::new(&mut self.config.trail_amount, 0.0..=0.95))
+                        .on_hover_text("How much of the previous frame stays behind; 0 clears every frame");
+                });
             });
             
             ui.separator();
             
             // Reset button
             if ui.button("🔄 Reset Settings").clicked() {
-                self.config = VisualizerConfig::default();
+                self.config.apply_preset(VisualizerConfig::default());
             }
         });
     }
@@ -979,11 +2290,13 @@ impl MusicVisualizerApp {
         let pending = self.pending_tracks.borrow().clone();
         if !pending.is_empty() {
             for (name, file_type, url) in pending {
+                self.load_track_duration(&url);
                 self.playlist.tracks.push(PlaylistTrack {
                     name,
                     duration: 0.0, // Will be updated when metadata loads
                     file_type,
                     url,
+                    bpm: None,
                 });
             }
             self.pending_tracks.borrow_mut().clear();
@@ -995,6 +2308,45 @@ impl MusicVisualizerApp {
         }
     }
     
+    /// Load only the metadata of `url` in a throwaway audio element and queue
+    /// its duration for `apply_loaded_durations`.
+    fn load_track_duration(&self, url: &str) {
+        let Some(document) = web_sys::window().and_then(|w| w.document()) else { return };
+        let Some(probe) = document
+            .create_element("audio")
+            .ok()
+            .and_then(|el| el.dyn_into::<web_sys::HtmlAudioElement>().ok())
+        else {
+            return;
+        };
+        probe.set_preload("metadata");
+        
+        let loaded_durations = self.loaded_durations.clone();
+        let probe_for_cb = probe.clone();
+        let url_for_cb = url.to_string();
+        let on_loaded = Closure::wrap(Box::new(move || {
+            let duration = probe_for_cb.duration();
+            if duration.is_finite() {
+                loaded_durations.borrow_mut().push((url_for_cb.clone(), duration));
+            }
+            // Release the element; it is never attached to the document
+            probe_for_cb.set_onloadedmetadata(None);
+            probe_for_cb.remove_attribute("src").ok();
+        }) as Box<dyn FnMut()>);
+        probe.set_onloadedmetadata(Some(on_loaded.as_ref().unchecked_ref()));
+        on_loaded.forget();
+        probe.set_src(url);
+    }
+    
+    fn apply_loaded_durations(&mut self) {
+        let loaded: Vec<(String, f64)> = self.loaded_durations.borrow_mut().drain(..).collect();
+        for (url, duration) in loaded {
+            for track in self.playlist.tracks.iter_mut().filter(|t| t.url == url) {
+                track.duration = duration;
+            }
+        }
+    }
+    
     fn trigger_file_input(&self) {
         // Create a hidden file input element and trigger it
         if let Some(window) = web_sys::window() {
@@ -1025,6 +2377,7 @@ impl MusicVisualizerApp {
                 let audio_data = self.audio_data.clone();
                 let file_audio_initialized = self.file_audio_initialized.clone();
                 let pending_tracks = self.pending_tracks.clone();
+                let web_audio_config = self.web_audio_config;
                 
                 let closure = Closure::wrap(Box::new(move |event: web_sys::Event| {
                     let input = event.target()
@@ -1073,8 +2426,7 @@ impl MusicVisualizerApp {
                                             if audio_context.borrow().is_none() {
                                                 if let Ok(ctx) = web_sys::AudioContext::new() {
                                                     if let Ok(analyser) = ctx.create_analyser() {
-                                                        analyser.set_fft_size(512);
-                                                        analyser.set_smoothing_time_constant(0.8);
+                                                        web_audio_config.apply(&analyser);
                                                         
                                                         if let Ok(source) = ctx.create_media_element_source(&audio) {
                                                             source.connect_with_audio_node(&analyser).ok();
@@ -1085,28 +2437,12 @@ impl MusicVisualizerApp {
                                                             *file_audio_initialized.borrow_mut() = true;
                                                             
                                                             // Start polling audio data
-                                                            let analyser_for_poll = analyser_node.clone();
-                                                            let audio_data_for_poll = audio_data.clone();
-                                                            
-                                                            let poll_callback = Closure::wrap(Box::new(move || {
-                                                                if let Some(ref analyser) = *analyser_for_poll.borrow() {
-                                                                    let freq_len = analyser.frequency_bin_count() as usize;
-                                                                    let time_len = analyser.fft_size() as usize;
-                                                                    let mut freq_data = vec![0u8; freq_len];
-                                                                    let mut time_data = vec![0u8; time_len];
-                                                                    
-                                                                    analyser.get_byte_frequency_data(&mut freq_data);
-                                                                    analyser.get_byte_time_domain_data(&mut time_data);
-                                                                    
-                                                                    *audio_data_for_poll.borrow_mut() = (freq_data, time_data);
-                                                                }
-                                                            }) as Box<dyn Fn()>);
-                                                            
-                                                            window.set_interval_with_callback_and_timeout_and_arguments_0(
-                                                                poll_callback.as_ref().unchecked_ref(),
-                                                                16,
-                                                            ).ok();
-                                                            poll_callback.forget();
+                                                            start_analyser_polling(
+                                                                &window,
+                                                                analyser_node.clone(),
+                                                                audio_data.clone(),
+                                                                web_audio_config.poll_interval_ms,
+                                                            );
                                                         }
                                                     }
                                                 }
@@ -1142,9 +2478,11 @@ impl MusicVisualizerApp {
         let track = self.playlist.tracks[index].clone();
 
         if track.file_type == "youtube" {
-            // YouTube: show embedded player, use demo mode for visualization
-            self.demo_mode = true; // Visualization will use demo/simulated audio
-            *self.youtube_error.borrow_mut() = None;
+            // YouTube: show embedded player and follow its play/pause state
+            *self.url_error.borrow_mut() = Some(YOUTUBE_CORS_NOTE.to_string());
+            *self.youtube_playback.borrow_mut() = YoutubePlayback::default();
+            self.youtube_clock = 0.0;
+            self.install_youtube_listener();
             
             if let Some(video_id) = extract_youtube_id(&track.url) {
                 // Create or update YouTube iframe player
@@ -1161,38 +2499,53 @@ impl MusicVisualizerApp {
                             .unwrap_or_else(|| {
                                 let div = document.create_element("div").unwrap();
                                 div.set_id("youtube_player_container");
-                                div.set_attribute("style", 
-                                    "position:fixed; bottom:10px; right:10px; z-index:9999; \
-                                     background:#222; border-radius:8px; padding:5px; \
-                                     box-shadow: 0 4px 12px rgba(0,0,0,0.5);"
-                                ).ok();
+                                div.set_attribute("style", YOUTUBE_CONTAINER_STYLE).ok();
                                 document.body().unwrap().append_child(&div).ok();
                                 div
                             });
 
-                        // Set iframe content
+                        // Set iframe content; enablejsapi lets the player report its state
+                        let origin = window.location().origin().unwrap_or_default();
                         let embed_url = format!(
-                            "https://www.youtube.com/embed/{}?autoplay=1&controls=1",
-                            video_id
+                            "{}/embed/{}?autoplay=1&controls=1&enablejsapi=1&origin={}",
+                            YOUTUBE_ORIGIN,
+                            video_id,
+                            js_sys::encode_uri_component(&origin)
                         );
-                        container.set_inner_html(&format!(
-                            r#"<iframe width="320" height="180" src="{}" 
-                               frameborder="0" allow="autoplay; encrypted-media" 
-                               allowfullscreen style="border-radius:4px;"></iframe>
-                               <button onclick="this.parentElement.style.display='none'" 
-                               style="position:absolute;top:-8px;right:-8px;background:#ff4444;
-                               color:white;border:none;border-radius:50%;width:24px;height:24px;
-                               cursor:pointer;font-size:14px;">✕</button>"#,
-                            embed_url
-                        ));
+                        // Build the player with DOM calls so nothing from the link is parsed as HTML
+                        container.set_inner_html("");
+                        if let Ok(iframe) = document.create_element("iframe") {
+                            iframe.set_id("youtube_player");
+                            for (name, value) in [
+                                ("width", "320"),
+                                ("height", "180"),
+                                ("src", embed_url.as_str()),
+                                ("frameborder", "0"),
+                                ("allow", "autoplay; encrypted-media"),
+                                ("allowfullscreen", ""),
+                                ("style", "border-radius:4px;"),
+                            ] {
+                                iframe.set_attribute(name, value).ok();
+                            }
+                            container.append_child(&iframe).ok();
+                        }
+                        if let Ok(close) = document.create_element("button") {
+                            close.set_text_content(Some("✕"));
+                            close.set_attribute("onclick", "this.parentElement.style.display='none'").ok();
+                            close.set_attribute("style", YOUTUBE_CLOSE_STYLE).ok();
+                            container.append_child(&close).ok();
+                        }
+                        container.set_attribute("style", YOUTUBE_CONTAINER_STYLE).ok();
+                        self.subscribe_to_youtube_player();
                     }
                 }
             } else {
-                *self.youtube_error.borrow_mut() = Some("Could not extract YouTube video ID".to_string());
+                *self.url_error.borrow_mut() = Some("Could not extract YouTube video ID".to_string());
             }
         } else {
             // Standard audio file playback
             self.demo_mode = false;
+            *self.url_error.borrow_mut() = None;
             self.play_audio_url(&track.url);
             
             // Hide YouTube player if visible
@@ -1212,6 +2565,21 @@ impl MusicVisualizerApp {
         self.ensure_audio_element_initialized();
         
         if let Some(ref audio) = *self.audio_element.borrow() {
+            // Report load/decode failures; replacing the handler drops the previous track's
+            let url_error = self.url_error.clone();
+            let failed_url = url.to_string();
+            let on_error = Closure::wrap(Box::new(move || {
+                // Local files are blob: URLs, so only name remote ones
+                let msg = if failed_url.starts_with("blob:") {
+                    "Unsupported or unreadable audio file".to_string()
+                } else {
+                    format!("Unsupported or unreachable URL: {}", failed_url)
+                };
+                *url_error.borrow_mut() = Some(msg);
+            }) as Box<dyn FnMut()>);
+            audio.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+            *self.audio_error_handler.borrow_mut() = Some(on_error);
+            
             audio.set_src(url);
             audio.set_current_time(0.0);
             let _ = audio.play();
@@ -1247,8 +2615,7 @@ impl MusicVisualizerApp {
                 if self.audio_context.borrow().is_none() {
                     if let Ok(ctx) = web_sys::AudioContext::new() {
                         if let Ok(analyser) = ctx.create_analyser() {
-                            analyser.set_fft_size(512);
-                            analyser.set_smoothing_time_constant(0.8);
+                            self.web_audio_config.apply(&analyser);
                             
                             if let Ok(source) = ctx.create_media_element_source(&audio) {
                                 source.connect_with_audio_node(&analyser).ok();
@@ -1259,28 +2626,12 @@ impl MusicVisualizerApp {
                                 *self.file_audio_initialized.borrow_mut() = true;
                                 
                                 // Start polling audio data
-                                let analyser_for_poll = self.analyser_node.clone();
-                                let audio_data_for_poll = self.audio_data.clone();
-                                
-                                let poll_callback = Closure::wrap(Box::new(move || {
-                                    if let Some(ref analyser) = *analyser_for_poll.borrow() {
-                                        let freq_len = analyser.frequency_bin_count() as usize;
-                                        let time_len = analyser.fft_size() as usize;
-                                        let mut freq_data = vec![0u8; freq_len];
-                                        let mut time_data = vec![0u8; time_len];
-                                        
-                                        analyser.get_byte_frequency_data(&mut freq_data);
-                                        analyser.get_byte_time_domain_data(&mut time_data);
-                                        
-                                        *audio_data_for_poll.borrow_mut() = (freq_data, time_data);
-                                    }
-                                }) as Box<dyn Fn()>);
-                                
-                                window.set_interval_with_callback_and_timeout_and_arguments_0(
-                                    poll_callback.as_ref().unchecked_ref(),
-                                    16,
-                                ).ok();
-                                poll_callback.forget();
+                                start_analyser_polling(
+                                    &window,
+                                    self.analyser_node.clone(),
+                                    self.audio_data.clone(),
+                                    self.web_audio_config.poll_interval_ms,
+                                );
                             }
                         }
                     }
@@ -1290,6 +2641,11 @@ impl MusicVisualizerApp {
     }
     
     fn toggle_playback(&mut self) {
+        if self.current_track_is_youtube() {
+            let playing = self.youtube_playback.borrow().playing;
+            self.youtube_command(if playing { "pauseVideo" } else { "playVideo" });
+            return;
+        }
         if let Some(ref audio) = *self.audio_element.borrow() {
             if self.playlist.is_playing {
                 let _ = audio.pause();
@@ -1301,7 +2657,131 @@ impl MusicVisualizerApp {
         }
     }
     
+    /// Enter or leave presentation mode, taking the page fullscreen with it.
+    /// The browser may refuse fullscreen; the panels are hidden either way.
+    fn set_presentation_mode(&mut self, ctx: &egui::Context, on: bool) {
+        self.presentation_mode = on;
+        self.presentation_overlay_until = if on { ctx.input(|i| i.time) + PRESENTATION_OVERLAY_SECS } else { 0.0 };
+        let Some(document) = web_sys::window().and_then(|w| w.document()) else { return };
+        if on {
+            self.install_fullscreen_listener(&document);
+            if let Some(root) = document.document_element() {
+                let _ = root.request_fullscreen();
+            }
+        } else if document.fullscreen_element().is_some() {
+            document.exit_fullscreen();
+        }
+    }
+    
+    /// Watch for the page leaving fullscreen, so exiting it with the
+    /// browser's own Esc also leaves presentation mode. Installed once.
+    fn install_fullscreen_listener(&mut self, document: &web_sys::Document) {
+        if self.fullscreen_listener_installed {
+            return;
+        }
+        let exited = self.fullscreen_exited.clone();
+        let document_for_cb = document.clone();
+        let on_change = Closure::wrap(Box::new(move || {
+            if document_for_cb.fullscreen_element().is_none() {
+                exited.set(true);
+            }
+        }) as Box<dyn FnMut()>);
+        if document
+            .add_event_listener_with_callback("fullscreenchange", on_change.as_ref().unchecked_ref())
+            .is_ok()
+        {
+            self.fullscreen_listener_installed = true;
+        }
+        on_change.forget();
+    }
+    
+    /// Leave presentation mode once the page has left fullscreen. A refused
+    /// fullscreen request never fires the event, so the panels stay hidden then.
+    fn sync_presentation_with_fullscreen(&mut self, ctx: &egui::Context) {
+        if !self.fullscreen_exited.take() || !self.presentation_mode {
+            return;
+        }
+        let fullscreen = web_sys::window()
+            .and_then(|w| w.document())
+            .is_some_and(|d| d.fullscreen_element().is_some());
+        if !fullscreen {
+            self.set_presentation_mode(ctx, false);
+        }
+    }
+    
+    fn toggle_shuffle(&mut self) {
+        self.playlist.is_shuffled = !self.playlist.is_shuffled;
+        if self.playlist.is_shuffled {
+            self.playlist.shuffle_playlist(&mut self.rng_seed);
+        }
+    }
+    
+    /// Playback hotkeys, listed in [`SHORTCUTS`]. Ignored while a text field
+    /// such as the URL box has focus.
+    fn handle_shortcuts(&mut self, ctx: &egui::Context) {
+        use egui::{Key, Modifiers};
+        
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+        let (toggle_help, toggle_presentation, play, seek_back, seek_fwd, prev, next, louder, quieter, shuffle, freeze) = ctx.input_mut(|i| {
+            (
+                i.events.iter().any(|e| matches!(e, egui::Event::Text(t) if t == "?")),
+                i.consume_key(Modifiers::NONE, Key::F11),
+                i.consume_key(Modifiers::NONE, Key::Space),
+                i.consume_key(Modifiers::SHIFT, Key::ArrowLeft),
+                i.consume_key(Modifiers::SHIFT, Key::ArrowRight),
+                i.consume_key(Modifiers::NONE, Key::ArrowLeft),
+                i.consume_key(Modifiers::NONE, Key::ArrowRight),
+                i.consume_key(Modifiers::NONE, Key::ArrowUp),
+                i.consume_key(Modifiers::NONE, Key::ArrowDown),
+                i.consume_key(Modifiers::NONE, Key::S),
+                i.consume_key(Modifiers::NONE, Key::F),
+            )
+        });
+        
+        if toggle_help {
+            self.show_shortcuts = !self.show_shortcuts;
+        }
+        if self.show_shortcuts && ctx.input_mut(|i| i.consume_key(Modifiers::NONE, Key::Escape)) {
+            self.show_shortcuts = false;
+        }
+        if self.presentation_mode && ctx.input_mut(|i| i.consume_key(Modifiers::NONE, Key::Escape)) {
+            self.presentation_overlay_until = ctx.input(|i| i.time) + PRESENTATION_OVERLAY_SECS;
+        }
+        if toggle_presentation {
+            self.set_presentation_mode(ctx, !self.presentation_mode);
+        }
+        if play {
+            self.toggle_playback();
+        }
+        if seek_back || seek_fwd {
+            let step = if seek_fwd { SEEK_STEP_SECS } else { -SEEK_STEP_SECS };
+            self.seek_to((self.playlist.current_time + step).max(0.0));
+        }
+        if prev {
+            self.play_previous();
+        }
+        if next {
+            self.play_next();
+        }
+        if louder || quieter {
+            let step = if louder { VOLUME_STEP } else { -VOLUME_STEP };
+            self.playlist.volume = (self.playlist.volume + step).clamp(0.0, 1.0);
+            self.update_volume();
+        }
+        if shuffle {
+            self.toggle_shuffle();
+        }
+        if freeze {
+            self.frozen = !self.frozen;
+        }
+    }
+    
     fn stop_playback(&mut self) {
+        if self.current_track_is_youtube() {
+            self.youtube_command("stopVideo");
+        }
         if let Some(ref audio) = *self.audio_element.borrow() {
             audio.pause().ok();
             audio.set_current_time(0.0);
@@ -1311,11 +2791,18 @@ impl MusicVisualizerApp {
     }
     
     fn play_next(&mut self) {
-        if let Some(next_idx) = self.playlist.get_next_index() {
+        // Wraps to the first track unless repeat is off
+        if let Some(next_idx) = self.playlist.get_manual_next_index() {
             self.play_track(next_idx);
-        } else if !self.playlist.tracks.is_empty() {
-            // Loop to first track
-            self.play_track(0);
+        } else {
+            self.stop_playback();
+        }
+    }
+
+    /// Advance when the current track finishes, honouring the repeat mode.
+    fn handle_track_end(&mut self) {
+        if let Some(idx) = self.playlist.get_index_after_end() {
+            self.play_track(idx);
         } else {
             self.stop_playback();
         }
@@ -1336,6 +2823,43 @@ impl MusicVisualizerApp {
         }
     }
     
+    /// The elapsed time label; double-click it to type a position (`mm:ss`)
+    /// and press Enter to seek there. Escape or clicking away cancels.
+    fn seek_time_ui(&mut self, ui: &mut egui::Ui, current_time: &str) {
+        let id = ui.make_persistent_id("seek_time");
+        let duration = self.playlist.duration;
+        let Some(text) = &mut self.seek_text else {
+            let label = ui
+                .add(egui::Label::new(current_time).sense(egui::Sense::click()))
+                .on_hover_text("Double-click to type a position");
+            if label.double_clicked() {
+                self.seek_text = Some(current_time.to_string());
+                ui.memory_mut(|m| m.request_focus(id));
+            }
+            return;
+        };
+        let target = PlaylistState::parse_time(text).filter(|&t| duration <= 0.0 || t <= duration);
+        let mut edit = egui::TextEdit::singleline(text).id(id).desired_width(48.0);
+        if target.is_none() {
+            edit = edit.text_color(ui.visuals().error_fg_color);
+        }
+        let response = ui
+            .add(edit)
+            .on_hover_text(format!("mm:ss, up to {}", PlaylistState::format_time(duration)));
+        if !response.lost_focus() {
+            return;
+        }
+        if ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+            // Out-of-range or malformed input stays open for correction
+            let Some(time) = target else {
+                response.request_focus();
+                return;
+            };
+            self.seek_to(time);
+        }
+        self.seek_text = None;
+    }
+    
     fn seek_to(&mut self, time: f64) {
         if let Some(ref audio) = *self.audio_element.borrow() {
             let duration = audio.duration();
@@ -1367,7 +2891,7 @@ impl MusicVisualizerApp {
             
             // Update shuffle order
             if self.playlist.is_shuffled {
-                self.playlist.shuffle_playlist();
+                self.playlist.shuffle_playlist(&mut self.rng_seed);
             }
         }
     }
@@ -1379,7 +2903,93 @@ impl MusicVisualizerApp {
         self.playlist.shuffle_order.clear();
     }
     
+    fn current_track_is_youtube(&self) -> bool {
+        self.playlist
+            .current_index
+            .and_then(|i| self.playlist.tracks.get(i))
+            .is_some_and(|t| t.file_type == "youtube")
+    }
+    
+    /// Listen for the embedded player's postMessage updates. Installed once;
+    /// messages from any origin but the player's are ignored.
+    fn install_youtube_listener(&mut self) {
+        if self.youtube_listener_installed {
+            return;
+        }
+        let Some(window) = web_sys::window() else { return };
+        let playback = self.youtube_playback.clone();
+        let on_message = Closure::wrap(Box::new(move |event: web_sys::MessageEvent| {
+            if event.origin() != YOUTUBE_ORIGIN {
+                return;
+            }
+            let Some(text) = event.data().as_string() else { return };
+            if let Ok(msg) = js_sys::JSON::parse(&text) {
+                playback.borrow_mut().apply_message(&msg);
+            }
+        }) as Box<dyn FnMut(web_sys::MessageEvent)>);
+        if window
+            .add_event_listener_with_callback("message", on_message.as_ref().unchecked_ref())
+            .is_ok()
+        {
+            self.youtube_listener_installed = true;
+        }
+        on_message.forget();
+    }
+    
+    /// Once the iframe loads, ask the player to start sending state updates.
+    fn subscribe_to_youtube_player(&self) {
+        let Some(iframe) = web_sys::window()
+            .and_then(|w| w.document())
+            .and_then(|d| d.get_element_by_id("youtube_player"))
+            .and_then(|el| el.dyn_into::<web_sys::HtmlIFrameElement>().ok())
+        else {
+            return;
+        };
+        let iframe_for_cb = iframe.clone();
+        let on_load = Closure::wrap(Box::new(move || {
+            let Some(player) = iframe_for_cb.content_window() else { return };
+            for msg in [
+                r#"{"event":"listening","id":"music_visualizer","channel":"widget"}"#,
+                r#"{"event":"command","func":"addEventListener","args":["onStateChange"],"id":"music_visualizer","channel":"widget"}"#,
+            ] {
+                player.post_message(&msg.into(), YOUTUBE_ORIGIN).ok();
+            }
+        }) as Box<dyn FnMut()>);
+        iframe.set_onload(Some(on_load.as_ref().unchecked_ref()));
+        on_load.forget();
+    }
+    
+    /// Send an IFrame API command such as `playVideo` to the embedded player.
+    fn youtube_command(&self, func: &str) {
+        let player = web_sys::window()
+            .and_then(|w| w.document())
+            .and_then(|d| d.get_element_by_id("youtube_player"))
+            .and_then(|el| el.dyn_into::<web_sys::HtmlIFrameElement>().ok())
+            .and_then(|iframe| iframe.content_window());
+        if let Some(player) = player {
+            let msg = format!(r#"{{"event":"command","func":"{}","args":[]}}"#, func);
+            player.post_message(&msg.into(), YOUTUBE_ORIGIN).ok();
+        }
+    }
+    
     fn update_playback_state(&mut self) {
+        if self.current_track_is_youtube() {
+            let yt = {
+                let mut yt = self.youtube_playback.borrow_mut();
+                let snapshot = *yt;
+                yt.ended = false;
+                snapshot
+            };
+            if yt.connected {
+                self.playlist.is_playing = yt.playing;
+                self.playlist.current_time = yt.current_time;
+                self.playlist.duration = yt.duration;
+            }
+            if yt.ended {
+                self.handle_track_end();
+            }
+            return;
+        }
         let (current_time, duration, ended) = {
             if let Some(ref audio) = *self.audio_element.borrow() {
                 let ct = audio.current_time();
@@ -1396,7 +3006,7 @@ impl MusicVisualizerApp {
         
         // Check if track ended
         if ended {
-            self.play_next();
+            self.handle_track_end();
         }
     }
 }
@@ -1407,19 +3017,30 @@ impl eframe::App for MusicVisualizerApp {
         
         // Process any pending tracks from file input
         self.process_pending_tracks();
+        self.apply_loaded_durations();
+        self.sync_presentation_with_fullscreen(ctx);
+        self.handle_shortcuts(ctx);
+        if ctx.input(|i| !i.events.is_empty()) {
+            // Any key or pointer activity wakes the visuals from attract mode
+            self.last_activity = self.time;
+        }
         
         // Update playback state from audio element
         self.update_playback_state();
         
-        // Update audio and animation
-        self.update_audio(dt);
-        self.update_animation(dt);
+        // Update audio and animation; frozen visuals keep their analysis,
+        // clock and particles as they are while playback carries on
+        if !self.frozen {
+            self.update_audio(dt);
+            self.band_history.push(&self.audio, self.time);
+            self.update_animation(dt);
+        }
         
-        // Request repaint for animation
-        ctx.request_repaint();
+        // Request the next animation frame, capped by `max_fps`
+        self.schedule_repaint(ctx);
         
-        // Side panel for settings
-        if self.show_settings {
+        // Side panel for settings; presentation mode hides every panel
+        if self.show_settings && !self.presentation_mode {
             egui::SidePanel::left("settings_panel")
                 .resizable(true)
                 .default_width(280.0)
@@ -1428,23 +3049,44 @@ impl eframe::App for MusicVisualizerApp {
                 });
         }
         
-        // Main visualization area
-        egui::CentralPanel::default().show(ctx, |ui| {
+        if self.presentation_mode {
+            self.draw_presentation_overlay(ctx);
+        }
+        self.draw_shortcuts_help(ctx);
+        self.draw_band_debug(ctx);
+        
+        // Main visualization area; presentation mode gives it the whole window, edge to edge
+        let presenting = self.presentation_mode;
+        let central_frame = if presenting {
+            egui::Frame::NONE
+        } else {
+            egui::Frame::central_panel(&ctx.style())
+        };
+        egui::CentralPanel::default().frame(central_frame).show(ctx, |ui| {
             let _available = ui.available_rect_before_wrap();
             
             // Toggle settings button
-            ui.horizontal(|ui| {
-                if ui.button(if self.show_settings { "◀ Hide" } else { "▶ Show" }).clicked() {
-                    self.show_settings = !self.show_settings;
-                }
-                ui.label(format!("FPS: {:.0}", 1.0 / dt));
-            });
+            if !presenting {
+                ui.horizontal(|ui| {
+                    if ui.button(if self.show_settings { "◀ Hide" } else { "▶ Show" }).clicked() {
+                        self.show_settings = !self.show_settings;
+                    }
+                    if ui.button("⛶ Present").on_hover_text("Hide all panels (F11)").clicked() {
+                        self.set_presentation_mode(ctx, true);
+                    }
+                    ui.toggle_value(&mut self.frozen, "❄ Freeze")
+                        .on_hover_text("Hold the visuals while audio keeps playing (F)");
+                    ui.label(format!("FPS: {:.0}", 1.0 / dt));
+                });
+            }
             
             let remaining = ui.available_rect_before_wrap();
             
             // Layout visualization areas
-            let spectrum_height = if self.show_spectrum { 80.0 } else { 0.0 };
-            let waveform_height = if self.show_waveform { 60.0 } else { 0.0 };
+            let show_spectrum = self.show_spectrum && !presenting;
+            let show_waveform = self.show_waveform && !presenting;
+            let spectrum_height = if show_spectrum { 80.0 } else { 0.0 };
+            let waveform_height = if show_waveform { 60.0 } else { 0.0 };
             let bottom_ui_height = spectrum_height + waveform_height;
             
             let fractal_rect = Rect::from_min_max(
@@ -1453,24 +3095,28 @@ impl eframe::App for MusicVisualizerApp {
             );
             
             // Draw appropriate visualizer for selected mode
-            let fractal_response = ui.allocate_rect(fractal_rect, egui::Sense::hover());
-            if fractal_response.hovered() {
-                // Could add mouse interaction here
-            }
+            let fractal_response = ui.allocate_rect(fractal_rect, egui::Sense::click_and_drag());
             match self.visualizer_mode {
-                VisualizerMode::Fractal => self.draw_fractal(ui, fractal_rect),
+                VisualizerMode::Fractal => {
+                    self.handle_camera_input(ui, &fractal_response);
+                    self.draw_fractal(ui, fractal_rect);
+                }
                 VisualizerMode::UnknownPleasures => {
                     // Delegate drawing to the Unknown Pleasures visualizer (mutable)
-                    self.unknown_visualizer.draw(ui, fractal_rect, &self.audio, &self.config, self.time);
+                    self.unknown_visualizer.draw(ui, fractal_rect, &self.audio, &self.config, self.time, self.frozen);
+                }
+                VisualizerMode::RadialSpectrum => {
+                    self.draw_radial_spectrum(ui, fractal_rect);
                 }
             }
             
             // Draw particles
             let painter = ui.painter();
             self.draw_particles(painter, fractal_rect.center());
+            self.draw_strobe(painter, fractal_rect);
             
             // Draw spectrum analyzer
-            if self.show_spectrum {
+            if show_spectrum {
                 let spectrum_rect = Rect::from_min_max(
                     Pos2::new(remaining.min.x, remaining.max.y - bottom_ui_height),
                     Pos2::new(remaining.max.x, remaining.max.y - waveform_height),
@@ -1480,7 +3126,7 @@ impl eframe::App for MusicVisualizerApp {
             }
             
             // Draw waveform
-            if self.show_waveform {
+            if show_waveform {
                 let waveform_rect = Rect::from_min_max(
                     Pos2::new(remaining.min.x, remaining.max.y - waveform_height),
                     remaining.max,
@@ -1496,39 +3142,65 @@ impl eframe::App for MusicVisualizerApp {
 
 // init_web_audio moved to src/audio.rs
 
-/// Extract YouTube video ID from various URL formats (youtube.com/watch?v=..., youtu.be/..., etc.)
-fn extract_youtube_id(url: &str) -> Option<String> {
-    // Try youtu.be/VIDEO_ID
-    if url.contains("youtu.be/") {
-        let parts: Vec<&str> = url.split("youtu.be/").collect();
-        if let Some(part) = parts.get(1) {
-            let id = part.split('?').next().unwrap_or(part).split('&').next().unwrap_or(part);
-            if !id.is_empty() {
-                return Some(id.to_string());
-            }
-        }
+/// Extensions the URL box accepts, matching the file picker.
+const SUPPORTED_AUDIO_EXTENSIONS: [&str; 6] = ["mp3", "wav", "ogg", "flac", "aac", "m4a"];
+
+/// Check a pasted URL and work out its playlist name and file type.
+/// YouTube links must carry a valid video ID; anything else must be an
+/// http(s) link to a file with a supported audio extension.
+fn classify_url(url: &str) -> Result<(String, String), String> {
+    if is_youtube_host(&url_host(url)) {
+        return match extract_youtube_id(url) {
+            Some(id) => Ok((format!("YouTube - {}", id), "youtube".to_string())),
+            None => Err("Unsupported YouTube link: no valid video ID found".to_string()),
+        };
     }
-    // Try youtube.com/watch?v=VIDEO_ID
-    if url.contains("v=") {
-        let parts: Vec<&str> = url.split("v=").collect();
-        if let Some(part) = parts.get(1) {
-            let id = part.split('&').next().unwrap_or(part);
-            if !id.is_empty() {
-                return Some(id.to_string());
-            }
-        }
+    if !(url.starts_with("http://") || url.starts_with("https://")) {
+        return Err("Unsupported URL: expected an http(s) link or a YouTube URL".to_string());
     }
-    // Try youtube.com/embed/VIDEO_ID
-    if url.contains("/embed/") {
-        let parts: Vec<&str> = url.split("/embed/").collect();
-        if let Some(part) = parts.get(1) {
-            let id = part.split('?').next().unwrap_or(part).split('&').next().unwrap_or(part);
-            if !id.is_empty() {
-                return Some(id.to_string());
-            }
-        }
+    // Ignore any query or fragment when looking at the file name
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let name = path.rsplit('/').next().unwrap_or_default();
+    let ext = name.rsplit_once('.').map(|(_, ext)| ext.to_lowercase()).unwrap_or_default();
+    if !SUPPORTED_AUDIO_EXTENSIONS.contains(&ext.as_str()) {
+        return Err(format!(
+            "Unsupported URL: expected a link ending in .{}",
+            SUPPORTED_AUDIO_EXTENSIONS.join(", .")
+        ));
     }
-    None
+    Ok((name.to_string(), ext))
+}
+
+/// Lowercased host of a link, with or without a scheme, minus any user info and port.
+fn url_host(url: &str) -> String {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+    host.split(':').next().unwrap_or_default().to_ascii_lowercase()
+}
+
+/// youtu.be, youtube.com, youtube-nocookie.com and their subdomains.
+fn is_youtube_host(host: &str) -> bool {
+    ["youtu.be", "youtube.com", "youtube-nocookie.com"]
+        .iter()
+        .any(|domain| host == *domain || host.strip_suffix(domain).is_some_and(|sub| sub.ends_with('.')))
+}
+
+/// Extract YouTube video ID from various URL formats (youtube.com/watch?v=..., youtu.be/..., etc.)
+/// Only a well-formed ID is returned, since it ends up in the embed URL.
+fn extract_youtube_id(url: &str) -> Option<String> {
+    // youtu.be/VIDEO_ID, youtube.com/watch?v=VIDEO_ID and youtube.com/embed/VIDEO_ID
+    ["youtu.be/", "v=", "/embed/"]
+        .iter()
+        .filter_map(|marker| url.split_once(marker))
+        .map(|(_, rest)| rest.split(['?', '&', '#', '/']).next().unwrap_or_default())
+        .find(|id| is_youtube_id(id))
+        .map(str::to_string)
+}
+
+/// True for an 11-character video ID made of `A-Za-z0-9_-`.
+fn is_youtube_id(id: &str) -> bool {
+    id.len() == 11 && id.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
 }
 
 // WASM entry point
@@ -1562,3 +3234,75 @@ pub fn start() -> Result<(), JsValue> {
     
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn share_string_round_trips_and_rejects_garbage() {
+        let config = VisualizerConfig {
+            up_max_lines: 42,
+            base_color: Color32::from_rgb(1, 2, 3),
+            a_weighting: true,
+            ..Default::default()
+        };
+        let shared = config.to_share_string();
+        assert!(shared.starts_with(SHARE_PREFIX));
+        let back = VisualizerConfig::from_share_string(&format!("  {shared}\n")).unwrap();
+        assert_eq!(back.up_max_lines, 42);
+        assert_eq!(back.base_color, Color32::from_rgb(1, 2, 3));
+        assert!(back.a_weighting);
+        
+        assert!(VisualizerConfig::from_share_string("hello").is_err());
+        assert!(VisualizerConfig::from_share_string("mv1:!!!").is_err());
+        assert!(VisualizerConfig::from_share_string(&shared[..shared.len() / 2]).is_err());
+    }
+
+    #[test]
+    fn custom_presets_round_trip_through_their_json() {
+        let presets = vec![
+            CustomPreset { name: "Mine".into(), config: VisualizerPreset::EmberTree.config() },
+            CustomPreset { name: "Lines".into(), config: VisualizerConfig::preset_unknown_pleasures_image() },
+        ];
+        // The same JSON `save_custom_presets` writes to localStorage
+        let json = serde_json::to_string(&presets).unwrap();
+        let loaded: Vec<CustomPreset> = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.len(), 2);
+        for (saved, back) in presets.iter().zip(&loaded) {
+            assert_eq!(saved.name, back.name);
+            assert_eq!(saved.config.to_share_string(), back.config.to_share_string());
+        }
+    }
+    
+    #[test]
+    fn classify_url_checks_the_extension() {
+        let (name, ext) = classify_url("https://example.com/music/Song.MP3?token=1").unwrap();
+        assert_eq!((name.as_str(), ext.as_str()), ("Song.MP3", "mp3"));
+        assert_eq!(classify_url("https://youtu.be/dQw4w9WgXcQ").unwrap().1, "youtube");
+        assert_eq!(classify_url("youtube.com/watch?v=dQw4w9WgXcQ").unwrap().0, "YouTube - dQw4w9WgXcQ");
+        // YouTube links without a usable ID are refused, not queued
+        assert!(classify_url("https://youtu.be/abc123").is_err());
+        assert!(classify_url("https://www.youtube.com/feed/trending").is_err());
+        // the host decides, not a mention of YouTube elsewhere in the link
+        assert_eq!(classify_url("https://example.com/youtube.com/clip.mp3?v=dQw4w9WgXcQ").unwrap().1, "mp3");
+        assert!(classify_url("https://notyoutube.com/watch?v=dQw4w9WgXcQ").is_err());
+        assert!(classify_url("https://example.com/page.html").is_err());
+        assert!(classify_url("https://example.com/").is_err());
+        assert!(classify_url("not a url.mp3").is_err());
+    }
+
+    #[test]
+    fn youtube_ids_are_extracted_and_validated() {
+        for url in [
+            "https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=42",
+            "https://youtu.be/dQw4w9WgXcQ?si=share",
+            "https://www.youtube.com/embed/dQw4w9WgXcQ",
+        ] {
+            assert_eq!(extract_youtube_id(url).as_deref(), Some("dQw4w9WgXcQ"), "{url}");
+        }
+        assert_eq!(extract_youtube_id("https://youtu.be/abc123"), None);
+        assert_eq!(extract_youtube_id(r#"https://youtu.be/dQw4w9WgX"><script>"#), None);
+        assert_eq!(extract_youtube_id("https://www.youtube.com/"), None);
+    }
+}
@@ -1,32 +1,361 @@
-use eframe::egui::{self, Color32, Pos2, Rect, Stroke};
-use crate::MusicVisualizerApp;
-
-// HSL to RGB color conversion (moved here with UI code)
-pub fn hsl_to_rgb(h: f32, s: f32, l: f32) -> Color32 {
-    let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
-    let x = c * (1.0 - ((h * 6.0) % 2.0 - 1.0).abs());
-    let m = l - c / 2.0;
-
-    let (r, g, b) = match (h * 6.0) as u32 {
-        0 => (c, x, 0.0),
-        1 => (x, c, 0.0),
-        2 => (0.0, c, x),
-        3 => (0.0, x, c),
-        4 => (x, 0.0, c),
-        _ => (c, 0.0, x),
-    };
+use std::cell::Cell;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use eframe::egui::{self, emath::Rot2, Color32, ColorImage, Pos2, Rect, Stroke, TextureHandle, TextureOptions, Vec2};
+use crate::{AudioAnalysis, ConfigParam, FractalKind, MusicVisualizerApp, ParticleShape, WaveformStyle, MAX_BRANCHES, MAX_STROBE_INTENSITY, MIN_BRANCHES, PRESENTATION_OVERLAY_SECS, SHORTCUTS, VisualizerConfig};
+use crate::color::{hsl_to_rgb, lerp_color, rgb_to_hsl};
+
+// Koch and Sierpinski grow as 4^n and 3^n segments, so their recursion is capped
+const MAX_KOCH_DEPTH: u32 = 6;
+const MAX_SIERPINSKI_DEPTH: u32 = 7;
+// Chaos-game points plotted per depth level for the fern
+const FERN_POINTS_PER_DEPTH: u32 = 600;
+const MAX_FERN_POINTS: u32 = 20_000;
+// Per-frame tree segment budget; tree depth is capped to fit it and `draw_branch`
+// stops drawing once it is spent
+const MAX_TREE_SEGMENTS: u32 = 131_072;
+// Fractal camera zoom limits
+const MIN_CAMERA_ZOOM: f32 = 0.1;
+const MAX_CAMERA_ZOOM: f32 = 10.0;
+// Step the fractal's float inputs are rounded to before hashing, so drift too
+// small to see (smoothed levels settling in silence) doesn't redraw it
+const FRACTAL_KEY_QUANTUM: f32 = 1e-4;
+// Power-curve exponent of the log spectrum axis, as in the Unknown Pleasures band mapping
+const SPECTRUM_LOG_EXPONENT: f32 = 2.5;
+// Screen width of one min/max/RMS column in the envelope waveform
+const ENVELOPE_COLUMN_WIDTH: f32 = 2.0;
+// Resolution of the fractal trail buffer relative to screen points
+const TRAIL_SCALE: f32 = 0.5;
+// VU meter: lit segments across the bar, how long the peak marker holds and how fast it then falls
+const VU_SEGMENTS: usize = 24;
+const VU_PEAK_HOLD_SECS: f64 = 1.0;
+const VU_PEAK_FALL_PER_SEC: f32 = 0.6;
+
+/// Audio-reactive values shared by every fractal kind for one frame.
+struct FractalParams {
+    center: Pos2,
+    size: f32,
+    angle: f32,
+    width: f32,
+    depth: u32,
+    brightness: f32,
+    color: Color32,
+    clip_rect: Rect,
+}
+
+/// One tree segment still to be drawn and the half-angle its children fan across.
+#[derive(Clone, Copy)]
+struct Branch {
+    start: Pos2,
+    length: f32,
+    angle: f32,
+    spread: f32,
+    depth: u32,
+    color: Color32,
+}
 
-    Color32::from_rgb(
-        ((r + m) * 255.0) as u8,
-        ((g + m) * 255.0) as u8,
-        ((b + m) * 255.0) as u8,
+/// Deepest tree recursion whose `branches^depth` segments stay within
+/// `MAX_TREE_SEGMENTS`; with two branches this is 17, above the usual depth.
+fn max_tree_depth(branches: u32) -> u32 {
+    MAX_TREE_SEGMENTS.ilog(branches.clamp(MIN_BRANCHES, MAX_BRANCHES))
+}
+
+/// Scale `color` by brightness and a 0..1 depth factor, fading alpha with it.
+fn shade(color: Color32, brightness: f32, depth_factor: f32) -> Color32 {
+    Color32::from_rgba_unmultiplied(
+        (color.r() as f32 * brightness * depth_factor) as u8,
+        (color.g() as f32 * brightness * depth_factor) as u8,
+        (color.b() as f32 * brightness * depth_factor) as u8,
+        (255.0 * depth_factor) as u8,
     )
 }
 
+/// Square around `center` that still covers `rect` however it is turned about
+/// `center`, so shapes culled against it can be rotated afterwards.
+fn rotation_safe_clip(rect: Rect, center: Pos2) -> Rect {
+    let reach = [rect.left_top(), rect.right_top(), rect.right_bottom(), rect.left_bottom()]
+        .into_iter()
+        .map(|corner| corner.distance(center))
+        .fold(0.0, f32::max);
+    Rect::from_center_size(center, Vec2::splat(2.0 * reach))
+}
+
+/// `shape` turned by `rot` about `center`. Fractals only emit line segments
+/// and fern dots, and a dot just moves since it is too small to show a turn.
+fn rotate_about(shape: &egui::Shape, center: Pos2, rot: Rot2) -> egui::Shape {
+    let turn = |p: Pos2| center + rot * (p - center);
+    match shape {
+        egui::Shape::LineSegment { points: [a, b], stroke } => egui::Shape::LineSegment {
+            points: [turn(*a), turn(*b)],
+            stroke: *stroke,
+        },
+        egui::Shape::Rect(r) => {
+            let mut r = r.clone();
+            r.rect = Rect::from_center_size(turn(r.rect.center()), r.rect.size());
+            egui::Shape::Rect(r)
+        }
+        other => other.clone(),
+    }
+}
+
+/// Cheap culling: false only when the segment's bounding box misses `clip_rect`.
+fn segment_visible(start: Pos2, end: Pos2, clip_rect: Rect) -> bool {
+    clip_rect.contains(start)
+        || clip_rect.contains(end)
+        || Rect::from_two_pos(start, end).intersects(clip_rect)
+}
+
+/// Mean 0..1 level of each bar's slice of the byte spectrum. Every bar covers
+/// at least one bin; the log axis spaces slice edges along `t^SPECTRUM_LOG_EXPONENT`
+/// from bin 1, leaving out the DC bin.
+fn spectrum_bar_levels(spectrum: &[u8], bar_count: usize, log_axis: bool) -> Vec<f32> {
+    let len = spectrum.len();
+    if len == 0 {
+        return vec![0.0; bar_count];
+    }
+    let edge = |i: usize| {
+        let t = i as f32 / bar_count as f32;
+        if log_axis && len > 1 {
+            1 + (t.powf(SPECTRUM_LOG_EXPONENT) * (len - 1) as f32) as usize
+        } else {
+            (t * len as f32) as usize
+        }
+    };
+
+    (0..bar_count)
+        .map(|i| {
+            let start = edge(i).min(len - 1);
+            let end = edge(i + 1).clamp(start + 1, len);
+            let sum: f32 = spectrum[start..end].iter().map(|&v| v as f32).sum();
+            sum / ((end - start) as f32 * 255.0)
+        })
+        .collect()
+}
+
+/// Start and end of radial bar `i` of `bar_count`: it leaves the ring of radius
+/// `inner` at `rotation` plus its share of a full turn and reaches out by
+/// `level` of `max_len`, keeping a stub when silent.
+fn radial_bar(center: Pos2, rotation: f32, inner: f32, max_len: f32, i: usize, bar_count: usize, level: f32) -> [Pos2; 2] {
+    let dir = Vec2::angled(rotation + i as f32 / bar_count as f32 * std::f32::consts::TAU);
+    [center + dir * inner, center + dir * (inner + level.max(0.01) * max_len)]
+}
+
+/// Ease `smoothed` toward `target`, keeping `smoothing` (0..1) of the gap per
+/// 60 Hz frame so the decay rate doesn't depend on frame rate. A length change
+/// (new bar count or buffer size) restarts from `target`.
+fn smooth_toward(smoothed: &mut Vec<f32>, target: &[f32], smoothing: f32, dt: f32) {
+    if smoothed.len() != target.len() {
+        smoothed.clear();
+        smoothed.extend_from_slice(target);
+        return;
+    }
+    let keep = smoothing.clamp(0.0, 0.99).powf(dt * 60.0);
+    for (s, &t) in smoothed.iter_mut().zip(target) {
+        *s = t + (*s - t) * keep;
+    }
+}
+
+/// `rect` filled from `top` at its upper edge to `bottom` at its lower edge.
+fn vertical_gradient(rect: Rect, top: Color32, bottom: Color32) -> egui::Shape {
+    let mut mesh = egui::Mesh::default();
+    for (pos, color) in [(rect.left_top(), top), (rect.right_top(), top), (rect.right_bottom(), bottom), (rect.left_bottom(), bottom)] {
+        mesh.colored_vertex(pos, color);
+    }
+    mesh.add_triangle(0, 1, 2);
+    mesh.add_triangle(0, 2, 3);
+    egui::Shape::mesh(mesh)
+}
+
+/// Filled outline for one particle at `pos`, `size` across. Lines are drawn
+/// as a streak trailing behind `vel`.
+pub fn particle_shape(shape: ParticleShape, pos: Pos2, size: f32, vel: Vec2, color: Color32) -> egui::Shape {
+    match shape {
+        ParticleShape::Circle => egui::Shape::circle_filled(pos, size, color),
+        ParticleShape::Square => {
+            egui::Shape::rect_filled(Rect::from_center_size(pos, Vec2::splat(size * 1.6)), 0.0, color)
+        }
+        ParticleShape::Star => {
+            // A star is not convex, so fill it as a triangle fan around its centre
+            let mut mesh = egui::Mesh::default();
+            mesh.colored_vertex(pos, color);
+            for i in 0..=10 {
+                let radius = if i % 2 == 0 { size * 1.4 } else { size * 0.6 };
+                let angle = i as f32 * std::f32::consts::TAU / 10.0 - std::f32::consts::FRAC_PI_2;
+                mesh.colored_vertex(pos + Vec2::angled(angle) * radius, color);
+                if i > 0 {
+                    mesh.add_triangle(0, i, i + 1);
+                }
+            }
+            egui::Shape::mesh(mesh)
+        }
+        ParticleShape::Line => {
+            let dir = if vel.length_sq() > 1e-6 { vel.normalized() } else { Vec2::X };
+            egui::Shape::line_segment(
+                [pos - dir * size * 2.0, pos],
+                Stroke::new((size * 0.4).max(1.0), color),
+            )
+        }
+    }
+}
+
+/// Segmented level meter in `rect`: `rms` lights the bar on a green, yellow,
+/// red ramp, the stretch up to the instantaneous `peak` is lit dimly, and a
+/// marker holds the recent peak before falling back. Levels are 0..1. The
+/// held peak is remembered under `id`, so give each meter its own.
+pub fn draw_vu_meter(ui: &mut egui::Ui, id: egui::Id, rect: Rect, rms: f32, peak: f32) {
+    let rms = rms.clamp(0.0, 1.0);
+    let peak = peak.clamp(rms, 1.0);
+
+    // Held peak: (level, time it was reached)
+    let (now, dt) = ui.input(|i| (i.time, i.stable_dt));
+    let held = ui.data_mut(|d| {
+        let (level, since) = d.get_temp_mut_or_insert_with(id, || (0.0f32, now));
+        if peak >= *level {
+            *level = peak;
+            *since = now;
+        } else if now - *since > VU_PEAK_HOLD_SECS {
+            *level = (*level - VU_PEAK_FALL_PER_SEC * dt).max(peak);
+        }
+        *level
+    });
+
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, Color32::from_gray(20));
+    let inner = rect.shrink(2.0);
+    let segment_width = inner.width() / VU_SEGMENTS as f32;
+    for s in 0..VU_SEGMENTS {
+        let level = (s as f32 + 0.5) / VU_SEGMENTS as f32;
+        let color = vu_color(level);
+        let color = if level <= rms {
+            color
+        } else if level <= peak {
+            color.gamma_multiply(0.45)
+        } else {
+            color.gamma_multiply(0.08)
+        };
+        let x = inner.left() + s as f32 * segment_width;
+        let segment = Rect::from_min_max(
+            Pos2::new(x + 0.5, inner.top()),
+            Pos2::new(x + segment_width - 0.5, inner.bottom()),
+        );
+        painter.rect_filled(segment, 0.0, color);
+    }
+
+    if held > 0.0 {
+        let x = inner.left() + held * inner.width();
+        painter.line_segment(
+            [Pos2::new(x, inner.top()), Pos2::new(x, inner.bottom())],
+            Stroke::new(2.0, vu_color(held)),
+        );
+    }
+}
+
+// Green at silence through yellow to red at full scale
+fn vu_color(level: f32) -> Color32 {
+    hsl_to_rgb((1.0 - level.clamp(0.0, 1.0)) * 0.33, 0.9, 0.5)
+}
+
+/// `color` with its hue rotated by `turns` (1.0 = a full turn); alpha is kept.
+fn shift_hue(color: Color32, turns: f32) -> Color32 {
+    let (h, s, l) = rgb_to_hsl(color);
+    let [r, g, b, _] = hsl_to_rgb((h + turns).rem_euclid(1.0), s, l).to_array();
+    Color32::from_rgba_unmultiplied(r, g, b, color.a())
+}
+
+/// Offscreen copy of recent fractal frames. Each frame it fades toward the
+/// background and the new line work is rasterized into it, so drawing it in
+/// place of an opaque fill leaves trails behind the moving fractal.
+#[derive(Default)]
+pub struct FractalTrails {
+    image: ColorImage,
+    texture: Option<TextureHandle>,
+}
+
+impl FractalTrails {
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Fill `rect` with the accumulated frames; false if nothing has been accumulated yet.
+    fn paint(&self, painter: &egui::Painter, rect: Rect) -> bool {
+        let Some(texture) = &self.texture else { return false };
+        painter.image(texture.id(), rect, Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0)), Color32::WHITE);
+        true
+    }
+
+    /// Fade toward the `[top, bottom]` background gradient, keeping `amount`
+    /// per 60 Hz frame, blend the line segments and filled rects of `shapes` on
+    /// top and upload the result.
+    fn accumulate(&mut self, ctx: &egui::Context, rect: Rect, shapes: &[egui::Shape], bg: [Color32; 2], amount: f32, dt: f32) {
+        let size = [(rect.width() * TRAIL_SCALE).max(1.0) as usize, (rect.height() * TRAIL_SCALE).max(1.0) as usize];
+        if self.image.size != size {
+            self.image = ColorImage::filled(size, bg[0]);
+        }
+        let keep = amount.clamp(0.0, 0.99).powf(dt * 60.0);
+        let rows = (size[1] - 1).max(1) as f32;
+        for (y, row) in self.image.pixels.chunks_mut(size[0]).enumerate() {
+            let bg = lerp_color(bg[0], bg[1], y as f32 / rows);
+            let fade = |c: u8, b: u8| (b as f32 + (c as f32 - b as f32) * keep).round() as u8;
+            for px in row {
+                *px = Color32::from_rgb(fade(px.r(), bg.r()), fade(px.g(), bg.g()), fade(px.b(), bg.b()));
+            }
+        }
+        let to_px = |p: Pos2| ((p - rect.min) * TRAIL_SCALE).to_pos2();
+        for shape in shapes {
+            match shape {
+                egui::Shape::LineSegment { points: [a, b], stroke } => {
+                    let radius = (stroke.width * TRAIL_SCALE * 0.5) as i32;
+                    let (a, b) = (to_px(*a), to_px(*b));
+                    let steps = (b - a).abs().max_elem().ceil().max(1.0);
+                    for i in 0..=steps as u32 {
+                        let p = a.lerp(b, i as f32 / steps);
+                        self.blend_square(p.x as i32, p.y as i32, radius, stroke.color);
+                    }
+                }
+                egui::Shape::Rect(r) => {
+                    let (min, max) = (to_px(r.rect.min), to_px(r.rect.max));
+                    let radius = ((max.x - min.x).max(max.y - min.y) * 0.5) as i32;
+                    let c = min.lerp(max, 0.5);
+                    self.blend_square(c.x as i32, c.y as i32, radius, r.fill);
+                }
+                _ => {}
+            }
+        }
+        match &mut self.texture {
+            Some(texture) => texture.set(self.image.clone(), TextureOptions::LINEAR),
+            None => self.texture = Some(ctx.load_texture("fractal_trails", self.image.clone(), TextureOptions::LINEAR)),
+        }
+    }
+
+    // Premultiplied "over" blend of `color` into a (2r+1)² square, clipped to the image
+    fn blend_square(&mut self, x: i32, y: i32, radius: i32, color: Color32) {
+        let [w, h] = self.image.size;
+        let keep = 255 - color.a() as u32;
+        for py in (y - radius).max(0)..=(y + radius).min(h as i32 - 1) {
+            for px in (x - radius).max(0)..=(x + radius).min(w as i32 - 1) {
+                let dst = &mut self.image.pixels[py as usize * w + px as usize];
+                let over = |s: u8, d: u8| (s as u32 + d as u32 * keep / 255).min(255) as u8;
+                *dst = Color32::from_rgb(over(color.r(), dst.r()), over(color.g(), dst.g()), over(color.b(), dst.b()));
+            }
+        }
+    }
+}
+
+/// The last fractal's shapes, built pointing up, and a hash of the inputs they
+/// came from. While the inputs hold still the shapes are turned by the current
+/// rotation and replayed instead of recursing again; any change to them,
+/// including a resize, regenerates the shapes.
+#[derive(Default)]
+pub struct FractalCache {
+    key: Option<u64>,
+    shapes: Vec<egui::Shape>,
+}
+
 impl MusicVisualizerApp {
     // Draw fractal and helpers (moved from lib.rs)
     pub fn get_current_color(&self) -> Color32 {
-        if self.config.color_cycle {
+        // Attract mode drifts through the hues even when cycling is off
+        if self.config.color_cycle || self.attract_mode {
             let hue = (self.time as f32 * self.config.color_cycle_speed) % 1.0;
             hsl_to_rgb(hue, 0.8, 0.6)
         } else {
@@ -34,34 +363,122 @@ impl MusicVisualizerApp {
         }
     }
 
-    pub fn draw_fractal(&self, ui: &mut egui::Ui, rect: Rect) {
-        let painter = ui.painter();
+    /// Colour for a beat particle at `t` (0..1) along the spectrum display:
+    /// the hue matches that bar and louder bands come out brighter. Falls back
+    /// to [`Self::get_current_color`] while the spectrum is silent.
+    pub fn spectrum_particle_color(&self, t: f32) -> Color32 {
+        let spectrum = &self.audio.frequency_data;
+        let along = if self.config.spectrum_log_axis { t.powf(SPECTRUM_LOG_EXPONENT) } else { t };
+        let level = match spectrum.len() {
+            0 => 0.0,
+            len => spectrum[((along * len as f32) as usize).min(len - 1)] as f32 / 255.0,
+        };
+        if level <= 0.01 {
+            return self.get_current_color();
+        }
+        hsl_to_rgb(t, 0.8, 0.35 + 0.4 * level)
+    }
+    
+    /// Scroll or pinch zooms around the pointer, dragging pans, double-click resets.
+    pub fn handle_camera_input(&mut self, ui: &egui::Ui, response: &egui::Response) {
+        if response.dragged() {
+            self.pan += response.drag_delta();
+        }
+        if response.double_clicked() {
+            self.pan = Vec2::ZERO;
+            self.zoom = 1.0;
+        }
+
+        if response.hovered() {
+            let (scroll, pinch) = ui.input(|i| (i.smooth_scroll_delta.y, i.zoom_delta()));
+            let factor = pinch * (scroll / 200.0).exp();
+            if factor != 1.0 {
+                let new_zoom = (self.zoom * factor).clamp(MIN_CAMERA_ZOOM, MAX_CAMERA_ZOOM);
+                // Keep the point under the cursor fixed while scaling
+                if let Some(pointer) = response.hover_pos() {
+                    let anchor = pointer - response.rect.center();
+                    self.pan = anchor - (anchor - self.pan) * (new_zoom / self.zoom);
+                }
+                self.zoom = new_zoom;
+            }
+        }
+    }
+
+    /// Spectrum bars radiating from a ring at the centre of `rect`. The ring
+    /// turns with `rotation` and its radius pulses with the smoothed bass.
+    pub fn draw_radial_spectrum(&self, ui: &mut egui::Ui, rect: Rect) {
+        let painter = ui.painter().with_clip_rect(rect);
+        painter.rect_filled(rect, 0.0, self.config.background_color);
+
+        let levels = spectrum_bar_levels(
+            &self.audio.frequency_data,
+            self.config.spectrum_bars.max(1) as usize,
+            self.config.spectrum_log_axis,
+        );
+        let size = rect.width().min(rect.height());
+        let inner = size * 0.18 * (1.0 + self.audio.smooth_bass * 0.5);
+        let max_len = size * 0.3;
+        let bar_count = levels.len();
+        // Bars fill about two thirds of their slice of the ring's circumference
+        let thickness = (std::f32::consts::TAU * inner / bar_count as f32 * 0.65).clamp(1.0, 12.0);
         let center = rect.center();
 
+        for (i, &level) in levels.iter().enumerate() {
+            let t = i as f32 / bar_count as f32;
+            let bar = radial_bar(center, self.rotation, inner, max_len, i, bar_count, level);
+            painter.line_segment(bar, Stroke::new(thickness, hsl_to_rgb(t, 0.8, 0.55)));
+        }
+        painter.circle_stroke(center, inner - thickness, Stroke::new(1.5, self.get_current_color()));
+    }
+
+    // Frame time for visual smoothing; zero while frozen so every buffer holds still
+    fn visual_dt(&self, ui: &egui::Ui) -> f32 {
+        if self.frozen {
+            0.0
+        } else {
+            ui.input(|i| i.stable_dt)
+        }
+    }
+    
+    pub fn draw_fractal(&mut self, ui: &mut egui::Ui, rect: Rect) {
+        // Panning can push shapes past the rect, so clip to it
+        let painter = ui.painter().with_clip_rect(rect);
+        let painter = &painter;
+        let center = rect.center() + self.pan;
+
         // Calculate reactive parameters
-        let zoom = self.config.base_zoom + self.audio.smooth_bass * self.config.zoom_bass_mult;
-        let width = self.config.base_width + self.audio.smooth_bass * self.config.width_bass_mult;
-        let depth = (self.config.base_depth as f32
-            + self.audio.spectral_centroid * self.config.depth_complexity_mult) as u32;
-        let brightness = self.config.base_brightness
-            + self.audio.smooth_treble * self.config.brightness_treble_mult;
+        let react = |param| self.config.reactivity.offset(param, &self.audio);
+        let zoom = self.config.base_zoom + react(ConfigParam::Zoom);
+        let width = self.config.base_width + react(ConfigParam::Width);
+        let depth = (self.config.base_depth as f32 + react(ConfigParam::Depth)).max(0.0) as u32;
+        let brightness = self.config.base_brightness + react(ConfigParam::Brightness);
 
         // Draw background with beat flash
         let bg_intensity = (self.beat_flash * 30.0) as u8;
-        let bg = Color32::from_rgb(
-            self.config.background_color.r().saturating_add(bg_intensity),
-            self.config.background_color.g().saturating_add(bg_intensity / 2),
-            self.config.background_color.b().saturating_add(bg_intensity),
-        );
-        painter.rect_filled(rect, 0.0, bg);
+        let flash = |c: Color32| Color32::from_rgb(c.r().saturating_add(bg_intensity), c.g().saturating_add(bg_intensity / 2), c.b().saturating_add(bg_intensity));
+        // The flash lifts both gradient stops; a flat fill is a gradient with equal stops
+        let bg = if self.config.background_gradient {
+            [flash(self.config.background_top), flash(self.config.background_bottom)]
+        } else {
+            [flash(self.config.background_color); 2]
+        };
+        // With trails on, last frame's fading copy replaces the opaque fill
+        let trails = self.config.trail_amount > 0.0;
+        if !trails {
+            self.fractal_trails.clear();
+        }
+        if !(trails && self.fractal_trails.paint(painter, rect)) {
+            painter.add(vertical_gradient(rect, bg[0], bg[1]));
+        }
 
-        // Clip drawing to rect
-        let clip_rect = rect;
+        // Shapes are built unrotated and turned as they are drawn, so cull
+        // against the area the rect could sweep through
+        let clip_rect = rotation_safe_clip(rect, center);
 
         // Calculate base length to fit within the rect (use smaller dimension)
         let max_size = rect.width().min(rect.height()) * 0.35;
-        let base_length = max_size * zoom;
-        let branch_angle = std::f32::consts::PI / 4.0 * width;
+        let base_length = max_size * zoom * self.zoom;
+        let branch_angle = self.config.fractal_spread_deg.to_radians() / 2.0 * width;
         let color = self.get_current_color();
 
         // Draw glow effect at center first (behind fractal)
@@ -72,112 +489,308 @@ impl MusicVisualizerApp {
                 color.b(),
                 (self.config.glow_intensity * self.audio.smooth_volume * 100.0) as u8,
             );
-            let glow_radius = (base_length * 0.5 * (1.0 + self.audio.smooth_bass)).min(max_size * 0.6);
+            let glow_radius = (base_length * 0.5 * (1.0 + self.audio.smooth_bass)).min(max_size * 0.6 * self.zoom);
             painter.circle_filled(center, glow_radius, glow_color);
         }
 
-        // Draw fractal tree starting from center, growing upward
-        self.draw_branch(
-            painter,
+        // Draw the selected shape starting from center, oriented upward
+        let angle = -std::f32::consts::FRAC_PI_2;
+        let params = FractalParams {
             center,
-            base_length,
-            -std::f32::consts::PI / 2.0 + self.rotation * 0.1,
-            branch_angle,
+            size: base_length,
+            angle,
+            width,
             depth,
             brightness,
             color,
             clip_rect,
-        );
+        };
+        
+        // Replay the cached shapes while nothing but the rotation has moved
+        let key = Self::fractal_key(&self.audio, &self.config, rect, &params, branch_angle);
+        if self.fractal_cache.key != Some(key) {
+            let mut shapes = std::mem::take(&mut self.fractal_cache.shapes);
+            shapes.clear();
+            match self.config.fractal_kind {
+                FractalKind::BinaryTree => {
+                    let trunk = Branch {
+                        start: center,
+                        length: base_length,
+                        angle,
+                        spread: branch_angle,
+                        depth: depth.min(max_tree_depth(self.config.fractal_branches)),
+                        color,
+                    };
+                    self.draw_branch(&mut shapes, trunk, &params, &Cell::new(MAX_TREE_SEGMENTS));
+                }
+                FractalKind::Koch => self.draw_koch(&mut shapes, &params),
+                FractalKind::Sierpinski => self.draw_sierpinski(&mut shapes, &params),
+                FractalKind::FractalFern => self.draw_fern(&mut shapes, &params),
+            }
+            self.fractal_cache = FractalCache { key: Some(key), shapes };
+        }
+        let rot = Rot2::from_angle(self.rotation * 0.1);
+        let shapes: Vec<egui::Shape> = self.fractal_cache.shapes.iter().map(|s| rotate_about(s, center, rot)).collect();
+
+        if trails {
+            let dt = self.visual_dt(ui);
+            self.fractal_trails.accumulate(ui.ctx(), rect, &shapes, bg, self.config.trail_amount, dt);
+        }
+        painter.extend(shapes);
+    }
+    
+    /// Hash of everything the fractal's shapes depend on: the rect and camera,
+    /// the reactive parameters, colour, kind, and the smoothed levels the tree
+    /// bends with. The rotation is left out since it is applied at draw time.
+    fn fractal_key(audio: &AudioAnalysis, config: &VisualizerConfig, rect: Rect, p: &FractalParams, branch_angle: f32) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        let floats = [
+            rect.min.x, rect.min.y, rect.max.x, rect.max.y,
+            p.center.x, p.center.y, p.size, p.width, p.brightness, branch_angle,
+            audio.smooth_mid, audio.smooth_treble, config.fractal_depth_hue_shift,
+        ];
+        for v in floats {
+            ((v / FRACTAL_KEY_QUANTUM).round() as i64).hash(&mut hasher);
+        }
+        (p.depth, p.color, config.fractal_kind, config.fractal_branches, config.base_depth).hash(&mut hasher);
+        hasher.finish()
     }
 
-    pub fn draw_branch(
-        &self,
-        painter: &egui::Painter,
-        start: Pos2,
-        length: f32,
-        angle: f32,
-        branch_angle: f32,
-        depth: u32,
-        brightness: f32,
-        color: Color32,
-        clip_rect: Rect,
-    ) {
-        if depth == 0 || length < 2.0 {
+    /// Vertices of an equilateral triangle around the center, the first one at
+    /// `angle`; `width` stretches it perpendicular to that axis.
+    fn fractal_triangle(p: &FractalParams) -> [Pos2; 3] {
+        let (ca, sa) = (p.angle.cos(), p.angle.sin());
+        [0.0f32, 1.0, 2.0].map(|k| {
+            let a = k * std::f32::consts::TAU / 3.0;
+            let (along, across) = (a.cos() * p.size, a.sin() * p.size * p.width);
+            Pos2::new(
+                p.center.x + along * ca - across * sa,
+                p.center.y + along * sa + across * ca,
+            )
+        })
+    }
+
+    /// Koch snowflake: width scales the spike height, depth the number of subdivisions.
+    fn draw_koch(&self, out: &mut Vec<egui::Shape>, p: &FractalParams) {
+        let depth = p.depth.min(MAX_KOCH_DEPTH);
+        let [a, b, c] = Self::fractal_triangle(p);
+        for (start, end) in [(a, b), (b, c), (c, a)] {
+            self.draw_koch_segment(out, start, end, depth, p);
+        }
+    }
+
+    fn draw_koch_segment(&self, out: &mut Vec<egui::Shape>, start: Pos2, end: Pos2, level: u32, p: &FractalParams) {
+        let d = end - start;
+
+        // The spike can rise up to a third of the segment, so cull on a padded box
+        let reach = Rect::from_two_pos(start, end).expand(d.length() * 0.3 * p.width.abs());
+        if !reach.intersects(p.clip_rect) {
             return;
         }
 
-        let end = Pos2::new(
-            start.x + angle.cos() * length,
-            start.y + angle.sin() * length,
-        );
+        if level == 0 || d.length() < 2.0 {
+            let color = shade(p.color, p.brightness, 1.0);
+            out.push(egui::Shape::line_segment([start, end], Stroke::new(1.0, color)));
+            return;
+        }
+
+        let p1 = start + d / 3.0;
+        let p3 = start + d * 2.0 / 3.0;
+        let normal = Vec2::new(d.y, -d.x).normalized();
+        let apex = start + d * 0.5 + normal * d.length() / 3.0 * (3.0f32.sqrt() / 2.0) * p.width;
+        for (s, e) in [(start, p1), (p1, apex), (apex, p3), (p3, end)] {
+            self.draw_koch_segment(out, s, e, level - 1, p);
+        }
+    }
+
+    /// Sierpinski triangle: width stretches the outer triangle, depth sets the subdivisions.
+    fn draw_sierpinski(&self, out: &mut Vec<egui::Shape>, p: &FractalParams) {
+        let depth = p.depth.min(MAX_SIERPINSKI_DEPTH);
+        self.draw_sierpinski_level(out, Self::fractal_triangle(p), depth, depth, p);
+    }
+
+    fn draw_sierpinski_level(&self, out: &mut Vec<egui::Shape>, tri: [Pos2; 3], level: u32, depth: u32, p: &FractalParams) {
+        let [a, b, c] = tri;
+        let bounds = Rect::from_two_pos(a, b).union(Rect::from_two_pos(c, c));
+        if !bounds.intersects(p.clip_rect) {
+            return;
+        }
+
+        if level == 0 || bounds.width().max(bounds.height()) < 4.0 {
+            // Triangles cut off early by size are dimmer than the finest ones
+            let depth_factor = 1.0 - level as f32 / (depth as f32 + 1.0);
+            let stroke = Stroke::new(1.0, shade(p.color, p.brightness, depth_factor));
+            out.push(egui::Shape::line_segment([a, b], stroke));
+            out.push(egui::Shape::line_segment([b, c], stroke));
+            out.push(egui::Shape::line_segment([c, a], stroke));
+            return;
+        }
+
+        let (ab, bc, ca) = (a.lerp(b, 0.5), b.lerp(c, 0.5), c.lerp(a, 0.5));
+        for sub in [[a, ab, ca], [ab, b, bc], [ca, bc, c]] {
+            self.draw_sierpinski_level(out, sub, level - 1, depth, p);
+        }
+    }
 
-        // Skip if both points are outside the clip rect
-        if !clip_rect.contains(start) && !clip_rect.contains(end) {
-            // Check if line might still cross the rect
-            let line_rect = Rect::from_two_pos(start, end);
-            if !line_rect.intersects(clip_rect) {
-                return;
+    /// Barnsley fern via the chaos game: depth sets the point count, width the
+    /// horizontal spread. A fixed seed keeps the shape stable between frames.
+    fn draw_fern(&self, out: &mut Vec<egui::Shape>, p: &FractalParams) {
+        let count = (p.depth * FERN_POINTS_PER_DEPTH).min(MAX_FERN_POINTS);
+
+        // The fern spans y in 0..10; stand it on its base, centred vertically
+        let scale = p.size / 5.0;
+        let up = p.angle + std::f32::consts::FRAC_PI_2;
+        let (ca, sa) = (up.cos(), up.sin());
+
+        let mut seed: u32 = 0x9E37_79B9;
+        let (mut x, mut y) = (0.0f32, 0.0f32);
+        for _ in 0..count {
+            // xorshift32
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            let r = seed as f32 / u32::MAX as f32;
+            (x, y) = if r < 0.01 {
+                (0.0, 0.16 * y)
+            } else if r < 0.86 {
+                (0.85 * x + 0.04 * y, -0.04 * x + 0.85 * y + 1.6)
+            } else if r < 0.93 {
+                (0.2 * x - 0.26 * y, 0.23 * x + 0.22 * y + 1.6)
+            } else {
+                (-0.15 * x + 0.28 * y, 0.26 * x + 0.24 * y + 0.44)
+            };
+
+            let (lx, ly) = (x * scale * p.width, (5.0 - y) * scale);
+            let pt = Pos2::new(p.center.x + lx * ca - ly * sa, p.center.y + lx * sa + ly * ca);
+            if p.clip_rect.contains(pt) {
+                let color = shade(p.color, p.brightness, 0.4 + 0.06 * y.clamp(0.0, 10.0));
+                out.push(egui::Shape::rect_filled(Rect::from_center_size(pt, egui::vec2(1.5, 1.5)), 0.0, color));
             }
         }
+    }
+
+    /// Binary tree: draw `b` and recurse into its children. Brightness and
+    /// culling come from `p`; drawing stops once `budget` is spent.
+    fn draw_branch(&self, out: &mut Vec<egui::Shape>, b: Branch, p: &FractalParams, budget: &Cell<u32>) {
+        if b.depth == 0 || b.length < 2.0 || budget.get() == 0 {
+            return;
+        }
+
+        let end = b.start + Vec2::angled(b.angle) * b.length;
+
+        // Skip if the line can't cross the clip rect
+        if !segment_visible(b.start, end, p.clip_rect) {
+            return;
+        }
 
         // Vary color based on depth
-        let depth_factor = depth as f32 / self.config.base_depth as f32;
-        let line_color = Color32::from_rgba_unmultiplied(
-            (color.r() as f32 * brightness * depth_factor) as u8,
-            (color.g() as f32 * brightness * depth_factor) as u8,
-            (color.b() as f32 * brightness * depth_factor) as u8,
-            (255.0 * depth_factor) as u8,
-        );
+        let depth_factor = b.depth as f32 / self.config.base_depth as f32;
+        let line_color = shade(b.color, p.brightness, depth_factor);
 
-        let stroke_width = (depth as f32 * 0.1).max(0.5);
-        painter.line_segment([start, end], Stroke::new(stroke_width, line_color));
+        let stroke_width = (b.depth as f32 * 0.1).max(0.5);
+        budget.set(budget.get() - 1);
+        out.push(egui::Shape::line_segment([b.start, end], Stroke::new(stroke_width, line_color)));
+
+        // Each level down rotates the hue, giving a trunk-to-tips gradient
+        let hue_shift = self.config.fractal_depth_hue_shift;
+        let color = if hue_shift == 0.0 { b.color } else { shift_hue(b.color, hue_shift) };
 
         // Audio-reactive branch angles
         let angle_mod = self.audio.smooth_mid * 0.2;
 
-        // Recursive branches
-        let new_length = length * (0.65 + self.audio.smooth_treble * 0.1);
+        // Recursive branches, fanned evenly across ±half_spread; mids narrow the fan
+        let length = b.length * (0.65 + self.audio.smooth_treble * 0.1);
 
-        self.draw_branch(painter, end, new_length, angle - branch_angle + angle_mod,
-            branch_angle * 0.95, depth - 1, brightness, color, clip_rect);
-        self.draw_branch(painter, end, new_length, angle + branch_angle - angle_mod,
-            branch_angle * 0.95, depth - 1, brightness, color, clip_rect);
+        let branches = self.config.fractal_branches.clamp(MIN_BRANCHES, MAX_BRANCHES);
+        let half_spread = b.spread - angle_mod;
+        for i in 0..branches {
+            let t = i as f32 / (branches - 1) as f32 * 2.0 - 1.0;
+            let child = Branch {
+                start: end,
+                length,
+                angle: b.angle + half_spread * t,
+                spread: b.spread * 0.95,
+                depth: b.depth - 1,
+                color,
+            };
+            self.draw_branch(out, child, p, budget);
+        }
     }
 
-    pub fn draw_spectrum(&self, ui: &mut egui::Ui, rect: Rect) {
+    pub fn draw_spectrum(&mut self, ui: &mut egui::Ui, rect: Rect) {
+        let levels = spectrum_bar_levels(
+            &self.audio.frequency_data,
+            self.config.spectrum_bars.max(1) as usize,
+            self.config.spectrum_log_axis,
+        );
+        let dt = self.visual_dt(ui);
+        smooth_toward(&mut self.spectrum_smoothed, &levels, self.config.spectrum_smoothing, dt);
+        let levels = self.spectrum_smoothed.clone();
+        if self.config.spectrum_peak_hold {
+            self.update_spectrum_peaks(&levels, dt);
+        }
         let painter = ui.painter();
-        let bar_count = 64;
-        let bar_width = rect.width() / bar_count as f32;
-
-        for i in 0..bar_count {
-            let idx = i * self.audio.frequency_data.len() / bar_count;
-            let value = if idx < self.audio.frequency_data.len() {
-                self.audio.frequency_data[idx] as f32 / 255.0
-            } else {
-                0.0
-            };
+        let bar_count = levels.len();
+        // Edges come from the bar index, not a running sum, so any count tiles the rect exactly
+        let edge = |i: usize| rect.left() + rect.width() * i as f32 / bar_count as f32;
 
+        for (i, &value) in levels.iter().enumerate() {
+            let (x0, x1) = (edge(i), edge(i + 1));
+            // Keep a 1px separator only while bars are wide enough to spare it
+            let x1 = if x1 - x0 > 3.0 { x1 - 1.0 } else { x1 };
             let height = value * rect.height();
-            let x = rect.left() + i as f32 * bar_width;
             let bar_rect = Rect::from_min_max(
-                Pos2::new(x, rect.bottom() - height),
-                Pos2::new(x + bar_width - 1.0, rect.bottom()),
+                Pos2::new(x0, rect.bottom() - height),
+                Pos2::new(x1, rect.bottom()),
             );
 
             let hue = i as f32 / bar_count as f32;
             let color = hsl_to_rgb(hue, 0.8, 0.5);
             painter.rect_filled(bar_rect, 0.0, color);
+
+            if self.config.spectrum_peak_hold {
+                if let Some(&peak) = self.spectrum_peaks.get(i) {
+                    let y = rect.bottom() - peak.clamp(0.0, 1.0) * rect.height();
+                    painter.line_segment(
+                        [Pos2::new(x0, y), Pos2::new(x1, y)],
+                        Stroke::new(1.5, hsl_to_rgb(hue, 0.8, 0.8)),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Raise each held peak to its bar's level, otherwise let it fall.
+    /// A change in bar count starts over from the current levels.
+    fn update_spectrum_peaks(&mut self, levels: &[f32], dt: f32) {
+        if self.spectrum_peaks.len() != levels.len() {
+            self.spectrum_peaks = levels.to_vec();
+            return;
+        }
+        let fall = self.config.spectrum_peak_decay.max(0.0) * dt;
+        for (peak, &level) in self.spectrum_peaks.iter_mut().zip(levels) {
+            *peak = (*peak - fall).max(level);
         }
     }
 
-    pub fn draw_waveform(&self, ui: &mut egui::Ui, rect: Rect) {
+    pub fn draw_waveform(&mut self, ui: &mut egui::Ui, rect: Rect) {
+        // Byte samples are centred on 128
+        let samples: Vec<f32> = self.audio.time_data.iter().map(|&v| (v as f32 - 128.0) / 128.0).collect();
+        let dt = self.visual_dt(ui);
+        smooth_toward(&mut self.waveform_smoothed, &samples, self.config.waveform_smoothing, dt);
+        let samples = &self.waveform_smoothed;
         let painter = ui.painter();
 
-        let points: Vec<Pos2> = self.audio.time_data.iter()
+        if self.config.waveform_style == WaveformStyle::Envelope {
+            Self::draw_waveform_envelope(painter, rect, samples);
+            return;
+        }
+
+        let points: Vec<Pos2> = samples.iter()
             .enumerate()
             .map(|(i, &v)| {
-                let x = rect.left() + (i as f32 / self.audio.time_data.len() as f32) * rect.width();
-                let y = rect.center().y + ((v as f32 - 128.0) / 128.0) * rect.height() * 0.5;
+                let x = rect.left() + (i as f32 / samples.len() as f32) * rect.width();
+                let y = rect.center().y + v * rect.height() * 0.5;
                 Pos2::new(x, y)
             })
             .collect();
@@ -191,16 +804,126 @@ impl MusicVisualizerApp {
         }
     }
 
+    /// Downsample `samples` (-1..1) into columns, filling each column's
+    /// min..max peak range with the RMS band drawn brighter on top.
+    fn draw_waveform_envelope(painter: &egui::Painter, rect: Rect, samples: &[f32]) {
+        if samples.is_empty() {
+            return;
+        }
+        let columns = ((rect.width() / ENVELOPE_COLUMN_WIDTH) as usize).clamp(1, samples.len());
+        let column_width = rect.width() / columns as f32;
+        let half_height = rect.height() * 0.5;
+        let y = |v: f32| rect.center().y - v.clamp(-1.0, 1.0) * half_height;
+
+        for c in 0..columns {
+            let start = c * samples.len() / columns;
+            let end = ((c + 1) * samples.len() / columns).max(start + 1);
+            let chunk = &samples[start..end];
+            let (min, max) = chunk
+                .iter()
+                .fold((f32::MAX, f32::MIN), |(lo, hi), &v| (lo.min(v), hi.max(v)));
+            let rms = (chunk.iter().map(|v| v * v).sum::<f32>() / chunk.len() as f32).sqrt();
+
+            let x0 = rect.left() + c as f32 * column_width;
+            let x1 = x0 + column_width;
+            let hue = c as f32 / columns as f32;
+            painter.rect_filled(
+                Rect::from_x_y_ranges(x0..=x1, y(max)..=y(min)),
+                0.0,
+                hsl_to_rgb(hue, 0.6, 0.35),
+            );
+            painter.rect_filled(
+                Rect::from_x_y_ranges(x0..=x1, y(rms)..=y(-rms)),
+                0.0,
+                hsl_to_rgb(hue, 0.7, 0.6),
+            );
+        }
+    }
+
+    /// Exit button shown over the presentation-mode visual for a few seconds
+    /// after the mouse moves or Esc is pressed. It stays up while hovered.
+    pub fn draw_presentation_overlay(&mut self, ctx: &egui::Context) {
+        // Wall-clock time, so the button still hides while the visuals are frozen
+        let now = ctx.input(|i| i.time);
+        if ctx.input(|i| i.pointer.is_moving()) {
+            self.presentation_overlay_until = now + PRESENTATION_OVERLAY_SECS;
+        }
+        if now >= self.presentation_overlay_until {
+            return;
+        }
+        
+        let mut exit = false;
+        let hovered = egui::Area::new(egui::Id::new("presentation_overlay"))
+            .anchor(egui::Align2::RIGHT_TOP, Vec2::new(-12.0, 12.0))
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        exit = ui.button("✖ Exit presentation").clicked();
+                        ui.label(egui::RichText::new("F11").weak());
+                    });
+                });
+            })
+            .response
+            .hovered();
+        
+        if hovered {
+            self.presentation_overlay_until = now + PRESENTATION_OVERLAY_SECS;
+        }
+        if exit {
+            self.set_presentation_mode(ctx, false);
+        }
+    }
+    
+    /// Floating list of the playback hotkeys, toggled with `?`.
+    pub fn draw_shortcuts_help(&mut self, ctx: &egui::Context) {
+        egui::Window::new("⌨ Keyboard shortcuts")
+            .open(&mut self.show_shortcuts)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, Vec2::ZERO)
+            .show(ctx, |ui| {
+                egui::Grid::new("shortcuts_grid").striped(true).show(ui, |ui| {
+                    for (keys, action) in SHORTCUTS {
+                        ui.strong(keys);
+                        ui.label(action);
+                        ui.end_row();
+                    }
+                });
+                ui.add_space(4.0);
+                ui.label(
+                    egui::RichText::new("Shortcuts are ignored while typing in a text field.")
+                        .small()
+                        .weak(),
+                );
+            });
+    }
+
+    /// Floating window of the band history plots, for tuning reactivity and
+    /// the beat detector.
+    pub fn draw_band_debug(&mut self, ctx: &egui::Context) {
+        let history = &self.band_history;
+        egui::Window::new("📈 Band debug")
+            .open(&mut self.show_band_debug)
+            .default_width(320.0)
+            .show(ctx, |ui| history.draw(ui));
+    }
+
     pub fn draw_particles(&mut self, painter: &egui::Painter, center: Pos2) {
         for p in &mut self.particles {
             let alpha = (p.life * 255.0) as u8;
             let color = Color32::from_rgba_unmultiplied(p.color.r(), p.color.g(), p.color.b(), alpha);
-            let pos = Pos2::new(
-                center.x + (p.pos.x - 400.0),
-                center.y + (p.pos.y - 300.0),
-            );
-            painter.circle_filled(pos, p.size * p.life, color);
+            painter.add(particle_shape(p.shape, p.screen_pos(center), p.size * p.life, p.vel, color));
+        }
+    }
+
+    /// Translucent full-rect flash that fades with `beat_flash`, when the beat
+    /// strobe is on. Opacity never exceeds `MAX_STROBE_INTENSITY`.
+    pub fn draw_strobe(&self, painter: &egui::Painter, rect: Rect) {
+        if !self.config.beat_strobe || self.beat_flash <= 0.0 {
+            return;
         }
+        let alpha = self.beat_flash * self.config.strobe_intensity.clamp(0.0, MAX_STROBE_INTENSITY);
+        painter.rect_filled(rect, 0.0, self.config.strobe_color.gamma_multiply(alpha));
     }
 
     // The full settings UI is implemented at crate root (lib.rs). We avoid
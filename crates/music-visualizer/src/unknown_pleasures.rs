@@ -1,7 +1,13 @@
 use eframe::egui::{self, Color32, Pos2, Rect, Stroke};
-use crate::{audio::AudioAnalysis, VisualizerConfig};
+use crate::color::lerp_color;
+use crate::{audio::AudioAnalysis, UpOrientation, VisualizerConfig};
 use std::f32::consts::TAU;
 
+// In auto-fit mode a full-amplitude peak reaches this many line spacings
+const AUTO_FIT_PEAK_LINES: f32 = 3.0;
+// Extra headroom for the per-sample jitter on top of the peak
+const AUTO_FIT_JITTER_ALLOWANCE: f32 = 1.15;
+
 pub struct UnknownPleasuresVisualizer {
     last_amplitudes: Vec<f32>,
 }
@@ -13,84 +19,59 @@ impl UnknownPleasuresVisualizer {
 
     /// Draw 80 horizontal lines, each representing a sub-frequency band of the
     /// spectrum. Creates a faux-3D perspective by scaling and offsetting farther
-    /// lines.
-    pub fn draw(&mut self, ui: &mut egui::Ui, rect: Rect, audio: &AudioAnalysis, cfg: &VisualizerConfig, time: f64) {
+    /// lines. While `frozen` the held amplitudes are redrawn without another
+    /// smoothing step.
+    pub fn draw(&mut self, ui: &mut egui::Ui, rect: Rect, audio: &AudioAnalysis, cfg: &VisualizerConfig, time: f64, frozen: bool) {
         let painter = ui.painter();
-        let bands = cfg.up_max_lines as usize;
-        let freq_len = audio.frequency_data.len().max(1);
+        let lines = if frozen {
+            self.layout_lines(&self.last_amplitudes, rect, cfg, time)
+        } else {
+            let lines = self.compute_lines(rect, audio, cfg, time);
+            self.last_amplitudes = self.smoothed_amplitudes(audio, cfg);
+            lines
+        };
+        let floor = floor_line(rect, cfg);
+        painter.extend(line_shapes(lines, &floor, cfg));
+    }
 
-        // Number of samples across each line (polyline resolution)
-        let samples = cfg.up_samples as usize;
+    /// Polylines for the next frame laid out in `rect`, nearest line first.
+    /// Applies one smoothing step to the stored amplitudes without keeping it,
+    /// so the geometry can be computed for export or tests without drawing.
+    pub fn compute_lines(&self, rect: Rect, audio: &AudioAnalysis, cfg: &VisualizerConfig, time: f64) -> Vec<Vec<Pos2>> {
+        self.layout_lines(&self.smoothed_amplitudes(audio, cfg), rect, cfg, time)
+    }
 
-        // Ensure smoothing buffer length
-        if self.last_amplitudes.len() < bands {
-            self.last_amplitudes.resize(bands, 0.0);
-        }
+    // One polyline per amplitude, nearest line first
+    fn layout_lines(&self, amplitudes: &[f32], rect: Rect, cfg: &VisualizerConfig, time: f64) -> Vec<Vec<Pos2>> {
+        let bands = amplitudes.len();
+        let rect = line_space(rect, cfg);
+
+        // Number of samples across each line (polyline resolution)
+        let samples = cfg.up_sample_count();
 
-    // Horizontal span
-    let width = rect.width();
+        // Horizontal span
+        let width = rect.width();
 
         // Precompute a time-based phase for simple animation
         let phase = (time as f32) * 2.0;
 
         let center = rect.center();
-        for i in 0..bands {
+        // Rotation angle in radians (if enabled)
+        let angle_rad = cfg.up_rotation_deg.to_radians();
+        let (ca, sa) = if cfg.up_isometric_rotate { (angle_rad.cos(), angle_rad.sin()) } else { (1.0f32, 0.0f32) };
+
+        amplitudes.iter().enumerate().map(|(i, &amp)| {
             let z = i as f32 / bands as f32; // 0..1 depth
 
-            // Log-like frequency mapping using exponent curve to bias low frequencies
-            let f0 = (i as f32) / (bands as f32);
-            let f1 = ((i + 1) as f32) / (bands as f32);
-            let exp = cfg.up_freq_curve_exponent.max(0.001);
-            let idx0 = ((f0.powf(exp)) * (freq_len as f32)).floor() as usize;
-            let idx1 = ((f1.powf(exp)) * (freq_len as f32)).floor() as usize;
-            let start = idx0.min(freq_len - 1);
-            let mut end = idx1.min(freq_len);
-            if end <= start { end = (start + 1).min(freq_len); }
+            // Perspective scaling: closer lines larger
+            let perspective = 1.0 - z * cfg.up_perspective;
 
-            // Average amplitude for this band (0.0..1.0)
-            let mut sum = 0u32;
-            for b in start..end {
-                sum += audio.frequency_data[b] as u32;
-            }
-            let base_amp = (sum as f32) / ((end - start) as f32 * 255.0);
-            // Apply audio-reactivity multipliers (bass/mid/treble)
-            let raw_amp = base_amp * (1.0
-                + cfg.up_bass_mult * audio.smooth_bass
-                + cfg.up_mid_mult * audio.smooth_mid
-                + cfg.up_treble_mult * audio.smooth_treble);
+            // Baseline for this line and the vertical amplitude scale
+            let (baseline, unit_scale) = line_placement(rect, cfg, i, bands);
+            let amp = if cfg.up_auto_fit { amp.min(1.0) } else { amp };
+            let amp_scale = unit_scale * amp * perspective;
 
-            // Temporal smoothing to reduce jitter
-            let last = self.last_amplitudes[i];
-            let smoothing = cfg.up_smoothing.clamp(0.0, 1.0);
-            let amp = last + (raw_amp - last) * smoothing;
-            self.last_amplitudes[i] = amp;
-
-            // Perspective scaling and offsets
-            let perspective = 1.0 - z * cfg.up_perspective; // closer lines larger
-            let line_thickness = (cfg.up_line_thickness * perspective).max(0.3);
-            let alpha = (200.0 * (1.0 - z)).max(40.0) as u8;
-
-            // Baseline for this line: spread vertically but push "far" lines up to create depth
-            let spacing = rect.height() / (bands as f32 * 0.9);
-            let baseline = rect.bottom() - (i as f32 * spacing) + z * (rect.height() * -0.2);
-
-            // Vertical amplitude scale
-            let amp_scale = cfg.up_vertical_scale * 100.0 * amp * perspective;
-
-            // Color mode: monochrome (white) or tinted using base_color
-            let color = if cfg.up_monochrome {
-                Color32::from_rgba_unmultiplied(255, 255, 255, alpha)
-            } else {
-                let base = cfg.base_color;
-                Color32::from_rgba_unmultiplied(base.r(), base.g(), base.b(), alpha)
-            };
-
-            // Generate polyline points
-            let mut prev: Option<Pos2> = None;
-            // Rotation angle in radians (if enabled)
-            let angle_rad = cfg.up_rotation_deg.to_radians();
-            let (ca, sa) = if cfg.up_isometric_rotate { (angle_rad.cos(), angle_rad.sin()) } else { (1.0f32, 0.0f32) };
-            for s in 0..samples {
+            (0..samples).map(|s| {
                 let t = s as f32 / (samples - 1) as f32;
                 // local x centered around 0 so rotation/zoom happens around center
                 let local_x = (t - 0.5) * width * cfg.up_line_length * cfg.up_zoom;
@@ -107,13 +88,231 @@ impl UnknownPleasuresVisualizer {
                 // Apply isometric rotation and translation back to center
                 let rx = local_x * ca - local_y * sa;
                 let ry = local_x * sa + local_y * ca;
-                let pt = Pos2::new(center.x + rx, center.y + ry);
+                orient(center, rx, ry, cfg)
+            }).collect()
+        }).collect()
+    }
 
-                if let Some(p0) = prev {
-                    painter.line_segment([p0, pt], Stroke::new(line_thickness, color));
-                }
-                prev = Some(pt);
-            }
+    /// One temporal smoothing step from the stored amplitudes towards the
+    /// current band levels, one value per line.
+    fn smoothed_amplitudes(&self, audio: &AudioAnalysis, cfg: &VisualizerConfig) -> Vec<f32> {
+        let bands = cfg.up_line_count();
+        let freq_len = audio.frequency_data.len().max(1);
+        let smoothing = cfg.up_smoothing.clamp(0.0, 1.0);
+        (0..bands).map(|i| {
+            // Mel-spaced or exponent-curve frequency mapping, both biased to low frequencies
+            let range = band_bins(i, bands, freq_len, cfg, audio.sample_rate);
+
+            // Average amplitude for this band (0.0..1.0)
+            let len = range.len();
+            let sum: u32 = audio.frequency_data.get(range).map_or(0, |s| s.iter().map(|&b| b as u32).sum());
+            let base_amp = (sum as f32) / (len as f32 * 255.0);
+            // Apply audio-reactivity multipliers (bass/mid/treble)
+            let raw_amp = base_amp * (1.0
+                + cfg.up_bass_mult * audio.smooth_bass
+                + cfg.up_mid_mult * audio.smooth_mid
+                + cfg.up_treble_mult * audio.smooth_treble);
+
+            let last = self.last_amplitudes.get(i).copied().unwrap_or(0.0);
+            last + (raw_amp - last) * smoothing
+        }).collect()
+    }
+}
+
+// Baseline of line `i` and the height a unit amplitude draws before perspective.
+// Manual mode spreads the lines over the rect and pushes far ones up for depth;
+// auto-fit derives both from the rect height and line count so the whole
+// (unrotated) stack, peaks included, stays inside the rect.
+fn line_placement(rect: Rect, cfg: &VisualizerConfig, i: usize, bands: usize) -> (f32, f32) {
+    if cfg.up_auto_fit {
+        // Room above the last line and below the first for a full-amplitude swing
+        let headroom = AUTO_FIT_PEAK_LINES * AUTO_FIT_JITTER_ALLOWANCE;
+        let spacing = rect.height() / (bands.saturating_sub(1) as f32 + 2.0 * headroom);
+        let baseline = rect.bottom() - (headroom + i as f32) * spacing;
+        (baseline, AUTO_FIT_PEAK_LINES * spacing)
+    } else {
+        let z = i as f32 / bands as f32;
+        let spacing = rect.height() / (bands as f32 * 0.9);
+        let baseline = rect.bottom() - (i as f32 * spacing) + z * (rect.height() * -0.2);
+        (baseline, cfg.up_vertical_scale * 100.0)
+    }
+}
+
+// Stroke width and colour of line `i`; farther lines are thinner and fainter
+fn line_style(i: usize, bands: usize, cfg: &VisualizerConfig) -> (f32, Color32) {
+    let z = i as f32 / bands as f32;
+    let perspective = 1.0 - z * cfg.up_perspective;
+    let zoom = if cfg.up_thickness_follows_zoom { cfg.up_zoom } else { 1.0 };
+    let line_thickness = (cfg.up_line_thickness * perspective * zoom).max(0.3);
+    let alpha = (200.0 * (1.0 - z)).max(40.0) as u8;
+
+    // Color mode: depth gradient, monochrome (white) or tinted using base_color
+    let color = if cfg.up_gradient {
+        let c = lerp_color(cfg.up_gradient_bottom, cfg.up_gradient_top, z);
+        Color32::from_rgba_unmultiplied(c.r(), c.g(), c.b(), alpha)
+    } else if cfg.up_monochrome {
+        Color32::from_rgba_unmultiplied(255, 255, 255, alpha)
+    } else {
+        let base = cfg.base_color;
+        Color32::from_rgba_unmultiplied(base.r(), base.g(), base.b(), alpha)
+    };
+    (line_thickness, color)
+}
+
+/// Everything one frame paints, as a single batch: one path per line (or one
+/// shape per segment with `up_segment_strokes`) plus its occlusion fill.
+/// Lines come back to front, so filling the area under each one hides the
+/// farther lines behind its peaks.
+fn line_shapes(lines: Vec<Vec<Pos2>>, floor: &[Pos2], cfg: &VisualizerConfig) -> Vec<egui::Shape> {
+    let bands = lines.len();
+    let mut shapes = Vec::with_capacity(bands * 2);
+    for (i, points) in lines.into_iter().enumerate().rev() {
+        if cfg.up_fill_occlusion {
+            shapes.push(egui::Shape::mesh(occlusion_mesh(&points, floor, cfg.background_color)));
         }
+        let (line_thickness, color) = line_style(i, bands, cfg);
+        let stroke = Stroke::new(line_thickness, color);
+        if cfg.up_segment_strokes {
+            // Separate segments leave room for per-segment colors, at the
+            // cost of visible joints at sharp peaks
+            shapes.extend(points.windows(2).map(|w| egui::Shape::line_segment([w[0], w[1]], stroke)));
+        } else {
+            // One feathered path per line joins the segments cleanly
+            shapes.push(egui::Shape::line(points, stroke));
+        }
+    }
+    shapes
+}
+
+// Projection of each line's samples onto the bottom edge; the same for every
+// line, since only the waveform height varies with depth
+fn floor_line(rect: Rect, cfg: &VisualizerConfig) -> Vec<Pos2> {
+    let samples = cfg.up_sample_count();
+    let rect = line_space(rect, cfg);
+    let center = rect.center();
+    let angle_rad = cfg.up_rotation_deg.to_radians();
+    let (ca, sa) = if cfg.up_isometric_rotate { (angle_rad.cos(), angle_rad.sin()) } else { (1.0f32, 0.0f32) };
+    let floor_y = rect.bottom() - center.y;
+    (0..samples).map(|s| {
+        let t = s as f32 / (samples - 1) as f32;
+        let local_x = (t - 0.5) * rect.width() * cfg.up_line_length * cfg.up_zoom;
+        orient(center, local_x * ca - floor_y * sa, local_x * sa + floor_y * ca, cfg)
+    }).collect()
+}
+
+// The rect the lines are laid out in: `rect` itself, or for vertical lines the
+// same centre with width and height swapped, so line length follows the tall
+// axis and spacing and perspective follow the wide one
+fn line_space(rect: Rect, cfg: &VisualizerConfig) -> Rect {
+    match cfg.up_orientation {
+        UpOrientation::Horizontal => rect,
+        UpOrientation::Vertical => Rect::from_center_size(rect.center(), egui::vec2(rect.height(), rect.width())),
+    }
+}
+
+// Screen position of a point `along` its line and `across` the stack from
+// `center`; vertical lines run down the screen and stack from the left
+fn orient(center: Pos2, along: f32, across: f32, cfg: &VisualizerConfig) -> Pos2 {
+    match cfg.up_orientation {
+        UpOrientation::Horizontal => Pos2::new(center.x + along, center.y + across),
+        UpOrientation::Vertical => Pos2::new(center.x - across, center.y + along),
+    }
+}
+
+// Lowest frequency of the Mel-spaced bands
+const MEL_MIN_HZ: f32 = 20.0;
+
+fn hz_to_mel(hz: f32) -> f32 {
+    2595.0 * (1.0 + hz / 700.0).log10()
+}
+
+fn mel_to_hz(mel: f32) -> f32 {
+    700.0 * (10f32.powf(mel / 2595.0) - 1.0)
+}
+
+// Bin range of line `i`: equal steps in Mel between MEL_MIN_HZ and Nyquist, or
+// the `up_freq_curve_exponent` power curve over the bins
+fn band_bins(i: usize, bands: usize, freq_len: usize, cfg: &VisualizerConfig, sample_rate: f32) -> std::ops::Range<usize> {
+    let f0 = (i as f32) / (bands as f32);
+    let f1 = ((i + 1) as f32) / (bands as f32);
+    let (t0, t1) = if cfg.up_mel_scale {
+        let nyquist = if sample_rate > 0.0 { sample_rate / 2.0 } else { 22_050.0 };
+        let (lo, hi) = (hz_to_mel(MEL_MIN_HZ), hz_to_mel(nyquist));
+        let to_fraction = |f: f32| mel_to_hz(lo + (hi - lo) * f) / nyquist;
+        (to_fraction(f0), to_fraction(f1))
+    } else {
+        let exp = cfg.up_freq_curve_exponent.max(0.001);
+        (f0.powf(exp), f1.powf(exp))
+    };
+    let idx0 = (t0 * (freq_len as f32)).floor() as usize;
+    let idx1 = (t1 * (freq_len as f32)).floor() as usize;
+    let start = idx0.min(freq_len - 1);
+    let mut end = idx1.min(freq_len);
+    if end <= start { end = (start + 1).min(freq_len); }
+    start..end
+}
+
+/// Opaque mesh covering the area between a line and its bottom-edge
+/// projection. Built from per-segment quads since the outline is not convex.
+fn occlusion_mesh(points: &[Pos2], floor: &[Pos2], color: Color32) -> egui::Mesh {
+    let mut mesh = egui::Mesh::default();
+    for (p, f) in points.iter().zip(floor) {
+        mesh.colored_vertex(*p, color);
+        mesh.colored_vertex(*f, color);
+    }
+    for k in 0..points.len().saturating_sub(1) as u32 {
+        let (a, b) = (2 * k, 2 * k + 2);
+        mesh.add_triangle(a, a + 1, b);
+        mesh.add_triangle(b, a + 1, b + 1);
+    }
+    mesh
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eframe::egui::Vec2;
+
+    #[test]
+    fn compute_lines_matches_config() {
+        let mut audio = AudioAnalysis::new();
+        audio.simulate_demo(0.5, 120.0, 1.0, 1.0);
+        let cfg = VisualizerConfig::default();
+        let rect = Rect::from_min_size(Pos2::ZERO, Vec2::new(800.0, 600.0));
+        let lines = UnknownPleasuresVisualizer::new().compute_lines(rect, &audio, &cfg, 1.0);
+        assert_eq!(lines.len(), cfg.up_max_lines as usize);
+        assert!(lines.iter().all(|l| l.len() == cfg.up_samples as usize));
+    }
+
+    #[test]
+    fn one_shape_per_line() {
+        let mut audio = AudioAnalysis::new();
+        audio.simulate_demo(0.5, 120.0, 1.0, 1.0);
+        let rect = Rect::from_min_size(Pos2::ZERO, Vec2::new(800.0, 600.0));
+        let mut cfg = VisualizerConfig::default();
+        let visualizer = UnknownPleasuresVisualizer::new();
+        let floor = floor_line(rect, &cfg);
+        let shapes = line_shapes(visualizer.compute_lines(rect, &audio, &cfg, 1.0), &floor, &cfg);
+        assert_eq!(shapes.len(), cfg.up_line_count());
+
+        // The per-segment path draws the same polylines one segment at a time
+        cfg.up_segment_strokes = true;
+        let segments = line_shapes(visualizer.compute_lines(rect, &audio, &cfg, 1.0), &floor, &cfg);
+        assert_eq!(segments.len(), cfg.up_line_count() * (cfg.up_sample_count() - 1));
+        let path_points: Vec<Pos2> = shapes.iter().flat_map(|s| match s {
+            egui::Shape::Path(path) => path.points.clone(),
+            _ => unreachable!(),
+        }).collect();
+        let segment_points: Vec<Pos2> = segments.chunks(cfg.up_sample_count() - 1).flat_map(|line| {
+            let mut points: Vec<Pos2> = line.iter().map(|s| match s {
+                egui::Shape::LineSegment { points, .. } => points[0],
+                _ => unreachable!(),
+            }).collect();
+            if let Some(egui::Shape::LineSegment { points: last, .. }) = line.last() {
+                points.push(last[1]);
+            }
+            points
+        }).collect();
+        assert_eq!(path_points, segment_points);
     }
 }